@@ -0,0 +1,68 @@
+//! Jump-lowered `if`/`when`/`times` against the quotation-based ops they
+//! replace.
+//!
+//! The compiler lowers these words to jumps when their quotations are
+//! literals. Calling a no-op word between the quotations and the word hides
+//! them from the compiler, so the same program runs through the VM's
+//! quotation-based ops instead. Each program runs in a fresh `ember`
+//! process; the loops are long enough that start-up is a small share of
+//! the time, and it is the same for both variants. The dynamic variants also
+//! pay for one extra word call per construct.
+
+use std::process::Command;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+const CASES: &[(&str, &str)] = &[
+    (
+        "if",
+        "0 20000 [ dup 2 % 0 = [ 1 + ] [ 3 + ] IF ] times print",
+    ),
+    (
+        "when",
+        "0 20000 [ dup 3 % 0 = [ 1 + ] WHEN 1 + ] times print",
+    ),
+    ("times", "0 200 [ 100 [ 1 + ] TIMES ] times print"),
+];
+
+/// `source` with the benchmarked word compiled through jumps, or through its
+/// quotation-based op when `dynamic`.
+fn variant(source: &str, dynamic: bool) -> String {
+    let prefix = if dynamic { "dyn " } else { "" };
+    let source = source
+        .replace("IF", &format!("{}if", prefix))
+        .replace("WHEN", &format!("{}when", prefix))
+        .replace("TIMES", &format!("{}times", prefix));
+    format!("def dyn end {}", source)
+}
+
+fn run(source: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ember"))
+        .arg("-e")
+        .arg(source)
+        .output()
+        .expect("failed to run ember");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).expect("output is UTF-8")
+}
+
+fn control_flow(c: &mut Criterion) {
+    for (name, source) in CASES {
+        let lowered = variant(source, false);
+        let dynamic = variant(source, true);
+        assert_eq!(run(&lowered), run(&dynamic), "{} paths disagree", name);
+
+        let mut group = c.benchmark_group(*name);
+        group.sample_size(20);
+        group.bench_function("jumps", |b| b.iter(|| run(&lowered)));
+        group.bench_function("quotation", |b| b.iter(|| run(&dynamic)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, control_flow);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+; Calculator in EMBER
+; Evaluates arithmetic written in reverse Polish notation, where each
+; operator follows its operands: "3 4 +" means 3 + 4
+
+; Apply one token: an operator works on the stack, anything else is a number
+def apply-token
+    dup "+" = [ drop + ] [
+        dup "-" = [ drop - ] [
+            dup "*" = [ drop * ] [
+                dup "/" = [ drop / ] [
+                    10 parse-int
+                ] if
+            ] if
+        ] if
+    ] if
+end
+
+def calc
+    " " split [ apply-token ] each
+end
+
+def show
+    dup " = " . swap calc to-string . print
+end
+
+"3 4 +" show
+"5 1 2 + 4 * + 3 -" show
+"2 3 4 * +" show
+"100 7 /" show
@@ -0,0 +1,27 @@
+; Word count in EMBER
+; Counts the lines, words, and characters of a piece of text
+
+def text
+    "the quick brown fox\njumps over  the lazy dog\nthe end"
+end
+
+; Split on a separator, dropping empty pieces (e.g. from repeated spaces)
+def pieces
+    split [ len 0 > ] filter
+end
+
+def line-count   "\n" pieces len end
+def word-count   "\n" split " " join " " pieces len end
+def char-count   len end
+
+; Count how often a given word appears
+def occurrences
+    swap "\n" split " " join " " pieces
+    [ over = ] filter len
+    swap drop
+end
+
+"lines: "      text line-count to-string . print
+"words: "      text word-count to-string . print
+"characters: " text char-count to-string . print
+"'the' appears " text "the" occurrences to-string . " times" . print
@@ -1,47 +1,210 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    bytecode::{CodeObject, Op, ProgramBc, compile_error::CompileError},
-    frontend::{lexer::Lexer, parser::Parser},
-    lang::{node::Node, program::Program, use_item::UseItem, value::Value},
+    bytecode::{
+        CodeObject, Op, OpSpan, ProgramBc, WordSite, compile_error::CompileError,
+        optimize::optimize_program,
+    },
+    frontend::{
+        lexer::{Lexer, Span},
+        parser::{DEFAULT_MAX_NESTING, Parser},
+        source::decode_source,
+    },
+    lang::{builtins, node::Node, program::Program, use_item::UseItem, value::Value},
 };
 
+/// Wall-clock time spent in each compilation phase.
+#[derive(Debug, Clone, Default)]
+pub struct CompileTimings {
+    /// Tokenizing every loaded file
+    pub lexing: Duration,
+    /// Parsing every loaded file
+    pub parsing: Duration,
+    /// Resolving, reading and registering imported files, excluding the
+    /// lexing and parsing counted above
+    pub loading: Duration,
+    /// Generating bytecode for words and main
+    pub compiling: Duration,
+    /// The peephole pass
+    pub optimizing: Duration,
+}
+
+impl CompileTimings {
+    pub fn total(&self) -> Duration {
+        self.lexing + self.parsing + self.loading + self.compiling + self.optimizing
+    }
+}
+
+/// Bounds on what the compiler accepts, so a pathological program fails
+/// with a clean error instead of exhausting the stack or memory.
+#[derive(Debug, Clone)]
+pub struct CompileLimits {
+    /// Deepest nesting of quotations and lists
+    pub max_nesting: usize,
+    /// Total ops emitted, counting every quotation body
+    pub max_ops: usize,
+    /// Number of word definitions, including imported ones
+    pub max_words: usize,
+}
+
+impl Default for CompileLimits {
+    fn default() -> Self {
+        CompileLimits {
+            max_nesting: DEFAULT_MAX_NESTING,
+            max_ops: 1_000_000,
+            max_words: 10_000,
+        }
+    }
+}
+
+/// Whether the platform's default file systems ignore case, so that `a.em`
+/// and `A.em` are the same file.
+const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Resolve an `import` path against the importing file's directory. `\` is
+/// accepted as a separator everywhere, so files written on Windows import
+/// on any platform (Windows itself also accepts `/`).
+fn resolve_import(base_dir: &Path, import: &str) -> PathBuf {
+    base_dir.join(import.replace('\\', "/"))
+}
+
+/// Identity of a canonicalized file for include deduplication.
+/// `canonicalize` keeps the spelling it was given on case-insensitive file
+/// systems, so fold case there.
+fn include_key(canonical: &Path, case_insensitive: bool) -> PathBuf {
+    if case_insensitive {
+        PathBuf::from(canonical.to_string_lossy().to_lowercase())
+    } else {
+        canonical.to_path_buf()
+    }
+}
+
+/// Most locals one word can bind. The VM makes room for every slot up to
+/// the highest one a word uses, so loaded bytecode is held to this too.
+pub const MAX_LOCALS: usize = 256;
+
+/// A quotation body being compiled by `compile_nodes`.
+struct PendingBody<'a> {
+    nodes: &'a [Node],
+    next: usize,
+    ops: Vec<Op>,
+}
+
 pub struct Compiler {
     /// Output bytecode program
     program_bc: ProgramBc,
 
     /// Accumulated word definitions (as AST nodes, for lazy compilation)
-    words: HashMap<String, Vec<Node>>,
+    words: BTreeMap<String, Vec<Node>>,
+
+    /// Where each node of a word in `words` starts
+    word_spans: BTreeMap<String, Vec<Span>>,
 
-    /// Files already included (prevents duplicates)
+    /// Files already included (prevents duplicates), keyed by `include_key`
     included: HashSet<PathBuf>,
 
     /// Aliases from 'use' statements
-    aliases: HashMap<String, String>,
+    aliases: BTreeMap<String, String>,
+
+    /// Variables declared with 'var'
+    variables: BTreeSet<String>,
+
+    /// Locals bound so far in the word being compiled, by slot; None
+    /// outside word bodies
+    locals: Option<Vec<String>>,
+
+    /// Time spent per phase, accumulated as compilation proceeds
+    timings: CompileTimings,
+
+    limits: CompileLimits,
+
+    /// Ops emitted so far, checked against `limits.max_ops`
+    total_ops: usize,
+
+    /// How many quotations and lists enclose the code being compiled
+    nesting: usize,
+
+    /// Set by the `if`/`when`/`times`/... lowering to where the ops it
+    /// rewrote start
+    lowered_from: Option<usize>,
+
+    /// Replace invalid UTF-8 in source files instead of failing
+    lossy_utf8: bool,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[allow(dead_code)]
 impl Compiler {
     pub fn new() -> Self {
+        Self::with_limits(CompileLimits::default())
+    }
+
+    pub fn with_limits(limits: CompileLimits) -> Self {
         Self {
             program_bc: ProgramBc {
                 code: vec![CodeObject::new()],
-                words: HashMap::new(),
+                words: BTreeMap::new(),
+                docs: BTreeMap::new(),
+                sites: BTreeMap::new(),
             },
-            words: HashMap::new(),
+            words: BTreeMap::new(),
+            word_spans: BTreeMap::new(),
             included: HashSet::new(),
-            aliases: HashMap::new(),
+            aliases: BTreeMap::new(),
+            variables: BTreeSet::new(),
+            locals: None,
+            timings: CompileTimings::default(),
+            limits,
+            total_ops: 0,
+            nesting: 0,
+            lowered_from: None,
+            lossy_utf8: false,
         }
     }
 
-    pub fn compile_from_file(mut self, path: &Path) -> Result<ProgramBc, CompileError> {
+    /// Decode source files lossily, warning about invalid UTF-8 instead of
+    /// rejecting it.
+    pub fn with_lossy_utf8(mut self, lossy: bool) -> Self {
+        self.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Treat `names` as declared variables, e.g. those declared on earlier
+    /// REPL lines.
+    pub fn with_variables(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.variables.extend(names);
+        self
+    }
+
+    pub fn compile_from_file(self, path: &Path) -> Result<ProgramBc, CompileError> {
+        self.compile_from_file_with_timings(path)
+            .map(|(program, _)| program)
+    }
+
+    /// Like `compile_from_file`, also reporting how long each phase took.
+    pub fn compile_from_file_with_timings(
+        mut self,
+        path: &Path,
+    ) -> Result<(ProgramBc, CompileTimings), CompileError> {
         // Load the file and all its imports (recursively)
-        let main_program = self.load_file_recursive(path)?;
+        let started = Instant::now();
+        let (main_program, main_spans) = self.load_file_recursive(path)?;
+        self.timings.loading = started
+            .elapsed()
+            .saturating_sub(self.timings.lexing + self.timings.parsing);
+
+        let started = Instant::now();
 
-        // Clone the words HashMap to avoid borrow checker issues
+        // Clone the words map to avoid borrow checker issues
         // (We need to iterate over words while calling compile_nodes which borrows self mutably)
         let words_to_compile: Vec<(String, Vec<Node>)> = self
             .words
@@ -51,22 +214,30 @@ impl Compiler {
 
         // Now compile all words to bytecode
         for (name, body) in words_to_compile {
-            let mut word_ops = self.compile_nodes(&body)?;
-            word_ops.push(Op::Return);
-            self.program_bc.words.insert(name, word_ops);
+            self.compile_named_word(name, &body)?;
         }
 
         // Compile main
-        let mut main_ops = self.compile_nodes(&main_program)?;
+        let (mut main_ops, spans) = self.compile_nodes_with_spans(&main_program, &main_spans)?;
         main_ops.push(Op::Return);
-        self.program_bc.code[0].ops = main_ops;
+        self.program_bc.code[0] = CodeObject {
+            ops: main_ops,
+            spans,
+        };
+        self.timings.compiling = started.elapsed();
 
-        Ok(self.program_bc)
+        let started = Instant::now();
+        optimize_program(&mut self.program_bc);
+        self.timings.optimizing = started.elapsed();
+
+        Ok((self.program_bc, self.timings))
     }
 
     /// Compile from AST (for backward compatibility, REPL, testing)
     /// Does NOT handle imports - use compile_from_file for that
     pub fn compile_program(mut self, program: &Program) -> Result<ProgramBc, CompileError> {
+        self.program_bc.docs.extend(program.docs.clone());
+
         // Process definitions
         for def in &program.definitions {
             self.process_definition(def, None)?;
@@ -81,20 +252,26 @@ impl Compiler {
 
         // Compile accumulated words
         for (name, body) in words_to_compile {
-            let mut word_ops = self.compile_nodes(&body)?;
-            word_ops.push(Op::Return);
-            self.program_bc.words.insert(name, word_ops);
+            self.compile_named_word(name, &body)?;
         }
 
         // Compile main
-        let mut main_ops = self.compile_nodes(&program.main)?;
+        let (mut main_ops, spans) =
+            self.compile_nodes_with_spans(&program.main, &program.main_spans)?;
         main_ops.push(Op::Return);
-        self.program_bc.code[0].ops = main_ops;
+        self.program_bc.code[0] = CodeObject {
+            ops: main_ops,
+            spans,
+        };
+
+        optimize_program(&mut self.program_bc);
 
         Ok(self.program_bc)
     }
 
-    fn load_file_recursive(&mut self, path: &Path) -> Result<Vec<Node>, CompileError> {
+    /// Load `path` and its imports, returning the main nodes of `path` and
+    /// where each starts.
+    fn load_file_recursive(&mut self, path: &Path) -> Result<(Vec<Node>, Vec<Span>), CompileError> {
         // Normalize to .em extension
         let mut path_buf = path.to_path_buf();
 
@@ -108,8 +285,11 @@ impl Compiler {
         })?;
 
         // Already included? Skip (prevents infinite loops and duplicate definitions)
-        if !self.included.insert(canonical.clone()) {
-            return Ok(Vec::new()); // Return empty - already processed
+        if !self
+            .included
+            .insert(include_key(&canonical, CASE_INSENSITIVE_FS))
+        {
+            return Ok(Default::default()); // Return empty - already processed
         }
 
         // Get base directory for resolving imports
@@ -118,24 +298,34 @@ impl Compiler {
             .ok_or_else(|| CompileError::new("cannot get parent directory"))?;
 
         // Read and parse
-        let source = std::fs::read_to_string(&canonical).map_err(|e| {
+        let bytes = std::fs::read(&canonical).map_err(|e| {
             CompileError::new(format!("cannot read '{}': {}", canonical.display(), e))
         })?;
+        let decoded = decode_source(&bytes, self.lossy_utf8)
+            .map_err(|e| CompileError::new(format!("in '{}': {}", canonical.display(), e)))?;
+        if let Some(warning) = &decoded.warning {
+            eprintln!("Warning: in {}: {}", canonical.display(), warning);
+        }
+        let source = decoded.text;
 
+        let started = Instant::now();
         let mut lexer = Lexer::new(&source);
         let tokens = lexer
             .tokenize()
             .map_err(|e| CompileError::new(format!("in '{}': {}", canonical.display(), e)))?;
+        self.timings.lexing += started.elapsed();
 
-        let mut parser = Parser::new(tokens);
+        let started = Instant::now();
+        let mut parser = Parser::new(tokens).with_max_nesting(self.limits.max_nesting);
         let program = parser
             .parse()
             .map_err(|e| CompileError::new(format!("in '{}': {}", canonical.display(), e)))?;
+        self.timings.parsing += started.elapsed();
 
         // Process imports FIRST (depth-first, like Forth INCLUDE)
         for def in &program.definitions {
             if let Node::Import(import_path) = def {
-                let import_full = base_dir.join(import_path);
+                let import_full = resolve_import(base_dir, import_path);
                 self.load_file_recursive(&import_full)?;
                 // Note: we discard the result because definitions are accumulated
                 // in self.words, not returned
@@ -143,12 +333,13 @@ impl Compiler {
         }
 
         // Now process definitions from THIS file
+        self.program_bc.docs.extend(program.docs.clone());
         for def in &program.definitions {
             self.process_definition(def, Some(&canonical))?;
         }
 
         // Return main code (only meaningful for the top-level file)
-        Ok(program.main)
+        Ok((program.main, program.main_spans))
     }
 
     fn process_definition(
@@ -157,7 +348,12 @@ impl Compiler {
         source_file: Option<&Path>,
     ) -> Result<(), CompileError> {
         match def {
-            Node::Def { name, body } => {
+            Node::Def {
+                name,
+                body,
+                line,
+                spans,
+            } => {
                 if self.words.contains_key(name) {
                     // Allow redefinition with a warning (Forth-style)
                     eprintln!(
@@ -175,17 +371,21 @@ impl Compiler {
                 // If body is exactly one node and it's a quotation literal,
                 // use the quotation's contents as the body instead.
                 // This allows: def double [dup +]  to work like: def double dup + end
-                let actual_body = if body.len() == 1 {
+                // Nodes inside the quotation have no spans of their own
+                let (actual_body, actual_spans) = if body.len() == 1 {
                     if let Node::Literal(Value::Quotation(inner)) = &body[0] {
-                        inner.clone()
+                        (inner.clone(), Vec::new())
                     } else {
-                        body.clone()
+                        (body.clone(), spans.clone())
                     }
                 } else {
-                    body.clone()
+                    (body.clone(), spans.clone())
                 };
 
                 self.words.insert(name.clone(), actual_body);
+                self.word_spans.insert(name.clone(), actual_spans);
+                self.record_site(name.clone(), *line, source_file);
+                self.check_word_count()?;
             }
 
             Node::Module {
@@ -196,10 +396,15 @@ impl Compiler {
                     if let Node::Def {
                         name: word_name,
                         body,
+                        line,
+                        spans,
                     } = inner_def
                     {
                         let qualified = format!("{}.{}", module_name, word_name);
-                        self.words.insert(qualified, body.clone());
+                        self.words.insert(qualified.clone(), body.clone());
+                        self.word_spans.insert(qualified.clone(), spans.clone());
+                        self.record_site(qualified, *line, source_file);
+                        self.check_word_count()?;
                     }
                 }
             }
@@ -229,19 +434,163 @@ impl Compiler {
 
             Node::Import(_) => {}
 
+            Node::Var(name) => {
+                self.variables.insert(name.clone());
+            }
+
             _ => {}
         }
 
         Ok(())
     }
 
+    /// Note where `name` was defined, keeping any definition it replaces.
+    fn record_site(&mut self, name: String, line: usize, source_file: Option<&Path>) {
+        let mut replaces = Vec::new();
+        if let Some(earlier) = self.program_bc.sites.remove(&name) {
+            replaces.extend(earlier.replaces.iter().cloned());
+            replaces.push(WordSite {
+                replaces: Vec::new(),
+                spans: Vec::new(),
+                ..earlier
+            });
+        }
+        let site = WordSite {
+            file: source_file.map(|path| path.display().to_string()),
+            line,
+            replaces,
+            spans: Vec::new(),
+        };
+        self.program_bc.sites.insert(name, site);
+    }
+
+    fn check_word_count(&self) -> Result<(), CompileError> {
+        if self.words.len() > self.limits.max_words {
+            return Err(CompileError::limit_exceeded(
+                "number of word definitions",
+                self.limits.max_words,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Compile a sequence of nodes.
+    ///
+    /// Nested quotations are compiled with an explicit worklist rather than
+    /// by recursion: a quotation's body is finished before the node after it
+    /// is compiled, so the `if`/`when`/`times` lowering still sees compiled
+    /// quotations on top of `ops`.
     pub fn compile_nodes(&mut self, nodes: &[Node]) -> Result<Vec<Op>, CompileError> {
-        let mut ops = Vec::new();
-        for node in nodes {
-            self.compile_node(node, &mut ops)?;
+        self.compile_nodes_with_spans(nodes, &[])
+            .map(|(ops, _)| ops)
+    }
+
+    /// Like `compile_nodes`, also returning where the ops came from, given
+    /// where each of `nodes` starts. Ops inlined from a quotation by the
+    /// `if`/`when`/`times`/`while` lowering are placed at the quotation.
+    fn compile_nodes_with_spans(
+        &mut self,
+        nodes: &[Node],
+        spans: &[Span],
+    ) -> Result<(Vec<Op>, Vec<OpSpan>), CompileError> {
+        let mut op_spans: Vec<OpSpan> = Vec::new();
+        let base_nesting = self.nesting;
+        let mut pending = vec![PendingBody {
+            nodes,
+            next: 0,
+            ops: Vec::new(),
+        }];
+
+        loop {
+            // Nesting level of the body at the top of the worklist
+            let depth = base_nesting + pending.len() - 1;
+            let body = pending.last_mut().expect("worklist is never empty");
+            if depth == base_nesting
+                && let Some(span) = spans.get(body.next)
+            {
+                op_spans.push(OpSpan {
+                    op: body.ops.len(),
+                    span: span.clone(),
+                });
+            }
+            match body.nodes.get(body.next) {
+                Some(Node::Literal(Value::Quotation(inner))) => {
+                    body.next += 1;
+                    if depth + 1 > self.limits.max_nesting {
+                        return Err(CompileError::limit_exceeded(
+                            "quotation nesting",
+                            self.limits.max_nesting,
+                        ));
+                    }
+                    pending.push(PendingBody {
+                        nodes: inner,
+                        next: 0,
+                        ops: Vec::new(),
+                    });
+                }
+                Some(node) => {
+                    body.next += 1;
+                    // Values inside list literals nest below this body
+                    self.nesting = depth;
+                    let result = self.compile_node(node, &mut body.ops);
+                    self.nesting = base_nesting;
+                    result?;
+                    if let Some(start) = self.lowered_from.take()
+                        && depth == base_nesting
+                    {
+                        op_spans.retain(|entry| entry.op <= start);
+                    }
+                }
+                None => {
+                    let done = pending.pop().expect("worklist is never empty");
+                    self.total_ops += done.ops.len();
+                    if self.total_ops > self.limits.max_ops {
+                        return Err(CompileError::limit_exceeded(
+                            "total number of ops",
+                            self.limits.max_ops,
+                        ));
+                    }
+                    match pending.last_mut() {
+                        Some(parent) => parent
+                            .ops
+                            .push(Op::Push(Value::CompiledQuotation(done.ops))),
+                        None => return Ok((done.ops, op_spans)),
+                    }
+                }
+            }
         }
+    }
+
+    /// Compile a word body, with its own locals.
+    fn compile_word(
+        &mut self,
+        body: &[Node],
+        spans: &[Span],
+    ) -> Result<(Vec<Op>, Vec<OpSpan>), CompileError> {
+        self.locals = Some(Vec::new());
+        let result = self.compile_nodes_with_spans(body, spans);
+        self.locals = None;
+        let (mut ops, spans) = result?;
+        capture_locals(&mut ops);
+        ops.push(Op::Return);
+        Ok((ops, spans))
+    }
+
+    /// Compile an accumulated word into the program, keeping its spans with
+    /// its definition site.
+    fn compile_named_word(&mut self, name: String, body: &[Node]) -> Result<(), CompileError> {
+        let spans = self.word_spans.remove(&name).unwrap_or_default();
+        let (ops, spans) = self.compile_word(body, &spans)?;
+        if let Some(site) = self.program_bc.sites.get_mut(&name) {
+            site.spans = spans;
+        }
+        self.program_bc.words.insert(name, ops);
+        Ok(())
+    }
 
-        Ok(ops)
+    /// The slot of a local of the word being compiled.
+    fn local_slot(&self, name: &str) -> Option<usize> {
+        self.locals.as_ref()?.iter().position(|local| local == name)
     }
 
     fn compile_module(
@@ -250,10 +599,9 @@ impl Compiler {
         definitions: &[Node],
     ) -> Result<(), CompileError> {
         for node in definitions {
-            if let Node::Def { name, body } = node {
+            if let Node::Def { name, body, .. } = node {
                 let qualified_name = format!("{}.{}", module_name, name);
-                let mut word_ops = self.compile_nodes(body)?;
-                word_ops.push(Op::Return);
+                let (word_ops, _) = self.compile_word(body, &[])?;
                 self.program_bc.words.insert(qualified_name, word_ops);
             }
         }
@@ -267,35 +615,6 @@ impl Compiler {
                 ops.push(Op::Push(compiled_value));
             }
 
-            // Stack ops
-            Node::Dup => ops.push(Op::Dup),
-            Node::Drop => ops.push(Op::Drop),
-            Node::Swap => ops.push(Op::Swap),
-            Node::Over => ops.push(Op::Over),
-            Node::Rot => ops.push(Op::Rot),
-
-            // Arithmetic
-            Node::Add => ops.push(Op::Add),
-            Node::Sub => ops.push(Op::Sub),
-            Node::Mul => ops.push(Op::Mul),
-            Node::Div => ops.push(Op::Div),
-            Node::Mod => ops.push(Op::Mod),
-            Node::Neg => ops.push(Op::Neg),
-            Node::Abs => ops.push(Op::Abs),
-
-            // Comparison
-            Node::Eq => ops.push(Op::Eq),
-            Node::NotEq => ops.push(Op::Ne),
-            Node::Lt => ops.push(Op::Lt),
-            Node::Gt => ops.push(Op::Gt),
-            Node::LtEq => ops.push(Op::Le),
-            Node::GtEq => ops.push(Op::Ge),
-
-            // Logic
-            Node::And => ops.push(Op::And),
-            Node::Or => ops.push(Op::Or),
-            Node::Not => ops.push(Op::Not),
-
             // Control flow - try jump optimization, fall back to quotation-based
             Node::If => {
                 if !self.try_emit_if_jumps(ops) {
@@ -307,7 +626,18 @@ impl Compiler {
                     ops.push(Op::When);
                 }
             }
-            Node::Call => ops.push(Op::Call),
+
+            // Logic - quotation operands short-circuit
+            Node::And => {
+                if !self.try_emit_short_circuit_jumps(ops, true) {
+                    ops.push(Op::And);
+                }
+            }
+            Node::Or => {
+                if !self.try_emit_short_circuit_jumps(ops, false) {
+                    ops.push(Op::Or);
+                }
+            }
 
             // Loops - try jump optimization, fall back to quotation-based
             Node::Times => {
@@ -315,61 +645,55 @@ impl Compiler {
                     ops.push(Op::Times);
                 }
             }
+            Node::While | Node::Until => {
+                let until = matches!(node, Node::Until);
+                if !self.try_emit_while_jumps(ops, until) {
+                    ops.push(if until { Op::Until } else { Op::While });
+                }
+            }
 
-            // These remain quotation-based for now (could optimize later)
-            Node::Each => ops.push(Op::Each),
-            Node::Map => ops.push(Op::Map),
-            Node::Filter => ops.push(Op::Filter),
-            Node::Fold => ops.push(Op::Fold),
-            Node::Range => ops.push(Op::Range),
-
-            // List ops
-            Node::Len => ops.push(Op::Len),
-            Node::Head => ops.push(Op::Head),
-            Node::Tail => ops.push(Op::Tail),
-            Node::Cons => ops.push(Op::Cons),
-            Node::Concat => ops.push(Op::Concat),
-            Node::StringConcat => ops.push(Op::StringConcat),
-
-            // I/O
-            Node::Print => ops.push(Op::Print),
-            Node::Emit => ops.push(Op::Emit),
-            Node::Read => ops.push(Op::Read),
-            Node::Debug => ops.push(Op::Debug),
-
-            // stdlib
-            Node::Min => ops.push(Op::Min),
-            Node::Max => ops.push(Op::Max),
-            Node::Pow => ops.push(Op::Pow),
-            Node::Sqrt => ops.push(Op::Sqrt),
-            Node::Nth => ops.push(Op::Nth),
-            Node::Append => ops.push(Op::Append),
-            Node::Sort => ops.push(Op::Sort),
-            Node::Reverse => ops.push(Op::Reverse),
-            Node::Chars => ops.push(Op::Chars),
-            Node::Join => ops.push(Op::Join),
-            Node::Split => ops.push(Op::Split),
-            Node::Upper => ops.push(Op::Upper),
-            Node::Lower => ops.push(Op::Lower),
-            Node::Trim => ops.push(Op::Trim),
-            Node::Clear => ops.push(Op::Clear),
-            Node::Depth => ops.push(Op::Depth),
-            Node::Type => ops.push(Op::Type),
-            Node::ToString => ops.push(Op::ToString),
-            Node::ToInt => ops.push(Op::ToInt),
-
-            // Combinators
-            Node::Dip => ops.push(Op::Dip),
-            Node::Keep => ops.push(Op::Keep),
-            Node::Bi => ops.push(Op::Bi),
-            Node::Bi2 => ops.push(Op::Bi2),
-            Node::Tri => ops.push(Op::Tri),
-            Node::Both => ops.push(Op::Both),
-            Node::Compose => ops.push(Op::Compose),
-            Node::Curry => ops.push(Op::Curry),
-            Node::Apply => ops.push(Op::Apply),
+            // Variables
+            Node::Store(name) | Node::Load(name) => {
+                if !self.variables.contains(name) {
+                    return Err(CompileError::UndeclaredVariable(name.clone()));
+                }
+                ops.push(match node {
+                    Node::Store(_) => Op::Store(name.clone()),
+                    _ => Op::Load(name.clone()),
+                });
+            }
+
+            // Locals
+            Node::BindLocal(name) => {
+                let Some(locals) = self.locals.as_mut() else {
+                    return Err(CompileError::local_outside_word(name));
+                };
+                if self.nesting > 0 {
+                    return Err(CompileError::local_in_quotation(name));
+                }
+                let slot = match locals.iter().position(|local| local == name) {
+                    Some(slot) => slot,
+                    None if locals.len() == MAX_LOCALS => {
+                        return Err(CompileError::limit_exceeded(
+                            "number of locals in one word",
+                            MAX_LOCALS,
+                        ));
+                    }
+                    None => {
+                        locals.push(name.clone());
+                        locals.len() - 1
+                    }
+                };
+                ops.push(Op::StoreLocal(slot));
+            }
+            Node::Word(name) if let Some(slot) = self.local_slot(name) => {
+                ops.push(Op::LoadLocal(slot));
+            }
 
             // Word calls
+            Node::Word(name) if self.variables.contains(name) && !self.words.contains_key(name) => {
+                return Err(CompileError::variable_as_word(name));
+            }
             Node::Word(name) => {
                 // Check if this word has an alias (from 'use' statements)
                 let resolved = self
@@ -405,12 +729,34 @@ impl Compiler {
             Node::Import(path) => {
                 return Err(CompileError::import_in_runtime(path));
             }
+
+            // Every other builtin word maps straight to its op
+            node => match builtins::op_for(node) {
+                Some(op) => ops.push(op),
+                None => return Err(CompileError::unhandled(node)),
+            },
         }
 
         Ok(())
     }
 
     fn compile_value(&mut self, value: &Value) -> Result<Value, CompileError> {
+        if !matches!(value, Value::Quotation(_) | Value::List(_) | Value::Map(_)) {
+            return self.compile_nested_value(value);
+        }
+        if self.nesting + 1 > self.limits.max_nesting {
+            return Err(CompileError::limit_exceeded(
+                "quotation nesting",
+                self.limits.max_nesting,
+            ));
+        }
+        self.nesting += 1;
+        let compiled = self.compile_nested_value(value);
+        self.nesting -= 1;
+        compiled
+    }
+
+    fn compile_nested_value(&mut self, value: &Value) -> Result<Value, CompileError> {
         match value {
             Value::Quotation(nodes) => {
                 let compiled_ops = self.compile_nodes(nodes)?;
@@ -422,6 +768,14 @@ impl Compiler {
                     items.iter().map(|it| self.compile_value(it)).collect();
                 Ok(Value::List(compiled_items?))
             }
+            Value::Bytes(bytes) => Ok(Value::Bytes(bytes.clone())),
+            Value::Map(entries) => {
+                let compiled_entries: Result<_, CompileError> = entries
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), self.compile_value(v)?)))
+                    .collect();
+                Ok(Value::Map(compiled_entries?))
+            }
             Value::Integer(n) => Ok(Value::Integer(*n)),
             Value::Float(n) => Ok(Value::Float(*n)),
             Value::String(s) => Ok(Value::String(s.clone())),
@@ -435,6 +789,8 @@ impl Compiler {
 
     /// Try to optimize `if` using jumps.
     /// Expects stack to have: ... then-quot else-quot
+    /// A literal that isn't a quotation is a branch that just pushes it, so
+    /// `cond 1 2 if` selects a value without brackets.
     /// Returns true if optimization succeeded, false to fall back to Op::If
     fn try_emit_if_jumps(&mut self, ops: &mut Vec<Op>) -> bool {
         if ops.len() < 2 {
@@ -443,18 +799,21 @@ impl Compiler {
 
         let len = ops.len();
 
-        // Check if last two ops are compiled quotations
-        let (then_ops, else_ops) = match (&ops[len - 2], &ops[len - 1]) {
-            (
-                Op::Push(Value::CompiledQuotation(then_ops)),
-                Op::Push(Value::CompiledQuotation(else_ops)),
-            ) => (then_ops.clone(), else_ops.clone()),
+        // Check if last two ops are literals
+        let branch = |op: &Op| match op {
+            Op::Push(Value::CompiledQuotation(body)) => Some(body.clone()),
+            Op::Push(value) => Some(vec![Op::Push(value.clone())]),
+            _ => None,
+        };
+        let (then_ops, else_ops) = match (branch(&ops[len - 2]), branch(&ops[len - 1])) {
+            (Some(then_ops), Some(else_ops)) => (then_ops, else_ops),
             _ => return false,
         };
 
         // Remove the two Push ops
         ops.pop();
         ops.pop();
+        self.lowered_from = Some(ops.len());
 
         // Emit jump-based if:
         //   JumpIfFalse(then_len + 2)  ; skip then + jump
@@ -487,6 +846,7 @@ impl Compiler {
 
         // Remove the Push op
         ops.pop();
+        self.lowered_from = Some(ops.len());
 
         // Emit jump-based when:
         //   JumpIfFalse(then_len + 1)  ; skip then
@@ -499,6 +859,55 @@ impl Compiler {
         true
     }
 
+    /// Try to compile `and`/`or` with a quotation operand as a short circuit.
+    /// Expects stack to have: ... bool-or-quot quot
+    ///
+    /// The right quotation only runs when the left operand doesn't already
+    /// decide the result, and a literal left quotation is simply inlined:
+    /// `[ a ] [ b ] and` is `a [ b ] [ false ] if`, and `[ a ] [ b ] or` is
+    /// `a [ true ] [ b ] if`, except that what `b` leaves is checked to be a
+    /// bool, as the strict `and`/`or` would.
+    /// Returns true if optimization succeeded, false to fall back to Op::And/Or
+    fn try_emit_short_circuit_jumps(&mut self, ops: &mut Vec<Op>, is_and: bool) -> bool {
+        let right = match ops.last() {
+            Some(Op::Push(Value::CompiledQuotation(right))) => right.clone(),
+            _ => return false,
+        };
+        ops.pop();
+
+        if let Some(Op::Push(Value::CompiledQuotation(left))) = ops.last() {
+            let left = left.clone();
+            ops.pop();
+            self.lowered_from = Some(ops.len());
+            ops.extend(left);
+        } else {
+            self.lowered_from = Some(ops.len());
+        }
+
+        // and:                          or:
+        //   JumpIfFalse(right_len + 3)    JumpIfFalse(3)
+        //   <right>                       Push(true)
+        //   CheckBool                     Jump(right_len + 2)
+        //   Jump(2)                       <right>
+        //   Push(false)                   CheckBool
+        let right_len = right.len() as i32;
+        if is_and {
+            ops.push(Op::JumpIfFalse(right_len + 3));
+            ops.extend(right);
+            ops.push(Op::CheckBool);
+            ops.push(Op::Jump(2));
+            ops.push(Op::Push(Value::Bool(false)));
+        } else {
+            ops.push(Op::JumpIfFalse(3));
+            ops.push(Op::Push(Value::Bool(true)));
+            ops.push(Op::Jump(right_len + 2));
+            ops.extend(right);
+            ops.push(Op::CheckBool);
+        }
+
+        true
+    }
+
     /// Emit jump-based times loop if a compiled quotation is on top of ops.
     /// Returns true if optimization was applied, false otherwise.
     ///
@@ -509,17 +918,18 @@ impl Compiler {
     /// ```text
     ///   Position   Instruction       Stack effect
     ///   --------   -----------       ------------
-    ///   0:         Dup               n → n n
-    ///   1:         Push(0)           n n → n n 0
-    ///   2:         Le                n n 0 → n (n≤0)
-    ///   3:         JumpIfTrue(exit)  n (n≤0) → n  [exit if counter ≤ 0]
-    ///   4:         ToAux             n → ε  [aux: n]
-    ///   5..5+B-1:  <body ops>        execute body, may push values
-    ///   5+B:       FromAux           → n  [aux: ε]
-    ///   6+B:       Push(1)           n → n 1
-    ///   7+B:       Sub               n 1 → n-1
-    ///   8+B:       Jump(back)        loop back to position 0
-    ///   9+B:       Drop              n → ε  [cleanup counter]
+    ///   0:         CheckTimes        n → n  [fail unless a count ≥ 0]
+    ///   1:         Dup               n → n n
+    ///   2:         Push(0)           n n → n n 0
+    ///   3:         Le                n n 0 → n (n≤0)
+    ///   4:         JumpIfTrue(exit)  n (n≤0) → n  [exit if counter ≤ 0]
+    ///   5:         ToAux             n → ε  [aux: n]
+    ///   6..6+B-1:  <body ops>        execute body, may push values
+    ///   6+B:       FromAux           → n  [aux: ε]
+    ///   7+B:       Push(1)           n → n 1
+    ///   8+B:       Sub               n 1 → n-1
+    ///   9+B:       Jump(back)        loop back to position 1
+    ///   10+B:      Drop              n → ε  [cleanup counter]
     /// ```
     /// Where B = body_ops.len()
     fn try_emit_times_jumps(&mut self, ops: &mut Vec<Op>) -> bool {
@@ -535,46 +945,87 @@ impl Compiler {
 
         // Remove the Push(CompiledQuotation) op
         ops.pop();
+        self.lowered_from = Some(ops.len());
 
         let body_len = body_ops.len() as i32;
 
         // Calculate positions (0-indexed from start of this loop construct):
-        // 0: Dup
-        // 1: Push(0)
-        // 2: Le
-        // 3: JumpIfTrue
-        // 4: ToAux
-        // 5 to 5+body_len-1: body
-        // 5+body_len: FromAux
-        // 6+body_len: Push(1)
-        // 7+body_len: Sub
-        // 8+body_len: Jump
-        // 9+body_len: Drop (exit target)
-
-        // JumpIfTrue at position 3 needs to reach Drop at position 9+body_len
+        // 0: CheckTimes
+        // 1: Dup
+        // 2: Push(0)
+        // 3: Le
+        // 4: JumpIfTrue
+        // 5: ToAux
+        // 6 to 6+body_len-1: body
+        // 6+body_len: FromAux
+        // 7+body_len: Push(1)
+        // 8+body_len: Sub
+        // 9+body_len: Jump
+        // 10+body_len: Drop (exit target)
+
+        // JumpIfTrue at position 4 needs to reach Drop at position 10+body_len
         // With `continue` in VM (offset applied directly, no ip+=1):
-        // target = current + offset → 9+body_len = 3 + offset → offset = 6+body_len
+        // target = current + offset → 10+body_len = 4 + offset → offset = 6+body_len
         let exit_offset = 6 + body_len;
 
-        // Jump at position 8+body_len needs to reach Dup at position 0
-        // target = current + offset → 0 = (8+body_len) + offset → offset = -(8+body_len)
+        // Jump at position 9+body_len needs to reach Dup at position 1
+        // target = current + offset → 1 = (9+body_len) + offset → offset = -(8+body_len)
         let jump_back = -(8 + body_len);
 
-        // Emit the loop structure
-        ops.push(Op::Dup); // 0
-        ops.push(Op::Push(Value::Integer(0))); // 1
-        ops.push(Op::Le); // 2
-        ops.push(Op::JumpIfTrue(exit_offset)); // 3
+        // Emit the loop structure; the counter is checked once, up front
+        ops.push(Op::CheckTimes); // 0
+        ops.push(Op::Dup); // 1
+        ops.push(Op::Push(Value::Integer(0))); // 2
+        ops.push(Op::Le); // 3
+        ops.push(Op::JumpIfTrue(exit_offset)); // 4
 
-        ops.push(Op::ToAux); // 4
-        ops.extend(body_ops); // 5 to 5+body_len-1
-        ops.push(Op::FromAux); // 5+body_len
+        ops.push(Op::ToAux); // 5
+        ops.extend(body_ops); // 6 to 6+body_len-1
+        ops.push(Op::FromAux); // 6+body_len
 
-        ops.push(Op::Push(Value::Integer(1))); // 6+body_len
-        ops.push(Op::Sub); // 7+body_len
-        ops.push(Op::Jump(jump_back)); // 8+body_len
+        ops.push(Op::Push(Value::Integer(1))); // 7+body_len
+        ops.push(Op::Sub); // 8+body_len
+        ops.push(Op::Jump(jump_back)); // 9+body_len
 
-        ops.push(Op::Drop); // 9+body_len
+        ops.push(Op::Drop); // 10+body_len
+
+        true
+    }
+
+    /// Emit a jump-based `while` (or `until`) loop if the last two ops push
+    /// compiled quotations. Returns true if optimization was applied.
+    ///
+    /// ```text
+    ///   0..C-1:    <cond ops>
+    ///   C:         JumpIfFalse(B + 2)    ; JumpIfTrue for until
+    ///   C+1..C+B:  <body ops>
+    ///   C+B+1:     Jump(-(C + B + 1))    ; back to the condition
+    /// ```
+    /// Where C = cond_ops.len() and B = body_ops.len()
+    fn try_emit_while_jumps(&mut self, ops: &mut Vec<Op>, until: bool) -> bool {
+        let [
+            ..,
+            Op::Push(Value::CompiledQuotation(cond_ops)),
+            Op::Push(Value::CompiledQuotation(body_ops)),
+        ] = ops.as_slice()
+        else {
+            return false;
+        };
+        let (cond_ops, body_ops) = (cond_ops.clone(), body_ops.clone());
+        ops.truncate(ops.len() - 2);
+        self.lowered_from = Some(ops.len());
+
+        let cond_len = cond_ops.len() as i32;
+        let body_len = body_ops.len() as i32;
+
+        ops.extend(cond_ops);
+        ops.push(if until {
+            Op::JumpIfTrue(body_len + 2)
+        } else {
+            Op::JumpIfFalse(body_len + 2)
+        });
+        ops.extend(body_ops);
+        ops.push(Op::Jump(-(cond_len + 1 + body_len)));
 
         true
     }
@@ -630,7 +1081,7 @@ impl Compiler {
         result.extend(cond_ops);
         result.push(Op::JumpIfFalse(body_len + 2));
         result.extend(body_ops);
-        result.push(Op::Jump(-(cond_len + 1 + body_len + 1)));
+        result.push(Op::Jump(-(cond_len + 1 + body_len)));
         Ok(result)
     }
 
@@ -650,25 +1101,97 @@ impl Compiler {
 
         let mut result = Vec::new();
 
-        result.push(Op::Dup); // 0
-        result.push(Op::Push(Value::Integer(0))); // 1
-        result.push(Op::Le); // 2
-        result.push(Op::JumpIfTrue(exit_offset)); // 3
+        result.push(Op::CheckTimes); // 0
+        result.push(Op::Dup); // 1
+        result.push(Op::Push(Value::Integer(0))); // 2
+        result.push(Op::Le); // 3
+        result.push(Op::JumpIfTrue(exit_offset)); // 4
 
-        result.push(Op::ToAux); // 4
-        result.extend(body_ops); // 5 to 5+body_len-1
-        result.push(Op::FromAux); // 5+body_len
+        result.push(Op::ToAux); // 5
+        result.extend(body_ops); // 6 to 6+body_len-1
+        result.push(Op::FromAux); // 6+body_len
 
-        result.push(Op::Push(Value::Integer(1))); // 6+body_len
-        result.push(Op::Sub); // 7+body_len
-        result.push(Op::Jump(jump_back)); // 8+body_len
+        result.push(Op::Push(Value::Integer(1))); // 7+body_len
+        result.push(Op::Sub); // 8+body_len
+        result.push(Op::Jump(jump_back)); // 9+body_len
 
-        result.push(Op::Drop); // 9+body_len
+        result.push(Op::Drop); // 10+body_len
 
         Ok(result)
     }
 }
 
+/// Make the quotations a word body pushes that read its locals capture
+/// them: `Op::Capture` fills the values in when the quotation is pushed, so
+/// it can run after the word returns, or inside another word with locals
+/// of its own. Quotations the compiler lowered to jumps run in the word's
+/// body and read the locals directly.
+fn capture_locals(ops: &mut [Op]) {
+    for op in ops.iter_mut() {
+        if let Op::Push(Value::CompiledQuotation(body)) = op
+            && reads_locals(body)
+        {
+            *op = Op::Capture(std::mem::take(body));
+        }
+    }
+}
+
+/// Whether `ops` or a quotation in them, at any depth, reads a local.
+fn reads_locals(ops: &[Op]) -> bool {
+    ops.iter().any(|op| match op {
+        Op::LoadLocal(_) => true,
+        Op::Push(Value::CompiledQuotation(body)) => reads_locals(body),
+        _ => false,
+    })
+}
+
+/// The body length of the `times` loop lowered by `try_emit_times_jumps`
+/// that starts at `i`, if one does. The decompiler uses it to recognize the
+/// loop.
+pub(crate) fn lowered_times_at(ops: &[Op], i: usize) -> Option<usize> {
+    let [
+        Op::CheckTimes,
+        Op::Dup,
+        Op::Push(Value::Integer(0)),
+        Op::Le,
+        Op::JumpIfTrue(exit),
+        Op::ToAux,
+        ..,
+    ] = ops.get(i..)?
+    else {
+        return None;
+    };
+    let body_len = usize::try_from(*exit).ok()?.checked_sub(6)?;
+    let body_end = i + 6 + body_len;
+    let tail = ops.get(body_end..body_end + 5)?;
+    let expected_back = -(8 + body_len as i32);
+    matches!(
+        tail,
+        [Op::FromAux, Op::Push(Value::Integer(1)), Op::Sub, Op::Jump(back), Op::Drop]
+            if *back == expected_back
+    )
+    .then_some(body_len)
+}
+
+/// The condition and body lengths of the `while` or `until` loop lowered
+/// by `try_emit_while_jumps` that starts at `i`, and whether it is an
+/// `until`. The decompiler uses it to recognize the loop.
+pub(crate) fn lowered_while_at(ops: &[Op], i: usize) -> Option<(usize, usize, bool)> {
+    ops.iter().enumerate().skip(i).find_map(|(j, op)| {
+        let (Op::JumpIfFalse(exit) | Op::JumpIfTrue(exit)) = op else {
+            return None;
+        };
+        // The exit lands just past the jump back to the condition
+        let back = j + usize::try_from(*exit).ok()?.checked_sub(1)?;
+        match ops.get(back)? {
+            Op::Jump(offset) if back > j && back as i64 + *offset as i64 == i as i64 => {
+                Some((j - i, back - j - 1, matches!(op, Op::JumpIfTrue(_))))
+            }
+            _ => None,
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -746,6 +1269,8 @@ mod tests {
         let nodes = vec![Node::Def {
             name: "foo".to_string(),
             body: vec![],
+            line: 1,
+            spans: Vec::new(),
         }];
 
         let result = Compiler::new().compile_nodes(&nodes);
@@ -812,7 +1337,7 @@ mod tests {
         assert!(matches!(ops[3], Op::JumpIfFalse(4)));
         assert!(matches!(ops[4], Op::Push(Value::Integer(1))));
         assert!(matches!(ops[5], Op::Sub));
-        assert!(matches!(ops[6], Op::Jump(-7)));
+        assert!(matches!(ops[6], Op::Jump(-6)));
     }
 
     #[test]
@@ -821,7 +1346,8 @@ mod tests {
         let ops = Compiler::new().compile_times_jumps(&body).unwrap();
 
         assert!(ops.len() > 4);
-        assert!(matches!(ops[0], Op::Dup));
+        assert!(matches!(ops[0], Op::CheckTimes));
+        assert!(matches!(ops[1], Op::Dup));
         assert!(matches!(ops.last(), Some(Op::Drop)));
     }
 
@@ -913,6 +1439,35 @@ mod tests {
         assert!(matches!(ops[0], Op::Times));
     }
 
+    #[test]
+    fn test_while_and_until_optimize_to_jumps() {
+        // [ dup ] [ 1 ] while / until
+        for (node, exit) in [
+            (Node::While, Op::JumpIfFalse(3)),
+            (Node::Until, Op::JumpIfTrue(3)),
+        ] {
+            let nodes = vec![
+                Node::Literal(Value::Quotation(vec![Node::Dup])),
+                Node::Literal(Value::Quotation(vec![Node::Literal(Value::Integer(1))])),
+                node,
+            ];
+            let ops = Compiler::new().compile_nodes(&nodes).unwrap();
+            assert_eq!(
+                ops,
+                vec![Op::Dup, exit, Op::Push(Value::Integer(1)), Op::Jump(-3)]
+            );
+            assert_eq!(
+                lowered_while_at(&ops, 0).map(|(c, b, _)| (c, b)),
+                Some((1, 1))
+            );
+        }
+
+        let ops = Compiler::new()
+            .compile_nodes(&[Node::Literal(Value::Quotation(vec![])), Node::While])
+            .unwrap();
+        assert!(matches!(ops.last(), Some(Op::While)));
+    }
+
     #[test]
     fn test_nested_if_optimizes() {
         // true [ false [ 1 ] [ 2 ] if ] [ 3 ] if
@@ -942,6 +1497,199 @@ mod tests {
             .count();
         assert!(jump_count >= 1); // At least outer; inner is in compiled quotation
     }
+
+    #[test]
+    fn test_compile_from_file_reports_timings() {
+        // 10_modules.em imports a module, so every phase runs
+        let (program, timings) = Compiler::new()
+            .compile_from_file_with_timings(Path::new("examples/10_modules.em"))
+            .unwrap();
+        assert!(!program.words.is_empty());
+        assert_eq!(
+            timings.total(),
+            timings.lexing
+                + timings.parsing
+                + timings.loading
+                + timings.compiling
+                + timings.optimizing
+        );
+    }
+
+    #[test]
+    fn test_resolve_import_accepts_backslashes() {
+        let expected = Path::new("proj").join("lib").join("utils.em");
+        assert_eq!(resolve_import(Path::new("proj"), "lib\\utils.em"), expected);
+        assert_eq!(resolve_import(Path::new("proj"), "lib/utils.em"), expected);
+        assert_eq!(
+            resolve_import(Path::new("proj"), "..\\shared\\x.em"),
+            Path::new("proj").join("..").join("shared").join("x.em")
+        );
+    }
+
+    #[test]
+    fn test_include_key_folds_case_only_when_asked() {
+        let a = Path::new("/Proj/Lib/Utils.em");
+        let b = Path::new("/proj/lib/utils.em");
+        assert_eq!(include_key(a, true), include_key(b, true));
+        assert_ne!(include_key(a, false), include_key(b, false));
+    }
+
+    #[test]
+    fn test_windows_style_project() {
+        // CRLF line endings and a backslash import, as written on Windows
+        let dir = std::env::temp_dir().join(format!("ember-windows-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(
+            dir.join("lib").join("util.em"),
+            "def twice\r\n  2 *\r\nend\r\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.em"),
+            "import \"lib\\\\util.em\"\r\nimport \"lib/util.em\"\r\n21 twice\r\n",
+        )
+        .unwrap();
+
+        let program = Compiler::new()
+            .compile_from_file(&dir.join("main.em"))
+            .unwrap();
+        assert!(program.words.contains_key("twice"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_word_sites() {
+        let dir = std::env::temp_dir().join(format!("ember-sites-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("lib.em"), "def greet 1 end\n").unwrap();
+        std::fs::write(
+            dir.join("main.em"),
+            "import \"lib.em\"\n\ndef greet 2 end\nmodule m\n  def inner 3 end\n",
+        )
+        .unwrap();
+
+        let program = Compiler::new()
+            .compile_from_file(&dir.join("main.em"))
+            .unwrap();
+        let file = |name: &str| Some(dir.canonicalize().unwrap().join(name).display().to_string());
+        let greet = &program.sites["greet"];
+        assert_eq!((&greet.file, greet.line), (&file("main.em"), 3));
+        assert_eq!(greet.replaces.len(), 1);
+        assert_eq!(
+            (&greet.replaces[0].file, greet.replaces[0].line),
+            (&file("lib.em"), 1)
+        );
+        assert_eq!(program.sites["m.inner"].line, 5);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_non_utf8_source() {
+        let path = std::env::temp_dir().join(format!("ember-latin1-{}.em", std::process::id()));
+        std::fs::write(&path, b"\"caf\xe9\" print").unwrap();
+
+        let err = Compiler::new().compile_from_file(&path).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid UTF-8 at byte 4"),
+            "{}",
+            err
+        );
+        let program = Compiler::new()
+            .with_lossy_utf8(true)
+            .compile_from_file(&path)
+            .unwrap();
+        assert!(matches!(
+            &program.code[0].ops[0],
+            Op::Push(Value::String(s)) if s == "caf\u{fffd}"
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // =========================================================================
+    // Compile limits
+    // =========================================================================
+
+    fn nested_quotation(depth: usize) -> Vec<Node> {
+        let mut nodes = vec![Node::Literal(Value::Integer(1))];
+        for _ in 0..depth {
+            nodes = vec![Node::Literal(Value::Quotation(nodes))];
+        }
+        nodes
+    }
+
+    fn limits(max_nesting: usize, max_ops: usize, max_words: usize) -> CompileLimits {
+        CompileLimits {
+            max_nesting,
+            max_ops,
+            max_words,
+        }
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nodes = nested_quotation(5);
+        assert!(
+            Compiler::with_limits(limits(5, 100, 100))
+                .compile_nodes(&nodes)
+                .is_ok()
+        );
+
+        let err = Compiler::with_limits(limits(4, 100, 100))
+            .compile_nodes(&nodes)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("quotation nesting exceeds the limit of 4")
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting_does_not_recurse() {
+        // Far deeper than recursive compilation could survive on a test thread
+        let nodes = nested_quotation(100_000);
+        let ops = Compiler::with_limits(limits(usize::MAX, usize::MAX, 100))
+            .compile_nodes(&nodes)
+            .unwrap();
+        assert_eq!(ops.len(), 1);
+        // Dropping a value this deep would itself recurse
+        std::mem::forget(ops);
+        std::mem::forget(nodes);
+    }
+
+    #[test]
+    fn test_op_limit() {
+        let nodes = vec![Node::Dup; 11];
+        let err = Compiler::with_limits(limits(10, 10, 10))
+            .compile_nodes(&nodes)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("total number of ops exceeds the limit of 10")
+        );
+    }
+
+    #[test]
+    fn test_word_limit() {
+        let source = "def a 1 end def b 2 end def c 3 end";
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+
+        let err = Compiler::with_limits(limits(10, 100, 2))
+            .compile_program(&program)
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("number of word definitions exceeds the limit of 2")
+        );
+        assert!(
+            Compiler::with_limits(limits(10, 100, 3))
+                .compile_program(&program)
+                .is_ok()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -954,27 +1702,28 @@ mod times_tests {
         let ops = Compiler::new().compile_times_jumps(&body).unwrap();
 
         // compile_times_jumps doesn't include the initial Push for counter
-        // It starts from Dup
-        assert!(matches!(ops[0], Op::Dup), "should start with Dup");
+        // It starts by checking the counter
+        assert!(matches!(ops[0], Op::CheckTimes), "should check the counter");
+        assert!(matches!(ops[1], Op::Dup), "loop should start with Dup");
         assert!(
-            matches!(ops[1], Op::Push(Value::Integer(0))),
+            matches!(ops[2], Op::Push(Value::Integer(0))),
             "should push threshold 0"
         );
-        assert!(matches!(ops[2], Op::Le), "should compare with Le");
+        assert!(matches!(ops[3], Op::Le), "should compare with Le");
         assert!(
-            matches!(ops[3], Op::JumpIfTrue(_)),
+            matches!(ops[4], Op::JumpIfTrue(_)),
             "should conditionally exit"
         );
-        assert!(matches!(ops[4], Op::ToAux), "should hide counter");
+        assert!(matches!(ops[5], Op::ToAux), "should hide counter");
         assert!(
-            matches!(ops[5], Op::Push(Value::Integer(42))),
+            matches!(ops[6], Op::Push(Value::Integer(42))),
             "body should be compiled"
         );
-        assert!(matches!(ops[6], Op::FromAux), "should restore counter");
-        assert!(matches!(ops[7], Op::Push(Value::Integer(1))));
-        assert!(matches!(ops[8], Op::Sub));
-        assert!(matches!(ops[9], Op::Jump(_)), "should jump back");
-        assert!(matches!(ops[10], Op::Drop), "should end with Drop");
+        assert!(matches!(ops[7], Op::FromAux), "should restore counter");
+        assert!(matches!(ops[8], Op::Push(Value::Integer(1))));
+        assert!(matches!(ops[9], Op::Sub));
+        assert!(matches!(ops[10], Op::Jump(_)), "should jump back");
+        assert!(matches!(ops[11], Op::Drop), "should end with Drop");
     }
 
     #[test]
@@ -983,12 +1732,12 @@ mod times_tests {
         let body = vec![Node::Dup, Node::Swap, Node::Drop];
         let ops = Compiler::new().compile_times_jumps(&body).unwrap();
 
-        // Body starts after ToAux (position 4)
-        assert!(matches!(ops[4], Op::ToAux));
-        assert!(matches!(ops[5], Op::Dup));
-        assert!(matches!(ops[6], Op::Swap));
-        assert!(matches!(ops[7], Op::Drop));
-        assert!(matches!(ops[8], Op::FromAux));
+        // Body starts after ToAux (position 5)
+        assert!(matches!(ops[5], Op::ToAux));
+        assert!(matches!(ops[6], Op::Dup));
+        assert!(matches!(ops[7], Op::Swap));
+        assert!(matches!(ops[8], Op::Drop));
+        assert!(matches!(ops[9], Op::FromAux));
     }
 
     #[test]
@@ -1011,7 +1760,7 @@ mod times_tests {
         if let Op::Jump(offset) = ops[ops.len() - 2] {
             let jump_pos = (ops.len() - 2) as i32;
             let target = jump_pos + offset;
-            assert_eq!(target, 0, "should jump back to start of loop");
+            assert_eq!(target, 1, "should jump back to start of loop");
         }
     }
 
@@ -1024,8 +1773,8 @@ mod times_tests {
 
             let ops = Compiler::new().compile_times_jumps(&body).unwrap();
 
-            if let Op::JumpIfTrue(forward) = ops[3] {
-                let target = 3 + forward;
+            if let Op::JumpIfTrue(forward) = ops[4] {
+                let target = 4 + forward;
                 assert_eq!(
                     target as usize,
                     ops.len() - 1,
@@ -1038,7 +1787,7 @@ mod times_tests {
             if let Op::Jump(backward) = &ops[back_jump_pos] {
                 let target = back_jump_pos as i32 + backward;
                 assert_eq!(
-                    target, 0,
+                    target, 1,
                     "backward jump should land on Dup for body_size={}",
                     body_size
                 );
@@ -1052,19 +1801,19 @@ mod times_tests {
         let ops = Compiler::new().compile_times_jumps(&body).unwrap();
 
         println!("{:?}", ops);
-        // [Dup, Push(Integer(0)), Le, JumpIfTrue(9), ToAux, Dup, Swap, Drop, FromAux, Push(Integer(1)), Sub, Jump(-11), Drop]
+        // [CheckTimes, Dup, Push(Integer(0)), Le, JumpIfTrue(9), ToAux, Dup, Swap, Drop, FromAux, Push(Integer(1)), Sub, Jump(-11), Drop]
 
-        assert!(matches!(ops[4], Op::ToAux));
-        assert!(matches!(ops[5], Op::Dup));
-        assert!(matches!(ops[6], Op::Swap));
+        assert!(matches!(ops[5], Op::ToAux));
+        assert!(matches!(ops[6], Op::Dup));
+        assert!(matches!(ops[7], Op::Swap));
     }
 
     fn extract_push_threshold(ops: &[Op]) -> i64 {
-        // Threshold is at position 1 (after Dup)
-        if let Op::Push(Value::Integer(n)) = &ops[1] {
+        // Threshold is at position 2 (after CheckTimes and Dup)
+        if let Op::Push(Value::Integer(n)) = &ops[2] {
             *n
         } else {
-            panic!("expected Push(Integer) at position 1, got {:?}", ops[1]);
+            panic!("expected Push(Integer) at position 2, got {:?}", ops[2]);
         }
     }
 }
@@ -1191,8 +1940,8 @@ mod jump_optimization_tests {
     }
 
     #[test]
-    fn test_if_no_optimization_non_quotation_values() {
-        // 10 20 if  -- integers, not quotations
+    fn test_if_scalar_branches_are_wrapped() {
+        // 10 20 if  -- integers select a value
         let nodes = vec![
             Node::Literal(Value::Integer(10)),
             Node::Literal(Value::Integer(20)),
@@ -1201,12 +1950,14 @@ mod jump_optimization_tests {
 
         let ops = Compiler::new().compile_nodes(&nodes).unwrap();
 
-        // Should fall back to Op::If
-        assert!(matches!(ops[2], Op::If));
+        assert!(matches!(ops[0], Op::JumpIfFalse(3)));
+        assert!(matches!(ops[1], Op::Push(Value::Integer(10))));
+        assert!(matches!(ops[2], Op::Jump(2)));
+        assert!(matches!(ops[3], Op::Push(Value::Integer(20))));
     }
 
     #[test]
-    fn test_if_no_optimization_mixed_values() {
+    fn test_if_mixed_branches_are_wrapped() {
         // [ 10 ] 20 if  -- one quotation, one integer
         let nodes = vec![
             Node::Literal(Value::Quotation(vec![Node::Literal(Value::Integer(10))])),
@@ -1216,7 +1967,61 @@ mod jump_optimization_tests {
 
         let ops = Compiler::new().compile_nodes(&nodes).unwrap();
 
-        assert!(ops.iter().any(|op| matches!(op, Op::If)));
+        assert!(!ops.iter().any(|op| matches!(op, Op::If)));
+        assert!(matches!(ops[1], Op::Push(Value::Integer(10))));
+        assert!(matches!(ops[3], Op::Push(Value::Integer(20))));
+    }
+
+    #[test]
+    fn test_and_with_quotations_short_circuits() {
+        // [ true ] [ false ] and
+        let nodes = vec![
+            Node::Literal(Value::Quotation(vec![Node::Literal(Value::Bool(true))])),
+            Node::Literal(Value::Quotation(vec![Node::Literal(Value::Bool(false))])),
+            Node::And,
+        ];
+
+        let ops = Compiler::new().compile_nodes(&nodes).unwrap();
+
+        assert!(matches!(ops[0], Op::Push(Value::Bool(true))));
+        assert!(matches!(ops[1], Op::JumpIfFalse(4)));
+        assert!(matches!(ops[2], Op::Push(Value::Bool(false))));
+        assert!(matches!(ops[3], Op::CheckBool));
+        assert!(matches!(ops[4], Op::Jump(2)));
+        assert!(matches!(ops[5], Op::Push(Value::Bool(false))));
+        assert_eq!(ops.len(), 6);
+    }
+
+    #[test]
+    fn test_or_with_bool_and_quotation_short_circuits() {
+        // true [ false ] or
+        let nodes = vec![
+            Node::Literal(Value::Bool(true)),
+            Node::Literal(Value::Quotation(vec![Node::Literal(Value::Bool(false))])),
+            Node::Or,
+        ];
+
+        let ops = Compiler::new().compile_nodes(&nodes).unwrap();
+
+        assert!(matches!(ops[1], Op::JumpIfFalse(3)));
+        assert!(matches!(ops[2], Op::Push(Value::Bool(true))));
+        assert!(matches!(ops[3], Op::Jump(3)));
+        assert!(matches!(ops[4], Op::Push(Value::Bool(false))));
+        assert!(matches!(ops[5], Op::CheckBool));
+        assert!(!ops.iter().any(|op| matches!(op, Op::Or)));
+    }
+
+    #[test]
+    fn test_and_with_bools_stays_strict() {
+        let nodes = vec![
+            Node::Literal(Value::Bool(true)),
+            Node::Literal(Value::Bool(false)),
+            Node::And,
+        ];
+
+        let ops = Compiler::new().compile_nodes(&nodes).unwrap();
+
+        assert!(matches!(ops[2], Op::And));
     }
 
     // =========================================================================
@@ -1315,33 +2120,35 @@ mod jump_optimization_tests {
 
         // New structure with ToAux/FromAux:
         // 0: Push(5)         - counter
-        // 1: Dup             - check
-        // 2: Push(0)         - check
-        // 3: Le              - check
-        // 4: JumpIfTrue(...) - exit if counter <= 0
-        // 5: ToAux           - hide counter
-        // 6: Push(1)         - body
-        // 7: FromAux         - restore counter
-        // 8: Push(1)         - decrement
-        // 9: Sub             - decrement
-        // 10: Jump(...)      - loop back
-        // 11: Drop           - cleanup
+        // 1: CheckTimes      - counter is a count
+        // 2: Dup             - check
+        // 3: Push(0)         - check
+        // 4: Le              - check
+        // 5: JumpIfTrue(...) - exit if counter <= 0
+        // 6: ToAux           - hide counter
+        // 7: Push(1)         - body
+        // 8: FromAux         - restore counter
+        // 9: Push(1)         - decrement
+        // 10: Sub            - decrement
+        // 11: Jump(...)      - loop back
+        // 12: Drop           - cleanup
 
         assert!(matches!(ops[0], Op::Push(Value::Integer(5))));
-        assert!(matches!(ops[1], Op::Dup));
-        assert!(matches!(ops[2], Op::Push(Value::Integer(0))));
-        assert!(matches!(ops[3], Op::Le));
-        assert!(matches!(ops[4], Op::JumpIfTrue(_)));
-        assert!(matches!(ops[5], Op::ToAux));
+        assert!(matches!(ops[1], Op::CheckTimes));
+        assert!(matches!(ops[2], Op::Dup));
+        assert!(matches!(ops[3], Op::Push(Value::Integer(0))));
+        assert!(matches!(ops[4], Op::Le));
+        assert!(matches!(ops[5], Op::JumpIfTrue(_)));
+        assert!(matches!(ops[6], Op::ToAux));
         // Body
-        assert!(matches!(ops[6], Op::Push(Value::Integer(1))));
+        assert!(matches!(ops[7], Op::Push(Value::Integer(1))));
         // Restore counter
-        assert!(matches!(ops[7], Op::FromAux));
+        assert!(matches!(ops[8], Op::FromAux));
         // Decrement
-        assert!(matches!(ops[8], Op::Push(Value::Integer(1))));
-        assert!(matches!(ops[9], Op::Sub));
-        assert!(matches!(ops[10], Op::Jump(_)));
-        assert!(matches!(ops[11], Op::Drop));
+        assert!(matches!(ops[9], Op::Push(Value::Integer(1))));
+        assert!(matches!(ops[10], Op::Sub));
+        assert!(matches!(ops[11], Op::Jump(_)));
+        assert!(matches!(ops[12], Op::Drop));
     }
 
     #[test]
@@ -1356,9 +2163,10 @@ mod jump_optimization_tests {
         let ops = Compiler::new().compile_nodes(&nodes).unwrap();
 
         // Should still compile with empty body
-        // Structure: Push(5), Dup, Push(0), Le, JumpIfTrue, ToAux, FromAux, Push(1), Sub, Jump, Drop
+        // Structure: Push(5), CheckTimes, Dup, Push(0), Le, JumpIfTrue, ToAux, FromAux, Push(1), Sub, Jump, Drop
         assert!(matches!(ops[0], Op::Push(Value::Integer(5))));
-        assert!(matches!(ops[1], Op::Dup));
+        assert!(matches!(ops[1], Op::CheckTimes));
+        assert!(matches!(ops[2], Op::Dup));
         assert!(matches!(ops.last(), Some(Op::Drop)));
 
         // Verify ToAux and FromAux are present
@@ -1389,21 +2197,22 @@ mod jump_optimization_tests {
 
         // Structure:
         // 0: Push(3)
-        // 1: Dup
-        // 2: Push(0)
-        // 3: Le
-        // 4: JumpIfTrue(8) -> position 12 (Drop)
-        // 5: ToAux
-        // 6: Dup           (body)
-        // 7: Drop          (body)
-        // 8: FromAux
-        // 9: Push(1)
-        // 10: Sub
-        // 11: Jump(-10) -> position 1 (Dup)
-        // 12: Drop
+        // 1: CheckTimes
+        // 2: Dup
+        // 3: Push(0)
+        // 4: Le
+        // 5: JumpIfTrue(8) -> position 13 (Drop)
+        // 6: ToAux
+        // 7: Dup           (body)
+        // 8: Drop          (body)
+        // 9: FromAux
+        // 10: Push(1)
+        // 11: Sub
+        // 12: Jump(-10) -> position 2 (Dup)
+        // 13: Drop
 
         // Find the JumpIfTrue and verify it targets the final Drop
-        let exit_jump_pos = 4;
+        let exit_jump_pos = 5;
         if let Op::JumpIfTrue(offset) = ops[exit_jump_pos] {
             let target = exit_jump_pos as i32 + offset;
             assert_eq!(
@@ -1412,14 +2221,14 @@ mod jump_optimization_tests {
                 "exit jump should target final Drop"
             );
         } else {
-            panic!("expected JumpIfTrue at position 4");
+            panic!("expected JumpIfTrue at position 5");
         }
 
-        // Find the backward Jump and verify it targets position 1 (Dup)
+        // Find the backward Jump and verify it targets position 2 (Dup)
         let back_jump_pos = ops.len() - 2; // Second to last
         if let Op::Jump(offset) = ops[back_jump_pos] {
             let target = back_jump_pos as i32 + offset;
-            assert_eq!(target, 1, "backward jump should target Dup at position 1");
+            assert_eq!(target, 2, "backward jump should target Dup at position 2");
         } else {
             panic!("expected Jump at second-to-last position");
         }
@@ -1639,6 +2448,56 @@ mod jump_optimization_tests {
         assert!(ops.iter().any(|op| matches!(op, Op::Map)));
     }
 
+    #[test]
+    fn test_variables_must_be_declared() {
+        let compile = |source: &str| {
+            let tokens = Lexer::new(source).tokenize().unwrap();
+            let program = Parser::new(tokens).parse().unwrap();
+            Compiler::new().compile_program(&program)
+        };
+        let program = compile("var n 5 n ! n @").unwrap();
+        assert_eq!(
+            program.code[0].ops[1..3],
+            [Op::Store("n".into()), Op::Load("n".into())]
+        );
+
+        let err = compile("5 n !").unwrap_err().to_string();
+        assert!(err.contains("variable 'n' is not declared"), "{}", err);
+        let err = compile("var n n").unwrap_err().to_string();
+        assert!(err.contains("read it with `n @`"), "{}", err);
+    }
+
+    #[test]
+    fn test_locals() {
+        let compile = |source: &str| {
+            let tokens = Lexer::new(source).tokenize().unwrap();
+            let program = Parser::new(tokens).parse().unwrap();
+            Compiler::new().compile_program(&program)
+        };
+        let program = compile("def area :> w :> h w h * end").unwrap();
+        assert_eq!(
+            program.words["area"],
+            [
+                Op::StoreLocal(0),
+                Op::StoreLocal(1),
+                Op::LoadLocal(0),
+                Op::LoadLocal(1),
+                Op::Mul,
+                Op::Return
+            ]
+        );
+        let program = compile("def scale-by :> k [ k * ] map end").unwrap();
+        assert_eq!(
+            program.words["scale-by"][1],
+            Op::Capture(vec![Op::LoadLocal(0), Op::Mul])
+        );
+
+        let err = compile("5 :> x").unwrap_err().to_string();
+        assert!(err.contains("only be bound inside a word"), "{}", err);
+        let err = compile("def f [ :> x ] call end").unwrap_err().to_string();
+        assert!(err.contains("not inside a quotation"), "{}", err);
+    }
+
     // =========================================================================
     // Quotation with control flow inside
     // =========================================================================
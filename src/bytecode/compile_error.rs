@@ -1,4 +1,4 @@
-use crate::lang::{node::Node, value::Value};
+use crate::lang::{builtins, node::Node, value::Value};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -15,6 +15,10 @@ pub enum CompileError {
         reason: String,
         hint: Option<String>,
     },
+    /// `name !` or `name @` without a `var name` declaration
+    UndeclaredVariable(String),
+    /// The program is larger than the compiler's configured limits allow
+    LimitExceeded { what: String, limit: usize },
     /// Internal compiler error (shouldn't happen in normal use)
     Internal(String),
 }
@@ -84,6 +88,51 @@ impl CompileError {
         }
     }
 
+    /// Create an error for a variable used as a word
+    pub fn variable_as_word(name: &str) -> Self {
+        CompileError::InvalidPosition {
+            node_type: "variable".to_string(),
+            name: Some(name.to_string()),
+            reason: "variables cannot be called like words".to_string(),
+            hint: Some(format!(
+                "read it with `{} @`, or set it with `{} !`",
+                name, name
+            )),
+        }
+    }
+
+    /// Create an error for a local bound outside a word definition
+    pub fn local_outside_word(name: &str) -> Self {
+        CompileError::InvalidPosition {
+            node_type: "local".to_string(),
+            name: Some(name.to_string()),
+            reason: "locals can only be bound inside a word definition".to_string(),
+            hint: Some(format!(
+                "use a variable outside words: `var {}` and `{} !`",
+                name, name
+            )),
+        }
+    }
+
+    /// Create an error for a local bound inside a quotation
+    pub fn local_in_quotation(name: &str) -> Self {
+        CompileError::InvalidPosition {
+            node_type: "local".to_string(),
+            name: Some(name.to_string()),
+            reason: "locals must be bound in the word's own body, not inside a quotation"
+                .to_string(),
+            hint: Some("bind it before the quotation; the quotation can still read it".to_string()),
+        }
+    }
+
+    /// Create an error for a program exceeding one of the `CompileLimits`
+    pub fn limit_exceeded(what: impl Into<String>, limit: usize) -> Self {
+        CompileError::LimitExceeded {
+            what: what.into(),
+            limit,
+        }
+    }
+
     /// Create an internal compiler error
     #[allow(dead_code)]
     pub fn internal(msg: impl Into<String>) -> Self {
@@ -122,6 +171,22 @@ impl std::fmt::Display for CompileError {
                 }
                 Ok(())
             }
+            CompileError::UndeclaredVariable(name) => {
+                write!(
+                    f,
+                    "compile error: variable '{}' is not declared\n  hint: declare it at the top \
+                     level with `var {}`",
+                    name, name
+                )
+            }
+            CompileError::LimitExceeded { what, limit } => {
+                write!(
+                    f,
+                    "compile error: {} exceeds the limit of {}\n  hint: split the program up, \
+                     or raise the limit if it is legitimately this large",
+                    what, limit
+                )
+            }
             CompileError::Internal(msg) => {
                 write!(f, "compile error: internal error: {}", msg)
             }
@@ -140,85 +205,22 @@ fn node_type_name(node: &Node) -> &'static str {
             Value::String(_) => "string literal",
             Value::Bool(_) => "bool literal",
             Value::List(_) => "list literal",
+            Value::Bytes(_) => "bytes literal",
+            Value::Map(_) => "map literal",
             Value::Quotation(_) => "quotation",
             Value::CompiledQuotation(_) => "compiled quotation",
         },
-        Node::Dup => "dup",
-        Node::Drop => "drop",
-        Node::Swap => "swap",
-        Node::Over => "over",
-        Node::Rot => "rot",
-        Node::Add => "+",
-        Node::Sub => "-",
-        Node::Mul => "*",
-        Node::Div => "/",
-        Node::Mod => "%",
-        Node::Neg => "neg",
-        Node::Abs => "abs",
-        Node::Eq => "=",
-        Node::NotEq => "!=",
-        Node::Lt => "<",
-        Node::Gt => ">",
-        Node::LtEq => "<=",
-        Node::GtEq => ">=",
-        Node::And => "and",
-        Node::Or => "or",
-        Node::Not => "not",
-        Node::If => "if",
-        Node::When => "when",
-        Node::Call => "call",
-        Node::Times => "times",
-        Node::Each => "each",
-        Node::Map => "map",
-        Node::Filter => "filter",
-        Node::Fold => "fold",
-        Node::Range => "range",
-        Node::Len => "len",
-        Node::Head => "head",
-        Node::Tail => "tail",
-        Node::Cons => "cons",
-        Node::Concat => "concat",
-        Node::StringConcat => "++",
-        Node::Print => "print",
-        Node::Emit => "emit",
-        Node::Read => "read",
-        Node::Debug => "debug",
-        Node::Min => "min",
-        Node::Max => "max",
-        Node::Pow => "pow",
-        Node::Sqrt => "sqrt",
-        Node::Nth => "nth",
-        Node::Append => "append",
-        Node::Sort => "sort",
-        Node::Reverse => "reverse",
-        Node::Chars => "chars",
-        Node::Join => "join",
-        Node::Split => "split",
-        Node::Upper => "upper",
-        Node::Lower => "lower",
-        Node::Trim => "trim",
-        Node::Clear => "clear",
-        Node::Depth => "depth",
-        Node::Type => "type",
-        Node::ToString => "to-string",
-        Node::ToInt => "to-int",
-        Node::Dip => "dip",
-        Node::Keep => "keep",
-        Node::Bi => "bi",
-        Node::Bi2 => "bi2",
-        Node::Tri => "tri",
-        Node::Both => "both",
-        Node::Compose => "compose",
-        Node::Curry => "curry",
-        Node::Apply => "apply",
         Node::Def { .. } => "def",
         Node::Module { .. } => "module",
         Node::Word(_) => "word",
         Node::QualifiedWord { .. } => "qualified word",
         Node::Use { .. } => "use",
         Node::Import(_) => "import",
-        #[allow(unreachable_patterns)]
-        _ => "unknown",
+        Node::Var(_) => "var",
+        Node::Store(_) => "variable store",
+        Node::Load(_) => "variable load",
+        Node::BindLocal(_) => "local binding",
+        word => builtins::node_name(word).unwrap_or("unknown"),
     }
 }
 
@@ -297,6 +299,15 @@ mod tests {
         assert!(msg.contains("something went wrong"));
     }
 
+    #[test]
+    fn test_limit_exceeded_display() {
+        let err = CompileError::limit_exceeded("quotation nesting", 256);
+
+        let msg = err.to_string();
+        assert!(msg.contains("quotation nesting exceeds the limit of 256"));
+        assert!(msg.contains("hint"));
+    }
+
     #[test]
     fn test_new_creates_internal_error() {
         let err = CompileError::new("legacy error");
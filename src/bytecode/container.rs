@@ -0,0 +1,367 @@
+use crate::bytecode::{CodeObject, Op, ProgramBc, WordSite, legacy};
+use crate::runtime::hash_words::crc32;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, forward_to_deserialize_any};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// The first bytes of every `.ebc` file.
+pub const MAGIC: &[u8; 4] = b"EMBC";
+
+/// Layout of the container. Bump it when the header changes or when the
+/// encoding of `ProgramBc` changes in a way older builds can't read.
+///
+/// 2: code objects and word sites carry op spans.
+/// 3: the header names the op set the payload was numbered with.
+pub const FORMAT_VERSION: u16 = 3;
+
+/// Version of the ember build that wrote a file, kept for error messages.
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug)]
+pub struct ContainerError {
+    pub message: String,
+}
+
+impl std::fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ContainerError {}
+
+impl ContainerError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+/// What precedes the encoded program in a `.ebc` file:
+///
+/// ```text
+/// "EMBC"  format version (u16 LE)  compiler version length (u8)
+/// compiler version (UTF-8)  op set (u32 LE, from version 3)
+/// CRC-32 of the payload (u32 LE)  payload
+/// ```
+///
+/// The payload is the postcard encoding of the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub format_version: u16,
+    pub compiler_version: String,
+    /// `op_set()` of the build that wrote the file; None before version 3.
+    pub op_set: Option<u32>,
+    pub checksum: u32,
+}
+
+/// A hash of the names of `Op`'s variants in the order postcard numbers
+/// them, so a file whose ops are numbered differently from this build's,
+/// say by a fork that inserted one, can be told apart.
+pub fn op_set() -> u32 {
+    static OP_SET: OnceLock<u32> = OnceLock::new();
+    *OP_SET.get_or_init(|| crc32(op_names().join(" ").as_bytes()))
+}
+
+/// The names of `Op`'s variants, in order. The derived `Deserialize` hands
+/// them to `deserialize_enum`, which is all `Variants` implements.
+fn op_names() -> &'static [&'static str] {
+    struct Variants(&'static [&'static str]);
+
+    impl<'de> Deserializer<'de> for &mut Variants {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("only an enum's variants can be read"))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0 = variants;
+            Err(de::Error::custom("read the variants"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    let mut variants = Variants(&[]);
+    let _ = Op::deserialize(&mut variants);
+    variants.0
+}
+
+/// Encode a program with a header for the current build.
+pub fn encode(program: &ProgramBc) -> Result<Vec<u8>, ContainerError> {
+    let payload = postcard::to_allocvec(program)
+        .map_err(|e| ContainerError::new(format!("serialization failed: {}", e)))?;
+    let version = COMPILER_VERSION.as_bytes();
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 11 + version.len() + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(version.len() as u8);
+    bytes.extend_from_slice(version);
+    bytes.extend_from_slice(&op_set().to_le_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    Ok(bytes)
+}
+
+/// Decode the contents of a `.ebc` file. Files written before the container
+/// existed are bare postcard and still load, with no header (see
+/// `legacy`). The program is not verified here.
+pub fn decode(bytes: &[u8]) -> Result<(Option<Header>, ProgramBc), ContainerError> {
+    let Some(rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        let program = legacy::decode(bytes).map_err(|e| {
+            ContainerError::new(format!(
+                "not an ember bytecode file (no EMBC header, and not an older unversioned one: {})",
+                e
+            ))
+        })?;
+        return Ok((None, program));
+    };
+
+    let (header, payload) = read_header(rest)?;
+    if !(1..=FORMAT_VERSION).contains(&header.format_version) {
+        return Err(ContainerError::new(format!(
+            "bytecode format version {} (written by ember {}) is not supported; \
+             this build reads version {}. Recompile the source with --save-bc",
+            header.format_version, header.compiler_version, FORMAT_VERSION
+        )));
+    }
+    if crc32(payload) != header.checksum {
+        return Err(ContainerError::new(
+            "checksum mismatch: the file is corrupt or truncated",
+        ));
+    }
+    let deserialization_failed =
+        |e: postcard::Error| ContainerError::new(format!("deserialization failed: {}", e));
+    let program = if header.format_version == 1 {
+        postcard::from_bytes::<ProgramBcV1>(payload)
+            .map_err(deserialization_failed)?
+            .into()
+    } else {
+        postcard::from_bytes(payload).map_err(deserialization_failed)?
+    };
+    Ok((Some(header), program))
+}
+
+/// `ProgramBc` as version 1 files encode it, before op spans. Such programs
+/// load without spans.
+#[derive(Deserialize)]
+struct ProgramBcV1 {
+    code: Vec<CodeObjectV1>,
+    words: BTreeMap<String, Vec<Op>>,
+    docs: BTreeMap<String, String>,
+    sites: BTreeMap<String, WordSiteV1>,
+}
+
+#[derive(Deserialize)]
+struct CodeObjectV1 {
+    ops: Vec<Op>,
+}
+
+#[derive(Deserialize)]
+struct WordSiteV1 {
+    file: Option<String>,
+    line: usize,
+    replaces: Vec<WordSiteV1>,
+}
+
+impl From<ProgramBcV1> for ProgramBc {
+    fn from(old: ProgramBcV1) -> Self {
+        ProgramBc {
+            code: old
+                .code
+                .into_iter()
+                .map(|code| CodeObject {
+                    ops: code.ops,
+                    spans: Vec::new(),
+                })
+                .collect(),
+            words: old.words,
+            docs: old.docs,
+            sites: old
+                .sites
+                .into_iter()
+                .map(|(name, site)| (name, site.into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<WordSiteV1> for WordSite {
+    fn from(old: WordSiteV1) -> Self {
+        WordSite {
+            file: old.file,
+            line: old.line,
+            replaces: old.replaces.into_iter().map(Into::into).collect(),
+            spans: Vec::new(),
+        }
+    }
+}
+
+/// Split the header after the magic from the payload.
+fn read_header(bytes: &[u8]) -> Result<(Header, &[u8]), ContainerError> {
+    let truncated = || ContainerError::new("truncated header");
+    let (version, rest) = bytes.split_first_chunk::<2>().ok_or_else(truncated)?;
+    let (&len, rest) = rest.split_first().ok_or_else(truncated)?;
+    let (compiler, mut rest) = rest.split_at_checked(len as usize).ok_or_else(truncated)?;
+    let format_version = u16::from_le_bytes(*version);
+    let mut op_set = None;
+    if format_version >= 3 {
+        let (ops, after) = rest.split_first_chunk::<4>().ok_or_else(truncated)?;
+        op_set = Some(u32::from_le_bytes(*ops));
+        rest = after;
+    }
+    let (checksum, payload) = rest.split_first_chunk::<4>().ok_or_else(truncated)?;
+
+    let header = Header {
+        format_version,
+        compiler_version: String::from_utf8_lossy(compiler).into_owned(),
+        op_set,
+        checksum: u32::from_le_bytes(*checksum),
+    };
+    Ok((header, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Engine;
+    use crate::bytecode::OpSpan;
+    use crate::bytecode::verify::verify_program;
+    use crate::frontend::lexer::Span;
+    use crate::lang::value::Value;
+    use serde::Serialize;
+
+    fn program() -> ProgramBc {
+        let mut program = ProgramBc::new();
+        program.code[0].ops = vec![Op::Push(Value::Integer(42)), Op::Print];
+        program.code[0].spans = vec![OpSpan {
+            op: 0,
+            span: Span { line: 3, col: 5 },
+        }];
+        program
+    }
+
+    /// The version 1 encoding of `program()`, before spans: one main with
+    /// its ops and nothing else.
+    fn program_v1() -> Vec<u8> {
+        #[derive(Serialize)]
+        struct Old {
+            code: Vec<(Vec<Op>,)>,
+            words: BTreeMap<String, Vec<Op>>,
+            docs: BTreeMap<String, String>,
+            sites: BTreeMap<String, ()>,
+        }
+        let old = Old {
+            code: vec![(program().code[0].ops.clone(),)],
+            words: BTreeMap::new(),
+            docs: BTreeMap::new(),
+            sites: BTreeMap::new(),
+        };
+        postcard::to_allocvec(&old).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bytes = encode(&program()).unwrap();
+        assert!(bytes.starts_with(b"EMBC\x03\x00"));
+
+        let (header, loaded) = decode(&bytes).unwrap();
+        let header = header.unwrap();
+        assert_eq!(header.format_version, FORMAT_VERSION);
+        assert_eq!(header.compiler_version, COMPILER_VERSION);
+        assert_eq!(header.op_set, Some(op_set()));
+        assert_eq!(loaded.code[0].ops, program().code[0].ops);
+        assert_eq!(loaded.code[0].spans, program().code[0].spans);
+    }
+
+    #[test]
+    fn test_unversioned_files_load() {
+        // Written by ember before the container, with the op and value
+        // numbering of the time
+        let (header, hello) = decode(include_bytes!("../../tests/legacy/hello_world.ebc")).unwrap();
+        assert!(header.is_none());
+        assert_eq!(
+            hello.code[0].ops,
+            vec![
+                Op::Push(Value::String("Hello, World!".to_string())),
+                Op::Print,
+                Op::Return,
+            ]
+        );
+        assert!(hello.code[0].spans.is_empty());
+
+        let (_, modules) = decode(include_bytes!("../../tests/legacy/10_modules.ebc")).unwrap();
+        verify_program(&modules).unwrap();
+        let mut engine = Engine::new();
+        engine.capture_output();
+        engine.vm().run_compiled(&modules).unwrap();
+        assert_eq!(engine.take_output(), "3.14159265359\n25\n");
+    }
+
+    #[test]
+    fn test_version_2_files_load() {
+        let payload = postcard::to_allocvec(&program()).unwrap();
+        let mut bytes = b"EMBC\x02\x00\x050.1.0".to_vec();
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+
+        let (header, loaded) = decode(&bytes).unwrap();
+        assert_eq!(header.unwrap().op_set, None);
+        assert_eq!(loaded.code[0].spans, program().code[0].spans);
+    }
+
+    #[test]
+    fn test_op_set_follows_the_op_numbering() {
+        let names = op_names();
+        assert_eq!(names.first(), Some(&"Push"));
+        assert_eq!(names.last(), Some(&"NextLine"));
+        let mut renumbered = names.to_vec();
+        renumbered.swap(0, 1);
+        assert_ne!(crc32(renumbered.join(" ").as_bytes()), op_set());
+    }
+
+    #[test]
+    fn test_version_1_files_load() {
+        let payload = program_v1();
+        let mut bytes = b"EMBC\x01\x00\x050.1.0".to_vec();
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+
+        let (header, loaded) = decode(&bytes).unwrap();
+        assert_eq!(header.unwrap().format_version, 1);
+        assert_eq!(loaded.code[0].ops, program().code[0].ops);
+    }
+
+    #[test]
+    fn test_rejects_other_versions() {
+        let mut bytes = encode(&program()).unwrap();
+        bytes[4] = 9;
+        let err = decode(&bytes).unwrap_err().to_string();
+        assert!(err.contains("bytecode format version 9"), "{}", err);
+        assert!(err.contains("this build reads version 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_detects_corruption() {
+        let mut bytes = encode(&program()).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(decode(&bytes).unwrap_err().to_string().contains("checksum"));
+
+        let err = decode(b"EMBC\x01").unwrap_err();
+        assert_eq!(err.to_string(), "truncated header");
+    }
+}
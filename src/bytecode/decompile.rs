@@ -0,0 +1,261 @@
+use crate::bytecode::Op;
+use crate::bytecode::compile::{lowered_times_at, lowered_while_at};
+use crate::lang::builtins;
+use crate::lang::value::Value;
+
+// =============================================================================
+// Decompiler
+// =============================================================================
+//
+// Renders ops back into Ember source, for `quot->string` and `see`. The
+// compiler's jump lowerings of `if`, `when`, `times`, `while` and `until`
+// are recognized and turned back into the words with literal quotations,
+// and so are the `and`/`or` short circuits. The optimizer's
+// rewrites are undone the same way (`Peek(1)` is `over`, `Peek(n)` is
+// `n pick`), so the text compiles to the same behaviour, though not
+// always to the same ops. Locals come back named after their slots
+// (`local0`, `local1`, ...), since the ops don't keep their names. Values
+// with no literal syntax (bytes) and hand-built jumps that match no pattern
+// can't be rendered.
+
+/// Source text for `ops`, or None if some part has no source form.
+pub fn decompile(ops: &[Op]) -> Option<String> {
+    let mut words = Vec::new();
+    write_ops(ops, &mut words)?;
+    Some(words.join(" "))
+}
+
+fn write_ops(ops: &[Op], out: &mut Vec<String>) -> Option<()> {
+    let mut i = 0;
+    while i < ops.len() {
+        if let Some(consumed) = write_times(ops, i, out) {
+            i += consumed;
+            continue;
+        }
+        if let Some(consumed) = write_while(ops, i, out) {
+            i += consumed;
+            continue;
+        }
+        match &ops[i] {
+            Op::JumpIfFalse(offset) => {
+                i += write_branch(ops, i, *offset, out)?;
+                continue;
+            }
+            Op::Push(value) => out.push(literal(value)?),
+            Op::CallWord(name) => out.push(name.clone()),
+            Op::CallQualified { module, word } => out.push(format!("{}.{}", module, word)),
+            Op::Store(name) => out.push(format!("{} !", name)),
+            Op::Load(name) => out.push(format!("{} @", name)),
+            Op::StoreLocal(slot) => out.push(format!(":> local{}", slot)),
+            Op::LoadLocal(slot) => out.push(format!("local{}", slot)),
+            Op::Capture(body) => out.push(literal(&Value::CompiledQuotation(body.clone()))?),
+            Op::Peek(0) => out.push("dup".to_string()),
+            Op::Peek(1) => out.push("over".to_string()),
+            Op::Peek(n) => out.push(format!("{} pick", n)),
+            // The end of a word body
+            Op::Return if i + 1 == ops.len() => {}
+            other => out.push(builtins::op_name(other)?.to_string()),
+        }
+        i += 1;
+    }
+    Some(())
+}
+
+/// `[ then ] [ else ] if` or `[ then ] when` from the `JumpIfFalse` at `i`.
+/// Returns how many ops it covered.
+fn write_branch(ops: &[Op], i: usize, offset: i32, out: &mut Vec<String>) -> Option<usize> {
+    let end = i.checked_add(usize::try_from(offset).ok()?)?;
+    if offset < 1 || end > ops.len() {
+        return None;
+    }
+
+    // An `if` ends its then-branch with a jump over the else-branch
+    if let Some(Op::Jump(skip)) = ops.get(end - 1).filter(|_| offset >= 2)
+        && let Ok(skip) = usize::try_from(*skip)
+        && skip >= 1
+        && end - 1 + skip <= ops.len()
+    {
+        let else_end = end - 1 + skip;
+        if let Some(words) = short_circuit(&ops[i + 1..end - 1], &ops[end..else_end]) {
+            out.extend(words);
+            return Some(else_end - i);
+        }
+        let mut then_words = Vec::new();
+        let mut else_words = Vec::new();
+        if write_ops(&ops[i + 1..end - 1], &mut then_words).is_some()
+            && write_ops(&ops[end..else_end], &mut else_words).is_some()
+        {
+            push_block(then_words, out);
+            push_block(else_words, out);
+            out.push("if".to_string());
+            return Some(else_end - i);
+        }
+    }
+
+    let mut then_words = Vec::new();
+    write_ops(&ops[i + 1..end], &mut then_words)?;
+    push_block(then_words, out);
+    out.push("when".to_string());
+    Some(end - i)
+}
+
+/// `[ right ] and` or `[ right ] or` from the branches of a lowered short
+/// circuit (see `Compiler::try_emit_short_circuit_jumps`).
+fn short_circuit(then_ops: &[Op], else_ops: &[Op]) -> Option<Vec<String>> {
+    let (right, word) = match (then_ops, else_ops) {
+        ([right @ .., Op::CheckBool], [Op::Push(Value::Bool(false))]) => (right, "and"),
+        ([Op::Push(Value::Bool(true))], [right @ .., Op::CheckBool]) => (right, "or"),
+        _ => return None,
+    };
+    let mut words = Vec::new();
+    let mut body = Vec::new();
+    write_ops(right, &mut body)?;
+    push_block(body, &mut words);
+    words.push(word.to_string());
+    Some(words)
+}
+
+/// `[ body ] times` if a lowered times loop starts at `i` (see
+/// `Compiler::try_emit_times_jumps`). Returns how many ops it covered.
+fn write_times(ops: &[Op], i: usize, out: &mut Vec<String>) -> Option<usize> {
+    let body_len = lowered_times_at(ops, i)?;
+    let mut body = Vec::new();
+    write_ops(&ops[i + 6..i + 6 + body_len], &mut body)?;
+    push_block(body, out);
+    out.push("times".to_string());
+    Some(body_len + 11)
+}
+
+/// `[ cond ] [ body ] while` (or `until`) if a lowered loop starts at `i`
+/// (see `Compiler::try_emit_while_jumps`). Returns how many ops it covered.
+fn write_while(ops: &[Op], i: usize, out: &mut Vec<String>) -> Option<usize> {
+    let (cond_len, body_len, until) = lowered_while_at(ops, i)?;
+    let body_start = i + cond_len + 1;
+    let mut cond = Vec::new();
+    let mut body = Vec::new();
+    write_ops(&ops[i..i + cond_len], &mut cond)?;
+    write_ops(&ops[body_start..body_start + body_len], &mut body)?;
+    push_block(cond, out);
+    push_block(body, out);
+    out.push(if until { "until" } else { "while" }.to_string());
+    Some(cond_len + body_len + 2)
+}
+
+fn push_block(words: Vec<String>, out: &mut Vec<String>) {
+    out.push("[".to_string());
+    out.extend(words);
+    out.push("]".to_string());
+}
+
+/// A value as a source literal, if it has one.
+fn literal(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Integer(n) => n.to_string(),
+        Value::Float(x) if x.is_finite() => {
+            // Display never uses an exponent, which the lexer can't read
+            let text = x.to_string();
+            if text.contains('.') {
+                text
+            } else {
+                format!("{}.0", text)
+            }
+        }
+        Value::Bool(b) => b.to_string(),
+        Value::String(s) => string_literal(s),
+        Value::List(items) => {
+            let items: Option<Vec<String>> = items.iter().map(literal).collect();
+            let mut words = vec!["{".to_string()];
+            words.extend(items?);
+            words.push("}".to_string());
+            words.join(" ")
+        }
+        Value::Map(entries) => {
+            let mut words = vec!["#{".to_string()];
+            for (key, value) in entries {
+                words.push(string_literal(key));
+                words.push(literal(value)?);
+            }
+            words.push("}".to_string());
+            words.join(" ")
+        }
+        Value::CompiledQuotation(ops) => {
+            let mut words = Vec::new();
+            write_ops(ops, &mut words)?;
+            let mut block = Vec::new();
+            push_block(words, &mut block);
+            block.join(" ")
+        }
+        _ => return None,
+    })
+}
+
+fn string_literal(s: &str) -> String {
+    let mut text = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            '\0' => text.push_str("\\0"),
+            other => text.push(other),
+        }
+    }
+    text.push('"');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compile::Compiler;
+    use crate::frontend::lexer::Lexer;
+    use crate::frontend::parser::Parser;
+
+    fn main_ops(source: &str) -> Vec<Op> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let mut bytecode = Compiler::new().compile_program(&program).unwrap();
+        bytecode.code.remove(0).ops
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for source in [
+            "1 2.5 + print",
+            "\"say \\\"hi\\\"\\n\" print",
+            "{ 1 { true false } } len",
+            "#{ \"a\" { 1 } \"b\" #{ } } keys",
+            "dup 0 > [ 1 ] [ 2 over ] if print",
+            "dup 10 < [ \"small\" print ] when",
+            "3 [ dup print 5 [ 1 + ] times ] times",
+            "0 [ dup 5 < ] [ 1 + ] while",
+            "[ dup 0 > [ false ] [ true ] if ] [ [ 1 - ] [ drop 0 ] if ] until",
+            "x [ 0 > [ 1 ] when ] [ [ 2 ] [ 3 ] if ] if",
+            "m.w [ sq ] map",
+        ] {
+            let ops = main_ops(source);
+            let text = decompile(&ops).unwrap();
+            assert_eq!(text, source);
+            assert_eq!(main_ops(&text), ops, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_short_circuits() {
+        for source in ["a [ b ] and", "a [ b ] or", "a [ b [ c ] or ] and"] {
+            let ops = main_ops(source);
+            assert_eq!(decompile(&ops).unwrap(), source);
+        }
+        let ops = main_ops("[ a ] [ b ] and");
+        assert_eq!(decompile(&ops).unwrap(), "a [ b ] and");
+    }
+
+    #[test]
+    fn test_no_source_form() {
+        assert!(decompile(&[Op::Jump(3)]).is_none());
+        assert!(decompile(&[Op::ToAux]).is_none());
+        assert!(decompile(&[Op::Push(Value::Bytes(vec![1]))]).is_none());
+    }
+}
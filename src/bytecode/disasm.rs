@@ -1,6 +1,10 @@
+use crate::bytecode::stack_check_error::stack_heights;
 use crate::bytecode::{Op, ProgramBc};
-use crate::lang::value::Value;
-use std::collections::HashMap;
+use crate::lang::value::{FloatFormat, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Compiled words, for inferring the stack effect of calls to them.
+type Words = BTreeMap<String, Vec<Op>>;
 
 /// Print disassembly of a bytecode program
 pub fn print_bc(bc: &ProgramBc) {
@@ -13,32 +17,39 @@ pub fn print_bc(bc: &ProgramBc) {
         } else {
             format!("code[{}]", ci)
         };
-        print_code_object(&label, &code.ops, 0);
+        // Main starts from an empty stack
+        let entry = (ci == 0).then_some(0);
+        print_code_object(&label, &code.ops, entry, &bc.words);
     }
 
-    // Print word definitions (sorted alphabetically)
-    let mut words: Vec<_> = bc.words.iter().collect();
-    words.sort_by_key(|(name, _)| *name);
-
-    for (name, ops) in words {
-        print_code_object(name, ops, 0);
+    // Print word definitions (the map keeps them sorted by name)
+    for (name, ops) in &bc.words {
+        print_code_object(name, ops, None, &bc.words);
     }
 }
 
-/// Print a single code object with optional indentation
-fn print_code_object(name: &str, ops: &[Op], indent: usize) {
-    let prefix = "  ".repeat(indent);
-
-    println!("{}════════════════════════════════════════", prefix);
-    println!("{} {}", prefix, name);
-    println!("{} {} instructions", prefix, ops.len());
-    println!("{}════════════════════════════════════════", prefix);
-    disassemble_ops(ops, indent);
+/// Print a single code object. `entry` is the stack depth it starts from,
+/// or None for word bodies, whose depths are shown relative to their entry.
+fn print_code_object(name: &str, ops: &[Op], entry: Option<i32>, words: &Words) {
+    println!("════════════════════════════════════════");
+    println!(" {}", name);
+    println!(" {} instructions, [stack depth] before each", ops.len());
+    println!("════════════════════════════════════════");
+    let heights = stack_heights(ops, entry, words);
+    disassemble_ops(ops, 0, &heights, entry.is_none(), words);
     println!();
 }
 
-/// Disassemble a slice of ops with indentation support
-pub fn disassemble_ops(ops: &[Op], indent: usize) {
+/// Disassemble a slice of ops with indentation support. `heights` holds
+/// the inferred stack depth before each op, signed when `relative` to the
+/// depth on entry.
+pub fn disassemble_ops(
+    ops: &[Op],
+    indent: usize,
+    heights: &[Option<i32>],
+    relative: bool,
+    words: &Words,
+) {
     let jump_targets = collect_jump_targets(ops);
     let prefix = "  ".repeat(indent);
 
@@ -55,7 +66,19 @@ pub fn disassemble_ops(ops: &[Op], indent: usize) {
             print!("  ");
         }
 
-        print_op(op, ip, indent);
+        let depth = format_depth(heights.get(ip).copied().flatten(), relative);
+        print!("[{:>3}] ", depth);
+        print_op(op, ip, indent, words);
+    }
+}
+
+/// A stack depth for the depth column: signed when `relative` to a body's
+/// entry, `?` where the checker can't infer it.
+fn format_depth(height: Option<i32>, relative: bool) -> String {
+    match height {
+        Some(h) if relative => format!("{:+}", h),
+        Some(h) => h.to_string(),
+        None => "?".to_string(),
     }
 }
 
@@ -81,7 +104,7 @@ fn collect_jump_targets(ops: &[Op]) -> Vec<usize> {
     targets
 }
 
-fn print_op(op: &Op, ip: usize, indent: usize) {
+fn print_op(op: &Op, ip: usize, indent: usize, words: &Words) {
     let prefix = "  ".repeat(indent);
 
     match op {
@@ -89,13 +112,13 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::Push(v) => match v {
             Value::CompiledQuotation(inner_ops) => {
                 println!("PUSH        [");
-                print_inline_quotation(inner_ops, indent + 1);
-                println!("{}          ]", prefix);
+                print_inline_quotation(inner_ops, indent + 1, words);
+                println!("{}                ]", prefix);
             }
             Value::List(items) if contains_quotation(items) => {
                 println!("PUSH        {{");
-                print_list_items(items, indent + 1);
-                println!("{}          }}", prefix);
+                print_list_items(items, indent + 1, words);
+                println!("{}                }}", prefix);
             }
             _ => println!("PUSH        {}", format_value(v)),
         },
@@ -106,6 +129,13 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::Swap => println!("SWAP"),
         Op::Over => println!("OVER"),
         Op::Rot => println!("ROT"),
+        Op::Peek(n) => println!("PEEK        {}", n),
+        Op::Tuck => println!("TUCK        ; ( a b -- b a b )"),
+        Op::Nip => println!("NIP         ; ( a b -- b )"),
+        Op::TwoDup => println!("2DUP        ; ( a b -- a b a b )"),
+        Op::TwoDrop => println!("2DROP       ; ( a b -- )"),
+        Op::Pick => println!("PICK        ; ( ... n -- ... x )"),
+        Op::Roll => println!("ROLL        ; ( ... n -- ... x )"),
 
         // Auxiliary stack operations
         Op::ToAux => println!("TO_AUX      ; ( a -- ) R:( -- a )"),
@@ -137,6 +167,8 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::If => println!("IF          ; ( cond then else -- result )"),
         Op::When => println!("WHEN        ; ( cond then -- )"),
         Op::Call => println!("CALL        ; ( quot -- result )"),
+        Op::Try => println!("TRY         ; ( body handler -- ... )"),
+        Op::Throw => println!("THROW       ; ( x -- )"),
 
         // Control flow - jumps
         Op::Jump(offset) => {
@@ -157,6 +189,11 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
 
         // Loops & higher-order
         Op::Times => println!("TIMES       ; ( n quot -- )"),
+        Op::CheckTimes => println!("CHECK_TIMES ; ( n -- n )"),
+        Op::CheckBool => println!("CHECK_BOOL  ; ( bool -- bool )"),
+        Op::NextLine => println!("NEXT_LINE   ; ( n -- n' line true | false )"),
+        Op::While => println!("WHILE       ; ( cond body -- )"),
+        Op::Until => println!("UNTIL       ; ( cond body -- )"),
         Op::Each => println!("EACH        ; ( list quot -- )"),
         Op::Map => println!("MAP         ; ( list quot -- list )"),
         Op::Filter => println!("FILTER      ; ( list quot -- list )"),
@@ -182,6 +219,16 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::Max => println!("MAX         ; ( a b -- max )"),
         Op::Pow => println!("POW         ; ( base exp -- result )"),
         Op::Sqrt => println!("SQRT        ; ( n -- sqrt )"),
+        Op::Sin => println!("SIN         ; ( x -- sin )"),
+        Op::Cos => println!("COS         ; ( x -- cos )"),
+        Op::Tan => println!("TAN         ; ( x -- tan )"),
+        Op::Atan2 => println!("ATAN2       ; ( y x -- angle )"),
+        Op::Ln => println!("LN          ; ( x -- ln )"),
+        Op::Log10 => println!("LOG10       ; ( x -- log10 )"),
+        Op::Exp => println!("EXP         ; ( x -- e^x )"),
+        Op::Floor => println!("FLOOR       ; ( x -- floor )"),
+        Op::Ceil => println!("CEIL        ; ( x -- ceil )"),
+        Op::Round => println!("ROUND       ; ( x -- round )"),
         Op::Nth => println!("NTH         ; ( list n -- item )"),
         Op::Append => println!("APPEND      ; ( list item -- list )"),
         Op::Sort => println!("SORT        ; ( list -- list )"),
@@ -197,6 +244,19 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::Type => println!("TYPE        ; ( value -- str )"),
         Op::ToString => println!("TO_STRING   ; ( value -- str )"),
         Op::ToInt => println!("TO_INT      ; ( str -- int )"),
+        Op::ToFloat => println!("TO_FLOAT    ; ( x -- float )"),
+        Op::ParseInt => println!("PARSE_INT   ; ( str radix -- int )"),
+
+        // Vector arithmetic
+        Op::VAdd => println!("V_ADD       ; ( xs ys -- zs )"),
+        Op::VMul => println!("V_MUL       ; ( xs ys -- zs )"),
+        Op::Scale => println!("SCALE       ; ( xs k -- ys )"),
+        Op::DotProduct => println!("DOT         ; ( xs ys -- n )"),
+
+        // Matrix helpers
+        Op::Transpose => println!("TRANSPOSE   ; ( rows -- cols )"),
+        Op::Shape => println!("SHAPE       ; ( x -- dims )"),
+        Op::FlattenDepth => println!("FLATTEN_DEPTH ; ( list n -- list )"),
 
         // Combinators
         Op::Dip => println!("DIP         ; ( a quot -- a )"),
@@ -209,6 +269,19 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
         Op::Curry => println!("CURRY       ; ( value quot -- quot )"),
         Op::Apply => println!("APPLY       ; ( list quot -- result )"),
 
+        // Variables
+        Op::Store(name) => println!("STORE       \"{}\"  ; ( x -- )", name),
+        Op::Load(name) => println!("LOAD        \"{}\"  ; ( -- x )", name),
+
+        // Locals
+        Op::StoreLocal(slot) => println!("STORE_LOCAL {}  ; ( x -- )", slot),
+        Op::LoadLocal(slot) => println!("LOAD_LOCAL  {}  ; ( -- x )", slot),
+        Op::Capture(inner_ops) => {
+            println!("CAPTURE     [");
+            print_inline_quotation(inner_ops, indent + 1, words);
+            println!("{}                ]", prefix);
+        }
+
         // Word calls
         Op::CallWord(name) => println!("CALL_WORD   \"{}\"", name),
         Op::CallQualified { module, word } => {
@@ -220,10 +293,11 @@ fn print_op(op: &Op, ip: usize, indent: usize) {
     }
 }
 
-/// Print inline quotation contents
-fn print_inline_quotation(ops: &[Op], indent: usize) {
+/// Print inline quotation contents, with depths relative to its entry
+fn print_inline_quotation(ops: &[Op], indent: usize, words: &Words) {
     let prefix = "  ".repeat(indent);
     let jump_targets = collect_jump_targets(ops);
+    let heights = stack_heights(ops, None, words);
 
     for (ip, op) in ops.iter().enumerate() {
         if jump_targets.contains(&ip) {
@@ -238,7 +312,9 @@ fn print_inline_quotation(ops: &[Op], indent: usize) {
             print!("  ");
         }
 
-        print_op(op, ip, indent);
+        let depth = format_depth(heights.get(ip).copied().flatten(), true);
+        print!("[{:>3}] ", depth);
+        print_op(op, ip, indent, words);
     }
 }
 
@@ -250,14 +326,14 @@ fn contains_quotation(items: &[Value]) -> bool {
 }
 
 /// Print list items with quotation expansion
-fn print_list_items(items: &[Value], indent: usize) {
+fn print_list_items(items: &[Value], indent: usize, words: &Words) {
     let prefix = "  ".repeat(indent);
 
     for (i, item) in items.iter().enumerate() {
         match item {
             Value::CompiledQuotation(ops) => {
                 println!("{}  [{}]: [", prefix, i);
-                print_inline_quotation(ops, indent + 1);
+                print_inline_quotation(ops, indent + 1, words);
                 println!("{}        ]", prefix);
             }
             Value::Quotation(nodes) => {
@@ -273,7 +349,8 @@ fn print_list_items(items: &[Value], indent: usize) {
 fn format_value(value: &Value) -> String {
     match value {
         Value::Integer(n) => format!("{}", n),
-        Value::Float(f) => format!("{:?}", f),
+        // Constants are always shown exactly, whatever the run's float format
+        Value::Float(f) => FloatFormat::Shortest.format(*f),
         Value::String(s) => format!("{:?}", s),
         Value::Bool(b) => format!("{}", b),
         Value::List(items) => {
@@ -286,6 +363,18 @@ fn format_value(value: &Value) -> String {
                 format!("{{ {} }}", inner.join(" "))
             }
         }
+        Value::Bytes(bytes) => format!("<{} bytes>", bytes.len()),
+        Value::Map(entries) => {
+            if entries.is_empty() {
+                "#{ }".to_string()
+            } else {
+                let inner: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("{:?}: {}", k, format_value(v)))
+                    .collect();
+                format!("#{{ {} }}", inner.join(" "))
+            }
+        }
         Value::Quotation(nodes) => {
             format!("[ <{} nodes> ]", nodes.len())
         }
@@ -352,6 +441,67 @@ pub fn disassemble_to_string(ops: &[Op]) -> String {
     output
 }
 
+/// Return disassembly of a whole program as a String: main, then words in
+/// name order, with pushed quotations expanded beneath the op that pushes
+/// them. The layout is stable, so it is suitable for snapshot tests.
+#[allow(dead_code)]
+pub fn program_to_string(bc: &ProgramBc) -> String {
+    let mut output = String::new();
+
+    for (ci, code) in bc.code.iter().enumerate() {
+        let label = if ci == 0 {
+            "main".to_string()
+        } else {
+            format!("code[{}]", ci)
+        };
+        output.push_str(&format!("-- {} ({} ops)\n", label, code.ops.len()));
+        write_ops_nested(&mut output, &code.ops, 0);
+    }
+
+    for (name, ops) in &bc.words {
+        output.push_str(&format!("-- {} ({} ops)\n", name, ops.len()));
+        write_ops_nested(&mut output, ops, 0);
+    }
+
+    output
+}
+
+fn write_ops_nested(output: &mut String, ops: &[Op], indent: usize) {
+    let prefix = "    ".repeat(indent);
+    for line in disassemble_to_string(ops).lines() {
+        output.push_str(&prefix);
+        output.push_str(line);
+        output.push('\n');
+    }
+    for (ip, op) in ops.iter().enumerate() {
+        match op {
+            Op::Push(value) => write_nested_value(output, ip, value, indent),
+            Op::Capture(inner) => {
+                let prefix = "    ".repeat(indent + 1);
+                output.push_str(&format!("{}[quotation at {:04}]\n", prefix, ip));
+                write_ops_nested(output, inner, indent + 1);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_nested_value(output: &mut String, ip: usize, value: &Value, indent: usize) {
+    let prefix = "    ".repeat(indent + 1);
+    match value {
+        Value::CompiledQuotation(inner) => {
+            output.push_str(&format!("{}[quotation at {:04}]\n", prefix, ip));
+            write_ops_nested(output, inner, indent + 1);
+        }
+        Value::List(items) => {
+            for item in items {
+                write_nested_value(output, ip, item, indent);
+            }
+        }
+        _ => {}
+    }
+}
+
 fn format_op_string(op: &Op, ip: usize) -> String {
     match op {
         Op::Push(v) => format!("PUSH        {}", format_value(v)),
@@ -369,6 +519,11 @@ fn format_op_string(op: &Op, ip: usize) -> String {
             let target = (ip as i32 + *offset) as usize;
             format!("JUMP_TRUE   {:+} (→ {:04})", offset, target)
         }
+        Op::Store(name) => format!("STORE       \"{}\"", name),
+        Op::Load(name) => format!("LOAD        \"{}\"", name),
+        Op::StoreLocal(slot) => format!("STORE_LOCAL {}", slot),
+        Op::LoadLocal(slot) => format!("LOAD_LOCAL  {}", slot),
+        Op::Capture(ops) => format!("CAPTURE     [ <{} ops> ]", ops.len()),
         Op::CallWord(name) => format!("CALL_WORD   \"{}\"", name),
         Op::CallQualified { module, word } => format!("CALL_QUAL   \"{}.{}\"", module, word),
         Op::Return => "RETURN".to_string(),
@@ -410,7 +565,8 @@ pub fn print_bc_stats(bc: &ProgramBc) {
 
     println!("Op frequency:");
     let mut counts: Vec<_> = op_counts.iter().collect();
-    counts.sort_by(|a, b| b.1.cmp(a.1));
+    // Ties in name order, so the top ten are the same on every run
+    counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
 
     for (op, count) in counts.iter().take(10) {
         let pct = (**count as f64 / total_ops as f64) * 100.0;
@@ -424,13 +580,13 @@ fn count_ops<'a>(ops: &'a [Op], counts: &mut HashMap<&'a str, usize>) {
         *counts.entry(name).or_insert(0) += 1;
 
         // Count nested quotations
-        if let Op::Push(Value::CompiledQuotation(inner)) = op {
+        if let Op::Push(Value::CompiledQuotation(inner)) | Op::Capture(inner) = op {
             count_ops(inner, counts);
         }
     }
 }
 
-fn op_name(op: &Op) -> &'static str {
+pub(crate) fn op_name(op: &Op) -> &'static str {
     match op {
         Op::Push(_) => "PUSH",
         Op::Dup => "DUP",
@@ -438,6 +594,13 @@ fn op_name(op: &Op) -> &'static str {
         Op::Swap => "SWAP",
         Op::Over => "OVER",
         Op::Rot => "ROT",
+        Op::Peek(_) => "PEEK",
+        Op::Tuck => "TUCK",
+        Op::Nip => "NIP",
+        Op::TwoDup => "2DUP",
+        Op::TwoDrop => "2DROP",
+        Op::Pick => "PICK",
+        Op::Roll => "ROLL",
         Op::ToAux => "TO_AUX",
         Op::FromAux => "FROM_AUX",
         Op::Add => "ADD",
@@ -459,10 +622,17 @@ fn op_name(op: &Op) -> &'static str {
         Op::If => "IF",
         Op::When => "WHEN",
         Op::Call => "CALL",
+        Op::Try => "TRY",
+        Op::Throw => "THROW",
         Op::Jump(_) => "JUMP",
         Op::JumpIfFalse(_) => "JUMP_FALSE",
         Op::JumpIfTrue(_) => "JUMP_TRUE",
         Op::Times => "TIMES",
+        Op::CheckTimes => "CHECK_TIMES",
+        Op::CheckBool => "CHECK_BOOL",
+        Op::NextLine => "NEXT_LINE",
+        Op::While => "WHILE",
+        Op::Until => "UNTIL",
         Op::Each => "EACH",
         Op::Map => "MAP",
         Op::Filter => "FILTER",
@@ -482,6 +652,16 @@ fn op_name(op: &Op) -> &'static str {
         Op::Max => "MAX",
         Op::Pow => "POW",
         Op::Sqrt => "SQRT",
+        Op::Sin => "SIN",
+        Op::Cos => "COS",
+        Op::Tan => "TAN",
+        Op::Atan2 => "ATAN2",
+        Op::Ln => "LN",
+        Op::Log10 => "LOG10",
+        Op::Exp => "EXP",
+        Op::Floor => "FLOOR",
+        Op::Ceil => "CEIL",
+        Op::Round => "ROUND",
         Op::Nth => "NTH",
         Op::Append => "APPEND",
         Op::Sort => "SORT",
@@ -497,6 +677,15 @@ fn op_name(op: &Op) -> &'static str {
         Op::Type => "TYPE",
         Op::ToString => "TO_STRING",
         Op::ToInt => "TO_INT",
+        Op::ToFloat => "TO_FLOAT",
+        Op::ParseInt => "PARSE_INT",
+        Op::VAdd => "V_ADD",
+        Op::VMul => "V_MUL",
+        Op::Scale => "SCALE",
+        Op::DotProduct => "DOT",
+        Op::Transpose => "TRANSPOSE",
+        Op::Shape => "SHAPE",
+        Op::FlattenDepth => "FLATTEN_DEPTH",
         Op::Dip => "DIP",
         Op::Keep => "KEEP",
         Op::Bi => "BI",
@@ -506,6 +695,11 @@ fn op_name(op: &Op) -> &'static str {
         Op::Compose => "COMPOSE",
         Op::Curry => "CURRY",
         Op::Apply => "APPLY",
+        Op::Store(_) => "STORE",
+        Op::Load(_) => "LOAD",
+        Op::StoreLocal(_) => "STORE_LOCAL",
+        Op::LoadLocal(_) => "LOAD_LOCAL",
+        Op::Capture(_) => "CAPTURE",
         Op::CallWord(_) => "CALL_WORD",
         Op::CallQualified { .. } => "CALL_QUAL",
         Op::Return => "RETURN",
@@ -541,7 +735,15 @@ mod tests {
         ];
 
         // Verify it compiles - visual check for arrows
-        disassemble_ops(&ops, 0);
+        disassemble_ops(&ops, 0, &[], false, &Words::new());
+    }
+
+    #[test]
+    fn test_format_depth() {
+        assert_eq!(format_depth(Some(2), false), "2");
+        assert_eq!(format_depth(Some(0), true), "+0");
+        assert_eq!(format_depth(Some(-1), true), "-1");
+        assert_eq!(format_depth(None, false), "?");
     }
 
     #[test]
@@ -561,6 +763,32 @@ mod tests {
         assert!(formatted.contains("quotations"));
     }
 
+    #[test]
+    fn test_program_to_string_expands_quotations() {
+        let mut bc = ProgramBc::new();
+        bc.code[0].ops = vec![
+            Op::Push(Value::CompiledQuotation(vec![Op::Dup, Op::Mul])),
+            Op::Call,
+        ];
+        bc.words.insert("zed".to_string(), vec![Op::Return]);
+        bc.words.insert("abc".to_string(), vec![Op::Return]);
+
+        let text = program_to_string(&bc);
+        let expected = "\
+-- main (2 ops)
+0000   PUSH        [ <2 ops> ]
+0001   CALL
+    [quotation at 0000]
+    0000   DUP
+    0001   MUL
+-- abc (1 ops)
+0000   RETURN
+-- zed (1 ops)
+0000   RETURN
+";
+        assert_eq!(text, expected);
+    }
+
     #[test]
     fn test_op_counts() {
         let ops = vec![
@@ -0,0 +1,250 @@
+use crate::bytecode::container::{Header, op_set};
+use crate::bytecode::disasm::op_name;
+use crate::bytecode::{Op, ProgramBc};
+use crate::lang::builtins;
+use crate::lang::value::Value;
+use crate::runtime::native;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// What a compiled program contains and needs, for `ember inspect`: enough to
+/// judge a third-party `.ebc` before running it.
+#[derive(Debug, Default)]
+pub struct Inspection {
+    /// Size of the encoded program in bytes.
+    pub size: usize,
+    /// None for files written before the `EMBC` container.
+    pub header: Option<Header>,
+    pub words: usize,
+    pub main_ops: usize,
+    /// Ops in word bodies.
+    pub word_ops: usize,
+    /// How often each op appears, nested quotations included.
+    pub op_counts: BTreeMap<&'static str, usize>,
+    /// Pushed literals by type.
+    pub constants: BTreeMap<&'static str, usize>,
+    /// Total length of string literals.
+    pub string_bytes: usize,
+    /// Words with a doc comment.
+    pub documented: usize,
+    /// Words with a recorded definition site.
+    pub sited: usize,
+    /// Sandbox flag -> words that need it.
+    pub capabilities: BTreeMap<&'static str, BTreeSet<String>>,
+    /// Called words that neither the program nor this build defines.
+    pub unresolved: BTreeSet<String>,
+}
+
+impl Inspection {
+    pub fn of(program: &ProgramBc, size: usize) -> Self {
+        let mut inspection = Inspection {
+            size,
+            words: program.words.len(),
+            main_ops: program.code.iter().map(|c| c.ops.len()).sum(),
+            word_ops: program.words.values().map(Vec::len).sum(),
+            documented: program.docs.len(),
+            sited: program.sites.len(),
+            ..Default::default()
+        };
+        for code in &program.code {
+            inspection.scan(&code.ops, program);
+        }
+        for ops in program.words.values() {
+            inspection.scan(ops, program);
+        }
+        inspection
+    }
+
+    pub fn with_header(mut self, header: Option<Header>) -> Self {
+        self.header = header;
+        self
+    }
+
+    fn scan(&mut self, ops: &[Op], program: &ProgramBc) {
+        for op in ops {
+            *self.op_counts.entry(op_name(op)).or_insert(0) += 1;
+            match op {
+                Op::Push(value) => self.constant(value, program),
+                Op::Capture(body) => self.scan(body, program),
+                Op::CallWord(name) if !program.words.contains_key(name) => {
+                    if let Some(flag) = native::capability(name) {
+                        self.capabilities
+                            .entry(flag)
+                            .or_default()
+                            .insert(name.clone());
+                    } else if native::lookup(name).is_none() {
+                        self.unresolved.insert(name.clone());
+                    }
+                }
+                Op::Print | Op::Emit | Op::Read | Op::Debug => {
+                    self.capabilities
+                        .entry("allow_io")
+                        .or_default()
+                        .extend(builtins::op_name(op).map(str::to_string));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn constant(&mut self, value: &Value, program: &ProgramBc) {
+        *self.constants.entry(value.type_name()).or_insert(0) += 1;
+        match value {
+            Value::String(s) => self.string_bytes += s.len(),
+            Value::CompiledQuotation(ops) => self.scan(ops, program),
+            Value::List(items) => {
+                for item in items {
+                    self.constant(item, program);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The report `ember inspect` prints.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "size         {} bytes", self.size);
+        match &self.header {
+            Some(header) => {
+                let _ = write!(
+                    out,
+                    "header       EMBC format {}, written by ember {}",
+                    header.format_version, header.compiler_version
+                );
+                if let Some(ops) = header.op_set {
+                    let _ = write!(out, ", op set {:08x}", ops);
+                }
+                let _ = writeln!(out, ", crc32 {:08x}", header.checksum);
+                // Ops numbered differently decode as other ops, or not at all
+                if header.op_set.is_some_and(|ops| ops != op_set()) {
+                    let _ = writeln!(
+                        out,
+                        "             op set differs from this build's {:08x}",
+                        op_set()
+                    );
+                }
+            }
+            None => {
+                let _ = writeln!(out, "header       none (unversioned postcard encoding)");
+            }
+        }
+        let _ = writeln!(out, "words        {}", self.words);
+        let _ = writeln!(
+            out,
+            "ops          {} (main {}, words {})",
+            self.main_ops + self.word_ops,
+            self.main_ops,
+            self.word_ops
+        );
+
+        let constants: Vec<String> = self
+            .constants
+            .iter()
+            .map(|(kind, n)| format!("{} {}", n, kind))
+            .collect();
+        let total: usize = self.constants.values().sum();
+        let _ = write!(out, "constants    {}", total);
+        if total > 0 {
+            let _ = write!(out, " ({})", constants.join(", "));
+        }
+        if self.string_bytes > 0 {
+            let _ = write!(out, "; {} bytes of string data", self.string_bytes);
+        }
+        out.push('\n');
+
+        let _ = writeln!(
+            out,
+            "debug info   {} def sites, {} doc comments",
+            self.sited, self.documented
+        );
+
+        if self.capabilities.is_empty() {
+            let _ = writeln!(out, "needs        nothing beyond the default sandbox");
+        } else {
+            for (flag, words) in &self.capabilities {
+                let words: Vec<&str> = words.iter().map(String::as_str).collect();
+                let _ = writeln!(out, "needs        {} ({})", flag, words.join(", "));
+            }
+        }
+        if !self.unresolved.is_empty() {
+            let words: Vec<&str> = self.unresolved.iter().map(String::as_str).collect();
+            let _ = writeln!(
+                out,
+                "unavailable  {} (not defined here or in this build)",
+                words.join(", ")
+            );
+        }
+
+        let _ = writeln!(out, "\nop counts:");
+        let mut counts: Vec<_> = self.op_counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (op, count) in counts {
+            let _ = writeln!(out, "  {:<14} {:>6}", op, count);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compile::Compiler;
+    use crate::frontend::lexer::Lexer;
+    use crate::frontend::parser::Parser;
+
+    #[test]
+    fn test_inspection() {
+        let source = "; Runs ls\ndef ls \"ls\" [] run-process end\n\
+                      ls \"*.em\" glob [ \"x\" print ] each frobnicate";
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let bytecode = Compiler::new().compile_program(&program).unwrap();
+        let inspection = Inspection::of(&bytecode, 100);
+
+        assert_eq!(inspection.words, 1);
+        assert_eq!(inspection.documented, 1);
+        assert_eq!(inspection.sited, 1);
+        assert_eq!(inspection.constants["string"], 3);
+        assert_eq!(inspection.string_bytes, 7);
+        assert_eq!(inspection.op_counts["PRINT"], 1);
+        assert!(inspection.capabilities["allow_fs"].contains("glob"));
+        assert!(inspection.capabilities["allow_process"].contains("run-process"));
+        assert!(inspection.capabilities["allow_io"].contains("print"));
+        assert!(inspection.unresolved.contains("frobnicate"));
+
+        let report = inspection.render();
+        assert!(report.contains("header       none"));
+        assert!(!report.contains("op set"));
+        assert!(report.contains("needs        allow_fs (glob)"));
+        assert!(report.contains("unavailable  frobnicate"));
+    }
+
+    #[test]
+    fn test_header_names_the_op_set() {
+        let mut inspection = Inspection {
+            header: Some(Header {
+                format_version: 3,
+                compiler_version: "0.1.0".to_string(),
+                op_set: Some(op_set()),
+                checksum: 0xdeadbeef,
+            }),
+            ..Inspection::default()
+        };
+        let report = inspection.render();
+        let line = format!(
+            "header       EMBC format 3, written by ember 0.1.0, op set {:08x}, crc32 deadbeef\n",
+            op_set()
+        );
+        assert!(report.contains(&line), "{}", report);
+        assert!(!report.contains("differs"));
+
+        inspection.header.as_mut().unwrap().op_set = Some(op_set() ^ 1);
+        let report = inspection.render();
+        assert!(
+            report.contains("op set differs from this build's"),
+            "{}",
+            report
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::bytecode::Op;
+use crate::frontend::lexer::Span;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// A compiled bytecode program.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,7 +11,13 @@ pub struct ProgramBc {
     pub code: Vec<CodeObject>,
 
     /// Compiled word definitions: name -> ops
-    pub words: HashMap<String, Vec<Op>>,
+    pub words: BTreeMap<String, Vec<Op>>,
+
+    /// Documentation comments of user words, for `describe`.
+    pub docs: BTreeMap<String, String>,
+
+    /// Where each user word was defined, for `ember which`.
+    pub sites: BTreeMap<String, WordSite>,
 }
 
 impl ProgramBc {
@@ -18,19 +25,105 @@ impl ProgramBc {
     pub fn new() -> Self {
         Self {
             code: vec![CodeObject::new()],
-            words: HashMap::new(),
+            words: BTreeMap::new(),
+            docs: BTreeMap::new(),
+            sites: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for ProgramBc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `def` a word came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WordSite {
+    /// Source file, or None for code compiled from a string.
+    pub file: Option<String>,
+    /// 1-based line of the `def`.
+    pub line: usize,
+    /// Earlier definitions of the same name that this one replaced, oldest
+    /// first.
+    pub replaces: Vec<WordSite>,
+    /// Where the ops of the body came from, like `CodeObject::spans`.
+    pub spans: Vec<OpSpan>,
+}
+
+impl WordSite {
+    /// `file:line`, or `line N` without a file.
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}", file, self.line),
+            None => format!("line {}", self.line),
         }
     }
 }
 
 /// A single compiled instruction stream.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CodeObject {
     pub ops: Vec<Op>,
+    /// Source positions of the ops, in op order. Ops inside quotations have
+    /// none of their own; an error there is reported at the op that ran the
+    /// quotation.
+    pub spans: Vec<OpSpan>,
 }
 
 impl CodeObject {
     pub fn new() -> Self {
-        Self { ops: Vec::new() }
+        Self {
+            ops: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+}
+
+/// The ops from `op` up to the next entry's came from the node at `span`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpSpan {
+    pub op: usize,
+    pub span: Span,
+}
+
+/// Where the op at `ip` came from, in a table of `OpSpan`s.
+pub fn span_at(spans: &[OpSpan], ip: usize) -> Option<&Span> {
+    let after = spans.partition_point(|entry| entry.op <= ip);
+    after.checked_sub(1).map(|i| &spans[i].span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::value::Value;
+
+    fn round_trip(program: &ProgramBc) -> ProgramBc {
+        let bytes = postcard::to_allocvec(program).unwrap();
+        postcard::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_float_bits_survive_serialization() {
+        // Includes a NaN with a non-default payload and a signed zero
+        let samples = [
+            f64::from_bits(0x7ff8_0000_dead_beef),
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            -0.0,
+            f64::MIN_POSITIVE / 2.0,
+        ];
+        let mut program = ProgramBc::new();
+        program.code[0].ops = samples.iter().map(|&x| Op::Push(Value::Float(x))).collect();
+
+        let loaded = round_trip(&program);
+        for (op, expected) in loaded.code[0].ops.iter().zip(samples) {
+            match op {
+                Op::Push(Value::Float(x)) => assert_eq!(x.to_bits(), expected.to_bits()),
+                other => panic!("expected float push, got {:?}", other),
+            }
+        }
     }
 }
@@ -0,0 +1,245 @@
+use crate::bytecode::{CodeObject, Op, ProgramBc};
+use crate::lang::value::Value;
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+use std::collections::BTreeMap;
+
+// =============================================================================
+// Unversioned .ebc files
+// =============================================================================
+//
+// Before the EMBC container, a .ebc file was the bare postcard encoding of
+// a program with only `code` and `words`. Ops and values are encoded by
+// their position in the enum, and both enums have grown in the middle since
+// then, so those files are decoded with copies of the types as they were
+// and converted. The copies must not change.
+
+/// Decode a file written before the container existed.
+pub fn decode(bytes: &[u8]) -> Result<ProgramBc, postcard::Error> {
+    let old: ProgramV0 = postcard::from_bytes(bytes)?;
+    Ok(ProgramBc {
+        code: old
+            .code
+            .into_iter()
+            .map(|code| CodeObject {
+                ops: convert(code.ops),
+                spans: Vec::new(),
+            })
+            .collect(),
+        words: old
+            .words
+            .into_iter()
+            .map(|(name, ops)| (name, convert(ops)))
+            .collect(),
+        ..ProgramBc::default()
+    })
+}
+
+fn convert(ops: Vec<OpV0>) -> Vec<Op> {
+    ops.into_iter().map(Op::from).collect()
+}
+
+#[derive(Deserialize)]
+struct ProgramV0 {
+    code: Vec<CodeObjectV0>,
+    words: BTreeMap<String, Vec<OpV0>>,
+}
+
+#[derive(Deserialize)]
+struct CodeObjectV0 {
+    ops: Vec<OpV0>,
+}
+
+#[derive(Deserialize)]
+enum ValueV0 {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    List(Vec<ValueV0>),
+    /// Never written: the compiler turned every quotation into a
+    /// `CompiledQuotation`. Only holds the place of the next variant;
+    /// decoding one fails, as postcard can't skip a value of unknown type.
+    Quotation(IgnoredAny),
+    CompiledQuotation(Vec<OpV0>),
+}
+
+#[derive(Deserialize)]
+enum OpV0 {
+    Push(ValueV0),
+    Dup,
+    Drop,
+    Swap,
+    Over,
+    Rot,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Neg,
+    Abs,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    And,
+    Or,
+    Not,
+    If,
+    When,
+    Call,
+    Jump(i32),
+    JumpIfFalse(i32),
+    JumpIfTrue(i32),
+    Return,
+    Times,
+    Each,
+    Map,
+    Filter,
+    Fold,
+    Range,
+    Len,
+    Head,
+    Tail,
+    Cons,
+    Concat,
+    StringConcat,
+    Print,
+    Emit,
+    Read,
+    Debug,
+    Min,
+    Max,
+    Pow,
+    Sqrt,
+    Nth,
+    Append,
+    Sort,
+    Reverse,
+    Chars,
+    Join,
+    Split,
+    Upper,
+    Lower,
+    Trim,
+    Clear,
+    Depth,
+    Type,
+    ToString,
+    ToInt,
+    Dip,
+    Keep,
+    Bi,
+    Bi2,
+    Tri,
+    Both,
+    Compose,
+    Curry,
+    Apply,
+    CallWord(String),
+    CallQualified { module: String, word: String },
+    ToAux,
+    FromAux,
+}
+
+impl From<ValueV0> for Value {
+    fn from(old: ValueV0) -> Self {
+        match old {
+            ValueV0::Integer(n) => Value::Integer(n),
+            ValueV0::Float(x) => Value::Float(x),
+            ValueV0::String(s) => Value::String(s),
+            ValueV0::Bool(b) => Value::Bool(b),
+            ValueV0::List(items) => Value::List(items.into_iter().map(Into::into).collect()),
+            ValueV0::Quotation(_) => unreachable!("postcard never decodes IgnoredAny"),
+            ValueV0::CompiledQuotation(ops) => Value::CompiledQuotation(convert(ops)),
+        }
+    }
+}
+
+impl From<OpV0> for Op {
+    fn from(old: OpV0) -> Self {
+        use OpV0::*;
+        match old {
+            Push(value) => Op::Push(value.into()),
+            Dup => Op::Dup,
+            Drop => Op::Drop,
+            Swap => Op::Swap,
+            Over => Op::Over,
+            Rot => Op::Rot,
+            Add => Op::Add,
+            Sub => Op::Sub,
+            Mul => Op::Mul,
+            Div => Op::Div,
+            Mod => Op::Mod,
+            Neg => Op::Neg,
+            Abs => Op::Abs,
+            Eq => Op::Eq,
+            Ne => Op::Ne,
+            Lt => Op::Lt,
+            Gt => Op::Gt,
+            Le => Op::Le,
+            Ge => Op::Ge,
+            And => Op::And,
+            Or => Op::Or,
+            Not => Op::Not,
+            If => Op::If,
+            When => Op::When,
+            Call => Op::Call,
+            Jump(offset) => Op::Jump(offset),
+            JumpIfFalse(offset) => Op::JumpIfFalse(offset),
+            JumpIfTrue(offset) => Op::JumpIfTrue(offset),
+            Return => Op::Return,
+            Times => Op::Times,
+            Each => Op::Each,
+            Map => Op::Map,
+            Filter => Op::Filter,
+            Fold => Op::Fold,
+            Range => Op::Range,
+            Len => Op::Len,
+            Head => Op::Head,
+            Tail => Op::Tail,
+            Cons => Op::Cons,
+            Concat => Op::Concat,
+            StringConcat => Op::StringConcat,
+            Print => Op::Print,
+            Emit => Op::Emit,
+            Read => Op::Read,
+            Debug => Op::Debug,
+            Min => Op::Min,
+            Max => Op::Max,
+            Pow => Op::Pow,
+            Sqrt => Op::Sqrt,
+            Nth => Op::Nth,
+            Append => Op::Append,
+            Sort => Op::Sort,
+            Reverse => Op::Reverse,
+            Chars => Op::Chars,
+            Join => Op::Join,
+            Split => Op::Split,
+            Upper => Op::Upper,
+            Lower => Op::Lower,
+            Trim => Op::Trim,
+            Clear => Op::Clear,
+            Depth => Op::Depth,
+            Type => Op::Type,
+            ToString => Op::ToString,
+            ToInt => Op::ToInt,
+            Dip => Op::Dip,
+            Keep => Op::Keep,
+            Bi => Op::Bi,
+            Bi2 => Op::Bi2,
+            Tri => Op::Tri,
+            Both => Op::Both,
+            Compose => Op::Compose,
+            Curry => Op::Curry,
+            Apply => Op::Apply,
+            CallWord(name) => Op::CallWord(name),
+            CallQualified { module, word } => Op::CallQualified { module, word },
+            ToAux => Op::ToAux,
+            FromAux => Op::FromAux,
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::bytecode::{Op, ProgramBc};
+use crate::lang::value::Value;
+use crate::runtime::native;
+
+/// Link separately compiled programs into one, for `ember bundle`.
+///
+/// Main code runs in the order given. Words are merged by name: the same
+/// definition in several modules (e.g. a shared import) is kept once, while
+/// different definitions under one name are an error, since the bundle would
+/// silently pick one. Every call must resolve to a word of some module or to
+/// a native word. Literals are stored inline in the ops, so there is no
+/// separate constant pool to merge.
+pub fn link(programs: Vec<(String, ProgramBc)>) -> Result<ProgramBc, String> {
+    let mut linked = ProgramBc::new();
+    // Module each word came from, for conflict messages
+    let mut origin = std::collections::BTreeMap::new();
+
+    for (module, program) in programs {
+        let mut code = program.code.into_iter();
+        if let Some(main) = code.next() {
+            // Its spans are dropped: a span doesn't say which file it is
+            // in, and each module's main came from a different one
+            let mut ops = main.ops;
+            // Jumps to the end now fall through to the next module's main
+            if ops.last() == Some(&Op::Return) {
+                ops.pop();
+            }
+            linked.code[0].ops.extend(ops);
+        }
+        linked.code.extend(code);
+
+        for (name, ops) in program.words {
+            match linked.words.get(&name) {
+                Some(existing) if *existing != ops => {
+                    return Err(format!(
+                        "'{}' is defined differently in {} and {}",
+                        name, origin[&name], module
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    origin.insert(name.clone(), module.clone());
+                    linked.words.insert(name, ops);
+                }
+            }
+        }
+        for (name, doc) in program.docs {
+            linked.docs.entry(name).or_insert(doc);
+        }
+        for (name, site) in program.sites {
+            linked.sites.entry(name).or_insert(site);
+        }
+    }
+    linked.code[0].ops.push(Op::Return);
+
+    let mut unresolved = Vec::new();
+    for ops in linked
+        .code
+        .iter()
+        .map(|c| &c.ops)
+        .chain(linked.words.values())
+    {
+        unresolved_calls(ops, &linked, &mut unresolved);
+    }
+    unresolved.sort();
+    unresolved.dedup();
+    if !unresolved.is_empty() {
+        return Err(format!("unresolved words: {}", unresolved.join(", ")));
+    }
+
+    Ok(linked)
+}
+
+fn unresolved_calls(ops: &[Op], program: &ProgramBc, out: &mut Vec<String>) {
+    for op in ops {
+        match op {
+            Op::CallWord(name)
+                if !program.words.contains_key(name) && native::lookup(name).is_none() =>
+            {
+                out.push(name.clone())
+            }
+            Op::CallQualified { module, word } => {
+                let name = format!("{}.{}", module, word);
+                if !program.words.contains_key(&name) {
+                    out.push(name);
+                }
+            }
+            Op::Push(value) => unresolved_in_value(value, program, out),
+            Op::Capture(body) => unresolved_calls(body, program, out),
+            _ => {}
+        }
+    }
+}
+
+fn unresolved_in_value(value: &Value, program: &ProgramBc, out: &mut Vec<String>) {
+    match value {
+        Value::CompiledQuotation(ops) => unresolved_calls(ops, program, out),
+        Value::List(items) => {
+            for item in items {
+                unresolved_in_value(item, program, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compile::Compiler;
+    use crate::frontend::lexer::Lexer;
+    use crate::frontend::parser::Parser;
+
+    fn compiled(source: &str) -> ProgramBc {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        Compiler::new().compile_program(&program).unwrap()
+    }
+
+    #[test]
+    fn test_link() {
+        let lib = compiled("def sq dup * end def cube dup sq * end");
+        let app = compiled("def sq dup * end 3 cube print");
+
+        let linked = link(vec![
+            ("lib".into(), lib.clone()),
+            ("app".into(), app.clone()),
+        ])
+        .unwrap();
+        assert_eq!(linked.words.len(), 2);
+        assert_eq!(linked.code[0].ops.last(), Some(&Op::Return));
+        assert_eq!(
+            linked.code[0]
+                .ops
+                .iter()
+                .filter(|op| **op == Op::Return)
+                .count(),
+            1
+        );
+
+        // app alone can't find cube
+        let err = link(vec![("app".into(), app)]).unwrap_err();
+        assert_eq!(err, "unresolved words: cube");
+
+        let other = compiled("def sq dup dup * * end");
+        let err = link(vec![("lib".into(), lib), ("other".into(), other)]).unwrap_err();
+        assert_eq!(err, "'sq' is defined differently in lib and other");
+    }
+}
@@ -1,9 +1,16 @@
 pub mod compile;
 pub mod compile_error;
+pub mod container;
+pub mod decompile;
 pub mod disasm;
+pub mod inspect;
 pub mod ir;
+mod legacy;
+pub mod link;
 pub mod op;
+pub mod optimize;
 pub mod stack_check_error;
+pub mod verify;
 
-pub use ir::{CodeObject, ProgramBc};
+pub use ir::{CodeObject, OpSpan, ProgramBc, WordSite};
 pub use op::Op;
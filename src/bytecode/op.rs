@@ -4,6 +4,11 @@ use serde::{Deserialize, Serialize};
 // =============================================================================
 // OP - Bytecode instructions
 // =============================================================================
+//
+// .ebc files store each op by its position in this enum. Add new variants
+// at the end: one inserted in the middle renumbers every op after it, and
+// files written before then no longer load. If the order has to change,
+// bump `FORMAT_VERSION` in container.rs and regenerate examples/*.ebc.
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Op {
@@ -16,6 +21,18 @@ pub enum Op {
     Swap,
     Over,
     Rot,
+    /// Push a copy of the value `n` slots below the top without popping.
+    /// `Peek(0)` behaves like `Dup`, `Peek(1)` like `Over`.
+    Peek(usize),
+    /// Copy the top value below the second: `( a b -- b a b )`.
+    Tuck,
+    Nip,
+    TwoDup,
+    TwoDrop,
+    /// `( xn ... x0 n -- xn ... x0 xn )`, with `n` popped at run time
+    Pick,
+    /// `( xn ... x0 n -- ... x0 xn )`, with `n` popped at run time
+    Roll,
 
     // arithmetic
     Add,
@@ -42,9 +59,11 @@ pub enum Op {
     // ==========================================================================
     // Control flow - quotation-based (kept for dynamic quotations)
     // ==========================================================================
-    If,   // ( cond then-quot else-quot -- result )
-    When, // ( cond then-quot -- )
-    Call, // ( quot -- result )
+    If,    // ( cond then-quot else-quot -- result )
+    When,  // ( cond then-quot -- )
+    Call,  // ( quot -- result )
+    Try,   // ( body-quot handler-quot -- result )
+    Throw, // ( x -- )
 
     // ==========================================================================
     // Phase 3: Jump instructions for flat control flow
@@ -62,6 +81,8 @@ pub enum Op {
 
     // loops & higher-order (still quotation-based for now)
     Times,
+    While,
+    Until,
     Each,
     Map,
     Filter,
@@ -87,6 +108,16 @@ pub enum Op {
     Max,
     Pow,
     Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Atan2,
+    Ln,
+    Log10,
+    Exp,
+    Floor,
+    Ceil,
+    Round,
     Nth,
     Append,
     Sort,
@@ -102,6 +133,8 @@ pub enum Op {
     Type,
     ToString,
     ToInt,
+    ToFloat,
+    ParseInt,
 
     Dip,
     Keep,
@@ -113,6 +146,32 @@ pub enum Op {
     Curry,
     Apply,
 
+    // vector arithmetic
+    VAdd,
+    VMul,
+    Scale,
+    DotProduct,
+
+    // matrix helpers
+    Transpose,
+    Shape,
+    FlattenDepth,
+
+    // variables
+    /// Pop the top value into the named variable.
+    Store(String),
+    /// Push the named variable's value.
+    Load(String),
+
+    // locals
+    /// Pop the top value into a slot of the running word's locals.
+    StoreLocal(usize),
+    /// Push the value in a slot of the running word's locals.
+    LoadLocal(usize),
+    /// Push the quotation with every `LoadLocal` in it, at any depth,
+    /// replaced by the value in that slot now.
+    Capture(Vec<Op>),
+
     // User-defined word calls
     CallWord(String),
     CallQualified {
@@ -127,4 +186,14 @@ pub enum Op {
     ToAux,
     /// Move top of auxiliary stack to main stack
     FromAux,
+
+    /// Fail unless the top value, the counter a lowered `times` loop is
+    /// about to start on, is a non-negative integer. Leaves it in place.
+    CheckTimes,
+    /// Fail unless the top value, what the quotation of a lowered `and` or
+    /// `or` left, is a bool. Leaves it in place.
+    CheckBool,
+    /// The `next` of a `stdin-lines` sequence: read an input line, counting
+    /// the lines read in the state. ( n -- n' line true | false )
+    NextLine,
 }
@@ -0,0 +1,562 @@
+use crate::bytecode::stack_check_error::{effect, stack_heights};
+use crate::bytecode::{Op, OpSpan, ProgramBc};
+use crate::lang::builtins::StackDelta;
+use crate::lang::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+// =============================================================================
+// Peephole optimizer
+// =============================================================================
+//
+// Rewrites short op sequences into cheaper equivalents. Every rewrite here is
+// local: it never looks past a jump target, so a pattern can only fire when
+// control is guaranteed to flow through all of its ops in order. Jump offsets
+// are recomputed after rewriting.
+//
+// Removing an op also removes its underflow check, so rewrites that drop a
+// `Dup` or `Swap` only fire where the stack checker has proven the stack is
+// deep enough for it anyway.
+//
+//   Pattern            Replacement   Why
+//   -------            -----------   ---
+//   Dup ... Drop       ...           the copy is dropped without being read:
+//   Push(v) ... Drop   ...           the ops between never reach it
+//   Swap Swap          (nothing)     identity
+//   Swap Over          Tuck          ( a b -- b a b ) without popping
+//   Over               Peek(1)       read in place instead of pop/pop/push x3
+//   Push(n) Pick       Peek(n)       a constant depth needs no operand, for
+//                                    n up to MAX_PEEK; deeper ones are left
+//                                    for the VM to report
+//   Push(a) Push(b) +  Push(a + b)   integer constant folding, also - and *;
+//                                    overflow is left for the VM to report
+//
+// `remove_dead_words` and `strip_debug_info` are separate passes, run by
+// `ember opt` on finished programs only: the REPL, `--entry` and `describe`
+// all need words and docs that main never mentions.
+
+/// The deepest constant `pick` turned into a `Peek`. No real stack is
+/// that deep, and it keeps depths well inside what the stack checker counts.
+const MAX_PEEK: i64 = u16::MAX as i64;
+
+/// Optimize every code object and word body in a program.
+pub fn optimize_program(program: &mut ProgramBc) {
+    // Stack effects of the words as written; rewriting doesn't change them
+    let words = program.words.clone();
+    for code in &mut program.code {
+        let (ops, new_index) = optimize_indexed(&code.ops, &words);
+        code.ops = ops;
+        code.spans = remap_spans(&code.spans, &new_index);
+    }
+    for (name, ops) in program.words.iter_mut() {
+        let (optimized, new_index) = optimize_indexed(ops, &words);
+        *ops = optimized;
+        if let Some(site) = program.sites.get_mut(name) {
+            site.spans = remap_spans(&site.spans, &new_index);
+        }
+    }
+}
+
+/// Optimize a single instruction stream, including nested quotations.
+/// Calls to words end what the stack checker can prove about it.
+pub fn optimize_ops(ops: &[Op]) -> Vec<Op> {
+    optimize_indexed(ops, &BTreeMap::new()).0
+}
+
+/// Move a span table onto optimized ops. When rewrites leave several
+/// entries at one op, the last one wins, since the ops before it are gone.
+fn remap_spans(spans: &[OpSpan], new_index: &[usize]) -> Vec<OpSpan> {
+    let mut out: Vec<OpSpan> = Vec::with_capacity(spans.len());
+    for entry in spans {
+        let op = new_index.get(entry.op).copied().unwrap_or(entry.op);
+        if out.last().is_some_and(|last| last.op == op) {
+            out.pop();
+        }
+        out.push(OpSpan {
+            op,
+            span: entry.span.clone(),
+        });
+    }
+    out
+}
+
+/// `optimize_ops`, also returning where each old op (or whatever replaced
+/// it) starts in the output.
+fn optimize_indexed(ops: &[Op], words: &BTreeMap<String, Vec<Op>>) -> (Vec<Op>, Vec<usize>) {
+    let targets = jump_targets(ops);
+    // Measured from entry, so each is a lower bound on the real height
+    let heights = stack_heights(ops, None, words);
+    let dead = dead_copies(ops, &targets, &heights);
+    // Patterns don't reach into ops that are jumped to or removed
+    let barriers: Vec<bool> = (0..ops.len()).map(|i| targets[i] || dead[i]).collect();
+    let rewrite = Rewrite {
+        ops,
+        barriers: &barriers,
+        heights: &heights,
+        words,
+    };
+
+    // new_index[i] = position in the output where old op `i` (or whatever
+    // replaced it) starts. One extra slot maps the one-past-end target.
+    let mut new_index = vec![0usize; ops.len() + 1];
+    let mut out: Vec<Op> = Vec::with_capacity(ops.len());
+    // Old position of each emitted jump, so offsets can be fixed afterwards
+    let mut emitted_from: Vec<usize> = Vec::with_capacity(ops.len());
+
+    let mut i = 0;
+    while i < ops.len() {
+        let (consumed, replacement) = if dead[i] { (1, vec![]) } else { rewrite.at(i) };
+
+        for slot in new_index.iter_mut().skip(i).take(consumed) {
+            *slot = out.len();
+        }
+        for op in replacement {
+            out.push(op);
+            emitted_from.push(i);
+        }
+        i += consumed;
+    }
+    new_index[ops.len()] = out.len();
+
+    // Re-target jumps using the old → new index map
+    for (pos, op) in out.iter_mut().enumerate() {
+        let old_pos = emitted_from[pos];
+        let offset = match op {
+            Op::Jump(offset) | Op::JumpIfFalse(offset) | Op::JumpIfTrue(offset) => offset,
+            _ => continue,
+        };
+        let old_target = old_pos as i64 + *offset as i64;
+        if old_target < 0 || old_target as usize > ops.len() {
+            // Out-of-bounds jumps are left for the VM to report
+            continue;
+        }
+        *offset = (new_index[old_target as usize] as i64 - pos as i64) as i32;
+    }
+
+    (out, new_index)
+}
+
+/// Which values are pushed, by `Dup` or `Push`, only to be dropped again
+/// without anything reading them. Marks both the op that pushes and the
+/// `Drop`.
+fn dead_copies(ops: &[Op], targets: &[bool], heights: &[Option<i32>]) -> Vec<bool> {
+    let mut dead = vec![false; ops.len()];
+    for (i, op) in ops.iter().enumerate() {
+        let checked = match op {
+            Op::Push(_) => true,
+            Op::Dup => heights[i].is_some_and(|h| h >= 1),
+            _ => false,
+        };
+        if checked
+            && !dead[i]
+            && let Some(drop) = unread_until_drop(ops, i, targets)
+        {
+            dead[i] = true;
+            dead[drop] = true;
+        }
+    }
+    dead
+}
+
+/// The `Drop` that discards the value pushed by `ops[start]`, if control
+/// runs straight to it and nothing in between reaches down to the value.
+fn unread_until_drop(ops: &[Op], start: usize, targets: &[bool]) -> Option<usize> {
+    // Values above the one pushed
+    let mut above = 0;
+    for (ip, op) in ops.iter().enumerate().skip(start + 1) {
+        if targets[ip] {
+            return None;
+        }
+        match op {
+            Op::Drop if above == 0 => return Some(ip),
+            // Read below their inputs, or the whole stack
+            Op::Pick | Op::Roll | Op::Depth => return None,
+            // Run a quotation that can reach below them
+            Op::Map | Op::Filter | Op::Fold => return None,
+            _ => {}
+        }
+        // Jumps, calls and quotations end the straight run
+        let Some(StackDelta::Fixed { inputs, outputs }) = effect(op) else {
+            return None;
+        };
+        if matches!(
+            op,
+            Op::Jump(_) | Op::JumpIfFalse(_) | Op::JumpIfTrue(_) | Op::Return
+        ) || inputs > above
+        {
+            return None;
+        }
+        above = above - inputs + outputs;
+    }
+    None
+}
+
+/// What a rewrite at one position may depend on.
+struct Rewrite<'a> {
+    ops: &'a [Op],
+    /// Ops a pattern must not extend into: jump targets and removed copies
+    barriers: &'a [bool],
+    /// Lower bounds on the stack height at each op, where known
+    heights: &'a [Option<i32>],
+    words: &'a BTreeMap<String, Vec<Op>>,
+}
+
+impl Rewrite<'_> {
+    /// Try to match a rewrite at `i`. Returns how many ops were consumed and
+    /// the ops to emit in their place. A non-matching position consumes one
+    /// op and emits it unchanged (after optimizing any quotation it pushes).
+    fn at(&self, i: usize) -> (usize, Vec<Op>) {
+        let ops = self.ops;
+        let barriers = self.barriers;
+
+        // Likewise for the second and third op of a fold
+        if let (Some(Op::Push(Value::Integer(a))), Some(Op::Push(Value::Integer(b))), Some(op)) =
+            (ops.get(i), ops.get(i + 1), ops.get(i + 2))
+            && !barriers[i + 1]
+            && !barriers[i + 2]
+        {
+            let folded = match op {
+                Op::Add => a.checked_add(*b),
+                Op::Sub => a.checked_sub(*b),
+                Op::Mul => a.checked_mul(*b),
+                _ => None,
+            };
+            if let Some(n) = folded {
+                return (3, vec![Op::Push(Value::Integer(n))]);
+            }
+        }
+
+        // Two-op patterns are only safe if nothing jumps into the second op
+        if let Some(next) = ops.get(i + 1)
+            && !barriers[i + 1]
+        {
+            let deep = |n| self.heights[i].is_some_and(|h| h >= n);
+            match (&ops[i], next) {
+                (Op::Swap, Op::Swap) if deep(2) => return (2, vec![]),
+                (Op::Swap, Op::Over) => return (2, vec![Op::Tuck]),
+                (Op::Push(Value::Integer(n)), Op::Pick) if (0..=MAX_PEEK).contains(n) => {
+                    return (2, vec![Op::Peek(*n as usize)]);
+                }
+                _ => {}
+            }
+        }
+
+        let op = match &ops[i] {
+            Op::Over => Op::Peek(1),
+            Op::Push(Value::CompiledQuotation(inner)) => {
+                Op::Push(Value::CompiledQuotation(self.nested(inner)))
+            }
+            Op::Capture(inner) => Op::Capture(self.nested(inner)),
+            other => other.clone(),
+        };
+        (1, vec![op])
+    }
+
+    fn nested(&self, ops: &[Op]) -> Vec<Op> {
+        optimize_indexed(ops, self.words).0
+    }
+}
+
+/// Remove words that main can never reach, through calls in word bodies and
+/// quotations. Returns the names removed.
+pub fn remove_dead_words(program: &mut ProgramBc) -> Vec<String> {
+    let mut reachable = BTreeSet::new();
+    let mut pending: Vec<&[Op]> = program.code.iter().map(|c| c.ops.as_slice()).collect();
+    while let Some(ops) = pending.pop() {
+        let mut calls = Vec::new();
+        called_words(ops, &mut calls);
+        for name in calls {
+            if let Some(body) = program.words.get(&name)
+                && reachable.insert(name)
+            {
+                pending.push(body);
+            }
+        }
+    }
+
+    let dead: Vec<String> = program
+        .words
+        .keys()
+        .filter(|name| !reachable.contains(*name))
+        .cloned()
+        .collect();
+    for name in &dead {
+        program.words.remove(name);
+        program.docs.remove(name);
+        program.sites.remove(name);
+    }
+    dead
+}
+
+/// Every word `ops` calls, including from nested quotations.
+fn called_words(ops: &[Op], calls: &mut Vec<String>) {
+    for op in ops {
+        match op {
+            Op::CallWord(name) => calls.push(name.clone()),
+            Op::CallQualified { module, word } => calls.push(format!("{}.{}", module, word)),
+            Op::Push(value) => called_in_value(value, calls),
+            Op::Capture(body) => called_words(body, calls),
+            _ => {}
+        }
+    }
+}
+
+fn called_in_value(value: &Value, calls: &mut Vec<String>) {
+    match value {
+        Value::CompiledQuotation(ops) => called_words(ops, calls),
+        Value::List(items) => {
+            for item in items {
+                called_in_value(item, calls);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Drop doc comments, definition sites and op spans, which only `describe`,
+/// `ember which` and error reports use.
+pub fn strip_debug_info(program: &mut ProgramBc) {
+    program.docs.clear();
+    program.sites.clear();
+    for code in &mut program.code {
+        code.spans.clear();
+    }
+}
+
+/// Mark every index that some jump lands on.
+pub(crate) fn jump_targets(ops: &[Op]) -> Vec<bool> {
+    let mut targets = vec![false; ops.len() + 1];
+    for (ip, op) in ops.iter().enumerate() {
+        if let Op::Jump(offset) | Op::JumpIfFalse(offset) | Op::JumpIfTrue(offset) = op {
+            let target = ip as i64 + *offset as i64;
+            if target >= 0 && (target as usize) <= ops.len() {
+                targets[target as usize] = true;
+            }
+        }
+    }
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i64) -> Op {
+        Op::Push(Value::Integer(n))
+    }
+
+    #[test]
+    fn test_dup_drop_removed() {
+        let ops = vec![int(1), Op::Dup, Op::Drop, Op::Return];
+        assert_eq!(optimize_ops(&ops), vec![int(1), Op::Return]);
+    }
+
+    #[test]
+    fn test_push_drop_removed() {
+        let ops = vec![int(1), int(2), Op::Drop];
+        assert_eq!(optimize_ops(&ops), vec![int(1)]);
+    }
+
+    #[test]
+    fn test_unread_copy_removed() {
+        // 7 dup 2 3 + print drop: the copy is never reached
+        let ops = vec![
+            int(7),
+            Op::Dup,
+            int(2),
+            int(3),
+            Op::Add,
+            Op::Print,
+            Op::Drop,
+        ];
+        assert_eq!(optimize_ops(&ops), vec![int(7), int(5), Op::Print]);
+
+        // These read it, or the depth it adds to
+        for reader in [Op::Print, Op::Depth, Op::CallWord("f".into())] {
+            let ops = vec![int(7), Op::Dup, int(1), Op::Drop, reader, Op::Drop];
+            assert_eq!(optimize_ops(&ops)[..2], [int(7), Op::Dup]);
+        }
+    }
+
+    #[test]
+    fn test_copy_kept_under_quotation() {
+        let list = Op::Push(Value::List(vec![Value::Integer(1), Value::Integer(2)]));
+        let quot = |ops| Op::Push(Value::CompiledQuotation(ops));
+
+        // 1 2 { 1 2 } [ over + ] map print drop: the quotation adds the 2
+        let ops = vec![
+            int(1),
+            int(2),
+            list.clone(),
+            quot(vec![Op::Over, Op::Add]),
+            Op::Map,
+            Op::Print,
+            Op::Drop,
+        ];
+        assert_eq!(optimize_ops(&ops)[..2], [int(1), int(2)]);
+
+        // 10 5 { 1 2 } 0 [ + over + ] fold print drop: and here the 5
+        let ops = vec![
+            int(10),
+            int(5),
+            list,
+            int(0),
+            quot(vec![Op::Add, Op::Over, Op::Add]),
+            Op::Fold,
+            Op::Print,
+            Op::Drop,
+        ];
+        assert_eq!(optimize_ops(&ops)[..2], [int(10), int(5)]);
+    }
+
+    #[test]
+    fn test_unchecked_depth_keeps_ops() {
+        // In a word body nothing is known about the stack on entry, so
+        // these still have to fail on an empty stack
+        for ops in [vec![Op::Dup, Op::Drop], vec![Op::Swap, Op::Swap]] {
+            assert_eq!(optimize_ops(&ops), ops);
+        }
+
+        let mut program = ProgramBc::new();
+        program.code[0].ops = vec![int(1), Op::CallWord("f".into())];
+        program.words.insert("f".into(), vec![Op::Dup, Op::Drop]);
+        optimize_program(&mut program);
+        assert_eq!(program.words["f"], vec![Op::Dup, Op::Drop]);
+    }
+
+    #[test]
+    fn test_swap_swap_removed() {
+        let ops = vec![int(1), int(2), Op::Swap, Op::Swap];
+        assert_eq!(optimize_ops(&ops), vec![int(1), int(2)]);
+    }
+
+    #[test]
+    fn test_swap_over_becomes_tuck() {
+        let ops = vec![int(1), int(2), Op::Swap, Op::Over];
+        assert_eq!(optimize_ops(&ops), vec![int(1), int(2), Op::Tuck]);
+    }
+
+    #[test]
+    fn test_over_becomes_peek() {
+        let ops = vec![int(1), int(2), Op::Over];
+        assert_eq!(optimize_ops(&ops), vec![int(1), int(2), Op::Peek(1)]);
+    }
+
+    #[test]
+    fn test_constant_pick_becomes_peek() {
+        let ops = vec![int(1), int(2), int(1), Op::Pick, int(-1), Op::Pick];
+        assert_eq!(
+            optimize_ops(&ops),
+            vec![int(1), int(2), Op::Peek(1), int(-1), Op::Pick]
+        );
+    }
+
+    #[test]
+    fn test_huge_constant_pick_stays_a_pick() {
+        for depth in [2147483647, 99999999999] {
+            let ops = vec![int(1), int(depth), Op::Pick];
+            assert_eq!(optimize_ops(&ops), ops);
+        }
+    }
+
+    #[test]
+    fn test_optimizes_inside_quotations() {
+        let ops = vec![Op::Push(Value::CompiledQuotation(vec![
+            int(1),
+            Op::Dup,
+            Op::Drop,
+        ]))];
+        assert_eq!(
+            optimize_ops(&ops),
+            vec![Op::Push(Value::CompiledQuotation(vec![int(1)]))]
+        );
+    }
+
+    #[test]
+    fn test_jump_offsets_fixed_after_removal() {
+        // JumpIfFalse skips the then-branch, which shrinks by two ops
+        let ops = vec![
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(5),
+            int(1),
+            Op::Dup,
+            Op::Drop,
+            Op::Jump(2),
+            int(2),
+            Op::Return,
+        ];
+        let optimized = optimize_ops(&ops);
+        assert_eq!(
+            optimized,
+            vec![
+                Op::Push(Value::Bool(true)),
+                Op::JumpIfFalse(3),
+                int(1),
+                Op::Jump(2),
+                int(2),
+                Op::Return,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backward_jump_fixed_after_removal() {
+        let ops = vec![
+            int(1),
+            int(2),
+            Op::Swap,
+            Op::Swap,
+            Op::TwoDrop,
+            Op::Jump(-5),
+        ];
+        assert_eq!(
+            optimize_ops(&ops),
+            vec![int(1), int(2), Op::TwoDrop, Op::Jump(-3)]
+        );
+    }
+
+    #[test]
+    fn test_integer_constants_folded() {
+        let ops = vec![int(2), int(3), Op::Mul, int(1), Op::Sub];
+        assert_eq!(optimize_ops(&ops), vec![int(6), int(1), Op::Sub]);
+
+        // Overflow stays a runtime error
+        let ops = vec![int(i64::MAX), int(1), Op::Add];
+        assert_eq!(optimize_ops(&ops), ops);
+    }
+
+    #[test]
+    fn test_dead_words_removed() {
+        let mut program = ProgramBc::new();
+        program.code[0].ops = vec![
+            Op::Push(Value::CompiledQuotation(vec![Op::CallWord("a".into())])),
+            Op::Call,
+        ];
+        program
+            .words
+            .insert("a".into(), vec![Op::CallWord("b".into())]);
+        program
+            .words
+            .insert("b".into(), vec![Op::CallWord("a".into())]);
+        program
+            .words
+            .insert("unused".into(), vec![Op::CallWord("b".into())]);
+        program.docs.insert("unused".into(), "Never called".into());
+
+        assert_eq!(remove_dead_words(&mut program), vec!["unused".to_string()]);
+        assert_eq!(program.words.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert!(program.docs.is_empty());
+    }
+
+    #[test]
+    fn test_pattern_not_applied_across_jump_target() {
+        // Something jumps to the Drop, so Dup/Drop must not be fused
+        let ops = vec![
+            int(1),
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfTrue(2),
+            Op::Dup,
+            Op::Drop,
+        ];
+        assert_eq!(optimize_ops(&ops), ops);
+    }
+}
@@ -1,4 +1,9 @@
-use crate::bytecode::Op;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::bytecode::optimize::jump_targets;
+use crate::bytecode::{Op, ProgramBc};
+use crate::lang::builtins::{self, StackDelta};
+use crate::lang::value::Value;
 
 #[derive(Debug)]
 pub struct StackCheckError {
@@ -19,146 +24,501 @@ impl StackCheckError {
     }
 }
 
-/// Returns (pops, pushes) for an op, or None if effect is unknown/dynamic.
-fn effect(op: &Op) -> Option<(i32, i32)> {
+/// The stack delta of an op, or None if it depends on a word call.
+/// Builtin words take theirs from the registry.
+pub(crate) fn effect(op: &Op) -> Option<StackDelta> {
     use Op::*;
-    Some(match op {
-        Push(_) => (0, 1),
-
-        Dup => (1, 2),
-        Drop => (1, 0),
-        Swap => (2, 2),
-        Over => (2, 3),
-        Rot => (3, 3),
-
-        Add | Sub | Mul | Div | Mod => (2, 1),
-        Neg | Abs => (1, 1),
-
-        Eq | Ne | Lt | Gt | Le | Ge => (2, 1),
-
-        And | Or => (2, 1),
-        Not => (1, 1),
+    let fixed = |inputs, outputs| Some(StackDelta::Fixed { inputs, outputs });
+    match op {
+        Push(_) => fixed(0, 1),
+        Peek(n) => fixed(n.saturating_add(1), n.saturating_add(2)),
+        Tuck => fixed(2, 3),
 
         // =================================================================
         // Phase 3: Jump instructions
         // =================================================================
-        Jump(_) => (0, 0),
-        JumpIfFalse(_) => (1, 0),
-        JumpIfTrue(_) => (1, 0),
-
-        // Control (quotation-based)
-        If => (3, 0),
-        When => (2, 0),
-        Call => (1, 0),
-
-        // Combinators
-        Dip => (2, 0), // ( a quot -- ... a ) - dynamic result
-        // Keep => (2, 0),    // ( a quot -- ... a ) - dynamic result
-        Bi => (3, 0),      // ( a p q -- ... ) - dynamic
-        Bi2 => (4, 0),     // ( a b p q -- ... ) - dynamic
-        Tri => (4, 0),     // ( a p q r -- ... ) - dynamic
-        Both => (3, 0),    // ( a b quot -- ... ) - dynamic
-        Compose => (2, 1), // ( quot quot -- quot )
-        Curry => (2, 1),   // ( value quot -- quot )
-        Apply => (2, 0),   // ( list quot -- ... ) - dynamic
-        // issue likely exists for other dynamic operations like Dip, Bi, Tri, Call, etc. They should all return None because their stack effects depend on the quotations they execute.
-        Keep => return None,
-
-        // Loops & higher-order
-        Times => (2, 0),
-        Each => (2, 0),
-        Map => (2, 1),
-        Filter => (2, 1),
-        Fold => (3, 1),
-        Range => (2, 1),
-
-        // List ops
-        Len => (1, 1),
-        Head => (1, 1),
-        Tail => (1, 1),
-        Cons => (2, 1),
-        Concat => (2, 1),
-        StringConcat => (2, 1),
-
-        // I/O
-        Print => (1, 0),
-        Emit => (1, 0),
-        Read => (0, 1),
-        Debug => (1, 1),
-
-        // Additional builtins
-        Min | Max | Pow => (2, 1),
-        Sqrt => (1, 1),
-        Nth => (2, 1),
-        Append => (2, 1),
-        Sort | Reverse => (1, 1),
-        Chars => (1, 1),
-        Join => (2, 1),
-        Split => (2, 1),
-        Upper | Lower | Trim => (1, 1),
-        Clear => (0, 0), // Actually clears stack, but can't express that
-        Depth => (0, 1),
-        Type => (1, 2),
-        ToString => (1, 1),
-        ToInt => (1, 1),
+        Jump(_) | Return => fixed(0, 0),
+        JumpIfFalse(_) | JumpIfTrue(_) => fixed(1, 0),
 
         // Aux stack ops - from main stack perspective:
         // ToAux pops 1 from main, pushes 0 to main (moves to aux)
         // FromAux pops 0 from main, pushes 1 to main (moves from aux)
-        ToAux => (1, 0),
-        FromAux => (0, 1),
+        ToAux => fixed(1, 0),
+        FromAux => fixed(0, 1),
+        CheckTimes | CheckBool => fixed(1, 1),
+        NextLine => Some(StackDelta::Dynamic { inputs: 1 }),
 
-        Return => (0, 0),
+        Store(_) => fixed(1, 0),
+        Load(_) => fixed(0, 1),
+        StoreLocal(_) => fixed(1, 0),
+        LoadLocal(_) | Capture(_) => fixed(0, 1),
 
-        // Unknown effect - can't statically analyze
-        CallWord(_) => return None,
-        CallQualified { .. } => return None,
-    })
+        // Resolved through `WordEffects`
+        CallWord(_) | CallQualified { .. } => None,
+
+        builtin => builtins::op_delta(builtin),
+    }
 }
 
-/// Check stack effects with a given initial stack height.
+/// Net (inputs, outputs) of user words with a fixed stack effect, inferred
+/// on first use.
+struct WordEffects<'a> {
+    words: &'a BTreeMap<String, Vec<Op>>,
+    known: HashMap<String, Option<(usize, usize)>>,
+}
+
+impl<'a> WordEffects<'a> {
+    fn new(words: &'a BTreeMap<String, Vec<Op>>) -> Self {
+        Self {
+            words,
+            known: HashMap::new(),
+        }
+    }
+
+    /// The effect of a call op, or None if it can't be known statically:
+    /// natives, recursion, and words that run quotations.
+    fn of_call(&mut self, op: &Op) -> Option<(usize, usize)> {
+        let name = match op {
+            Op::CallWord(name) => name.clone(),
+            Op::CallQualified { module, word } => format!("{}.{}", module, word),
+            _ => return None,
+        };
+        if let Some(known) = self.known.get(&name) {
+            return *known;
+        }
+        let words = self.words;
+        let body = words.get(&name)?;
+        // A recursive call sees None while the body is being inferred
+        self.known.insert(name.clone(), None);
+        let inferred = scan(&name, body, Entry::Caller, self, &mut Vec::new())
+            .ok()
+            .flatten();
+        self.known.insert(name, inferred);
+        inferred
+    }
+
+    /// The effect of running a quotation body, if it's fixed.
+    fn of_quotation(&mut self, body: &[Op]) -> Option<(usize, usize)> {
+        scan("quotation", body, Entry::Caller, self, &mut Vec::new())
+            .ok()
+            .flatten()
+    }
+}
+
+/// Where an instruction stream's stack heights are measured from.
+#[derive(Clone, Copy)]
+enum Entry {
+    /// Main code: the stack holds exactly this many values on entry, so
+    /// popping below it is an underflow.
+    Height(i32),
+    /// A word or quotation body: the caller supplies its inputs, so popping
+    /// below the entry height means it takes more of them.
+    Caller,
+}
+
+/// Follow every path through `ops`, checking for underflow (from a known
+/// height) and that paths which meet - after both branches of an `if`,
+/// around a `when` body, and at the head of a `times` loop - agree on the
+/// stack height. A path stops at the first op whose effect is unknown.
 ///
-/// NOTE: This is a simple linear scan that doesn't follow jump targets.
-/// For jump-based code, this provides basic validation but not full
-/// control-flow analysis. A complete solution would need to:
-/// 1. Build a control flow graph
-/// 2. Track stack heights at each basic block entry
-/// 3. Verify heights match at join points
-pub fn check_ops_with_initial(ops: &[Op], initial_height: i32) -> Result<(), StackCheckError> {
-    let mut h: i32 = initial_height;
+/// Paths that disagree are legal but leave the height unknown, so each one
+/// is reported in `warnings` and the join point is treated like an unknown
+/// op. Returns the stream's net (inputs, outputs) when every path reaches
+/// the end with the same known height.
+fn scan(
+    location: &str,
+    ops: &[Op],
+    entry: Entry,
+    words: &mut WordEffects,
+    warnings: &mut Vec<String>,
+) -> Result<Option<(usize, usize)>, StackCheckError> {
+    let mut unbalanced = HashSet::new();
+    loop {
+        match scan_paths(location, ops, entry, words, &unbalanced, &mut Vec::new()) {
+            Pass::Unbalanced { at, message } => {
+                warnings.push(format!("in {}: {}", location, message));
+                unbalanced.insert(at);
+            }
+            Pass::Error(error) => return Err(error),
+            Pass::Done(effect) if unbalanced.is_empty() => return Ok(effect),
+            Pass::Done(_) => return Ok(None),
+        }
+    }
+}
 
-    for (ip, op) in ops.iter().enumerate() {
-        match effect(op) {
-            Some((pops, pushes)) => {
-                h -= pops;
-                if h < 0 {
-                    return Err(StackCheckError::new(format!(
-                        "stack underflow at ip={}, op={:?}, needed {} items",
-                        ip, op, pops
-                    )));
-                }
-                h += pushes;
+/// Result of one walk over an instruction stream.
+enum Pass {
+    /// Two paths reach `at` with different heights. An error seen in the
+    /// same walk may be an artifact of that, so the walk is redone with
+    /// `at` treated as unknown.
+    Unbalanced {
+        at: usize,
+        message: String,
+    },
+    Error(StackCheckError),
+    Done(Option<(usize, usize)>),
+}
+
+fn scan_paths(
+    location: &str,
+    ops: &[Op],
+    entry: Entry,
+    words: &mut WordEffects,
+    unbalanced: &HashSet<usize>,
+    heights: &mut Vec<Option<i32>>,
+) -> Pass {
+    let start = match entry {
+        Entry::Height(h) => h,
+        Entry::Caller => 0,
+    };
+    // Height on arrival at each ip, and the ip it was first reached from.
+    // Index `ops.len()` is the exit.
+    let mut arrivals: Vec<Option<(i32, Option<usize>)>> = vec![None; ops.len() + 1];
+    let mut lowest = start;
+    let mut complete = true;
+    let mut error = None;
+    let targets = jump_targets(ops);
+    let mut pending = vec![(0, start, None)];
+
+    while let Some((ip, h, from)) = pending.pop() {
+        if unbalanced.contains(&ip) {
+            complete = false;
+            continue;
+        }
+        if let Some((seen, first)) = arrivals[ip] {
+            if seen != h {
+                return Pass::Unbalanced {
+                    at: ip,
+                    message: describe_mismatch(ops, &arrivals, ip, (first, seen), (from, h)),
+                };
+            }
+            continue;
+        }
+        arrivals[ip] = Some((h, from));
+        let Some(op) = ops.get(ip) else {
+            continue;
+        };
+
+        // A quotation pushed just before this op (and not reachable by a
+        // jump in between) is the one it runs
+        let quotation = match ip.checked_sub(1).map(|prev| &ops[prev]) {
+            Some(Op::Push(Value::CompiledQuotation(body)) | Op::Capture(body)) if !targets[ip] => {
+                words.of_quotation(body)
+            }
+            _ => None,
+        };
+        if let (Op::Map | Op::Filter, Some((inputs, outputs))) = (op, quotation)
+            && inputs != outputs
+        {
+            let (word, result) = match op {
+                Op::Map => ("map", "exactly one value"),
+                _ => ("filter", "a flag"),
+            };
+            error.get_or_insert_with(|| {
+                StackCheckError::new(format!(
+                    "in {}: the quotation given to {} at ip={} takes {} values and leaves {}; \
+                     it must replace each item with {}",
+                    location, word, ip, inputs, outputs, result
+                ))
+            });
+            continue;
+        }
+
+        let delta = match (op, quotation) {
+            (Op::Call, Some((inputs, outputs))) => Some(StackDelta::Fixed {
+                inputs: inputs + 1,
+                outputs,
+            }),
+            _ => effect(op).or_else(|| {
+                words
+                    .of_call(op)
+                    .map(|(inputs, outputs)| StackDelta::Fixed { inputs, outputs })
+            }),
+        };
+        let needed = match delta {
+            Some(StackDelta::Fixed { inputs, .. } | StackDelta::Dynamic { inputs }) => inputs,
+            _ => 0,
+        };
+        if matches!(entry, Entry::Height(_)) && h < height(needed) {
+            error.get_or_insert_with(|| {
+                StackCheckError::new(format!(
+                    "in {}: stack underflow at ip={}, op={:?}, needed {} items",
+                    location, ip, op, needed
+                ))
+            });
+            continue;
+        }
+        lowest = lowest.min(h.saturating_sub(height(needed)));
+
+        let after = match delta {
+            Some(StackDelta::Fixed { inputs, outputs }) => h
+                .saturating_sub(height(inputs))
+                .saturating_add(height(outputs)),
+            Some(StackDelta::Clears) if matches!(entry, Entry::Height(_)) => 0,
+            // The height now depends on a quotation or an unknown word, so
+            // this path can't be followed any further
+            _ => {
+                complete = false;
+                continue;
             }
-            None => {
-                // Unknown effect (e.g., user-defined call). From here, we can't
-                // soundly reason about stack height, so stop checking.
-                return Ok(());
+        };
+        for next in successors(ops, ip) {
+            if next <= ops.len() {
+                pending.push((next, after, Some(ip)));
             }
         }
     }
 
-    Ok(())
+    *heights = arrivals.iter().map(|a| a.map(|(h, _)| h)).collect();
+    if let Some(error) = error {
+        return Pass::Error(error);
+    }
+    Pass::Done(match arrivals[ops.len()] {
+        Some((exit, _)) if complete => Some((
+            start.saturating_sub(lowest) as usize,
+            exit.saturating_sub(lowest) as usize,
+        )),
+        _ => None,
+    })
+}
+
+/// A count of stack items as a height. Counts past `i32::MAX` (only a
+/// `Peek` that deep has them) saturate: no stack is that high either.
+fn height(count: usize) -> i32 {
+    i32::try_from(count).unwrap_or(i32::MAX)
+}
+
+/// The ips control can reach after `ops[ip]`. Out-of-range jump targets
+/// are left to the verifier.
+fn successors(ops: &[Op], ip: usize) -> Vec<usize> {
+    let target = |offset: i32| usize::try_from(ip as i64 + offset as i64).unwrap_or(usize::MAX);
+    match ops[ip] {
+        Op::Jump(offset) => vec![target(offset)],
+        Op::JumpIfFalse(offset) | Op::JumpIfTrue(offset) => vec![ip + 1, target(offset)],
+        Op::Return => vec![ops.len()],
+        _ => vec![ip + 1],
+    }
+}
+
+/// Explain two paths reaching `target` with different heights, in terms of
+/// the `if`, `when` or loop the compiler lowered to those jumps.
+fn describe_mismatch(
+    ops: &[Op],
+    arrivals: &[Option<(i32, Option<usize>)>],
+    target: usize,
+    first: (Option<usize>, i32),
+    second: (Option<usize>, i32),
+) -> String {
+    let is_forward_jump = |from: Option<usize>| matches!(from, Some(ip) if ip < target && matches!(ops[ip], Op::Jump(_)));
+    let is_back_edge = |from: Option<usize>| matches!(from, Some(ip) if ip >= target);
+    let skips_to_target = |from: Option<usize>| matches!(from, Some(ip) if ip + 1 < target && matches!(ops[ip], Op::JumpIfFalse(_)));
+
+    // `times`, `while` and `until`: the jump back to the loop head arrives
+    // with the height the body left
+    if is_back_edge(second.0) || is_back_edge(first.0) {
+        let (entry, back) = if is_back_edge(second.0) {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        return format!(
+            "loop body changes the stack height by {:+} on each iteration (loop at ip={})",
+            back.1 - entry.1,
+            target
+        );
+    }
+
+    // `if`: the then-branch ends by jumping over the else-branch
+    if is_forward_jump(first.0) || is_forward_jump(second.0) {
+        let (then_end, else_end) = if is_forward_jump(first.0) {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let else_start = then_end.0.unwrap_or(0) + 1;
+        // The condition jumps past the then-branch to the start of the else
+        let condition = (0..else_start).rev().find(|&ip| {
+            matches!(ops[ip], Op::JumpIfFalse(offset) if ip as i64 + offset as i64 == else_start as i64)
+        });
+        if let Some(Some((base, _))) = condition.map(|ip| arrivals[ip + 1]) {
+            return format!(
+                "if branches leave different stack heights: then {:+}, else {:+} (if at ip={})",
+                then_end.1 - base,
+                else_end.1 - base,
+                condition.unwrap_or(0)
+            );
+        }
+    }
+
+    // `when`: the condition skips straight to the end of the body
+    if skips_to_target(first.0) || skips_to_target(second.0) {
+        let (skipped, body_end) = if skips_to_target(first.0) {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        return format!(
+            "when body changes the stack height by {:+}; it must leave it unchanged (when at ip={})",
+            body_end.1 - skipped.1,
+            skipped.0.unwrap_or(0)
+        );
+    }
+
+    format!(
+        "paths reach ip={} with different stack heights ({} and {})",
+        target, first.1, second.1
+    )
+}
+
+/// The stack height on arrival at each op of `ops`, where it can be
+/// inferred, for the disassembler. Main code starts from `initial_height`;
+/// word and quotation bodies (None) are measured from their entry height,
+/// so the height goes negative as they take inputs from the caller. The
+/// extra last entry is the height at the end.
+pub fn stack_heights(
+    ops: &[Op],
+    initial_height: Option<i32>,
+    words: &BTreeMap<String, Vec<Op>>,
+) -> Vec<Option<i32>> {
+    let entry = initial_height.map_or(Entry::Caller, Entry::Height);
+    let mut effects = WordEffects::new(words);
+    let mut unbalanced = HashSet::new();
+    let mut heights = Vec::new();
+    while let Pass::Unbalanced { at, .. } =
+        scan_paths("", ops, entry, &mut effects, &unbalanced, &mut heights)
+    {
+        unbalanced.insert(at);
+    }
+    heights
+}
+
+/// The net (inputs, outputs) of the word `name`, if it's fixed.
+pub fn word_effect(words: &BTreeMap<String, Vec<Op>>, name: &str) -> Option<(usize, usize)> {
+    WordEffects::new(words).of_call(&Op::CallWord(name.to_string()))
+}
+
+/// Check stack effects with a given initial stack height.
+#[allow(dead_code)]
+pub fn check_ops_with_initial(ops: &[Op], initial_height: i32) -> Result<(), StackCheckError> {
+    check_ops_with_words(ops, initial_height, &BTreeMap::new(), &mut Vec::new())
+}
+
+/// Like `check_ops_with_initial`, but calls to the given words are checked
+/// against their inferred effects instead of ending the analysis.
+fn check_ops_with_words(
+    ops: &[Op],
+    initial_height: i32,
+    words: &BTreeMap<String, Vec<Op>>,
+    warnings: &mut Vec<String>,
+) -> Result<(), StackCheckError> {
+    let mut effects = WordEffects::new(words);
+    scan(
+        "main",
+        ops,
+        Entry::Height(initial_height),
+        &mut effects,
+        warnings,
+    )?;
+    check_quotations("main", ops, &mut effects, warnings)
 }
 
 /// Check stack effects starting from empty stack.
+#[allow(dead_code)]
 pub fn check_ops(ops: &[Op]) -> Result<(), StackCheckError> {
     check_ops_with_initial(ops, 0)
 }
 
+/// Check a program from an empty stack: main, then the branches and loops
+/// of every word body. Returns warnings for branches and loops that leave
+/// the stack height unbalanced.
+pub fn check_program(prog: &ProgramBc) -> Result<Vec<String>, StackCheckError> {
+    check_program_with(prog, 0, &prog.words)
+}
+
+/// Check a program whose main starts at `initial_height`, resolving calls
+/// through `words` (which may hold words defined before this program).
+pub fn check_program_with(
+    prog: &ProgramBc,
+    initial_height: i32,
+    words: &BTreeMap<String, Vec<Op>>,
+) -> Result<Vec<String>, StackCheckError> {
+    let mut warnings = Vec::new();
+    let main = prog.code.first().map_or(&[][..], |code| &code.ops);
+    check_ops_with_words(main, initial_height, words, &mut warnings)?;
+
+    let mut effects = WordEffects::new(words);
+    // In name order, so the first error reported is deterministic
+    for (name, body) in &prog.words {
+        let location = format!("word '{}'", name);
+        scan(&location, body, Entry::Caller, &mut effects, &mut warnings)?;
+        check_quotations(&location, body, &mut effects, &mut warnings)?;
+        if let Some(doc) = prog.docs.get(name) {
+            check_effect_comment(name, doc, &mut effects, &mut warnings);
+        }
+    }
+    Ok(warnings)
+}
+
+/// Warn if a doc comment's leading `( a b -- c )` doesn't fit the effect
+/// inferred for the word. Names stand for one value each; a comment is
+/// consistent if it takes at least the inputs the body reaches and changes
+/// the height by the same amount (`( a -- a )` fits an empty body).
+fn check_effect_comment(
+    name: &str,
+    doc: &str,
+    effects: &mut WordEffects,
+    warnings: &mut Vec<String>,
+) {
+    let Some((declared_in, declared_out)) = declared_effect(doc) else {
+        return;
+    };
+    let Some((inputs, outputs)) = effects.of_call(&Op::CallWord(name.to_string())) else {
+        return;
+    };
+    if declared_in >= inputs && declared_in + outputs == declared_out + inputs {
+        return;
+    }
+    warnings.push(format!(
+        "in word '{}': the comment says {} in, {} out, but the body takes {} and leaves {}",
+        name, declared_in, declared_out, inputs, outputs
+    ));
+}
+
+/// Input and output counts of a doc comment's leading `( a b -- c )`, if
+/// it has one that counts values (no `...` rest of the stack).
+fn declared_effect(doc: &str) -> Option<(usize, usize)> {
+    let inner = doc.strip_prefix('(')?;
+    let inner = &inner[..inner.find(')')?];
+    let (inputs, outputs) = inner.split_once("--")?;
+    let inputs: Vec<&str> = inputs.split_whitespace().collect();
+    let outputs: Vec<&str> = outputs.split_whitespace().collect();
+    if inputs.iter().chain(&outputs).any(|n| n.contains("..")) {
+        return None;
+    }
+    Some((inputs.len(), outputs.len()))
+}
+
+/// Check the bodies of quotation literals pushed by `ops`, which take
+/// their inputs from whatever runs them.
+fn check_quotations(
+    location: &str,
+    ops: &[Op],
+    effects: &mut WordEffects,
+    warnings: &mut Vec<String>,
+) -> Result<(), StackCheckError> {
+    for (ip, op) in ops.iter().enumerate() {
+        if let Op::Push(Value::CompiledQuotation(body)) | Op::Capture(body) = op {
+            let location = format!("quotation at ip={} in {}", ip, location);
+            scan(&location, body, Entry::Caller, effects, warnings)?;
+            check_quotations(&location, body, effects, warnings)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lang::value::Value;
 
     #[test]
     fn test_simple_ops() {
@@ -178,6 +538,22 @@ mod tests {
         assert!(result.unwrap_err().message.contains("underflow"));
     }
 
+    #[test]
+    fn test_huge_peek_underflows_without_overflow() {
+        // `n pick` with a constant n, as `#peek n` can still produce
+        for depth in [2147483647, 99999999999] {
+            let ops = vec![Op::Push(Value::Integer(1)), Op::Peek(depth)];
+            let err = check_ops(&ops).unwrap_err();
+            assert!(err.message.contains(&format!("needed {} items", depth + 1)));
+
+            let body = vec![Op::Peek(depth), Op::Drop, Op::Peek(depth)];
+            let words = words(&[("deep", body)]);
+            assert!(word_effect(&words, "deep").is_some());
+        }
+        let err = check_ops(&[Op::Peek(usize::MAX)]).unwrap_err();
+        assert!(err.message.contains("underflow"));
+    }
+
     #[test]
     fn test_jump_no_stack_effect() {
         let ops = vec![
@@ -275,4 +651,285 @@ mod tests {
         // Should return Ok because we stop analyzing at CallWord
         assert!(check_ops(&ops).is_ok());
     }
+
+    #[test]
+    fn test_quotation_combinators_stop_analysis() {
+        // `[ 1 ] call 2 +` - call's result depends on the quotation
+        let ops = vec![
+            Op::Push(Value::CompiledQuotation(vec![Op::Push(Value::Integer(1))])),
+            Op::Call,
+            Op::Push(Value::Integer(2)),
+            Op::Add,
+        ];
+        assert!(check_ops(&ops).is_ok());
+    }
+
+    #[test]
+    fn test_clear_empties_stack() {
+        let ops = vec![
+            Op::Push(Value::Integer(1)),
+            Op::Push(Value::Integer(2)),
+            Op::Clear,
+            Op::Add,
+        ];
+        assert!(check_ops(&ops).is_err());
+    }
+
+    fn words(entries: &[(&str, Vec<Op>)]) -> BTreeMap<String, Vec<Op>> {
+        entries
+            .iter()
+            .map(|(name, ops)| (name.to_string(), ops.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_calls_use_inferred_word_effects() {
+        let words = words(&[
+            ("pair", vec![Op::Push(Value::Integer(1)), Op::Dup]),
+            ("add3", vec![Op::Add, Op::Add]),
+            ("sum-pair", vec![Op::CallWord("pair".to_string()), Op::Add]),
+        ]);
+
+        let ops = vec![Op::CallWord("sum-pair".to_string()), Op::Print];
+        assert!(check_ops_with_words(&ops, 0, &words, &mut Vec::new()).is_ok());
+
+        let ops = vec![
+            Op::Push(Value::Integer(1)),
+            Op::Push(Value::Integer(2)),
+            Op::CallWord("add3".to_string()),
+        ];
+        let err = check_ops_with_words(&ops, 0, &words, &mut Vec::new()).unwrap_err();
+        assert!(err.message.contains("needed 3 items"), "{}", err);
+        assert!(check_ops_with_words(&ops, 1, &words, &mut Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn test_recursive_and_branching_words_stop_analysis() {
+        let words = words(&[
+            ("loop", vec![Op::Drop, Op::CallWord("loop".to_string())]),
+            ("branch", vec![Op::JumpIfFalse(2), Op::Dup]),
+        ]);
+        for name in ["loop", "branch"] {
+            let ops = vec![Op::CallWord(name.to_string()), Op::Add];
+            assert!(
+                check_ops_with_words(&ops, 0, &words, &mut Vec::new()).is_ok(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    fn warnings_for(ops: Vec<Op>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        check_ops_with_words(&ops, 0, &BTreeMap::new(), &mut warnings).unwrap();
+        warnings
+    }
+
+    #[test]
+    fn test_balanced_if_branches() {
+        // true [ 1 2 + ] [ 3 ] if 4 +
+        let ops = vec![
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(5),
+            Op::Push(Value::Integer(1)),
+            Op::Push(Value::Integer(2)),
+            Op::Add,
+            Op::Jump(2),
+            Op::Push(Value::Integer(3)),
+            Op::Push(Value::Integer(4)),
+            Op::Add,
+        ];
+        assert!(warnings_for(ops).is_empty());
+    }
+
+    #[test]
+    fn test_unbalanced_if_branches_warn() {
+        // 1 true [ drop ] [ 3 ] if
+        let ops = vec![
+            Op::Push(Value::Integer(1)),
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(3),
+            Op::Drop,
+            Op::Jump(2),
+            Op::Push(Value::Integer(3)),
+        ];
+        assert_eq!(
+            warnings_for(ops),
+            vec![
+                "in main: if branches leave different stack heights: then -1, else +1 (if at ip=2)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_when_body_warns() {
+        let ops = vec![
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(2),
+            Op::Push(Value::Integer(42)),
+        ];
+        let warnings = warnings_for(ops);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("when body changes the stack height by +1"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    /// `n [ body ] times` as the compiler lowers it.
+    fn times_loop(n: i64, body: Vec<Op>) -> Vec<Op> {
+        let len = body.len() as i32;
+        let mut ops = vec![
+            Op::Push(Value::Integer(n)),
+            Op::CheckTimes,
+            Op::Dup,
+            Op::Push(Value::Integer(0)),
+            Op::Le,
+            Op::JumpIfTrue(6 + len),
+            Op::ToAux,
+        ];
+        ops.extend(body);
+        ops.extend([
+            Op::FromAux,
+            Op::Push(Value::Integer(1)),
+            Op::Sub,
+            Op::Jump(-(8 + len)),
+            Op::Drop,
+        ]);
+        ops
+    }
+
+    #[test]
+    fn test_times_loop_height() {
+        // 0 5 [ 1 + ] times
+        let mut ops = vec![Op::Push(Value::Integer(0))];
+        ops.extend(times_loop(5, vec![Op::Push(Value::Integer(1)), Op::Add]));
+        assert!(warnings_for(ops).is_empty());
+
+        // 5 [ + ] times underflows on the first iteration
+        assert!(check_ops(&times_loop(5, vec![Op::Add])).is_err());
+
+        // 2 [ 1 ] times + is fine at run time, so the unbalanced loop only
+        // warns and the + after it isn't reported as an underflow
+        let mut ops = times_loop(2, vec![Op::Push(Value::Integer(1))]);
+        ops.push(Op::Add);
+        let warnings = warnings_for(ops);
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("loop body changes the stack height by +1 on each iteration"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn test_program_checks_word_bodies_and_quotations() {
+        let mut prog = ProgramBc::new();
+        prog.words.insert(
+            "maybe".to_string(),
+            vec![Op::JumpIfFalse(2), Op::Dup, Op::Return],
+        );
+        prog.code[0].ops = vec![Op::Push(Value::CompiledQuotation(vec![
+            Op::JumpIfFalse(3),
+            Op::Drop,
+            Op::Jump(1),
+        ]))];
+
+        let warnings = check_program(&prog).unwrap();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("in quotation at ip=0 in main: if branches"));
+        assert!(warnings[1].starts_with("in word 'maybe': when body"));
+    }
+
+    fn quot(body: Vec<Op>) -> Op {
+        Op::Push(Value::CompiledQuotation(body))
+    }
+
+    #[test]
+    fn test_call_of_literal_quotation() {
+        // [ 1 2 ] call +
+        let ops = vec![
+            quot(vec![
+                Op::Push(Value::Integer(1)),
+                Op::Push(Value::Integer(2)),
+            ]),
+            Op::Call,
+            Op::Add,
+        ];
+        assert!(check_ops(&ops).is_ok());
+
+        // [ drop ] call
+        let ops = vec![quot(vec![Op::Drop]), Op::Call];
+        let err = check_ops(&ops).unwrap_err();
+        assert!(err.message.contains("needed 2 items"), "{}", err);
+    }
+
+    #[test]
+    fn test_map_and_filter_quotation_arity() {
+        let list = Op::Push(Value::List(vec![Value::Integer(1)]));
+        let ok = vec![list.clone(), quot(vec![Op::Dup, Op::Mul]), Op::Map];
+        assert!(check_ops(&ok).is_ok());
+
+        let bad = vec![list.clone(), quot(vec![Op::Dup]), Op::Map];
+        let err = check_ops(&bad).unwrap_err();
+        assert!(
+            err.message
+                .contains("the quotation given to map at ip=2 takes 1 values and leaves 2"),
+            "{}",
+            err
+        );
+
+        let bad = vec![list, quot(vec![Op::Drop]), Op::Filter];
+        assert!(check_ops(&bad).is_err());
+    }
+
+    #[test]
+    fn test_stack_heights() {
+        // 1 true [ 2 ] [ 3 4 + ] if sq
+        let ops = vec![
+            Op::Push(Value::Integer(1)),
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(3),
+            Op::Push(Value::Integer(2)),
+            Op::Jump(4),
+            Op::Push(Value::Integer(3)),
+            Op::Push(Value::Integer(4)),
+            Op::Add,
+            Op::CallWord("sq".to_string()),
+            Op::Print,
+        ];
+        let known = words(&[("sq", vec![Op::Dup, Op::Mul])]);
+        let heights = stack_heights(&ops, Some(0), &known);
+        let expected = [0, 1, 2, 1, 2, 1, 2, 3, 2, 2, 1];
+        assert_eq!(heights, expected.map(Some).to_vec());
+
+        // Unknown past a call to an unknown word; bodies are relative
+        let heights = stack_heights(&ops[7..], None, &BTreeMap::new());
+        assert_eq!(heights, vec![Some(0), Some(-1), None, None]);
+    }
+
+    #[test]
+    fn test_effect_comments() {
+        let mut prog = ProgramBc::new();
+        let body = vec![Op::Dup, Op::Mul, Op::Return];
+        for name in ["sq", "sq-wrong", "sq-vague", "id"] {
+            prog.words.insert(name.to_string(), body.clone());
+        }
+        prog.words.insert("id".to_string(), vec![Op::Return]);
+        prog.docs.insert("sq".into(), "( n -- n*n ) Square.".into());
+        prog.docs
+            .insert("sq-wrong".into(), "( a b -- c ) Square.".into());
+        prog.docs
+            .insert("sq-vague".into(), "( ... -- ... ) Square.".into());
+        prog.docs.insert("id".into(), "( x -- x ) Nothing.".into());
+
+        let warnings = check_program(&prog).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "in word 'sq-wrong': the comment says 2 in, 1 out, but the body takes 1 and leaves 1"
+            ]
+        );
+    }
 }
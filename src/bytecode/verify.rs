@@ -0,0 +1,224 @@
+use crate::bytecode::compile::MAX_LOCALS;
+use crate::bytecode::{Op, ProgramBc};
+use crate::frontend::parser::DEFAULT_MAX_NESTING;
+use crate::lang::value::Value;
+
+#[derive(Debug)]
+pub struct VerifyError {
+    pub message: String,
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "verify error: {}", self.message)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl VerifyError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+/// Structural checks on a compiled program, independent of how it was
+/// produced. Bytecode loaded from disk may be stale or corrupt, so this
+/// catches malformed programs before the VM runs them:
+///
+/// - there is a main code object
+/// - every jump lands inside its own instruction stream (one past the end is
+///   allowed and means "fall off the end")
+/// - quotations and lists nest no deeper than the compiler allows, since
+///   the VM recurses into them
+/// - local slots are below `MAX_LOCALS`, since the VM makes room for every
+///   slot up to the one used
+///
+/// Nested quotations are checked as their own streams.
+pub fn verify_program(program: &ProgramBc) -> Result<(), VerifyError> {
+    if program.code.is_empty() {
+        return Err(VerifyError::new("program has no main code object"));
+    }
+
+    for (i, code) in program.code.iter().enumerate() {
+        let name = if i == 0 {
+            "main".to_string()
+        } else {
+            format!("code object {}", i)
+        };
+        verify_ops(&name, &code.ops, 0)?;
+    }
+
+    // In name order, so the first error reported is deterministic
+    for (name, ops) in &program.words {
+        verify_ops(&format!("word '{}'", name), ops, 0)?;
+    }
+
+    Ok(())
+}
+
+/// The same checks on a quotation built at run time.
+pub fn verify_quotation(ops: &[Op]) -> Result<(), VerifyError> {
+    verify_ops("quotation", ops, 0)
+}
+
+fn verify_ops(location: &str, ops: &[Op], depth: usize) -> Result<(), VerifyError> {
+    for (ip, op) in ops.iter().enumerate() {
+        match op {
+            Op::Jump(offset) | Op::JumpIfFalse(offset) | Op::JumpIfTrue(offset) => {
+                let target = ip as i64 + *offset as i64;
+                if target < 0 || target > ops.len() as i64 {
+                    return Err(VerifyError::new(format!(
+                        "in {}: jump at ip={} targets {}, outside 0..={}",
+                        location,
+                        ip,
+                        target,
+                        ops.len()
+                    )));
+                }
+            }
+            Op::StoreLocal(slot) | Op::LoadLocal(slot) if *slot >= MAX_LOCALS => {
+                return Err(VerifyError::new(format!(
+                    "in {}: local slot {} at ip={} is past the limit of {}",
+                    location, slot, ip, MAX_LOCALS
+                )));
+            }
+            Op::Push(value) => verify_value(location, ip, value, depth)?,
+            Op::Capture(body) => verify_quotation_at(location, ip, body, depth)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Check a quotation pushed (or captured) at `ip` as its own stream.
+fn verify_quotation_at(
+    location: &str,
+    ip: usize,
+    ops: &[Op],
+    depth: usize,
+) -> Result<(), VerifyError> {
+    check_nesting(location, ip, depth)?;
+    verify_ops(
+        &format!("quotation at ip={} in {}", ip, location),
+        ops,
+        depth + 1,
+    )
+}
+
+fn check_nesting(location: &str, ip: usize, depth: usize) -> Result<(), VerifyError> {
+    if depth >= DEFAULT_MAX_NESTING {
+        return Err(VerifyError::new(format!(
+            "in {}: value at ip={} nests more than {} levels deep",
+            location, ip, DEFAULT_MAX_NESTING
+        )));
+    }
+    Ok(())
+}
+
+fn verify_value(location: &str, ip: usize, value: &Value, depth: usize) -> Result<(), VerifyError> {
+    let nested = matches!(
+        value,
+        Value::CompiledQuotation(_) | Value::List(_) | Value::Map(_)
+    );
+    if nested {
+        check_nesting(location, ip, depth)?;
+    }
+
+    match value {
+        Value::CompiledQuotation(ops) => verify_quotation_at(location, ip, ops, depth),
+        Value::List(items) => items
+            .iter()
+            .try_for_each(|item| verify_value(location, ip, item, depth + 1)),
+        Value::Map(entries) => entries
+            .values()
+            .try_for_each(|item| verify_value(location, ip, item, depth + 1)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::CodeObject;
+
+    fn program(main: Vec<Op>) -> ProgramBc {
+        let mut program = ProgramBc::new();
+        program.code[0].ops = main;
+        program
+    }
+
+    #[test]
+    fn test_valid_program() {
+        let ops = vec![
+            Op::Push(Value::Bool(true)),
+            Op::JumpIfFalse(2),
+            Op::Push(Value::Integer(1)),
+            Op::Jump(-3),
+        ];
+        assert!(verify_program(&program(ops)).is_ok());
+    }
+
+    #[test]
+    fn test_jump_to_end_is_allowed() {
+        assert!(verify_program(&program(vec![Op::Jump(1)])).is_ok());
+    }
+
+    #[test]
+    fn test_jump_out_of_bounds() {
+        let err = verify_program(&program(vec![Op::Dup, Op::Jump(5)])).unwrap_err();
+        assert!(err.message.contains("in main: jump at ip=1 targets 6"));
+
+        let err = verify_program(&program(vec![Op::JumpIfTrue(-1)])).unwrap_err();
+        assert!(err.message.contains("targets -1"));
+    }
+
+    #[test]
+    fn test_checks_words_and_quotations() {
+        let mut prog = program(vec![]);
+        prog.words.insert("bad".to_string(), vec![Op::Jump(2)]);
+        let err = verify_program(&prog).unwrap_err();
+        assert!(err.message.contains("in word 'bad'"), "{}", err.message);
+
+        let quot = Value::CompiledQuotation(vec![Op::Jump(9)]);
+        let prog = program(vec![Op::Push(Value::List(vec![quot]))]);
+        let err = verify_program(&prog).unwrap_err();
+        assert!(
+            err.message.contains("quotation at ip=0 in main"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth| {
+            let mut value = Value::Integer(1);
+            for _ in 0..depth {
+                value = Value::CompiledQuotation(vec![Op::Push(value)]);
+            }
+            program(vec![Op::Push(value)])
+        };
+        assert!(verify_program(&nested(DEFAULT_MAX_NESTING)).is_ok());
+        let err = verify_program(&nested(DEFAULT_MAX_NESTING + 1)).unwrap_err();
+        assert!(err.message.contains("nests more than"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_missing_main() {
+        let prog = ProgramBc {
+            code: Vec::<CodeObject>::new(),
+            words: Default::default(),
+            docs: Default::default(),
+            sites: Default::default(),
+        };
+        assert!(
+            verify_program(&prog)
+                .unwrap_err()
+                .message
+                .contains("no main")
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::bytecode::ProgramBc;
+use crate::bytecode::compile::Compiler;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::frontend::source::decode_source;
+use crate::lang::value::Value;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::{VmBc, VmBcConfig};
+
+// =============================================================================
+// Engine
+// =============================================================================
+//
+// The entry point for programs that embed EMBER. An engine is one VM plus
+// the lexer, parser and compiler in front of it: hand it source and it
+// runs, keeping the words it defines for later calls, the way the REPL
+// does. The host moves values in and out through the stack, adds words of
+// its own with `register`, and can collect what the script prints instead
+// of letting it reach stdout.
+//
+// Errors of every stage come back as a `RuntimeError`, with the source
+// attached, so a host can print them exactly as the CLI would. Unlike the
+// VM on its own, an engine turns a panic inside EMBER into an error
+// (`catch_panics`), so a bug in a builtin can't take the host down.
+
+/// An EMBER interpreter to embed in a Rust program.
+pub struct Engine {
+    vm: VmBc,
+    /// Where output goes while it is being captured
+    captured: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// An engine with the default config, which allows everything a script
+    /// run from the command line may do.
+    pub fn new() -> Self {
+        Self::with_config(VmBcConfig {
+            catch_panics: true,
+            ..VmBcConfig::default()
+        })
+    }
+
+    /// An engine with the given limits and permissions, e.g. from
+    /// `Profile::Sandbox.config()` for scripts the host didn't write.
+    pub fn with_config(config: VmBcConfig) -> Self {
+        Engine {
+            vm: VmBc::with_config(config),
+            captured: None,
+        }
+    }
+
+    /// Compile and run `source` against the current stack. Words it defines
+    /// stay available to later calls.
+    pub fn eval(&mut self, source: &str) -> RuntimeResult<()> {
+        let program = compile(source)?;
+        self.vm.set_source(source.to_string());
+        self.vm.run_more(&program)
+    }
+
+    /// Compile and run a file and the files it imports, like `ember path`.
+    pub fn run_file(&mut self, path: &Path) -> RuntimeResult<()> {
+        let program = Compiler::new()
+            .compile_from_file(path)
+            .map_err(|e| RuntimeError::new(&e.to_string()).boxed())?;
+        // Only for showing errors in context; the compiler read it already
+        if let Some(source) = fs::read(path)
+            .ok()
+            .and_then(|bytes| decode_source(&bytes, true).ok())
+        {
+            self.vm.set_source(source.text);
+        }
+        self.vm.set_file(path.to_path_buf());
+        self.vm.run_more(&program)
+    }
+
+    /// Push a value for the script to use.
+    pub fn push(&mut self, value: Value) {
+        self.vm.push(value);
+    }
+
+    /// Take the top value off the stack, if there is one.
+    pub fn pop(&mut self) -> Option<Value> {
+        self.vm.pop().ok()
+    }
+
+    /// The stack, bottom first.
+    pub fn stack(&self) -> &[Value] {
+        self.vm.stack()
+    }
+
+    /// Add a word implemented by the host. It gets the VM to pop its inputs
+    /// from and push its results to, and fails with a `RuntimeError`:
+    ///
+    /// ```
+    /// # use ember::{Engine, Value};
+    /// let mut engine = Engine::new();
+    /// engine.register("double", |vm| {
+    ///     let n = vm.pop_int()?;
+    ///     vm.push(Value::Integer(n * 2));
+    ///     Ok(())
+    /// });
+    /// engine.eval("21 double").unwrap();
+    /// assert_eq!(engine.pop(), Some(Value::Integer(42)));
+    /// ```
+    ///
+    /// Words the script defines and builtins take precedence over it.
+    pub fn register(
+        &mut self,
+        name: &str,
+        word: impl Fn(&mut VmBc) -> RuntimeResult<()> + 'static,
+    ) {
+        self.vm.register_word(name, word);
+    }
+
+    /// Keep what the script prints from now on, for `take_output`, instead
+    /// of writing it to stdout.
+    pub fn capture_output(&mut self) {
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        self.vm.set_output(SharedBuffer(Rc::clone(&buffer)));
+        self.captured = Some(buffer);
+    }
+
+    /// What the script has printed since the last call, if output is being
+    /// captured. Invalid UTF-8 is replaced.
+    pub fn take_output(&mut self) -> String {
+        match &self.captured {
+            Some(buffer) => String::from_utf8_lossy(&buffer.take()).into_owned(),
+            None => String::new(),
+        }
+    }
+
+    /// Send what the script prints to `output`.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.vm.set_output(output);
+        self.captured = None;
+    }
+
+    /// Where `read` and the other input words read from; stdin by default.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.vm.set_input(input);
+    }
+
+    /// The VM underneath, for what the engine doesn't cover.
+    pub fn vm(&mut self) -> &mut VmBc {
+        &mut self.vm
+    }
+}
+
+fn compile(source: &str) -> RuntimeResult<ProgramBc> {
+    let compile_error = |e: String| {
+        RuntimeError::new(&e)
+            .with_source(source.to_string())
+            .boxed()
+    };
+    let tokens = Lexer::new(source)
+        .tokenize()
+        .map_err(|e| compile_error(e.to_string()))?;
+    let ast = Parser::new(tokens)
+        .parse()
+        .map_err(|e| compile_error(e.to_string()))?;
+    Compiler::new()
+        .compile_program(&ast)
+        .map_err(|e| compile_error(e.to_string()))
+}
+
+/// Output shared between the VM and the engine that reads it back.
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_and_stack_persist_between_evals() {
+        let mut engine = Engine::new();
+        engine.eval("def sq dup * end").unwrap();
+        engine.push(Value::Integer(7));
+        engine.eval("sq 1 +").unwrap();
+        assert_eq!(engine.stack(), &[Value::Integer(50)]);
+        assert_eq!(engine.pop(), Some(Value::Integer(50)));
+        assert_eq!(engine.pop(), None);
+    }
+
+    #[test]
+    fn test_host_words_and_captured_output() {
+        let mut engine = Engine::new();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        engine.register("log", move |vm| {
+            let message = vm.pop_string()?;
+            log.borrow_mut().push(message);
+            Ok(())
+        });
+        engine.register("fail", |_| Err(RuntimeError::new("host said no").boxed()));
+
+        engine.capture_output();
+        engine.eval("\"started\" log \"hello\" print").unwrap();
+        assert_eq!(*seen.borrow(), vec!["started"]);
+        assert_eq!(engine.take_output(), "hello\n");
+        assert_eq!(engine.take_output(), "");
+
+        // Host errors are script errors: `try` catches them
+        engine.eval("[ fail ] [ ] try").unwrap();
+        assert_eq!(
+            engine.pop(),
+            Some(Value::String("host said no".to_string()))
+        );
+        let err = engine.eval("fail").unwrap_err();
+        assert_eq!(err.call_stack, vec!["fail"]);
+    }
+
+    #[test]
+    fn test_errors_carry_their_source() {
+        let mut engine = Engine::new();
+        let err = engine.eval("1 [ 2").unwrap_err();
+        assert_eq!(err.source.as_deref(), Some("1 [ 2"));
+
+        let err = engine.eval("1\n\"a\" +").unwrap_err();
+        assert_eq!(err.span.map(|span| span.line), Some(2));
+        // The engine is still usable after an error
+        engine.eval("2 3 +").unwrap();
+        assert_eq!(engine.pop(), Some(Value::Integer(5)));
+    }
+}
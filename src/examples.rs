@@ -0,0 +1,60 @@
+//! Example programs compiled into the binary, so `ember examples` works
+//! without a checkout of the repository.
+
+/// A bundled example program.
+pub struct Example {
+    /// Name used on the command line.
+    pub name: &'static str,
+    pub description: &'static str,
+    pub source: &'static str,
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "fizzbuzz",
+        description: "Print the numbers 1 to 100, replacing multiples of 3 and 5",
+        source: include_str!("../examples/fizzbuzz.em"),
+    },
+    Example {
+        name: "word-count",
+        description: "Count the lines, words, and characters of a text",
+        source: include_str!("../examples/word_count.em"),
+    },
+    Example {
+        name: "calculator",
+        description: "Evaluate arithmetic in reverse Polish notation",
+        source: include_str!("../examples/calculator.em"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ember::bytecode::compile::Compiler;
+    use ember::frontend::lexer::Lexer;
+    use ember::frontend::parser::Parser;
+    use ember::runtime::vm_bc::VmBc;
+
+    #[test]
+    fn test_bundled_examples_run() {
+        for example in EXAMPLES {
+            let tokens = Lexer::new(example.source).tokenize().unwrap();
+            let program = Parser::new(tokens).parse().unwrap();
+            let bytecode = Compiler::new().compile_program(&program).unwrap();
+            let mut vm = VmBc::new();
+            vm.set_output(std::io::sink());
+            vm.run_compiled(&bytecode)
+                .unwrap_or_else(|e| panic!("example '{}' failed: {}", example.name, e));
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("calculator").unwrap().name, "calculator");
+        assert!(find("nope").is_none());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::frontend::token::Token;
+use crate::lang::builtins;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Span {
     pub line: usize,
     pub col: usize,
@@ -25,6 +27,16 @@ impl std::fmt::Display for LexerError {
     }
 }
 
+/// Characters that only appear in builtin names: '+' and '*' in the vector
+/// words `v+` and `v*`, '?' ending predicates such as `re-match?`, and '>'
+/// in conversions such as `quot->list`.
+const WORD_OPERATOR_CHARS: &str = "+*?>";
+
+/// Whether `ch` can continue an identifier.
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '-'
+}
+
 pub struct Lexer {
     source: Vec<char>,
     pos: usize,
@@ -71,7 +83,9 @@ impl Lexer {
 
     fn skip_whitespace(&mut self) {
         while let Some(ch) = self.current() {
-            if ch == ' ' || ch == '\t' || ch == '\r' {
+            // A '\r' ending a line is part of the newline token
+            let line_end = ch == '\r' && self.peek() == Some('\n');
+            if (ch == ' ' || ch == '\t' || ch == '\r') && !line_end {
                 self.advance();
             } else {
                 break;
@@ -83,7 +97,7 @@ impl Lexer {
         self.advance();
         let mut comment = String::new();
         while let Some(ch) = self.current() {
-            if ch == '\n' {
+            if ch == '\n' || (ch == '\r' && self.peek() == Some('\n')) {
                 break;
             }
             comment.push(ch);
@@ -115,7 +129,10 @@ impl Lexer {
                         Some('0') => string.push('\0'),
                         Some(ch) => {
                             return Err(LexerError {
-                                message: format!("unknown escape sequence: \\{}", ch),
+                                message: format!(
+                                    "unknown escape sequence: \\{} (write a literal backslash as \\\\)",
+                                    ch
+                                ),
                                 line: self.line,
                                 col: self.col,
                             });
@@ -186,16 +203,18 @@ impl Lexer {
                 });
             }
 
-            let mut value = i64::from_str_radix(&hex, 16).map_err(|_| LexerError {
-                message: format!("invalid hex number: 0x{}", hex),
+            // Parse with the sign attached so -0x8000000000000000 (i64::MIN) fits
+            let signed = if is_negative {
+                format!("-{}", hex)
+            } else {
+                hex
+            };
+            let value = i64::from_str_radix(&signed, 16).map_err(|_| LexerError {
+                message: format!("invalid hex number: 0x{}", signed.trim_start_matches('-')),
                 line: start_line,
                 col: start_col,
             })?;
 
-            if is_negative {
-                value = -value;
-            }
-
             return Ok(Token::Integer(value));
         }
 
@@ -240,27 +259,50 @@ impl Lexer {
             }
             Ok(Token::Float(value))
         } else {
-            let mut value: i64 = digits.parse().map_err(|_| LexerError {
+            // As above, keep the sign so i64::MIN is representable
+            if is_negative {
+                digits.insert(0, '-');
+            }
+            let value: i64 = digits.parse().map_err(|_| LexerError {
                 message: format!("invalid integer: {}", digits),
                 line: start_line,
                 col: start_col,
             })?;
-            if is_negative {
-                value = -value;
-            }
             Ok(Token::Integer(value))
         }
     }
 
+    /// How many characters from here make up an identifier. Operator
+    /// characters and '?' only count when the result is a builtin word such
+    /// as `v+` or `has-key?`, so `dup+` is still `dup +`.
+    fn identifier_len(&self) -> usize {
+        let rest = &self.source[self.pos..];
+        let plain = rest.iter().take_while(|&&ch| is_ident_char(ch)).count();
+        let longest = rest
+            .iter()
+            .take_while(|&&ch| is_ident_char(ch) || WORD_OPERATOR_CHARS.contains(ch))
+            .count();
+        if longest > plain {
+            let word: String = rest[..longest].iter().collect();
+            if builtins::lookup(&word).is_some() {
+                return longest;
+            }
+        }
+        plain
+    }
+
+    /// Whether the identifier characters from here spell a builtin word.
+    fn at_digit_keyword(&self) -> bool {
+        let word: String = self.source[self.pos..][..self.identifier_len()]
+            .iter()
+            .collect();
+        builtins::keyword(&word).is_some()
+    }
+
     fn read_identifier(&mut self) -> Token {
         let mut ident = String::new();
-        while let Some(ch) = self.current() {
-            if ch.is_alphanumeric() || ch == '_' || ch == '-' {
-                ident.push(ch);
-                self.advance();
-            } else {
-                break;
-            }
+        for _ in 0..self.identifier_len() {
+            ident.extend(self.advance());
         }
 
         match ident.as_str() {
@@ -268,90 +310,20 @@ impl Lexer {
             "true" => Token::Bool(true),
             "false" => Token::Bool(false),
 
-            // Stack ops
-            "dup" => Token::Dup,
-            "drop" => Token::Drop,
-            "swap" => Token::Swap,
-            "over" => Token::Over,
-            "rot" => Token::Rot,
-
-            // Arithmetic
-            "neg" => Token::Neg,
-            "abs" => Token::Abs,
-
-            // Logic
-            "and" => Token::And,
-            "or" => Token::Or,
-            "not" => Token::Not,
-
-            // Control flow
-            "if" => Token::If,
-            "when" => Token::When,
-            "cond" => Token::Cond,
-            "call" => Token::Call,
-
-            // Loops & higher-order
-            "times" => Token::Times,
-            "each" => Token::Each,
-            "map" => Token::Map,
-            "filter" => Token::Filter,
-            "fold" => Token::Fold,
-            "range" => Token::Range,
-
-            // List ops
-            "len" => Token::Len,
-            "head" => Token::Head,
-            "tail" => Token::Tail,
-            "cons" => Token::Cons,
-            "concat" => Token::Concat,
-
-            // I/O
-            "print" => Token::Print,
-            "emit" => Token::Emit,
-            "read" => Token::Read,
-            "debug" => Token::Debug,
-
-            // Additional builtins
-            "min" => Token::Min,
-            "max" => Token::Max,
-            "pow" => Token::Pow,
-            "sqrt" => Token::Sqrt,
-            "nth" => Token::Nth,
-            "append" => Token::Append,
-            "sort" => Token::Sort,
-            "reverse" => Token::Reverse,
-            "chars" => Token::Chars,
-            "join" => Token::Join,
-            "split" => Token::Split,
-            "upper" => Token::Upper,
-            "lower" => Token::Lower,
-            "trim" => Token::Trim,
-            "clear" => Token::Clear,
-            "depth" => Token::Depth,
-            "type" => Token::Type,
-            "to-string" => Token::ToString,
-            "to-int" => Token::ToInt,
-
             // Definition
             "def" => Token::Def,
             "end" => Token::End,
             "import" => Token::Import,
             "module" => Token::Module,
             "use" => Token::Use,
+            "var" => Token::Var,
 
-            // Concatenative Combinators
-            "dip" => Token::Dip,
-            "keep" => Token::Keep,
-            "bi" => Token::Bi,
-            "bi2" => Token::Bi2,
-            "tri" => Token::Tri,
-            "both" => Token::Both,
-            "compose" => Token::Compose,
-            "curry" => Token::Curry,
-            "apply" => Token::Apply,
-
-            // User-defined word
-            _ => Token::Ident(ident),
+            // Reserved, not yet parsed
+            "cond" => Token::Cond,
+
+            // Builtin words come from the registry; anything else is a
+            // user-defined word
+            _ => builtins::keyword(&ident).unwrap_or(Token::Ident(ident)),
         }
     }
 
@@ -375,6 +347,14 @@ impl Lexer {
                 self.advance();
                 Token::GtEq
             }
+            ('!', _) => {
+                self.advance();
+                Token::Store
+            }
+            ('@', _) => {
+                self.advance();
+                Token::Load
+            }
             ('+', _) => {
                 self.advance();
                 Token::Plus
@@ -439,6 +419,15 @@ impl Lexer {
                     });
                     self.advance();
                 }
+                // Windows line ending; skip_whitespace leaves only this '\r'
+                Some('\r') => {
+                    tokens.push(Spanned {
+                        token: Token::Newline,
+                        span,
+                    });
+                    self.advance();
+                    self.advance();
+                }
                 Some(';') => {
                     let token = self.read_comment();
                     tokens.push(Spanned { token, span });
@@ -468,6 +457,14 @@ impl Lexer {
                         span,
                     });
                 }
+                Some('#') if self.peek() == Some('{') => {
+                    self.advance();
+                    self.advance();
+                    tokens.push(Spanned {
+                        token: Token::MapBrace,
+                        span,
+                    });
+                }
                 Some('}') => {
                     self.advance();
                     tokens.push(Spanned {
@@ -475,10 +472,23 @@ impl Lexer {
                         span,
                     });
                 }
+                Some(':') if self.peek() == Some('>') => {
+                    self.advance();
+                    self.advance();
+                    tokens.push(Spanned {
+                        token: Token::Bind,
+                        span,
+                    });
+                }
                 Some('-') if self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) => {
                     let token = self.read_number()?;
                     tokens.push(Spanned { token, span });
                 }
+                // Builtins such as `2dup` start with a digit
+                Some(ch) if ch.is_ascii_digit() && self.at_digit_keyword() => {
+                    let token = self.read_identifier();
+                    tokens.push(Spanned { token, span });
+                }
                 Some(ch) if ch.is_ascii_digit() => {
                     let token = self.read_number()?;
                     tokens.push(Spanned { token, span });
@@ -487,7 +497,17 @@ impl Lexer {
                     let token = self.read_identifier();
                     tokens.push(Spanned { token, span });
                 }
-                Some(ch) if "+-*/%=<>!.".contains(ch) => {
+                // The hole `???`
+                Some('?') if self.source[self.pos..].starts_with(&['?'; 3]) => {
+                    for _ in 0..3 {
+                        self.advance();
+                    }
+                    tokens.push(Spanned {
+                        token: Token::Ident("???".to_string()),
+                        span,
+                    });
+                }
+                Some(ch) if "+-*/%=<>!@.".contains(ch) => {
                     if let Some(token) = self.read_operator() {
                         tokens.push(Spanned { token, span });
                     } else {
@@ -631,6 +651,53 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_map() {
+        let t = tokens("#{ \"a\" 1 }");
+        assert_eq!(
+            t,
+            vec![
+                Token::MapBrace,
+                Token::String("a".to_string()),
+                Token::Integer(1),
+                Token::RBrace
+            ]
+        )
+    }
+
+    #[test]
+    fn test_variables() {
+        let t = tokens("var n 42 n! n @ 1 != ");
+        assert_eq!(
+            t,
+            vec![
+                Token::Var,
+                Token::Ident("n".to_string()),
+                Token::Integer(42),
+                Token::Ident("n".to_string()),
+                Token::Store,
+                Token::Ident("n".to_string()),
+                Token::Load,
+                Token::Integer(1),
+                Token::NotEq
+            ]
+        )
+    }
+
+    #[test]
+    fn test_locals() {
+        let t = tokens(":> w :>h");
+        assert_eq!(
+            t,
+            vec![
+                Token::Bind,
+                Token::Ident("w".to_string()),
+                Token::Bind,
+                Token::Ident("h".to_string())
+            ]
+        )
+    }
+
     #[test]
     #[allow(clippy::approx_constant)]
     fn test_floats() {
@@ -728,7 +795,7 @@ mod tests {
 
     #[test]
     fn test_all_stack_ops_keywords() {
-        let t = tokens("dup drop swap over rot");
+        let t = tokens("dup drop swap over rot nip tuck 2dup 2drop pick roll");
         assert_eq!(
             t,
             vec![
@@ -736,9 +803,21 @@ mod tests {
                 Token::Drop,
                 Token::Swap,
                 Token::Over,
-                Token::Rot
+                Token::Rot,
+                Token::Nip,
+                Token::Tuck,
+                Token::TwoDup,
+                Token::TwoDrop,
+                Token::Pick,
+                Token::Roll
             ]
         );
+        // A number followed by a word still needs a space
+        assert_eq!(tokens("2 dup"), vec![Token::Integer(2), Token::Dup]);
+        assert_eq!(
+            tokens("2dupe"),
+            vec![Token::Integer(2), Token::Ident("dupe".into())]
+        );
     }
 
     #[test]
@@ -984,6 +1063,67 @@ mod tests {
         assert_eq!(t, vec![Token::Ident("foo-bar".to_string())]);
     }
 
+    #[test]
+    fn test_identifier_with_arrow() {
+        let t = tokens("quot->list a > b a>b");
+        assert_eq!(
+            t,
+            vec![
+                Token::Ident("quot->list".to_string()),
+                Token::Ident("a".to_string()),
+                Token::Gt,
+                Token::Ident("b".to_string()),
+                Token::Ident("a".to_string()),
+                Token::Gt,
+                Token::Ident("b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hole() {
+        let t = tokens("??? re-match?");
+        assert_eq!(
+            t,
+            vec![
+                Token::Ident("???".to_string()),
+                Token::Ident("re-match?".to_string()),
+            ]
+        );
+        assert!(Lexer::new("?x").tokenize().is_err());
+        // Other words can't end in '?'
+        assert!(Lexer::new("def even? 2 % 0 = end").tokenize().is_err());
+    }
+
+    #[test]
+    fn test_vector_words() {
+        let t = tokens("v+ v* scale dot");
+        assert_eq!(
+            t,
+            vec![Token::VAdd, Token::VMul, Token::Scale, Token::DotProduct]
+        );
+    }
+
+    #[test]
+    fn test_operators_after_identifiers() {
+        // Only builtin names take operator characters
+        let t = tokens("1 dup+ x*y v+1");
+        assert_eq!(
+            t,
+            vec![
+                Token::Integer(1),
+                Token::Dup,
+                Token::Plus,
+                Token::Ident("x".to_string()),
+                Token::Star,
+                Token::Ident("y".to_string()),
+                Token::Ident("v".to_string()),
+                Token::Plus,
+                Token::Integer(1),
+            ]
+        );
+    }
+
     // --------------------
     // Raw mode: comments/newlines/eof
     // --------------------
@@ -1020,7 +1160,7 @@ mod tests {
 
     #[test]
     fn test_unexpected_character_error() {
-        let mut lexer = Lexer::new("@");
+        let mut lexer = Lexer::new("$");
         let err = lexer.tokenize().unwrap_err();
         assert!(
             err.message.contains("unexpected character"),
@@ -1146,4 +1286,71 @@ mod tests {
         // Line 3
         at!(19, Token::Eof, 3, 1);
     }
+
+    #[test]
+    fn test_most_negative_integer_literals() {
+        assert_eq!(
+            tokens("-9223372036854775808"),
+            vec![Token::Integer(i64::MIN)]
+        );
+        assert_eq!(
+            tokens("-0x8000000000000000"),
+            vec![Token::Integer(i64::MIN)]
+        );
+
+        let err = Lexer::new("9223372036854775808").tokenize().unwrap_err();
+        assert!(err.message.contains("invalid integer"));
+        let err = Lexer::new("-0x8000000000000001").tokenize().unwrap_err();
+        assert_eq!(err.message, "invalid hex number: 0x8000000000000001");
+    }
+
+    #[test]
+    fn test_error_locations_at_end_of_input() {
+        // Errors at the end of input must still point into it, as the
+        // `fuzz` feature's tests check for arbitrary input
+        let err = Lexer::new("\"abc\\").tokenize().unwrap_err();
+        assert_eq!((err.line, err.col), (1, 6));
+        assert!(err.message.contains("EOF in escape"));
+
+        let err = Lexer::new("x\n\"open").tokenize().unwrap_err();
+        assert_eq!((err.line, err.col), (2, 1));
+
+        let err = Lexer::new("-0x").tokenize().unwrap_err();
+        assert_eq!((err.line, err.col), (1, 1));
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let src = "; hi\r\n\"a\" print\r\n  10\r\n";
+        let sp = Lexer::new(src).tokenize().unwrap();
+        let got: Vec<(Token, usize, usize)> = sp
+            .into_iter()
+            .map(|s| (s.token, s.span.line, s.span.col))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (Token::Comment("hi".to_string()), 1, 1),
+                (Token::Newline, 1, 5),
+                (Token::String("a".to_string()), 2, 1),
+                (Token::Print, 2, 5),
+                (Token::Newline, 2, 10),
+                (Token::Integer(10), 3, 3),
+                (Token::Newline, 3, 5),
+                (Token::Eof, 4, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lone_carriage_return_is_whitespace() {
+        assert_eq!(tokens("1\r2"), vec![Token::Integer(1), Token::Integer(2)]);
+    }
+
+    #[test]
+    fn test_unknown_escape_suggests_doubling_backslash() {
+        let err = Lexer::new(r#"import "lib\utils""#).tokenize().unwrap_err();
+        assert!(err.message.contains(r"unknown escape sequence: \u"));
+        assert!(err.message.contains(r"\\"));
+    }
 }
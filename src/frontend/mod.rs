@@ -1,5 +1,6 @@
 pub mod lexer;
 pub mod parser;
 pub mod parser_error;
+pub mod source;
 pub mod token;
 pub mod token_dumper;
@@ -1,10 +1,17 @@
 use crate::frontend::lexer::{Span, Spanned};
 use crate::frontend::parser_error::ParserError;
 use crate::frontend::token::Token;
+use crate::lang::builtins;
 use crate::lang::node::Node;
 use crate::lang::program::Program;
 use crate::lang::use_item::UseItem;
 use crate::lang::value::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Default for `Parser::with_max_nesting`. Parsing, compiling and running
+/// nested quotations all recurse, so unbounded nesting would overflow the
+/// Rust stack long before anything useful happened.
+pub const DEFAULT_MAX_NESTING: usize = 256;
 
 /// Recursive-descent parser for Ember.
 ///
@@ -13,7 +20,8 @@ use crate::lang::value::Value;
 /// - `main`: the remaining nodes (top-level executable code)
 ///
 /// Notes:
-/// - Comments and newlines are filtered out in `Parser::new`.
+/// - Comments and newlines are filtered out in `Parser::new`. Comment lines
+///   directly above a `def` are kept as that word's documentation.
 /// - Qualified words are recognized only in the strict form `Ident "." Ident`.
 ///   Any other use of `.` is parsed as the `StringConcat` operator.
 pub struct Parser {
@@ -24,6 +32,13 @@ pub struct Parser {
     /// Used to provide stable source locations for errors that occur after
     /// advancing past the last token or at end-of-file.
     last_span: Option<Span>,
+    /// Current depth of nested quotations and lists
+    depth: usize,
+    max_nesting: usize,
+    /// Comments that have a line to themselves, by line number
+    comment_lines: HashMap<usize, String>,
+    /// Documentation of the words parsed so far, by (qualified) name
+    docs: BTreeMap<String, String>,
 }
 
 impl Parser {
@@ -33,6 +48,18 @@ impl Parser {
     /// parsing. (This keeps line/col information intact, since spans come from
     /// the original tokens.)
     pub fn new(tokens: Vec<Spanned>) -> Self {
+        let mut comment_lines = HashMap::new();
+        let mut line_has_code = false;
+        for t in &tokens {
+            match &t.token {
+                Token::Comment(text) if !line_has_code => {
+                    comment_lines.insert(t.span.line, text.clone());
+                }
+                Token::Newline => line_has_code = false,
+                _ => line_has_code = true,
+            }
+        }
+
         // Filter out comments and newlines
         let tokens: Vec<Spanned> = tokens
             .into_iter()
@@ -42,9 +69,31 @@ impl Parser {
             tokens,
             pos: 0,
             last_span: None,
+            depth: 0,
+            max_nesting: DEFAULT_MAX_NESTING,
+            comment_lines,
+            docs: BTreeMap::new(),
         }
     }
 
+    /// Sets how deeply quotations and lists may nest before parsing fails.
+    pub fn with_max_nesting(mut self, max_nesting: usize) -> Self {
+        self.max_nesting = max_nesting;
+        self
+    }
+
+    /// Enters one level of quotation or list nesting.
+    fn enter_nested(&mut self) -> Result<(), ParserError> {
+        if self.depth >= self.max_nesting {
+            return Err(self.error(&format!(
+                "nesting too deep: more than {} levels of quotations and lists",
+                self.max_nesting
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     /// Returns the current token without consuming it.
     ///
     /// Returns `None` when the parser position is beyond the token list.
@@ -108,13 +157,14 @@ impl Parser {
     /// Parses a complete Ember program.
     ///
     /// Top-level forms are split into:
-    /// - `definitions`: `def`, `import`, `module`, `use`
+    /// - `definitions`: `def`, `import`, `module`, `use`, `var`
     /// - `main`: everything else
     ///
     /// The parser stops when it reaches `Token::Eof`.
     pub fn parse(&mut self) -> Result<Program, ParserError> {
         let mut definitions = Vec::new();
         let mut main = Vec::new();
+        let mut main_spans = Vec::new();
 
         while let Some(spanned) = self.current() {
             if matches!(spanned.token, Token::Eof) {
@@ -138,14 +188,24 @@ impl Parser {
                     let use_statement = self.parse_use()?;
                     definitions.push(use_statement);
                 }
+                Token::Var => {
+                    let var = self.parse_var()?;
+                    definitions.push(var);
+                }
                 _ => {
+                    main_spans.push(spanned.span.clone());
                     let node = self.parse_node()?;
                     main.push(node);
                 }
             }
         }
 
-        Ok(Program { definitions, main })
+        Ok(Program {
+            definitions,
+            main,
+            main_spans,
+            docs: std::mem::take(&mut self.docs),
+        })
     }
 
     /// Parses a word definition:
@@ -160,6 +220,7 @@ impl Parser {
     /// - If `<name>` is missing or not an identifier.
     /// - If EOF is reached before `end`.
     fn parse_definition(&mut self) -> Result<Node, ParserError> {
+        let def_line = self.current().map(|t| t.span.line).unwrap_or(1);
         self.advance(); // consume 'def'
 
         let name = match self.advance() {
@@ -171,6 +232,7 @@ impl Parser {
         };
 
         let mut body = Vec::new();
+        let mut spans = Vec::new();
 
         while let Some(spanned) = self.current() {
             if matches!(spanned.token, Token::End) {
@@ -182,11 +244,39 @@ impl Parser {
                 return Err(self.error("unexpected EOF, expected 'end'"));
             }
 
+            spans.push(spanned.span.clone());
             let node = self.parse_node()?;
             body.push(node);
         }
 
-        Ok(Node::Def { name, body })
+        if let Some(doc) = self.doc_above(def_line) {
+            self.docs.insert(name.clone(), doc);
+        }
+
+        Ok(Node::Def {
+            name,
+            body,
+            line: def_line,
+            spans,
+        })
+    }
+
+    /// The block of comment lines ending just above `line`, if any.
+    fn doc_above(&self, line: usize) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut current = line;
+        while current > 1 {
+            current -= 1;
+            match self.comment_lines.get(&current) {
+                Some(text) => lines.push(text.as_str()),
+                None => break,
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        lines.reverse();
+        Some(lines.join("\n"))
     }
 
     /// Parses an import statement:
@@ -254,6 +344,14 @@ impl Parser {
             }
         }
 
+        for def in &definitions {
+            if let Node::Def { name: word, .. } = def
+                && let Some(doc) = self.docs.remove(word)
+            {
+                self.docs.insert(format!("{}.{}", name, word), doc);
+            }
+        }
+
         Ok(Node::Module { name, definitions })
     }
 
@@ -304,12 +402,36 @@ impl Parser {
         Ok(Node::Use { module, item })
     }
 
+    /// Parses a variable declaration:
+    ///
+    /// ```text
+    /// var <name>
+    /// ```
+    ///
+    /// Returns `Node::Var(name)`.
+    ///
+    /// # Errors
+    /// - If `<name>` is missing or not an identifier.
+    fn parse_var(&mut self) -> Result<Node, ParserError> {
+        self.advance(); // consume 'var'
+
+        match self.advance() {
+            Some(Spanned {
+                token: Token::Ident(name),
+                ..
+            }) => Ok(Node::Var(name.clone())),
+            _ => Err(self.error("expected variable name after 'var'")),
+        }
+    }
+
     /// Parses a single executable node (literal, builtin, word call, etc.).
     ///
     /// This is the core "token to AST" mapping. Most tokens map directly to a
     /// corresponding `Node` variant.
     ///
-    /// Special case: qualified words.
+    /// Special cases:
+    /// - `Ident "!"` and `Ident "@"` become `Node::Store` and `Node::Load`
+    /// - `":>" Ident` becomes `Node::BindLocal`
     /// - `Ident "." Ident` becomes `Node::QualifiedWord { module, word }`
     /// - otherwise the initial `Ident` becomes `Node::Word(name)` and `.` (if any)
     ///   is handled later as `Node::StringConcat`.
@@ -351,296 +473,10 @@ impl Parser {
                 Node::Literal(list)
             }
 
-            // Stack operations
-            Token::Dup => {
-                self.advance();
-                Node::Dup
-            }
-            Token::Drop => {
-                self.advance();
-                Node::Drop
-            }
-            Token::Swap => {
-                self.advance();
-                Node::Swap
-            }
-            Token::Over => {
-                self.advance();
-                Node::Over
-            }
-            Token::Rot => {
-                self.advance();
-                Node::Rot
-            }
-
-            // Arithmetic
-            Token::Plus => {
-                self.advance();
-                Node::Add
-            }
-            Token::Minus => {
-                self.advance();
-                Node::Sub
-            }
-            Token::Star => {
-                self.advance();
-                Node::Mul
-            }
-            Token::Slash => {
-                self.advance();
-                Node::Div
-            }
-            Token::Percent => {
-                self.advance();
-                Node::Mod
-            }
-            Token::Neg => {
-                self.advance();
-                Node::Neg
-            }
-            Token::Abs => {
-                self.advance();
-                Node::Abs
-            }
-
-            // Comparison
-            Token::Eq => {
-                self.advance();
-                Node::Eq
-            }
-            Token::NotEq => {
-                self.advance();
-                Node::NotEq
-            }
-            Token::Lt => {
-                self.advance();
-                Node::Lt
-            }
-            Token::Gt => {
-                self.advance();
-                Node::Gt
-            }
-            Token::LtEq => {
-                self.advance();
-                Node::LtEq
-            }
-            Token::GtEq => {
-                self.advance();
-                Node::GtEq
-            }
-
-            // Logic
-            Token::And => {
-                self.advance();
-                Node::And
-            }
-            Token::Or => {
-                self.advance();
-                Node::Or
-            }
-            Token::Not => {
-                self.advance();
-                Node::Not
-            }
-
-            // Control flow
-            Token::If => {
-                self.advance();
-                Node::If
-            }
-            Token::When => {
-                self.advance();
-                Node::When
-            }
-            Token::Call => {
-                self.advance();
-                Node::Call
-            }
-
-            // Loops & higher-order
-            Token::Times => {
-                self.advance();
-                Node::Times
-            }
-            Token::Each => {
-                self.advance();
-                Node::Each
-            }
-            Token::Map => {
-                self.advance();
-                Node::Map
-            }
-            Token::Filter => {
-                self.advance();
-                Node::Filter
-            }
-            Token::Fold => {
-                self.advance();
-                Node::Fold
-            }
-            Token::Range => {
-                self.advance();
-                Node::Range
-            }
-
-            // List operations
-            Token::Len => {
-                self.advance();
-                Node::Len
-            }
-            Token::Head => {
-                self.advance();
-                Node::Head
-            }
-            Token::Tail => {
-                self.advance();
-                Node::Tail
-            }
-            Token::Cons => {
-                self.advance();
-                Node::Cons
-            }
-            Token::Concat => {
-                self.advance();
-                Node::Concat
-            }
-            Token::Dot => {
-                self.advance();
-                Node::StringConcat
-            }
-
-            // I/O
-            Token::Print => {
-                self.advance();
-                Node::Print
-            }
-            Token::Emit => {
-                self.advance();
-                Node::Emit
-            }
-            Token::Read => {
-                self.advance();
-                Node::Read
-            }
-            Token::Debug => {
-                self.advance();
-                Node::Debug
-            }
-
-            // Additional builtins
-            Token::Min => {
-                self.advance();
-                Node::Min
-            }
-            Token::Max => {
-                self.advance();
-                Node::Max
-            }
-            Token::Pow => {
-                self.advance();
-                Node::Pow
-            }
-            Token::Sqrt => {
-                self.advance();
-                Node::Sqrt
-            }
-            Token::Nth => {
-                self.advance();
-                Node::Nth
-            }
-            Token::Append => {
-                self.advance();
-                Node::Append
-            }
-            Token::Sort => {
-                self.advance();
-                Node::Sort
-            }
-            Token::Reverse => {
-                self.advance();
-                Node::Reverse
-            }
-            Token::Chars => {
-                self.advance();
-                Node::Chars
-            }
-            Token::Join => {
-                self.advance();
-                Node::Join
-            }
-            Token::Split => {
-                self.advance();
-                Node::Split
-            }
-            Token::Upper => {
-                self.advance();
-                Node::Upper
-            }
-            Token::Lower => {
-                self.advance();
-                Node::Lower
-            }
-            Token::Trim => {
-                self.advance();
-                Node::Trim
-            }
-            Token::Clear => {
-                self.advance();
-                Node::Clear
-            }
-            Token::Depth => {
-                self.advance();
-                Node::Depth
-            }
-            Token::Type => {
-                self.advance();
-                Node::Type
-            }
-            Token::ToString => {
-                self.advance();
-                Node::ToString
-            }
-            Token::ToInt => {
-                self.advance();
-                Node::ToInt
-            }
-
-            // Concatenative Combinators
-            Token::Dip => {
-                self.advance();
-                Node::Dip
-            }
-            Token::Keep => {
-                self.advance();
-                Node::Keep
-            }
-            Token::Bi => {
-                self.advance();
-                Node::Bi
-            }
-            Token::Bi2 => {
-                self.advance();
-                Node::Bi2
-            }
-            Token::Tri => {
-                self.advance();
-                Node::Tri
-            }
-            Token::Both => {
-                self.advance();
-                Node::Both
-            }
-            Token::Compose => {
-                self.advance();
-                Node::Compose
-            }
-            Token::Curry => {
-                self.advance();
-                Node::Curry
-            }
-            Token::Apply => {
-                self.advance();
-                Node::Apply
+            // Map
+            Token::MapBrace => {
+                let map = self.parse_map()?;
+                Node::Literal(map)
             }
 
             // User-defined word
@@ -648,8 +484,15 @@ impl Parser {
                 let name = name.clone();
                 self.advance();
 
-                // Check if this is a qualified word (Module.word)
-                if matches!(self.peek(), Some(Token::Dot)) {
+                // Check if this is a variable access (counter ! / counter @)
+                if matches!(self.peek(), Some(Token::Store)) {
+                    self.advance();
+                    Node::Store(name)
+                } else if matches!(self.peek(), Some(Token::Load)) {
+                    self.advance();
+                    Node::Load(name)
+                } else if matches!(self.peek(), Some(Token::Dot)) {
+                    // Check if this is a qualified word (Module.word)
                     // Peek ahead to see if followed by an identifier
                     if matches!(self.peek_next(), Some(Token::Ident(_))) {
                         self.advance(); // consume '.'
@@ -675,10 +518,39 @@ impl Parser {
                 }
             }
 
-            // Unexpected
-            _ => {
-                return Err(self.error(&format!("unexpected token: {:?}", spanned.token)));
+            // Local binding (:> name)
+            Token::Bind => {
+                self.advance();
+                match self.current() {
+                    Some(Spanned {
+                        token: Token::Ident(name),
+                        ..
+                    }) => {
+                        let name = name.clone();
+                        self.advance();
+                        Node::BindLocal(name)
+                    }
+                    _ => return Err(self.error("expected a local name after ':>'")),
+                }
             }
+
+            Token::Store | Token::Load => {
+                return Err(self.error(&format!(
+                    "'{}' must follow a variable name, as in `42 counter !` or `counter @`",
+                    spanned.token
+                )));
+            }
+
+            // Builtin words, mapped by the registry
+            token => match builtins::node_for(token) {
+                Some(node) => {
+                    self.advance();
+                    node
+                }
+                None => {
+                    return Err(self.error(&format!("unexpected token: {:?}", spanned.token)));
+                }
+            },
         };
         Ok(node)
     }
@@ -690,14 +562,72 @@ impl Parser {
     /// { 1 { 2 3 } 4 }   // nested lists allowed
     /// ```
     ///
-    /// Lists may contain only literal values (numbers, strings, bools, lists).
-    /// They do not contain arbitrary nodes.
+    /// Lists may contain only literal values (numbers, strings, bools, lists,
+    /// maps). They do not contain arbitrary nodes.
     ///
     /// # Errors
     /// - Unexpected token inside the list
     /// - EOF before `}`
     fn parse_list(&mut self) -> Result<Value, ParserError> {
-        self.advance(); // consume '{'
+        self.enter_nested()?;
+        let items = self.parse_literal_items("list");
+        self.depth -= 1;
+        items.map(Value::List)
+    }
+
+    /// Parses a map literal, keys and values alternating:
+    ///
+    /// ```text
+    /// #{ "name" "ann" "age" 36 }
+    /// #{ "tags" { "a" "b" } "owner" #{ "id" 7 } }
+    /// ```
+    ///
+    /// Keys are strings; values are literals, as in lists.
+    ///
+    /// # Errors
+    /// - A key without a value, a key that isn't a string, or a repeated key
+    /// - Unexpected token inside the map
+    /// - EOF before `}`
+    fn parse_map(&mut self) -> Result<Value, ParserError> {
+        let start = self
+            .current()
+            .map(|spanned| (spanned.span.line, spanned.span.col));
+        self.enter_nested()?;
+        let items = self.parse_literal_items("map");
+        self.depth -= 1;
+
+        let at_start = |message: String| match start {
+            Some((line, col)) => ParserError { message, line, col },
+            None => self.error(&message),
+        };
+        let items = items?;
+        if items.len() % 2 != 0 {
+            return Err(at_start(format!(
+                "map literal has a key without a value ({} items)",
+                items.len()
+            )));
+        }
+        let mut entries = std::collections::BTreeMap::new();
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            let Value::String(key) = key else {
+                return Err(at_start(format!(
+                    "map keys must be strings, got {}",
+                    key.type_name()
+                )));
+            };
+            if entries.contains_key(&key) {
+                return Err(at_start(format!("map literal repeats the key '{}'", key)));
+            }
+            entries.insert(key, value);
+        }
+        Ok(Value::Map(entries))
+    }
+
+    /// The literal values of a list or map, from its opening brace through
+    /// the closing `}`.
+    fn parse_literal_items(&mut self, what: &str) -> Result<Vec<Value>, ParserError> {
+        self.advance(); // consume '{' or '#{'
 
         let mut items = Vec::new();
 
@@ -705,7 +635,7 @@ impl Parser {
             match &spanned.token {
                 Token::RBrace => {
                     self.advance(); // consume '}'
-                    return Ok(Value::List(items));
+                    return Ok(items);
                 }
                 Token::Integer(n) => {
                     items.push(Value::Integer(*n));
@@ -727,13 +657,18 @@ impl Parser {
                     let nested = self.parse_list()?;
                     items.push(nested);
                 }
+                Token::MapBrace => {
+                    let nested = self.parse_map()?;
+                    items.push(nested);
+                }
                 Token::Eof => {
                     return Err(self.error("unexpected EOF, expected '}'"));
                 }
                 _ => {
-                    return Err(
-                        self.error(&format!("unexpected token in list: {:?}", spanned.token))
-                    );
+                    return Err(self.error(&format!(
+                        "unexpected token in {}: {:?}",
+                        what, spanned.token
+                    )));
                 }
             }
         }
@@ -753,6 +688,13 @@ impl Parser {
     /// # Errors
     /// - EOF before `]`
     fn parse_quotation(&mut self) -> Result<Value, ParserError> {
+        self.enter_nested()?;
+        let quotation = self.parse_quotation_body();
+        self.depth -= 1;
+        quotation
+    }
+
+    fn parse_quotation_body(&mut self) -> Result<Value, ParserError> {
         self.advance(); // consume '['
 
         let mut body = Vec::new();
@@ -815,7 +757,7 @@ mod tests {
         let program = parse("def square dup * end 5 square");
         assert_eq!(program.definitions.len(), 1);
         assert!(
-            matches!(&program.definitions[0], Node::Def { name, body } if name == "square" && body.len() == 2)
+            matches!(&program.definitions[0], Node::Def { name, body, .. } if name == "square" && body.len() == 2)
         );
     }
 
@@ -839,6 +781,63 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_map() {
+        let prog = parse(r#"#{ "b" 1 "a" { 2 #{ } } }"#);
+        let Node::Literal(Value::Map(entries)) = &prog.main[0] else {
+            panic!("expected a map literal, got {:?}", prog.main);
+        };
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert!(matches!(
+            &entries["a"],
+            Value::List(items) if matches!(items[1], Value::Map(_))
+        ));
+    }
+
+    #[test]
+    fn test_variables() {
+        let prog = parse("var n 1 n ! def inc n @ 1 + n ! end");
+        assert!(matches!(&prog.definitions[0], Node::Var(name) if name == "n"));
+        assert!(matches!(&prog.main[1], Node::Store(name) if name == "n"));
+        let Node::Def { body, .. } = &prog.definitions[1] else {
+            panic!("expected a def, got {:?}", prog.definitions);
+        };
+        assert!(matches!(&body[0], Node::Load(name) if name == "n"));
+
+        let err = parse_err("var 1");
+        assert_eq!(err.message, "expected variable name after 'var'");
+        let err = parse_err("1 !");
+        assert!(err.message.starts_with("'!' must follow a variable name"));
+    }
+
+    #[test]
+    fn test_locals() {
+        let prog = parse("def area :> w :> h w h * end");
+        let Node::Def { body, .. } = &prog.definitions[0] else {
+            panic!("expected a def, got {:?}", prog.definitions);
+        };
+        assert!(matches!(&body[0], Node::BindLocal(name) if name == "w"));
+        assert!(matches!(&body[2], Node::Word(name) if name == "w"));
+        let err = parse_err("def f :> 1 end");
+        assert_eq!(err.message, "expected a local name after ':>'");
+    }
+
+    #[test]
+    fn test_map_errors() {
+        let err = parse_err(r#"1 #{ "a" }"#);
+        assert_eq!(
+            err.message,
+            "map literal has a key without a value (1 items)"
+        );
+        assert_eq!((err.line, err.col), (1, 3));
+        let err = parse_err(r#"#{ 1 2 }"#);
+        assert_eq!(err.message, "map keys must be strings, got integer");
+        let err = parse_err(r#"#{ "a" 1 "a" 2 }"#);
+        assert_eq!(err.message, "map literal repeats the key 'a'");
+        let err = parse_err(r#"#{ "a" dup }"#);
+        assert!(err.message.starts_with("unexpected token in map"));
+    }
+
     #[test]
     fn test_filters_comments_and_newlines() {
         let program = parse(
@@ -1087,4 +1086,26 @@ mod tests {
         assert_eq!(err.line, 1);
         assert_eq!(err.col, 1);
     }
+
+    #[test]
+    fn test_nesting_limit() {
+        let deep = format!("{}{}", "[".repeat(10), "]".repeat(10));
+        let tokens = Lexer::new(&deep).tokenize().unwrap();
+        assert!(
+            Parser::new(tokens.clone())
+                .with_max_nesting(10)
+                .parse()
+                .is_ok()
+        );
+
+        let err = Parser::new(tokens).with_max_nesting(9).parse().unwrap_err();
+        assert!(err.message.contains("nesting too deep"), "{}", err.message);
+        assert_eq!(err.col, 10);
+    }
+
+    #[test]
+    fn test_default_nesting_limit_rejects_pathological_input() {
+        let deep = format!("{}{}", "{".repeat(100_000), "}".repeat(100_000));
+        assert!(parse_err(&deep).message.contains("nesting too deep"));
+    }
 }
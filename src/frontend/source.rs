@@ -0,0 +1,147 @@
+//! Turning the bytes of a source file into text for the lexer.
+//!
+//! EMBER source is UTF-8. A leading UTF-8 byte order mark is skipped; other
+//! encodings are reported with where the first bad byte is and what to do
+//! about it, or (in lossy mode) decoded with replacement characters.
+
+/// Decoded source, with a warning to show if anything was replaced.
+#[derive(Debug)]
+pub struct SourceText {
+    pub text: String,
+    pub warning: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct EncodingError {
+    pub message: String,
+    pub hint: String,
+}
+
+impl std::fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n  hint: {}", self.message, self.hint)
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Decode a source file. With `lossy`, invalid UTF-8 is replaced by U+FFFD
+/// and reported as a warning instead of an error.
+pub fn decode_source(bytes: &[u8], lossy: bool) -> Result<SourceText, EncodingError> {
+    if bytes.starts_with(b"\xff\xfe") || bytes.starts_with(b"\xfe\xff") {
+        return Err(EncodingError {
+            message: "file is UTF-16 encoded (it starts with a UTF-16 byte order mark)".to_string(),
+            hint: "EMBER reads UTF-8 only; re-save the file as UTF-8".to_string(),
+        });
+    }
+
+    let body = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+    let bom_len = bytes.len() - body.len();
+
+    let error = match std::str::from_utf8(body) {
+        Ok(text) => {
+            return Ok(SourceText {
+                text: text.to_string(),
+                warning: None,
+            });
+        }
+        Err(e) => e,
+    };
+
+    let valid = &body[..error.valid_up_to()];
+    // Valid by construction, so this can't fail
+    let prefix = std::str::from_utf8(valid).unwrap_or_default();
+    let line = prefix.matches('\n').count() + 1;
+    let col = prefix.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    let location = format!(
+        "invalid UTF-8 at byte {} (line {}, column {}): 0x{:02x}",
+        bom_len + error.valid_up_to(),
+        line,
+        col,
+        body[error.valid_up_to()]
+    );
+
+    if lossy {
+        let replaced = body
+            .utf8_chunks()
+            .filter(|chunk| !chunk.invalid().is_empty())
+            .count();
+        return Ok(SourceText {
+            text: String::from_utf8_lossy(body).into_owned(),
+            warning: Some(format!(
+                "{}; replaced {} invalid sequence{} with U+FFFD",
+                location,
+                replaced,
+                if replaced == 1 { "" } else { "s" }
+            )),
+        });
+    }
+
+    Err(EncodingError {
+        message: location,
+        hint: "EMBER source must be UTF-8. Stray bytes like this usually mean Latin-1 or \
+               Windows-1252 text: re-save the file as UTF-8, or run with --lossy-utf8 to \
+               replace them"
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_utf8() {
+        let source = decode_source("\"héllo\" print".as_bytes(), false).unwrap();
+        assert_eq!(source.text, "\"héllo\" print");
+        assert!(source.warning.is_none());
+    }
+
+    #[test]
+    fn test_strips_utf8_bom() {
+        let source = decode_source(b"\xef\xbb\xbf1 print", false).unwrap();
+        assert_eq!(source.text, "1 print");
+    }
+
+    #[test]
+    fn test_reports_latin1_location() {
+        // "caf\xe9" is "café" in Latin-1
+        let err = decode_source(b"1 print\n\"caf\xe9\" print", false).unwrap_err();
+        assert_eq!(
+            err.message,
+            "invalid UTF-8 at byte 12 (line 2, column 5): 0xe9"
+        );
+        assert!(err.hint.contains("Latin-1"));
+        assert!(err.hint.contains("--lossy-utf8"));
+    }
+
+    #[test]
+    fn test_offset_counts_bom() {
+        let err = decode_source(b"\xef\xbb\xbf\xff", false).unwrap_err();
+        assert!(
+            err.message
+                .starts_with("invalid UTF-8 at byte 3 (line 1, column 1)")
+        );
+    }
+
+    #[test]
+    fn test_rejects_utf16() {
+        let err = decode_source(b"\xff\xfe1\x00", true).unwrap_err();
+        assert!(err.message.contains("UTF-16"));
+    }
+
+    #[test]
+    fn test_lossy_decoding() {
+        let source = decode_source(b"\"caf\xe9 \xe9\" print", true).unwrap();
+        assert_eq!(source.text, "\"caf\u{fffd} \u{fffd}\" print");
+        let warning = source.warning.unwrap();
+        assert!(warning.contains("byte 4"), "{}", warning);
+        assert!(
+            warning.contains("replaced 2 invalid sequences"),
+            "{}",
+            warning
+        );
+    }
+}
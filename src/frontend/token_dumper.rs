@@ -102,10 +102,10 @@ impl TokenDumper {
 
             // structure
             LBracket | RBracket => "BRACKET",
-            LBrace | RBrace => "BRACE",
+            LBrace | RBrace | MapBrace => "BRACE",
 
             // ops / comparisons
-            Plus | Minus | Star | Slash | Percent | Dot => "OP",
+            Plus | Minus | Star | Slash | Percent | Dot | Store | Load | Bind => "OP",
             Eq | NotEq | Lt | LtEq | Gt | GtEq => "CMP",
 
             // everything else = keyword/builtin
@@ -120,7 +120,7 @@ impl TokenDumper {
             String(_) => Self::GRN,
             Integer(_) | Float(_) | Bool(_) => Self::CYN,
             Ident(_) => Self::YEL,
-            Plus | Minus | Star | Slash | Percent | Dot => Self::MAG,
+            Plus | Minus | Star | Slash | Percent | Dot | Store | Load | Bind => Self::MAG,
             Eq | NotEq | Lt | LtEq | Gt | GtEq => Self::MAG,
             _ => Self::RESET,
         }
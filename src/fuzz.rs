@@ -0,0 +1,303 @@
+//! Randomized robustness tests for the untrusted-input paths.
+//!
+//! - Source: generated input (a mix of Ember syntax fragments and arbitrary
+//!   characters) goes through `Lexer` and `Parser`, checking that neither
+//!   panics and that every reported location lies within the input.
+//! - Bytecode: the compiled examples are serialized, mutated, and loaded as
+//!   a `.ebc` file would be. Loading must fail cleanly or yield a verified
+//!   program, which must then run (within tight limits) without panicking.
+//!
+//! Inputs that once broke an invariant are kept as regression tests next to
+//! the code they broke.
+//!
+//! ```text
+//! cargo test --features fuzz                           # 20k inputs
+//! EMBER_FUZZ_ITERATIONS=1000000 cargo test --features fuzz
+//! EMBER_FUZZ_SEED=12345 cargo test --features fuzz     # replay a run
+//! ```
+
+use std::io::{self, Cursor};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+use std::{env, fs};
+
+use crate::decode_bytecode;
+use ember::bytecode::compile::Compiler;
+use ember::bytecode::container;
+use ember::frontend::lexer::Lexer;
+use ember::frontend::parser::Parser;
+use ember::runtime::vm_bc::{VmBc, VmBcConfig};
+
+const DEFAULT_ITERATIONS: u64 = 20_000;
+
+/// Pieces of real syntax, so generated input gets past the first token.
+const FRAGMENTS: &[&str] = &[
+    "[",
+    "]",
+    "{",
+    "}",
+    "\"",
+    "\\",
+    "\\n",
+    "\\\"",
+    "def",
+    "end",
+    "module",
+    "use",
+    "import",
+    "var",
+    "!",
+    "@",
+    ":>",
+    ".",
+    "*",
+    ":",
+    "#{",
+    "0x[",
+    "1",
+    "-1",
+    "1.5",
+    "1e9",
+    "-",
+    "+",
+    "0",
+    "99999999999999999999",
+    "1.",
+    ".5",
+    ";",
+    "; comment\n",
+    "\n",
+    "\r\n",
+    "\t",
+    " ",
+    "  ",
+    "dup",
+    "if",
+    "times",
+    "x",
+    "a.b",
+    "é",
+    "\u{0}",
+    "\u{feff}",
+    "😀",
+];
+
+/// xorshift64: tiny, deterministic, and good enough to pick fragments.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn generate(rng: &mut Rng) -> String {
+    let mut input = String::new();
+    for _ in 0..rng.below(200) {
+        if rng.below(4) == 0 {
+            // Any scalar value, weighted towards ASCII
+            let ch = if rng.below(2) == 0 {
+                char::from(rng.below(128) as u8)
+            } else {
+                char::from_u32(rng.next() as u32 % 0x11_0000).unwrap_or('\u{fffd}')
+            };
+            input.push(ch);
+        } else {
+            input.push_str(FRAGMENTS[rng.below(FRAGMENTS.len())]);
+            if rng.below(2) == 0 {
+                input.push(' ');
+            }
+        }
+    }
+    input
+}
+
+/// Whether `line:col` (1-based, counted in chars like the lexer does) points
+/// into `source` or just past the end of one of its lines.
+fn in_bounds(source: &str, line: usize, col: usize) -> bool {
+    match source.split('\n').nth(line.wrapping_sub(1)) {
+        Some(text) => col >= 1 && col <= text.chars().count() + 1,
+        None => false,
+    }
+}
+
+/// Run one input through the front end, returning a description of the
+/// first broken invariant.
+fn check(source: &str) -> Result<(), String> {
+    let tokens = match Lexer::new(source).tokenize() {
+        Ok(tokens) => tokens,
+        Err(e) if in_bounds(source, e.line, e.col) => return Ok(()),
+        Err(e) => return Err(format!("lexer error location out of bounds: {}", e)),
+    };
+    if let Some(t) = tokens
+        .iter()
+        .find(|t| !in_bounds(source, t.span.line, t.span.col))
+    {
+        return Err(format!("token span out of bounds: {:?}", t));
+    }
+
+    match Parser::new(tokens).parse() {
+        Err(e) if !in_bounds(source, e.line, e.col) => {
+            Err(format!("parser error location out of bounds: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Flip, overwrite, insert, delete or truncate a few bytes.
+fn mutate(rng: &mut Rng, bytes: &mut Vec<u8>) {
+    for _ in 0..=rng.below(3) {
+        if bytes.is_empty() {
+            return;
+        }
+        let at = rng.below(bytes.len());
+        match rng.below(5) {
+            0 => bytes[at] ^= 1 << rng.below(8),
+            1 => bytes[at] = rng.next() as u8,
+            2 => bytes.insert(at, rng.next() as u8),
+            3 => {
+                bytes.remove(at);
+            }
+            _ => bytes.truncate(at),
+        }
+    }
+}
+
+/// Load mutated bytecode the way `.ebc` files are loaded and, if that
+/// succeeds, run it.
+fn check_bytecode(bytes: &[u8]) -> Result<(), String> {
+    let Ok(program) = decode_bytecode(bytes) else {
+        return Ok(());
+    };
+
+    // Keep recursion shallow: the test thread's stack is small
+    let mut vm = VmBc::with_config(VmBcConfig {
+        max_call_depth: 32,
+        max_steps: Some(10_000),
+        max_wall_time: Some(Duration::from_secs(1)),
+        allow_fs: false,
+        allow_process: false,
+        allow_net: false,
+        allow_spawn: false,
+        ..Default::default()
+    });
+    vm.set_input(Cursor::new(Vec::new()));
+    vm.set_output(io::sink());
+    // Runtime errors are fine; only panics (caught by the caller) are not
+    let _ = vm.run_compiled(&program);
+    Ok(())
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Run `check` on `iterations` generated inputs, returning descriptions of
+/// the first few that broke an invariant or panicked.
+fn run_cases<T: std::fmt::Debug>(
+    iterations: u64,
+    mut next_input: impl FnMut() -> T,
+    check: impl Fn(&T) -> Result<(), String>,
+) -> Vec<String> {
+    // Report failures below instead of printing every panic message
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut failures = Vec::new();
+    for _ in 0..iterations {
+        let input = next_input();
+        let outcome =
+            panic::catch_unwind(AssertUnwindSafe(|| check(&input))).unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                    .unwrap_or_default();
+                Err(format!("panicked: {}", message))
+            });
+        if let Err(problem) = outcome {
+            failures.push(format!("{:?}\n  {}", input, problem));
+        }
+        if failures.len() >= 10 {
+            break;
+        }
+    }
+
+    panic::set_hook(hook);
+    failures
+}
+
+#[test]
+fn lexer_and_parser_survive_random_input() {
+    let seed = env_or("EMBER_FUZZ_SEED", 0x5eed_e3be_u64);
+    let iterations = env_or("EMBER_FUZZ_ITERATIONS", DEFAULT_ITERATIONS);
+
+    let mut rng = Rng(seed);
+    let failures = run_cases(iterations, || generate(&mut rng), |input| check(input));
+    assert!(
+        failures.is_empty(),
+        "front end failed on {} input(s) (seed {}):\n{}",
+        failures.len(),
+        seed,
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn mutated_bytecode_is_rejected_or_safe_to_run() {
+    let seed = env_or("EMBER_FUZZ_SEED", 0x5eed_e3be_u64);
+    let iterations = env_or("EMBER_FUZZ_ITERATIONS", DEFAULT_ITERATIONS);
+
+    let mut paths: Vec<_> = fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "em"))
+        .collect();
+    paths.sort();
+    let corpus: Vec<Vec<u8>> = paths
+        .iter()
+        .filter_map(|path| Compiler::new().compile_from_file(path).ok())
+        .map(|program| container::encode(&program).unwrap())
+        .collect();
+    assert!(!corpus.is_empty());
+
+    let mut rng = Rng(seed);
+    let failures = run_cases(
+        iterations,
+        || {
+            let mut bytes = corpus[rng.below(corpus.len())].clone();
+            mutate(&mut rng, &mut bytes);
+            bytes
+        },
+        |bytes| check_bytecode(bytes),
+    );
+    assert!(
+        failures.is_empty(),
+        "bytecode loading failed on {} input(s) (seed {}):\n{}",
+        failures.len(),
+        seed,
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn in_bounds_follows_lexer_positions() {
+    let source = "ab\né";
+    assert!(in_bounds(source, 1, 1));
+    assert!(in_bounds(source, 1, 3)); // the newline
+    assert!(in_bounds(source, 2, 2)); // end of input, counted in chars
+    assert!(!in_bounds(source, 2, 3));
+    assert!(!in_bounds(source, 3, 1));
+    assert!(!in_bounds(source, 0, 1));
+    assert!(!in_bounds(source, 1, 0));
+}
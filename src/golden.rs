@@ -0,0 +1,248 @@
+//! Snapshot tests for the programs under `examples/` and for error messages.
+//!
+//! Each example is compiled and run, and the result is compared against
+//! `tests/golden/<name>.snap`, which records the disassembly, the program's
+//! output, the final stack, and any error. A difference means codegen or
+//! semantics changed; if the change is intended, re-bless the snapshots:
+//!
+//! ```text
+//! cargo test --features golden                    # check
+//! EMBER_BLESS=1 cargo test --features golden      # rewrite snapshots
+//! ```
+//!
+//! The programs under `tests/golden/errors/` each fail, and their snapshots
+//! hold the error exactly as the CLI prints it, with ANSI escapes removed.
+//! Diagnostics are part of the interface: a change to one must show up as a
+//! snapshot diff in review, and a diff that loses a source line, a caret, a
+//! call stack or a help message is a regression, not a re-bless. To cover a
+//! new kind of error, add a small program there that fails with it.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{env, fs};
+
+use crate::spec::SharedBuffer;
+use ember::bytecode::compile::Compiler;
+use ember::bytecode::disasm::program_to_string;
+use ember::bytecode::stack_check_error::check_program;
+use ember::runtime::vm_bc::{VmBc, VmBcConfig};
+
+const EXAMPLES_DIR: &str = "examples";
+const SNAPSHOT_DIR: &str = "tests/golden";
+const ERRORS_DIR: &str = "tests/golden/errors";
+
+/// Every `.em` file under `dir`, recursively, in a stable order.
+fn collect_examples(dir: &Path, found: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_examples(&path, found);
+        } else if path.extension().is_some_and(|ext| ext == "em") {
+            found.push(path);
+        }
+    }
+}
+
+/// Errors mention absolute paths; keep snapshots machine-independent.
+fn scrub(text: String) -> String {
+    let root = env::current_dir().unwrap();
+    text.replace(&format!("{}/", root.display()), "")
+}
+
+/// Remove ANSI escape sequences, e.g. from a message built with `colorize`.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequences end at the first letter
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Compile and run one example, rendering everything observable about it.
+fn render(path: &Path) -> String {
+    let bytecode = match Compiler::new().compile_from_file(path) {
+        Ok(bc) => bc,
+        Err(e) => return format!("== compile error ==\n{}\n", scrub(e.to_string())),
+    };
+
+    let output = SharedBuffer::default();
+    let mut vm = VmBc::with_config(VmBcConfig {
+        max_wall_time: Some(Duration::from_secs(10)),
+        ..Default::default()
+    });
+    vm.set_input(Cursor::new(Vec::new()));
+    vm.set_output(output.clone());
+    let result = vm.run_compiled(&bytecode);
+
+    let mut text = String::new();
+    text.push_str("== disassembly ==\n");
+    text.push_str(&program_to_string(&bytecode));
+    text.push_str("== output ==\n");
+    text.push_str(&String::from_utf8_lossy(&output.0.borrow()));
+    text.push_str("== stack ==\n");
+    for value in vm.stack() {
+        text.push_str(&format!("{:?}\n", value));
+    }
+    if let Err(e) = result {
+        text.push_str("== error ==\n");
+        text.push_str(&scrub(e.message.clone()));
+        text.push('\n');
+    }
+    text
+}
+
+/// Compile, check and run a program that should fail, rendering its first
+/// error as the CLI prints it: compile and stack-check errors as `ember
+/// check` reports them, and runtime errors with their source context, call
+/// stack and help.
+fn render_error(path: &Path) -> String {
+    let bytecode = match Compiler::new().compile_from_file(path) {
+        Ok(bc) => bc,
+        Err(e) => return format!("== compile error ==\n{}\n", scrub(e.to_string())),
+    };
+    if let Err(e) = check_program(&bytecode) {
+        return format!("== check error ==\n{}\n", e);
+    }
+
+    let output = SharedBuffer::default();
+    let mut vm = VmBc::with_config(VmBcConfig {
+        max_wall_time: Some(Duration::from_secs(10)),
+        color: true,
+        ..Default::default()
+    });
+    vm.set_input(Cursor::new(Vec::new()));
+    vm.set_output(output.clone());
+    vm.set_source(fs::read_to_string(path).unwrap());
+    vm.set_file(path.to_path_buf());
+    match vm.run_compiled(&bytecode) {
+        Ok(()) => "== no error ==\n".to_string(),
+        Err(e) => format!("== runtime error =={}", strip_ansi(&e.to_string())),
+    }
+}
+
+/// Compare each program's rendering with its snapshot, or rewrite the
+/// snapshots when blessing. Returns a report per mismatch.
+fn compare_snapshots(programs: &[(PathBuf, PathBuf, String)], bless: bool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for (program, snapshot, actual) in programs {
+        if bless {
+            fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+            fs::write(snapshot, actual).unwrap();
+            continue;
+        }
+
+        match fs::read_to_string(snapshot) {
+            Ok(expected) if &expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{} differs from {}\n--- expected\n{}\n+++ actual\n{}",
+                program.display(),
+                snapshot.display(),
+                expected,
+                actual
+            )),
+            Err(_) => failures.push(format!(
+                "{} has no snapshot at {}",
+                program.display(),
+                snapshot.display()
+            )),
+        }
+    }
+    failures
+}
+
+fn snapshot_path(example: &Path) -> PathBuf {
+    let relative = example.strip_prefix(EXAMPLES_DIR).unwrap();
+    let name = relative
+        .with_extension("")
+        .to_string_lossy()
+        .replace('/', "__");
+    Path::new(SNAPSHOT_DIR).join(format!("{}.snap", name))
+}
+
+#[test]
+fn examples_match_snapshots() {
+    let bless = env::var_os("EMBER_BLESS").is_some();
+
+    let mut examples = Vec::new();
+    collect_examples(Path::new(EXAMPLES_DIR), &mut examples);
+    assert!(
+        !examples.is_empty(),
+        "no examples found in {}",
+        EXAMPLES_DIR
+    );
+
+    let programs: Vec<_> = examples
+        .into_iter()
+        .map(|example| {
+            let snapshot = snapshot_path(&example);
+            let actual = render(&example);
+            (example, snapshot, actual)
+        })
+        .collect();
+    let failures = compare_snapshots(&programs, bless);
+
+    assert!(
+        failures.is_empty(),
+        "{} snapshot(s) failed; if the change is intended, re-run with EMBER_BLESS=1\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn errors_match_snapshots() {
+    let bless = env::var_os("EMBER_BLESS").is_some();
+
+    let mut programs = Vec::new();
+    collect_examples(Path::new(ERRORS_DIR), &mut programs);
+    assert!(
+        !programs.is_empty(),
+        "no error programs found in {}",
+        ERRORS_DIR
+    );
+
+    let programs: Vec<_> = programs
+        .into_iter()
+        .map(|program| {
+            let snapshot = program.with_extension("snap");
+            let actual = render_error(&program);
+            (program, snapshot, actual)
+        })
+        .collect();
+    let quiet: Vec<String> = programs
+        .iter()
+        .filter(|(_, _, actual)| actual.starts_with("== no error =="))
+        .map(|(program, _, _)| program.display().to_string())
+        .collect();
+    assert!(
+        quiet.is_empty(),
+        "these ran without error: {}",
+        quiet.join(", ")
+    );
+
+    let failures = compare_snapshots(&programs, bless);
+    assert!(
+        failures.is_empty(),
+        "{} error snapshot(s) failed; review the new diagnostics, and if they \
+         lose nothing, re-run with EMBER_BLESS=1\n\n{}",
+        failures.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_strip_ansi() {
+    assert_eq!(strip_ansi("\x1b[31mred\x1b[0m plain"), "red plain");
+    assert_eq!(strip_ansi("\x1b[1;4mboth\x1b[0m"), "both");
+}
@@ -0,0 +1,524 @@
+//! The builtin word registry: the single list of keyword builtins, from
+//! which the name/token/node/op lookups and the reference docs shown by
+//! `describe` and the REPL's `:help` are generated.
+//!
+//! Native words (see `runtime::native`) are resolved by name at run time,
+//! so they only need docs here. User words are documented by the comment
+//! block directly above their `def`.
+
+use crate::bytecode::op::Op;
+use crate::frontend::token::Token;
+use crate::lang::node::Node;
+
+/// Stack effect and one-line description of a builtin word.
+#[derive(Debug)]
+pub struct BuiltinDoc {
+    pub name: &'static str,
+    pub effect: &'static str,
+    pub description: &'static str,
+}
+
+/// How an op changes the height of the data stack, for the stack checker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackDelta {
+    /// Pops `inputs` values, then pushes `outputs`.
+    Fixed { inputs: usize, outputs: usize },
+    /// Pops at least `inputs` values and runs a quotation, so the height
+    /// afterwards depends on what the quotation does.
+    Dynamic { inputs: usize },
+    /// Empties the stack, whatever its height.
+    Clears,
+}
+
+const fn fixed(inputs: usize, outputs: usize) -> StackDelta {
+    StackDelta::Fixed { inputs, outputs }
+}
+
+const fn dynamic(inputs: usize) -> StackDelta {
+    StackDelta::Dynamic { inputs }
+}
+
+const fn clears() -> StackDelta {
+    StackDelta::Clears
+}
+
+/// Declares the keyword builtins. Each entry reads
+///
+/// ```text
+/// "name": Token => Node => Op, fixed(pops, pushes), "( effect )", "Description.";
+/// ```
+///
+/// and generates the lookups between names, tokens, AST nodes and ops used
+/// by the lexer, parser, compiler and error messages, each op's stack delta
+/// for the checker, and the reference docs. A new keyword word needs its
+/// enum variants, one entry here, and its VM arm.
+macro_rules! builtins {
+    ($($name:literal: $token:ident => $node:ident => $op:ident, $delta:expr, $effect:literal, $description:literal;)*) => {
+        /// Reference docs for every keyword builtin, in registry order.
+        pub const KEYWORD_DOCS: &[BuiltinDoc] = &[$(BuiltinDoc {
+            name: $name,
+            effect: $effect,
+            description: $description,
+        }),*];
+
+        /// The token for a builtin word's name.
+        pub fn keyword(name: &str) -> Option<Token> {
+            match name {
+                $($name => Some(Token::$token),)*
+                _ => None,
+            }
+        }
+
+        /// The source name of a builtin word's token.
+        pub fn token_name(token: &Token) -> Option<&'static str> {
+            match token {
+                $(Token::$token => Some($name),)*
+                _ => None,
+            }
+        }
+
+        /// The AST node a builtin word's token parses to.
+        pub fn node_for(token: &Token) -> Option<Node> {
+            match token {
+                $(Token::$token => Some(Node::$node),)*
+                _ => None,
+            }
+        }
+
+        /// The source name of a builtin word's node.
+        pub fn node_name(node: &Node) -> Option<&'static str> {
+            match node {
+                $(Node::$node => Some($name),)*
+                _ => None,
+            }
+        }
+
+        /// The source name of a builtin word's op.
+        pub fn op_name(op: &Op) -> Option<&'static str> {
+            match op {
+                $(Op::$op => Some($name),)*
+                _ => None,
+            }
+        }
+
+        /// How a builtin word's op changes the stack height.
+        pub fn op_delta(op: &Op) -> Option<StackDelta> {
+            match op {
+                $(Op::$op => Some($delta),)*
+                _ => None,
+            }
+        }
+
+        /// The op a builtin word's node compiles to.
+        pub fn op_for(node: &Node) -> Option<Op> {
+            match node {
+                $(Node::$node => Some(Op::$op),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+builtins! {
+    // Stack
+    "dup": Dup => Dup => Dup, fixed(1, 2), "( a -- a a )", "Duplicate the top value.";
+    "drop": Drop => Drop => Drop, fixed(1, 0), "( a -- )", "Discard the top value.";
+    "swap": Swap => Swap => Swap, fixed(2, 2), "( a b -- b a )", "Exchange the top two values.";
+    "over": Over => Over => Over, fixed(2, 3), "( a b -- a b a )", "Copy the second value to the top.";
+    "rot": Rot => Rot => Rot, fixed(3, 3), "( a b c -- b c a )", "Rotate the third value to the top.";
+    "nip": Nip => Nip => Nip, fixed(2, 1), "( a b -- b )", "Discard the second value.";
+    "tuck": Tuck => Tuck => Tuck, fixed(2, 3), "( a b -- b a b )", "Copy the top value below the second.";
+    "2dup": TwoDup => TwoDup => TwoDup, fixed(2, 4), "( a b -- a b a b )", "Duplicate the top two values.";
+    "2drop": TwoDrop => TwoDrop => TwoDrop, fixed(2, 0), "( a b -- )", "Discard the top two values.";
+    "pick": Pick => Pick => Pick, fixed(2, 2), "( ... n -- ... x )", "Copy the value n below the top; `0 pick` is `dup`, `1 pick` is `over`.";
+    "roll": Roll => Roll => Roll, fixed(2, 1), "( ... n -- ... )", "Move the value n below the top to the top; `1 roll` is `swap`, `2 roll` is `rot`.";
+    "clear": Clear => Clear => Clear, clears(), "( ... -- )", "Empty the data stack.";
+    "depth": Depth => Depth => Depth, fixed(0, 1), "( -- n )", "Number of values on the data stack.";
+
+    // Arithmetic
+    "+": Plus => Add => Add, fixed(2, 1), "( a b -- sum )", "Add two numbers.";
+    "-": Minus => Sub => Sub, fixed(2, 1), "( a b -- diff )", "Subtract the top from the second.";
+    "*": Star => Mul => Mul, fixed(2, 1), "( a b -- prod )", "Multiply two numbers.";
+    "/": Slash => Div => Div, fixed(2, 1), "( a b -- q )", "Divide; integer division truncates.";
+    "%": Percent => Mod => Mod, fixed(2, 1), "( a b -- rem )", "Remainder of integer division.";
+    "neg": Neg => Neg => Neg, fixed(1, 1), "( a -- -a )", "Negate a number.";
+    "abs": Abs => Abs => Abs, fixed(1, 1), "( a -- |a| )", "Absolute value.";
+    "min": Min => Min => Min, fixed(2, 1), "( a b -- min )", "The smaller of two numbers; a float on either side gives a float.";
+    "max": Max => Max => Max, fixed(2, 1), "( a b -- max )", "The larger of two numbers; a float on either side gives a float.";
+    "pow": Pow => Pow => Pow, fixed(2, 1), "( base exp -- n )", "Raise `base` to the power `exp`.";
+    "sqrt": Sqrt => Sqrt => Sqrt, fixed(1, 1), "( n -- float )", "Square root.";
+    "sin": Sin => Sin => Sin, fixed(1, 1), "( x -- float )", "Sine of an angle in radians.";
+    "cos": Cos => Cos => Cos, fixed(1, 1), "( x -- float )", "Cosine of an angle in radians.";
+    "tan": Tan => Tan => Tan, fixed(1, 1), "( x -- float )", "Tangent of an angle in radians.";
+    "atan2": Atan2 => Atan2 => Atan2, fixed(2, 1), "( y x -- float )", "Angle in radians of the point (x, y), from -pi to pi.";
+    "ln": Ln => Ln => Ln, fixed(1, 1), "( x -- float )", "Natural logarithm of a positive number.";
+    "log10": Log10 => Log10 => Log10, fixed(1, 1), "( x -- float )", "Base-10 logarithm of a positive number.";
+    "exp": Exp => Exp => Exp, fixed(1, 1), "( x -- float )", "e raised to the power `x`.";
+    "floor": Floor => Floor => Floor, fixed(1, 1), "( x -- x' )", "Round down to a whole number; a float stays a float, an integer is unchanged.";
+    "ceil": Ceil => Ceil => Ceil, fixed(1, 1), "( x -- x' )", "Round up to a whole number; a float stays a float, an integer is unchanged.";
+    "round": Round => Round => Round, fixed(1, 1), "( x -- x' )", "Round to the nearest whole number, halves away from zero; a float stays a float, an integer is unchanged.";
+
+    // Comparison and logic
+    "=": Eq => Eq => Eq, fixed(2, 1), "( a b -- bool )", "True if the values are equal.";
+    "!=": NotEq => NotEq => Ne, fixed(2, 1), "( a b -- bool )", "True if the values differ.";
+    "<": Lt => Lt => Lt, fixed(2, 1), "( a b -- bool )", "True if a is less than b.";
+    ">": Gt => Gt => Gt, fixed(2, 1), "( a b -- bool )", "True if a is greater than b.";
+    "<=": LtEq => LtEq => Le, fixed(2, 1), "( a b -- bool )", "True if a is at most b.";
+    ">=": GtEq => GtEq => Ge, fixed(2, 1), "( a b -- bool )", "True if a is at least b.";
+    "and": And => And => And, fixed(2, 1), "( a b -- bool )", "Logical AND; written `a [ b ] and`, `b` only runs if `a` is true and must leave a bool.";
+    "or": Or => Or => Or, fixed(2, 1), "( a b -- bool )", "Logical OR; written `a [ b ] or`, `b` only runs if `a` is false and must leave a bool.";
+    "not": Not => Not => Not, fixed(1, 1), "( a -- bool )", "Logical NOT.";
+
+    // Control flow and combinators
+    "if": If => If => If, dynamic(3), "( bool then else -- ... )", "Call `then` if the flag is true, otherwise `else`; a literal branch is pushed as is.";
+    "when": When => When => When, dynamic(2), "( bool quot -- ... )", "Call `quot` only if the flag is true.";
+    "call": Call => Call => Call, dynamic(1), "( quot -- ... )", "Call a quotation.";
+    "try": Try => Try => Try, dynamic(2), "( body handler -- ... )", "Call `body`; if it fails, restore the stack and call `handler` with the error.";
+    "throw": Throw => Throw => Throw, fixed(1, 0), "( x -- )", "Fail with `x`, which the nearest `try` passes to its handler.";
+    "times": Times => Times => Times, dynamic(2), "( n quot -- ... )", "Call `quot` n times.";
+    "while": While => While => While, dynamic(2), "( cond body -- ... )", "Call `body` for as long as `cond` leaves true.";
+    "until": Until => Until => Until, dynamic(2), "( cond body -- ... )", "Call `body` until `cond` leaves true.";
+    "dip": Dip => Dip => Dip, dynamic(2), "( a quot -- ... a )", "Call `quot` with `a` set aside, then restore it.";
+    "keep": Keep => Keep => Keep, dynamic(2), "( a quot -- ... a )", "Call `quot` on `a`, then push `a` again.";
+    "bi": Bi => Bi => Bi, dynamic(3), "( a p q -- ... )", "Apply `p` and then `q` to the same value.";
+    "bi2": Bi2 => Bi2 => Bi2, dynamic(4), "( a b p q -- ... )", "Apply `p` and then `q` to the same pair of values.";
+    "tri": Tri => Tri => Tri, dynamic(4), "( a p q r -- ... )", "Apply three quotations to the same value.";
+    "both": Both => Both => Both, dynamic(3), "( a b quot -- ... )", "Apply `quot` to `a` and then to `b`.";
+    "compose": Compose => Compose => Compose, fixed(2, 1), "( p q -- quot )", "A quotation that calls `p` and then `q`.";
+    "curry": Curry => Curry => Curry, fixed(2, 1), "( a quot -- quot )", "A quotation that pushes `a` before calling `quot`.";
+    "apply": Apply => Apply => Apply, dynamic(2), "( list quot -- ... )", "Push the list's items, then call `quot`.";
+
+    // Lists
+    "len": Len => Len => Len, fixed(1, 1), "( seq -- n )", "Length of a list, string or bytes value.";
+    "head": Head => Head => Head, fixed(1, 1), "( list -- x )", "First element.";
+    "tail": Tail => Tail => Tail, fixed(1, 1), "( list -- list )", "Everything but the first element.";
+    "cons": Cons => Cons => Cons, fixed(2, 1), "( x list -- list )", "Prepend an element.";
+    "append": Append => Append => Append, fixed(2, 1), "( list x -- list )", "Append an element.";
+    "concat": Concat => Concat => Concat, fixed(2, 1), "( l1 l2 -- list )", "Concatenate two lists.";
+    "nth": Nth => Nth => Nth, fixed(2, 1), "( list i -- x )", "Element at index `i`, counting from 0.";
+    "range": Range => Range => Range, fixed(2, 1), "( start end -- list )", "Integers from `start` up to, not including, `end`.";
+    "each": Each => Each => Each, dynamic(2), "( list quot -- ... )", "Call `quot` with each element.";
+    "map": Map => Map => Map, fixed(2, 1), "( list quot -- list )", "Apply `quot` to each element, collecting the results.";
+    "filter": Filter => Filter => Filter, fixed(2, 1), "( list quot -- list )", "Keep the elements for which `quot` returns true.";
+    "fold": Fold => Fold => Fold, fixed(3, 1), "( list init quot -- x )", "Combine the elements, starting from `init`.";
+    "sort": Sort => Sort => Sort, fixed(1, 1), "( list -- list )", "Sort a list of integers ascending.";
+    "reverse": Reverse => Reverse => Reverse, fixed(1, 1), "( list -- list )", "Reverse a list.";
+
+    // Vectors
+    "v+": VAdd => VAdd => VAdd, fixed(2, 1), "( xs ys -- zs )", "Element-wise add; a scalar on either side is broadcast.";
+    "v*": VMul => VMul => VMul, fixed(2, 1), "( xs ys -- zs )", "Element-wise multiply; a scalar on either side is broadcast.";
+    "scale": Scale => Scale => Scale, fixed(2, 1), "( xs k -- ys )", "Multiply every element by a scalar.";
+    "dot": DotProduct => DotProduct => DotProduct, fixed(2, 1), "( xs ys -- n )", "Dot product of two numeric lists.";
+    "transpose": Transpose => Transpose => Transpose, fixed(1, 1), "( rows -- cols )", "Swap the rows and columns of a nested list.";
+    "shape": Shape => Shape => Shape, fixed(1, 1), "( x -- dims )", "Dimensions of a rectangular nested list.";
+    "flatten-depth": FlattenDepth => FlattenDepth => FlattenDepth, fixed(2, 1), "( list n -- list )", "Flatten `n` levels of nesting.";
+
+    // Strings
+    ".": Dot => StringConcat => StringConcat, fixed(2, 1), "( a b -- str )", "Concatenate the printed forms of two values.";
+    "chars": Chars => Chars => Chars, fixed(1, 1), "( str -- list )", "Split a string into one-character strings.";
+    "join": Join => Join => Join, fixed(2, 1), "( list sep -- str )", "Join the printed forms of the elements with `sep`.";
+    "split": Split => Split => Split, fixed(2, 1), "( str sep -- list )", "Split a string on every `sep`.";
+    "upper": Upper => Upper => Upper, fixed(1, 1), "( str -- str )", "Uppercase a string.";
+    "lower": Lower => Lower => Lower, fixed(1, 1), "( str -- str )", "Lowercase a string.";
+    "trim": Trim => Trim => Trim, fixed(1, 1), "( str -- str )", "Strip leading and trailing whitespace.";
+
+    // Conversion and types
+    "type": Type => Type => Type, fixed(1, 2), "( x -- x name )", "Push the name of the top value's type.";
+    "to-string": ToString => ToString => ToString, fixed(1, 1), "( x -- str )", "Printed form of a value.";
+    "to-int": ToInt => ToInt => ToInt, fixed(1, 1), "( x -- int )", "Parse a string or truncate a float.";
+    "to-float": ToFloat => ToFloat => ToFloat, fixed(1, 1), "( x -- float )", "Parse a string or widen an integer.";
+    "parse-int": ParseInt => ParseInt => ParseInt, fixed(2, 1), "( str radix -- int )", "Parse an integer in base 2 to 36.";
+
+    // I/O
+    "print": Print => Print => Print, fixed(1, 0), "( x -- )", "Print a value and a newline.";
+    "emit": Emit => Emit => Emit, fixed(1, 0), "( code -- )", "Print the character with this code point.";
+    "read": Read => Read => Read, fixed(0, 1), "( -- str )", "Read one line of input.";
+    "debug": Debug => Debug => Debug, fixed(1, 1), "( x -- x )", "Print the top value, marked `[DEBUG]`, to standard output, leaving it in place.";
+}
+
+macro_rules! docs {
+    ($($name:literal $effect:literal $description:literal;)*) => {
+        &[$(BuiltinDoc {
+            name: $name,
+            effect: $effect,
+            description: $description,
+        }),*]
+    };
+}
+
+/// Reference docs for the native words.
+pub const NATIVE_DOCS: &[BuiltinDoc] = docs! {
+    // I/O
+    "stdin-lines" "( -- seq )" "The remaining input lines as a sequence, read as they are asked for.";
+    "each-line" "( quot -- )" "Call `quot` with each input line as it is read.";
+
+    // Strings
+    "format" "( template values -- str )" "Fill `{}`, `{0}` and `{:>8.2}`-style placeholders with values from the list.";
+
+    // Binary data
+    "to-bytes" "( str|list -- bytes )" "UTF-8 bytes of a string, or a list of integers 0-255.";
+    "from-utf8" "( bytes -- str )" "Decode UTF-8, failing on invalid sequences.";
+    "byte-list" "( bytes -- list )" "Each byte as an integer.";
+    "pack-u32-le" "( n -- bytes )" "Four bytes, least significant first.";
+    "pack-u32-be" "( n -- bytes )" "Four bytes, most significant first.";
+    "unpack-u32-le" "( bytes offset -- n )" "Read a little-endian u32 at `offset`.";
+    "unpack-u32-be" "( bytes offset -- n )" "Read a big-endian u32 at `offset`.";
+    "base64-encode" "( bytes|str -- str )" "Base64 with padding.";
+    "base64-decode" "( str -- bytes )" "Decode base64; padding is optional.";
+    "hex-encode" "( bytes|str -- str )" "Lowercase hex, two digits per byte.";
+    "hex-decode" "( str -- bytes )" "Decode hex in either case.";
+
+    // Hashing
+    "crc32" "( data -- hex )" "CRC-32 (IEEE), 8 hex digits.";
+    "hash" "( data -- hex )" "FNV-1a 64-bit, 16 hex digits.";
+    "sha256" "( data -- hex )" "SHA-256, 64 hex digits (feature `sha256`).";
+
+    // Ranges and extremes
+    "range-step" "( start end step -- list )" "Integers from `start` toward `end`, excluding it, `step` apart; counts down for a negative step.";
+    "range-incl" "( start end -- list )" "Integers from `start` up to and including `end`.";
+    "between?" "( x lo hi -- bool )" "True if `lo <= x <= hi`, comparing ints and floats alike.";
+    "clamp" "( x lo hi -- y )" "`x` limited to `lo..=hi`; a float anywhere gives a float.";
+    "minimum" "( list -- x )" "Smallest number in a non-empty list.";
+    "maximum" "( list -- x )" "Largest number in a non-empty list.";
+    "divmod" "( a b -- pair )" "Floored quotient and remainder `{ q r }`; `r` has the sign of `b`.";
+    "floor-div" "( a b -- q )" "Integer division rounding down (`/` truncates toward zero).";
+    "ceil-div" "( a b -- q )" "Integer division rounding up.";
+
+    // Lists
+    "set-nth" "( list i x -- list )" "A copy of the list with the item at `i` replaced by `x`.";
+    "insert-at" "( list i x -- list )" "A copy with `x` inserted before index `i` (`i` may be the length).";
+    "remove-at" "( list i -- list )" "A copy without the item at `i`.";
+    "update-at" "( list i quot -- list )" "A copy with the item at `i` replaced by what `quot` makes of it.";
+    "unique" "( list -- list )" "Drop repeated items, keeping the first of each in order.";
+    "contains?" "( list x -- bool )" "Whether an item equals `x` (by `=`, so `1` is not `1.0`).";
+    "index-of" "( list x -- i )" "Index of the first item equal to `x`, or -1.";
+
+    // Pairs
+    "pair" "( a b -- pair )" "Make a pair: a two-item list.";
+    "unpair" "( pair -- a b )" "Push both halves of a pair.";
+    "first" "( pair -- a )" "The first half of a pair.";
+    "second" "( pair -- b )" "The second half of a pair.";
+    "zip" "( xs ys -- pairs )" "Pair up items at the same index, stopping at the shorter list.";
+    "partition" "( list quot -- pair )" "The items `quot` accepts and the rest, as a pair of lists.";
+
+    // Sequences
+    "sequence" "( state next -- seq )" "Items made on demand by `next ( state -- state' item true | false )`, for `each`, `map`, `filter` and `fold`.";
+    "seq-take" "( seq n -- list )" "The first `n` items of a sequence or list.";
+
+    // Maps
+    "get" "( map key -- value )" "Look up a key, failing if it is absent.";
+    "put" "( map key value -- map )" "Add a key, or replace its value.";
+    "remove" "( map key -- map )" "Drop a key, if present.";
+    "has-key?" "( map key -- bool )" "Whether the map has a key.";
+    "keys" "( map -- keys )" "The keys, sorted.";
+    "values" "( map -- values )" "The values, in key order.";
+    "get-in" "( data path -- value )" "Follow a path of map keys and list indexes into nested data.";
+    "set-in" "( data path value -- data )" "Replace the value at a path; the last step may add a map key.";
+    "update-in" "( data path quot -- data )" "Replace the value at a path with what `quot` makes of it.";
+
+    // Config files
+    "toml-parse" "( str -- map )" "Parse a TOML document into maps and lists (feature `toml`).";
+    "yaml-parse" "( str -- data )" "Parse a YAML document into maps and lists; null map values are left out (feature `yaml`).";
+
+    // Color
+    "colorize" "( str color -- str )" "Wrap in an ANSI color: black, red, green, yellow, blue, magenta, cyan, white or gray.";
+    "style" "( str style -- str )" "Wrap in an ANSI style: bold, dim, italic, underline or reverse.";
+
+    // Prompts
+    "prompt" "( question -- line )" "Write the question, read one line in reply; an error at end of input.";
+    "confirm" "( question -- bool )" "Ask a y/n question until answered; false at end of input.";
+    "read-password" "( question -- line )" "Like `prompt`, hiding what is typed at a terminal (feature `password`).";
+
+    // Progress
+    "with-progress" "( list quot -- )" "Like `each`, or `times` given a count, with a progress bar on stderr.";
+
+    // Tables
+    "print-table" "( rows header -- )" "Print a list of lists or maps as an aligned table; `{ }` as the header shows all map keys.";
+
+    // Paths
+    "glob" "( pattern -- paths )" "Matching paths, sorted.";
+    "path-join" "( base part -- path )" "Join two components with the platform separator.";
+    "basename" "( path -- name )" "Final component of a path.";
+    "dirname" "( path -- dir )" "Everything but the final component.";
+    "extension" "( path -- ext )" "Extension without the dot, `\"\"` if none.";
+    "canonicalize" "( path -- abs )" "Absolute path with symlinks and `..` resolved.";
+
+    // Files
+    "read-file" "( path -- str )" "Text of a UTF-8 file.";
+    "write-file" "( data path -- )" "Replace a file's contents with a string or bytes.";
+    "append-file" "( data path -- )" "Add a string or bytes to the end of a file, creating it if missing.";
+    "file-exists?" "( path -- bool )" "Whether a file or directory exists at the path.";
+    "delete-file" "( path -- )" "Delete a file.";
+
+    // Processes
+    "run-process" "( program args -- result )" "Run to completion; a map of status, success, stdout and stderr.";
+    "run-process-timeout" "( program args ms -- result )" "Like `run-process`, failing after `ms` milliseconds.";
+
+    // Persistence
+    "store-save" "( map path -- )" "Write a map to a file.";
+    "store-load" "( path -- map )" "Read a map written by `store-save`.";
+    "checkpoint" "( -- )" "Pause so the host can save the run, if it enabled checkpoints.";
+
+    // Handlers
+    "register-handler" "( name quot -- )" "Register `quot` for the host to call for the event `name`.";
+
+    // Desktop
+    "clipboard-get" "( -- str )" "Text on the system clipboard (feature `desktop`).";
+    "clipboard-set" "( str -- )" "Put text on the system clipboard (feature `desktop`).";
+    "open-url" "( url -- )" "Open a URL with the default handler, e.g. the browser (feature `desktop`).";
+
+    // Networking
+    "tcp-connect" "( addr -- conn )" "Connect to `host:port` (feature `net`).";
+    "tcp-listen" "( addr -- listener )" "Listen on `host:port`; port 0 picks a free port (feature `net`).";
+    "accept" "( listener -- conn )" "Wait for a client to connect (feature `net`).";
+    "tcp-send" "( conn data -- )" "Send all of `data` (feature `net`).";
+    "tcp-recv" "( conn -- data )" "Receive available data, `\"\"` at end of stream (feature `net`).";
+    "udp-bind" "( addr -- sock )" "Bind a UDP socket to `host:port` (feature `net`).";
+    "udp-send" "( sock data addr -- )" "Send one datagram (feature `net`).";
+    "udp-recv" "( sock -- data from )" "Wait for a datagram and its sender (feature `net`).";
+    "socket-addr" "( handle -- addr )" "Local `host:port` of a socket (feature `net`).";
+    "socket-close" "( handle -- )" "Close a socket (feature `net`).";
+
+    // SQLite
+    "sqlite-open" "( path -- db )" "Open a database file, or `\":memory:\"` (feature `sqlite`).";
+    "sqlite-exec" "( db sql params -- changes )" "Run a statement with `?` params; rows changed (feature `sqlite`).";
+    "sqlite-query" "( db sql params -- rows )" "Run a query with `?` params; rows as maps (feature `sqlite`).";
+    "sqlite-close" "( db -- )" "Close a database (feature `sqlite`).";
+
+    // Tasks
+    "spawn" "( quot -- task )" "Run a quotation on another thread in a VM of its own (feature `concurrency`, needs `allow_spawn`).";
+    "chan" "( -- ch )" "Make a channel for passing values between tasks (feature `concurrency`).";
+    "send" "( ch value -- )" "Put a copy of `value` on a channel (feature `concurrency`).";
+    "recv" "( ch -- value )" "Take the oldest value from a channel, waiting if it is empty (feature `concurrency`).";
+    "task-join" "( task -- stack )" "Wait for a task and push its final stack as a list; fails if the task did (feature `concurrency`).";
+    "task-join-timeout" "( task ms -- stack done? )" "Like `task-join`, pushing `{ } false` if the task is still running after `ms` (feature `concurrency`).";
+    "task-done?" "( task -- bool )" "Whether a task has finished, without waiting (feature `concurrency`).";
+    "atomic-new" "( n -- atomic )" "Make a counter tasks can share, starting at `n` (feature `concurrency`).";
+    "atomic-add" "( atomic n -- total )" "Add to a counter in one step and push the new total (feature `concurrency`).";
+    "atomic-get" "( atomic -- n )" "Current total of a counter (feature `concurrency`).";
+    "once-new" "( -- cell )" "Make an empty cell that can be set once (feature `concurrency`).";
+    "once-set" "( cell value -- set? )" "Put a copy of `value` in a cell unless it is already set (feature `concurrency`).";
+    "once-get" "( cell -- value )" "The value in a cell, waiting if it is not set yet (feature `concurrency`).";
+
+    // Regex
+    "re-match?" "( str pattern -- bool )" "True if the pattern matches anywhere (feature `regex`).";
+    "re-find" "( str pattern -- captures )" "First match and its groups, `{ }` if none (feature `regex`).";
+    "re-find-all" "( str pattern -- matches )" "Every non-overlapping match (feature `regex`).";
+    "re-replace" "( str pattern replacement -- str )" "Replace every match; `$1` expands groups (feature `regex`).";
+    "re-split" "( str pattern -- parts )" "Split on every match (feature `regex`).";
+
+    // Reflection
+    "quot->list" "( quot -- list )" "Describe each instruction of a quotation: word names, `{ literal }`s and `#jump`s.";
+    "list->quot" "( list -- quot )" "Build a quotation from `quot->list` descriptors (needs `allow_compile`).";
+    "eval" "( source -- ... )" "Compile and run source text on the current stack (needs `allow_compile`).";
+    "quot->string" "( quot -- str )" "Render a quotation as source text.";
+
+    // Help
+    "describe" "( name -- )" "Print the stack effect and description of a word.";
+    "see" "( name -- )" "Print the definition of a user word as source.";
+    "todo" "( -- )" "Fail with \"not yet implemented\", naming the running word and where it is defined.";
+    "???" "( -- )" "A hole: compiles anywhere, and fails like `todo` if it runs.";
+};
+
+/// Documentation for a builtin word, if it has any.
+pub fn lookup(name: &str) -> Option<&'static BuiltinDoc> {
+    KEYWORD_DOCS
+        .iter()
+        .chain(NATIVE_DOCS)
+        .find(|doc| doc.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::lexer::Lexer;
+    use crate::runtime::native;
+
+    #[test]
+    fn test_every_native_word_is_documented() {
+        for name in native::names() {
+            assert!(
+                NATIVE_DOCS.iter().any(|doc| doc.name == name),
+                "no documentation for '{}'",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_documented_natives_exist() {
+        for doc in NATIVE_DOCS {
+            // Native words behind a disabled feature are still documented
+            let feature_gated = doc.description.contains("(feature `");
+            assert!(
+                native::lookup(doc.name).is_some() || feature_gated,
+                "'{}' is documented but is not a native word",
+                doc.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_keywords_lex_parse_and_compile_through_the_registry() {
+        for doc in KEYWORD_DOCS {
+            let tokens = Lexer::new(doc.name).tokenize().unwrap();
+            let token = &tokens[0].token;
+            assert_eq!(token_name(token), Some(doc.name), "{:?}", token);
+            assert_eq!(token.to_string(), doc.name);
+
+            let node = node_for(token).unwrap();
+            assert_eq!(node_name(&node), Some(doc.name));
+            assert!(op_for(&node).is_some(), "{} has no op", doc.name);
+        }
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<&str> = KEYWORD_DOCS
+            .iter()
+            .chain(NATIVE_DOCS)
+            .map(|doc| doc.name)
+            .collect();
+        names.sort();
+        let count = names.len();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn test_effects_are_well_formed() {
+        for doc in KEYWORD_DOCS.iter().chain(NATIVE_DOCS) {
+            assert!(
+                doc.effect.starts_with("( ") && doc.effect.ends_with(" )"),
+                "{}: {}",
+                doc.name,
+                doc.effect
+            );
+            assert!(doc.effect.contains(" -- "), "{}: {}", doc.name, doc.effect);
+        }
+    }
+
+    #[test]
+    fn test_fixed_deltas_match_documented_effects() {
+        for doc in KEYWORD_DOCS {
+            let node = node_for(&keyword(doc.name).unwrap()).unwrap();
+            let op = op_for(&node).unwrap();
+            let Some(StackDelta::Fixed { inputs, outputs }) = op_delta(&op) else {
+                continue;
+            };
+            let (before, after) = doc.effect[2..doc.effect.len() - 2]
+                .split_once("--")
+                .unwrap();
+            assert_eq!(
+                (
+                    before.split_whitespace().count(),
+                    after.split_whitespace().count()
+                ),
+                (inputs, outputs),
+                "{} {}",
+                doc.name,
+                doc.effect
+            );
+        }
+    }
+}
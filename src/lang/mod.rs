@@ -10,7 +10,9 @@
 //! - `{ ... }` denotes an Ember list literal.
 //! - `[ ... ]` denotes an Ember quotation (anonymous function).
 
+pub mod builtins;
 pub mod node;
 pub mod program;
 pub mod use_item;
 pub mod value;
+pub mod value_serde;
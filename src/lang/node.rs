@@ -1,5 +1,6 @@
 use super::use_item::UseItem;
 use super::value::Value;
+use crate::frontend::lexer::Span;
 use serde::{Deserialize, Serialize};
 
 /// Abstract Syntax Tree node for the Ember language.
@@ -41,6 +42,38 @@ pub enum Node {
     /// Stack effect: `( a b c -- b c a )`
     Rot,
 
+    /// Drop the second value.
+    ///
+    /// Stack effect: `( a b -- b )`
+    Nip,
+
+    /// Copy the top value below the second.
+    ///
+    /// Stack effect: `( a b -- b a b )`
+    Tuck,
+
+    /// Copy the top two values.
+    ///
+    /// Stack effect: `( a b -- a b a b )`
+    TwoDup,
+
+    /// Drop the top two values.
+    ///
+    /// Stack effect: `( a b -- )`
+    TwoDrop,
+
+    /// Copy the value `n` below the top (after popping `n`); `0 pick` is
+    /// `dup` and `1 pick` is `over`.
+    ///
+    /// Stack effect: `( xn ... x0 n -- xn ... x0 xn )`
+    Pick,
+
+    /// Move the value `n` below the top (after popping `n`) to the top;
+    /// `1 roll` is `swap` and `2 roll` is `rot`.
+    ///
+    /// Stack effect: `( xn ... x0 n -- ... x0 xn )`
+    Roll,
+
     // ───────────────────────────── Arithmetic ───────────────────────────
     /// Add two numbers.
     ///
@@ -140,12 +173,33 @@ pub enum Node {
     /// Expected stack usage: `( [q] -- ... )`
     Call,
 
+    /// Execute `body`; if it fails, restore the stack and execute
+    /// `handler` with the error on top.
+    ///
+    /// Expected stack usage: `( [body] [handler] -- ... )`
+    Try,
+
+    /// Fail with a value, which the nearest `try` hands to its handler.
+    ///
+    /// Expected stack usage: `( x -- )`
+    Throw,
+
     // ───────────────────── Loops & higher-order combinators ─────────────
     /// Execute a quotation `n` times.
     ///
     /// Expected stack usage: `( n [body] -- ... )`
     Times,
 
+    /// Execute `body` for as long as `cond` leaves true.
+    ///
+    /// Expected stack usage: `( [cond] [body] -- ... )`
+    While,
+
+    /// Execute `body` until `cond` leaves true.
+    ///
+    /// Expected stack usage: `( [cond] [body] -- ... )`
+    Until,
+
     /// Apply a quotation to each element of a list.
     ///
     /// Expected stack usage: `( {xs} [f] -- )`
@@ -234,6 +288,36 @@ pub enum Node {
     /// Square root.
     Sqrt,
 
+    /// Sine, in radians.
+    Sin,
+
+    /// Cosine, in radians.
+    Cos,
+
+    /// Tangent, in radians.
+    Tan,
+
+    /// Four-quadrant arctangent of y/x.
+    Atan2,
+
+    /// Natural logarithm.
+    Ln,
+
+    /// Base-10 logarithm.
+    Log10,
+
+    /// e to a power.
+    Exp,
+
+    /// Round down.
+    Floor,
+
+    /// Round up.
+    Ceil,
+
+    /// Round to nearest, halves away from zero.
+    Round,
+
     /// Nth element of a list.
     Nth,
 
@@ -279,6 +363,60 @@ pub enum Node {
     /// Convert a value to integer.
     ToInt,
 
+    /// Convert a value to float.
+    ///
+    /// Strings are parsed locale-independently (`.` is always the decimal
+    /// separator) and accept `inf`, `-inf` and `NaN`.
+    ///
+    /// Stack effect: ( x -- float )
+    ToFloat,
+
+    /// Parse a string as an integer in the given radix (2-36).
+    ///
+    /// Stack effect: ( str radix -- int )
+    ParseInt,
+
+    // ──────────────────────── Vector arithmetic ─────────────────────────
+    /// Element-wise addition of numeric lists.
+    ///
+    /// A scalar on either side is broadcast across the other list.
+    ///
+    /// Stack effect: `( xs ys -- zs )`
+    VAdd,
+
+    /// Element-wise multiplication of numeric lists.
+    ///
+    /// A scalar on either side is broadcast across the other list.
+    ///
+    /// Stack effect: `( xs ys -- zs )`
+    VMul,
+
+    /// Multiply every element of a numeric list by a scalar.
+    ///
+    /// Stack effect: `( xs k -- ys )`
+    Scale,
+
+    /// Dot product (sum of pairwise products) of two numeric lists.
+    ///
+    /// Stack effect: `( xs ys -- n )`
+    DotProduct,
+
+    // ────────────────────────── Matrix helpers ──────────────────────────
+    /// Transpose a list of equal-length rows.
+    ///
+    /// Stack effect: `( {rows} -- {cols} )`
+    Transpose,
+
+    /// Dimensions of a (possibly nested) rectangular list.
+    ///
+    /// Stack effect: `( x -- {dims} )`
+    Shape,
+
+    /// Flatten nested lists by up to `n` levels.
+    ///
+    /// Stack effect: `( {xs} n -- {ys} )`
+    FlattenDepth,
+
     // ───────────────────────── Word references ──────────────────────────
     /// Call a user-defined word.
     Word(String),
@@ -298,6 +436,11 @@ pub enum Node {
         name: String,
         /// Body of the word.
         body: Vec<Node>,
+        /// Line of the `def` keyword in its source.
+        line: usize,
+        /// Where each node of `body` starts; empty for nodes that weren't
+        /// parsed from source.
+        spans: Vec<Span>,
     },
 
     /// Declare a module.
@@ -319,6 +462,27 @@ pub enum Node {
     /// Import another Ember source file.
     Import(String),
 
+    /// Declare a variable, set with `name !` and read with `name @`.
+    Var(String),
+
+    // ──────────────────────────── Variables ─────────────────────────────
+    /// Store into a variable: `42 counter !`.
+    ///
+    /// Stack effect: `( x -- )`
+    Store(String),
+
+    /// Fetch a variable's value: `counter @`.
+    ///
+    /// Stack effect: `( -- x )`
+    Load(String),
+
+    // ───────────────────────────── Locals ───────────────────────────────
+    /// Bind the top value to a local of the enclosing word: `:> width`.
+    /// Later mentions of the name in the word push the value.
+    ///
+    /// Stack effect: `( x -- )`
+    BindLocal(String),
+
     // Concatenative Combinators
     /// ( a quot -- ...results... a ) - execute quot with top hidden
     Dip,
@@ -1,11 +1,17 @@
 use super::node::Node;
+use crate::frontend::lexer::Span;
+use std::collections::BTreeMap;
 
 /// Parsed Ember program.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 #[allow(dead_code)]
 pub struct Program {
     /// Top-level definitions.
     pub definitions: Vec<Node>,
     /// Main executable nodes.
     pub main: Vec<Node>,
+    /// Where each node of `main` starts.
+    pub main_spans: Vec<Span>,
+    /// Comment block directly above each `def`, by word name.
+    pub docs: BTreeMap<String, String>,
 }
@@ -1,6 +1,7 @@
 use super::node::Node;
 use crate::bytecode::op::Op;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Runtime value in the Ember language.
 ///
@@ -22,6 +23,12 @@ pub enum Value {
     /// List literal value: `{ 1 2 3 }`.
     List(Vec<Value>),
 
+    /// Raw binary data, built with `to-bytes` or decoding words.
+    Bytes(Vec<u8>),
+
+    /// String-keyed map, kept in key order so output is deterministic.
+    Map(BTreeMap<String, Value>),
+
     /// Quotation (anonymous function): `[ dup * ]`.
     ///
     /// Quotations are executable sequences of AST nodes and can be passed
@@ -31,12 +38,71 @@ pub enum Value {
     CompiledQuotation(Vec<Op>),
 }
 
-impl std::fmt::Display for Value {
+/// How floats are written when a value is displayed by `print`,
+/// `to-string`, the exit stack report and the disassembler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    /// The shortest text that reads back as the same float: `0.1`, `3.0`,
+    /// `1e300`.
+    #[default]
+    Shortest,
+    /// Exactly this many digits after the decimal point: `0.30`.
+    Fixed(usize),
+    /// Round to this many significant digits, then write the result as
+    /// `Shortest` would, so `0.1 0.2 +` prints `0.3` with 15 digits.
+    Significant(usize),
+}
+
+impl FloatFormat {
+    pub fn format(self, x: f64) -> String {
+        match self {
+            FloatFormat::Shortest => format!("{:?}", x),
+            FloatFormat::Fixed(digits) => format!("{:.*}", digits, x),
+            FloatFormat::Significant(digits) if x.is_finite() => {
+                let rounded = format!("{:.*e}", digits.max(1) - 1, x);
+                let rounded: f64 = rounded.parse().unwrap_or(x);
+                format!("{:?}", rounded)
+            }
+            FloatFormat::Significant(_) => format!("{:?}", x),
+        }
+    }
+}
+
+impl std::str::FromStr for FloatFormat {
+    type Err = String;
+
+    /// Parse `shortest`, `fixed:N` or `sig:N`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = |n: &str| {
+            n.parse::<usize>()
+                .ok()
+                .filter(|&n| (1..=17).contains(&n))
+                .ok_or_else(|| format!("expected 1 to 17 digits, got '{}'", n))
+        };
+        match s.split_once(':') {
+            None if s == "shortest" => Ok(FloatFormat::Shortest),
+            Some(("fixed", n)) => digits(n).map(FloatFormat::Fixed),
+            Some(("sig", n)) => digits(n).map(FloatFormat::Significant),
+            _ => Err(format!(
+                "unknown float format '{}' (expected shortest, fixed:N or sig:N)",
+                s
+            )),
+        }
+    }
+}
+
+/// A value formatted with a particular [`FloatFormat`]; see [`Value::display`].
+pub struct ValueDisplay<'a> {
+    value: &'a Value,
+    floats: FloatFormat,
+}
+
+impl std::fmt::Display for ValueDisplay<'_> {
     /// Format a value using Ember surface syntax.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
+        match self.value {
             Value::Integer(n) => write!(f, "{}", n),
-            Value::Float(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", self.floats.format(*n)),
             Value::String(s) => write!(f, "{}", s),
             Value::Bool(b) => write!(f, "{}", b),
             Value::List(items) => {
@@ -45,7 +111,21 @@ impl std::fmt::Display for Value {
                     if i > 0 {
                         write!(f, " ")?;
                     }
-                    write!(f, "{}", item)?;
+                    write!(f, "{}", item.display(self.floats))?;
+                }
+                write!(f, " }}")
+            }
+            Value::Bytes(bytes) => {
+                write!(f, "0x[")?;
+                for byte in bytes {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "#{{")?;
+                for (key, value) in entries {
+                    write!(f, " {}: {}", key, value.display(self.floats))?;
                 }
                 write!(f, " }}")
             }
@@ -55,7 +135,21 @@ impl std::fmt::Display for Value {
     }
 }
 
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.display(FloatFormat::default()).fmt(f)
+    }
+}
+
 impl Value {
+    /// Format this value, writing floats according to `floats`.
+    pub fn display(&self, floats: FloatFormat) -> ValueDisplay<'_> {
+        ValueDisplay {
+            value: self,
+            floats,
+        }
+    }
+
     /// Get a human-readable type name for error messages
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -64,8 +158,48 @@ impl Value {
             Value::String(_) => "string",
             Value::Bool(_) => "boolean",
             Value::List(_) => "list",
+            Value::Bytes(_) => "bytes",
+            Value::Map(_) => "map",
             Value::Quotation(_) => "quotation",
             Value::CompiledQuotation(_) => "compiled quotation",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_format_policies() {
+        let sum = 0.1 + 0.2;
+        assert_eq!(FloatFormat::Shortest.format(sum), "0.30000000000000004");
+        assert_eq!(FloatFormat::Shortest.format(3.0), "3.0");
+        assert_eq!(FloatFormat::Fixed(2).format(sum), "0.30");
+        assert_eq!(FloatFormat::Significant(15).format(sum), "0.3");
+        assert_eq!(FloatFormat::Significant(3).format(1234.5), "1230.0");
+        assert_eq!(FloatFormat::Significant(3).format(f64::NAN), "NaN");
+    }
+
+    #[test]
+    fn test_float_format_from_str() {
+        assert_eq!("shortest".parse(), Ok(FloatFormat::Shortest));
+        assert_eq!("fixed:2".parse(), Ok(FloatFormat::Fixed(2)));
+        assert_eq!("sig:15".parse(), Ok(FloatFormat::Significant(15)));
+        assert!("sig:0".parse::<FloatFormat>().is_err());
+        assert!("round".parse::<FloatFormat>().is_err());
+    }
+
+    #[test]
+    fn test_display_applies_float_format_inside_collections() {
+        let value = Value::List(vec![Value::Float(0.5), Value::Integer(1)]);
+        assert_eq!(
+            value.display(FloatFormat::Fixed(1)).to_string(),
+            "{ 0.5 1 }"
+        );
+        assert_eq!(
+            value.display(FloatFormat::Fixed(3)).to_string(),
+            "{ 0.500 1 }"
+        );
+    }
+}
@@ -0,0 +1,878 @@
+//! # Serde bridge
+//!
+//! Converts between EMBER [`Value`]s and any Rust type that implements
+//! `Serialize` or `Deserialize`, so a host can push its own structs onto the
+//! stack and read typed results back without converting each field by hand:
+//!
+//! ```text
+//! vm.push(to_value(&order)?);
+//! vm.run_compiled(&program)?;
+//! let quote: Quote = from_value(vm.pop()?)?;
+//! ```
+//!
+//! Structs and maps become maps, sequences and tuples become lists, and
+//! enums follow serde's usual external tagging: a unit variant is its name,
+//! any other variant a one-entry map from its name to its contents. Map keys
+//! must be strings, or numbers and booleans, which are written as text.
+//!
+//! EMBER has no null. A `None` field is left out of its map, and reads back
+//! as `None` when absent; a `None` anywhere else, or a unit value, cannot
+//! be converted. Quotations can't be deserialized into Rust types.
+//!
+//! This is unrelated to the `Serialize` derive on `Value` itself, which is
+//! the bytecode's encoding of values.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize};
+
+use super::value::Value;
+
+/// Why a conversion failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueError(String);
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ValueError {}
+
+impl ser::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError(msg.to_string())
+    }
+}
+
+impl de::Error for ValueError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ValueError(msg.to_string())
+    }
+}
+
+/// Convert any serializable Rust value into an EMBER value.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, ValueError> {
+    value.serialize(ValueSerializer)
+}
+
+/// Read an EMBER value as a Rust type.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, ValueError> {
+    T::deserialize(value)
+}
+
+// =============================================================================
+// Rust -> Value
+// =============================================================================
+
+struct ValueSerializer;
+
+fn no_null(what: &str) -> ValueError {
+    ValueError(format!(
+        "{} has no EMBER value (only a None struct field or map entry can be left out)",
+        what
+    ))
+}
+
+fn tagged(variant: &str, value: Value) -> Value {
+    Value::Map(BTreeMap::from([(variant.to_string(), value)]))
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = ValueError;
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, ValueError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, ValueError> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, ValueError> {
+        i64::try_from(v)
+            .map(Value::Integer)
+            .map_err(|_| ValueError(format!("integer {} is out of range", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, ValueError> {
+        Ok(Value::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, ValueError> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, ValueError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, ValueError> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, ValueError> {
+        Err(no_null("None"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, ValueError> {
+        Err(no_null("()"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Value, ValueError> {
+        Err(no_null(name))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value, ValueError> {
+        Ok(Value::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, ValueError> {
+        Ok(tagged(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, ValueError> {
+        Ok(SerializeList {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ValueError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, ValueError> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap, ValueError> {
+        Ok(SerializeMap {
+            variant: None,
+            entries: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, ValueError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeMap, ValueError> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            entries: BTreeMap::new(),
+            key: None,
+        })
+    }
+}
+
+/// Collects a sequence, tuple or tuple variant into a list.
+struct SerializeList {
+    variant: Option<&'static str>,
+    items: Vec<Value>,
+}
+
+impl SerializeList {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.items.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, ValueError> {
+        let list = Value::List(self.items);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, list),
+            None => list,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+/// Collects a map, struct or struct variant into a map.
+struct SerializeMap {
+    variant: Option<&'static str>,
+    entries: BTreeMap<String, Value>,
+    /// The key of the entry whose value comes next
+    key: Option<String>,
+}
+
+impl SerializeMap {
+    /// Add an entry, leaving out a `None` value.
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), ValueError> {
+        match to_value(value) {
+            Ok(value) => {
+                self.entries.insert(key, value);
+                Ok(())
+            }
+            Err(_) if is_none(value) => Ok(()),
+            Err(e) => Err(ValueError(format!("{}: {}", key, e))),
+        }
+    }
+
+    fn finish(self) -> Result<Value, ValueError> {
+        let map = Value::Map(self.entries);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, map),
+            None => map,
+        })
+    }
+}
+
+/// Whether `value` serializes as `None`.
+fn is_none<T: Serialize + ?Sized>(value: &T) -> bool {
+    value.serialize(NoneProbe).is_ok()
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ValueError> {
+        self.key = Some(match to_value(key)? {
+            Value::String(s) => s,
+            Value::Integer(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            other => {
+                return Err(ValueError(format!(
+                    "map keys must be strings, numbers or booleans, got {}",
+                    other.type_name()
+                )));
+            }
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ValueError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ValueError("map value without a key".to_string()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = Value;
+    type Error = ValueError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ValueError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Value, ValueError> {
+        self.finish()
+    }
+}
+
+/// A serializer that only accepts `None`, to tell a left-out field from a
+/// failed one.
+struct NoneProbe;
+
+/// Every serializer method besides `serialize_none` fails.
+macro_rules! not_none {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret, ValueError> {
+                Err(ValueError("not None".to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for NoneProbe {
+    type Ok = ();
+    type Error = ValueError;
+    type SerializeSeq = ser::Impossible<(), ValueError>;
+    type SerializeTuple = ser::Impossible<(), ValueError>;
+    type SerializeTupleStruct = ser::Impossible<(), ValueError>;
+    type SerializeTupleVariant = ser::Impossible<(), ValueError>;
+    type SerializeMap = ser::Impossible<(), ValueError>;
+    type SerializeStruct = ser::Impossible<(), ValueError>;
+    type SerializeStructVariant = ser::Impossible<(), ValueError>;
+
+    fn serialize_none(self) -> Result<(), ValueError> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<(), ValueError> {
+        Err(ValueError("not None".to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), ValueError> {
+        Err(ValueError("not None".to_string()))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), ValueError> {
+        Err(ValueError("not None".to_string()))
+    }
+
+    not_none! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+// =============================================================================
+// Value -> Rust
+// =============================================================================
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self {
+            Value::Integer(n) => visitor.visit_i64(n),
+            Value::Float(x) => visitor.visit_f64(x),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::List(items) => visitor.visit_seq(ListAccess(items.into_iter())),
+            Value::Map(entries) => visitor.visit_map(EntryAccess {
+                entries: entries.into_iter(),
+                value: None,
+            }),
+            other @ (Value::Quotation(_) | Value::CompiledQuotation(_)) => Err(ValueError(
+                format!("a {} can't be read as data", other.type_name()),
+            )),
+        }
+    }
+
+    /// Values are never null, so an option that is present is `Some`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(Variant {
+                name: variant,
+                content: None,
+            }),
+            Value::Map(entries) if entries.len() == 1 => {
+                let (variant, content) = entries.into_iter().next().expect("one entry");
+                visitor.visit_enum(Variant {
+                    name: variant,
+                    content: Some(content),
+                })
+            }
+            other => Err(ValueError(format!(
+                "expected a {} variant name or a one-entry map, got {}",
+                name,
+                other.type_name()
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl IntoDeserializer<'_, ValueError> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
+
+struct ListAccess(std::vec::IntoIter<Value>);
+
+impl<'de> SeqAccess<'de> for ListAccess {
+    type Error = ValueError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ValueError> {
+        self.0.next().map(|item| seed.deserialize(item)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct EntryAccess {
+    entries: std::collections::btree_map::IntoIter<String, Value>,
+    /// The value of the entry whose key was just read
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for EntryAccess {
+    type Error = ValueError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ValueError> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(MapKey(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ValueError> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| ValueError("map value read before its key".to_string()))?;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// A map key, which can also be read back as the number or boolean it
+/// was written from.
+struct MapKey(String);
+
+impl<'de> de::Deserializer<'de> for MapKey {
+    type Error = ValueError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        visitor.visit_string(self.0)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0.parse() {
+            Ok(b) => visitor.visit_bool(b),
+            Err(_) => visitor.visit_string(self.0),
+        }
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        match self.0.parse() {
+            Ok(n) => visitor.visit_i64(n),
+            Err(_) => visitor.visit_string(self.0),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ValueError> {
+        self.deserialize_i64(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        Value::String(self.0).deserialize_enum(name, variants, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// An enum read from a variant name, or a one-entry map from the name to
+/// the variant's contents.
+struct Variant {
+    name: String,
+    content: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for Variant {
+    type Error = ValueError;
+    type Variant = VariantContent;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantContent), ValueError> {
+        let variant = seed.deserialize(Value::String(self.name))?;
+        Ok((variant, VariantContent(self.content)))
+    }
+}
+
+struct VariantContent(Option<Value>);
+
+impl VariantContent {
+    fn content(self, what: &str) -> Result<Value, ValueError> {
+        self.0
+            .ok_or_else(|| ValueError(format!("expected the {} of a variant", what)))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantContent {
+    type Error = ValueError;
+
+    fn unit_variant(self) -> Result<(), ValueError> {
+        match self.0 {
+            None => Ok(()),
+            Some(_) => Err(ValueError("a unit variant has no contents".to_string())),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ValueError> {
+        seed.deserialize(self.content("value")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        de::Deserializer::deserialize_seq(self.content("list")?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ValueError> {
+        de::Deserializer::deserialize_map(self.content("map")?, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shipping {
+        Pickup,
+        Courier(String),
+        Post { days: u8 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u32,
+        total: f64,
+        items: Vec<(String, i64)>,
+        coupon: Option<String>,
+        shipping: Shipping,
+        stock: HashMap<u16, bool>,
+    }
+
+    fn order() -> Order {
+        Order {
+            id: 7,
+            total: 12.5,
+            items: vec![("tea".into(), 2)],
+            coupon: None,
+            shipping: Shipping::Post { days: 3 },
+            stock: HashMap::from([(40, true)]),
+        }
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_struct_to_value() {
+        assert_eq!(
+            to_value(&order()).unwrap(),
+            map(&[
+                ("id", Value::Integer(7)),
+                (
+                    "items",
+                    Value::List(vec![Value::List(vec![
+                        Value::String("tea".into()),
+                        Value::Integer(2)
+                    ])])
+                ),
+                (
+                    "shipping",
+                    map(&[("Post", map(&[("days", Value::Integer(3))]))])
+                ),
+                ("stock", map(&[("40", Value::Bool(true))])),
+                ("total", Value::Float(12.5)),
+            ])
+        );
+        assert_eq!(
+            to_value(&Shipping::Pickup).unwrap(),
+            Value::String("Pickup".into())
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = to_value(&order()).unwrap();
+        assert_eq!(from_value::<Order>(value).unwrap(), order());
+
+        let courier = to_value(&Shipping::Courier("ups".into())).unwrap();
+        assert_eq!(
+            from_value::<Shipping>(courier).unwrap(),
+            Shipping::Courier("ups".into())
+        );
+        // Integers are accepted where floats are expected
+        assert_eq!(from_value::<f64>(Value::Integer(2)).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(to_value(&None::<i32>).is_err());
+        assert!(to_value(&vec![Some(1), None]).is_err());
+        assert!(to_value(&u64::MAX).is_err());
+        let err = from_value::<Order>(map(&[("id", Value::String("x".into()))])).unwrap_err();
+        assert!(err.to_string().contains("invalid type"), "{}", err);
+        assert!(from_value::<i64>(Value::CompiledQuotation(vec![])).is_err());
+    }
+}
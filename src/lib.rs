@@ -0,0 +1,29 @@
+//! EMBER, a small stack-based language, as a library.
+//!
+//! [`Engine`] is the way in for programs that embed it: it compiles and
+//! runs source, moves [`Value`]s between the host and the stack, and runs
+//! words the host registers. The modules below are the pieces it is built
+//! from, for hosts that need more control, and what the `ember` command
+//! line tool uses.
+//!
+//! ```
+//! use ember::{Engine, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.capture_output();
+//! engine.push(Value::Integer(4));
+//! engine.eval("def sq dup * end sq dup print").unwrap();
+//! assert_eq!(engine.take_output(), "16\n");
+//! assert_eq!(engine.pop(), Some(Value::Integer(16)));
+//! ```
+
+pub mod bytecode;
+mod engine;
+pub mod frontend;
+pub mod lang;
+pub mod runtime;
+
+pub use engine::Engine;
+pub use lang::value::Value;
+pub use runtime::runtime_error::{RuntimeError, RuntimeResult};
+pub use runtime::vm_bc::{CancelToken, Profile, VmBc, VmBcConfig};
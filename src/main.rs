@@ -1,16 +1,38 @@
-mod bytecode;
-mod frontend;
-mod lang;
-mod runtime;
+mod examples;
+mod repl;
+mod spec;
+mod tutorial;
 
-use std::{env, fs, path::Path};
+#[cfg(all(test, feature = "fuzz"))]
+mod fuzz;
+#[cfg(all(test, feature = "golden"))]
+mod golden;
 
-use crate::bytecode::ProgramBc;
-use crate::bytecode::compile::Compiler;
-use crate::bytecode::disasm::print_bc;
-use crate::frontend::lexer::Lexer;
-use crate::frontend::token_dumper::TokenDumper;
-use crate::runtime::vm_bc::VmBc;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+
+use ember::bytecode::compile::{CompileTimings, Compiler};
+use ember::bytecode::container;
+use ember::bytecode::disasm::print_bc;
+use ember::bytecode::inspect::Inspection;
+use ember::bytecode::link::link;
+use ember::bytecode::optimize::{optimize_program, remove_dead_words, strip_debug_info};
+use ember::bytecode::stack_check_error::{check_program, word_effect};
+use ember::bytecode::verify::verify_program;
+use ember::bytecode::{Op, ProgramBc, WordSite};
+use ember::frontend::lexer::Lexer;
+use ember::frontend::parser::Parser;
+use ember::frontend::source::decode_source;
+use ember::frontend::token::Token;
+use ember::frontend::token_dumper::TokenDumper;
+use ember::lang::builtins;
+use ember::lang::value::{FloatFormat, Value};
+use ember::runtime::explain::Explain;
+use ember::runtime::native;
+use ember::runtime::replay::InputLog;
+use ember::runtime::vm_bc::{Profile, VmBc, VmBcConfig, WordReload};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -21,8 +43,183 @@ fn main() {
     let ast = args.contains(&"--ast".to_string());
     let save_bc = args.contains(&"--save-bc".to_string());
     let disasm = args.contains(&"--disasm".to_string());
+    let timings = args.contains(&"--timings".to_string());
+    let lossy_utf8 = args.contains(&"--lossy-utf8".to_string());
+    let watch = args.contains(&"--watch".to_string());
+    let float_format = match args.iter().find_map(|a| a.strip_prefix("--floats=")) {
+        Some(policy) => policy.parse().unwrap_or_else(|e| {
+            eprintln!("Error: --floats: {}", e);
+            std::process::exit(1);
+        }),
+        None => FloatFormat::default(),
+    };
+    let profile: Profile = match args.iter().position(|a| a == "--profile") {
+        Some(pos) => match args.get(pos + 1).map(|name| name.parse()) {
+            Some(Ok(profile)) => profile,
+            Some(Err(e)) => {
+                eprintln!("Error: --profile: {}", e);
+                std::process::exit(1);
+            }
+            None => {
+                eprintln!("Error: --profile requires minimal, script, sandbox or pure");
+                std::process::exit(1);
+            }
+        },
+        None => Profile::default(),
+    };
+    let explain = args.iter().find_map(|a| match a.as_str() {
+        "--explain" => Some(Explain::default()),
+        _ => a.strip_prefix("--explain=").map(|spec| {
+            spec.parse().unwrap_or_else(|e| {
+                eprintln!("Error: --explain: {}", e);
+                std::process::exit(1);
+            })
+        }),
+    });
+    // The --no-* flags can only take permissions away from the profile
+    let base = profile.config();
+    let config = VmBcConfig {
+        float_format,
+        explain,
+        allow_fs: base.allow_fs && !args.contains(&"--no-fs".to_string()),
+        allow_process: base.allow_process && !args.contains(&"--no-process".to_string()),
+        allow_net: base.allow_net && !args.contains(&"--no-net".to_string()),
+        allow_compile: base.allow_compile && !args.contains(&"--no-compile".to_string()),
+        allow_spawn: base.allow_spawn && !args.contains(&"--no-spawn".to_string()),
+        color: !no_color && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+        progress: io::stderr().is_terminal(),
+        ..base
+    };
+    let report = ExitReport {
+        show_stack: args.contains(&"--stack".to_string()),
+        warn_dirty: !args.contains(&"--allow-dirty-stack".to_string()),
+    };
+    let entry = EntryPoint::from_args(&args);
+
+    if args.get(1).map(String::as_str) == Some("check") {
+        match args.get(2) {
+            Some(file) => std::process::exit(check_file(Path::new(file), lossy_utf8)),
+            None => {
+                eprintln!("Error: check requires a file");
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let filename = args.iter().skip(1).find(|a| !a.starts_with('-'));
+    if args.get(1).map(String::as_str) == Some("which") {
+        match (args.get(2), args.get(3)) {
+            (Some(word), Some(file)) => {
+                std::process::exit(which_word(word, Path::new(file), lossy_utf8))
+            }
+            _ => {
+                eprintln!("Error: which requires a word and a file");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.get(1).map(String::as_str) == Some("inspect") {
+        match args.get(2) {
+            Some(file) => std::process::exit(inspect_file(Path::new(file))),
+            None => {
+                eprintln!("Error: inspect requires a .ebc file");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.get(1).map(String::as_str) == Some("opt") {
+        let Some(file) = args.get(2) else {
+            eprintln!("Error: opt requires a .ebc file");
+            std::process::exit(1);
+        };
+        let input = Path::new(file);
+        let output = match args.iter().position(|a| a == "-o") {
+            Some(pos) => match args.get(pos + 1) {
+                Some(out) => Path::new(out).to_path_buf(),
+                None => {
+                    eprintln!("Error: -o requires a file");
+                    std::process::exit(1);
+                }
+            },
+            None => input.with_extension("opt.ebc"),
+        };
+        std::process::exit(optimize_file(
+            input,
+            &output,
+            args.contains(&"--strip".to_string()),
+            args.contains(&"--keep-words".to_string()),
+        ));
+    }
+
+    if args.get(1).map(String::as_str) == Some("bundle") {
+        let mut inputs = Vec::new();
+        let mut output = None;
+        let mut rest = args.iter().skip(2);
+        while let Some(arg) = rest.next() {
+            if arg == "-o" {
+                output = rest.next();
+            } else {
+                inputs.push(Path::new(arg));
+            }
+        }
+        let Some(output) = output.filter(|_| !inputs.is_empty()) else {
+            eprintln!("Error: bundle requires .ebc files and -o <output>");
+            std::process::exit(1);
+        };
+        std::process::exit(bundle_files(&inputs, Path::new(output)));
+    }
+
+    if args.get(1).map(String::as_str) == Some("spec") {
+        match args.get(2) {
+            Some(path) => std::process::exit(run_spec(Path::new(path))),
+            None => {
+                eprintln!("Error: spec requires a directory or .emt file");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if args.get(1).map(String::as_str) == Some("tutorial") {
+        let start = match args.get(2).map(|n| n.parse::<usize>()) {
+            None => 1,
+            Some(Ok(n)) if (1..=tutorial::LESSONS.len()).contains(&n) => n,
+            Some(_) => {
+                eprintln!(
+                    "Error: tutorial takes a lesson number from 1 to {}",
+                    tutorial::LESSONS.len()
+                );
+                std::process::exit(1);
+            }
+        };
+        tutorial::run(config, start);
+        return;
+    }
+
+    if args.get(1).map(String::as_str) == Some("examples") {
+        run_example(
+            args.get(2).map(String::as_str),
+            args.contains(&"--source".to_string()),
+            config,
+            report,
+            &entry,
+        );
+        return;
+    }
+
+    // `-e <source>` runs inline code, e.g. as a filter in a shell pipeline
+    if let Some(pos) = args.iter().position(|a| a == "-e") {
+        match args.get(pos + 1) {
+            Some(source) => run_inline(source, config, report, &entry),
+            None => {
+                eprintln!("Error: -e requires source code");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let filename = file_argument(&args);
 
     match filename {
         Some(filename) => {
@@ -31,17 +228,19 @@ fn main() {
             match path.extension().and_then(|e| e.to_str()) {
                 Some("em") => {
                     if tokens_only {
-                        let source = fs::read_to_string(filename).unwrap_or_else(|e| {
-                            eprintln!("Failed to read '{}': {}", filename, e);
-                            std::process::exit(1);
-                        });
+                        let source = read_source(path, lossy_utf8);
                         dump_tokens(&source, no_color, pretty);
                     } else {
-                        run_from_source(path, ast, save_bc, disasm);
+                        if watch {
+                            watch_for_reloads(path, lossy_utf8, config.reload.clone());
+                        }
+                        run_from_source(
+                            path, ast, save_bc, disasm, timings, lossy_utf8, config, report, &entry,
+                        );
                     }
                 }
                 Some("ebc") => {
-                    run_from_bytecode(path, disasm);
+                    run_from_bytecode(path, disasm, config, report, &entry);
                 }
                 _ => {
                     eprintln!("Error: expected a .em or .ebc file, got {}", filename);
@@ -51,8 +250,7 @@ fn main() {
         }
         None => {
             if args.len() == 1 {
-                println!("EMBER - Concatenative Functional Programming Language");
-                println!("Use --help for usage information");
+                repl::run(config);
             } else {
                 print_usage();
             }
@@ -60,6 +258,26 @@ fn main() {
     }
 }
 
+/// Read a source file for commands that don't compile it.
+fn read_source(path: &Path, lossy_utf8: bool) -> String {
+    let bytes = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("Failed to read '{}': {}", path.display(), e);
+        std::process::exit(1);
+    });
+    match decode_source(&bytes, lossy_utf8) {
+        Ok(decoded) => {
+            if let Some(warning) = decoded.warning {
+                eprintln!("Warning: in {}: {}", path.display(), warning);
+            }
+            decoded.text
+        }
+        Err(e) => {
+            eprintln!("Error: '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn dump_tokens(source: &str, no_color: bool, pretty: bool) {
     let mut lexer = Lexer::new(source);
 
@@ -87,34 +305,103 @@ fn print_usage() {
     println!("EMBER - Concatenative Functional Programming Language");
     println!();
     println!("Usage:");
+    println!("  ember                        Start the interactive REPL");
     println!("  ember <file.em>              Compile and run a program");
     println!("  ember <file.ebc>             Run pre-compiled bytecode");
+    println!("  ember run <file>             Same as `ember <file>`");
+    println!("  ember -e <source>            Run inline code");
+    println!("  ember check <file>           Compile and verify without running");
+    println!("  ember which <word> <file>    Show where a program defines a word");
+    println!("  ember inspect <file.ebc>     Summarize bytecode and the access it needs");
+    println!("  ember opt <file.ebc>         Optimize bytecode into <file>.opt.ebc");
+    println!("  ember bundle <a.ebc>... -o <out.ebc>  Link compiled files into one");
+    println!("  ember examples [name]        List the bundled examples, or run one");
+    println!("  ember tutorial [lesson]      Learn EMBER with guided exercises");
+    println!("  ember spec <dir|file.emt>    Run language spec tests");
     println!();
     println!("Options:");
     println!("  --save-bc                    Compile and save to .ebc file");
     println!("  --disasm                     Show bytecode disassembly");
     println!("  --ast                        Print AST and exit");
+    println!("  --timings                    Show time spent in each compile phase");
+    println!("  --stack                      Print the remaining stacks after running");
+    println!("  --allow-dirty-stack          Don't warn about values left on the stack");
+    println!("  --floats=<policy>            Float output: shortest (default), fixed:N or sig:N");
+    println!("  --explain[=N|words]          Describe each step as it runs (first N, or in words)");
     println!("  --tokens                     Show tokens only");
-    println!("  --no-color                   Disable colored output");
+    println!("  --watch                      Reload changed words while the program runs");
+    println!("  --entry <word>               Call <word> instead of the top-level code");
+    println!("  --arg <value>                Push <value> before running (repeatable)");
+    println!("  --args-list                  Push the --arg values as one list");
+    println!("  --record <log>               Save the input the program reads to <log>");
+    println!("  --replay <log>               Read input from a --record log instead of stdin");
+    println!("  --plugin <lib>               Load native words from a shared library (repeatable)");
+    println!(
+        "  --lossy-utf8                 Replace invalid UTF-8 in source files (with a warning)"
+    );
+    println!(
+        "  --profile <name>             Permissions and limits: minimal, script, sandbox or pure"
+    );
+    println!("  --no-fs                      Deny file system access (glob, canonicalize)");
+    println!("  --no-process                 Deny running other programs (run-process)");
+    println!("  --no-net                     Deny network access (socket words)");
+    println!("  --no-compile                 Deny building code at run time (eval, list->quot)");
+    println!("  --no-spawn                   Deny starting tasks (spawn)");
+    println!("  --no-color                   Disable colored output (also for colorize and style)");
     println!("  --pretty                     Pretty-print tokens");
+    println!("  --source                     With `examples <name>`, print the source instead");
+    println!("  -o <file>                    With `opt` or `bundle`, where to write the result");
+    println!("  --strip                      With `opt`, drop doc comments and definition sites");
+    println!("  --keep-words                 With `opt`, keep words main never calls");
     println!("  --help, -h                   Show this help");
 }
 
-fn run_from_source(path: &Path, ast: bool, save_bc: bool, disasm: bool) {
+#[allow(clippy::too_many_arguments)]
+fn run_from_source(
+    path: &Path,
+    ast: bool,
+    save_bc: bool,
+    disasm: bool,
+    timings: bool,
+    lossy_utf8: bool,
+    config: VmBcConfig,
+    report: ExitReport,
+    entry: &EntryPoint,
+) {
     println!("Compiling {}...", path.display());
 
     // Read source for error reporting
-    let source = match fs::read_to_string(path) {
-        Ok(s) => s,
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
         Err(e) => {
             eprintln!("Failed to read '{}': {}", path.display(), e);
             std::process::exit(1);
         }
     };
+    // Small bytecode files can be valid UTF-8, so ask the lexer too
+    if !looks_like_source(&bytes) && looks_like_bytecode(&bytes) {
+        eprintln!(
+            "Error: '{}' contains compiled bytecode, not source code",
+            path.display()
+        );
+        eprintln!(
+            "Hint: give it a .ebc extension and run `ember {}`",
+            path.with_extension("ebc").display()
+        );
+        std::process::exit(1);
+    }
+    // Any warning is reported by the compiler, which decodes the file again
+    let source = match decode_source(&bytes, lossy_utf8) {
+        Ok(decoded) => decoded.text,
+        Err(e) => {
+            eprintln!("Error: '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }
+    };
 
-    let compiler = Compiler::new();
-    let bytecode = match compiler.compile_from_file(path) {
-        Ok(bc) => bc,
+    let compiler = Compiler::new().with_lossy_utf8(lossy_utf8);
+    let (mut bytecode, phase_times) = match compiler.compile_from_file_with_timings(path) {
+        Ok(result) => result,
         Err(e) => {
             eprintln!("Compile error: {}", e);
             std::process::exit(1);
@@ -134,27 +421,383 @@ fn run_from_source(path: &Path, ast: bool, save_bc: bool, disasm: bool) {
         println!();
     }
 
+    let mut serializing = None;
     if save_bc {
         let output_path = path.with_extension("ebc");
+        let started = Instant::now();
         match save_bytecode(&bytecode, &output_path) {
             Ok(_) => println!("✓ Saved to {}", output_path.display()),
             Err(e) => {
                 eprintln!("Warning: failed to save bytecode: {}", e);
             }
         }
+        serializing = Some(started.elapsed());
+    }
+
+    if timings {
+        print_timings(&phase_times, serializing);
     }
 
+    entry.apply(&mut bytecode).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
     println!("Executing...");
-    execute_bytecode_with_source(&bytecode, source, path);
+    execute_bytecode_with_source(&bytecode, source, path, config, report, entry);
 }
 
-fn run_from_bytecode(path: &Path, disasm: bool) {
-    println!("Loading {}...", path.display());
+fn print_timings(times: &CompileTimings, serializing: Option<Duration>) {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    println!("Timings:");
+    println!("  lexing           {:>9.3} ms", ms(times.lexing));
+    println!("  parsing          {:>9.3} ms", ms(times.parsing));
+    println!("  import loading   {:>9.3} ms", ms(times.loading));
+    println!("  compiling words  {:>9.3} ms", ms(times.compiling));
+    println!("  optimizing       {:>9.3} ms", ms(times.optimizing));
+    let mut total = times.total();
+    if let Some(serializing) = serializing {
+        println!("  serializing      {:>9.3} ms", ms(serializing));
+        total += serializing;
+    }
+    println!("  total            {:>9.3} ms", ms(total));
+}
 
-    let bytecode = match load_bytecode(path) {
+/// Run every static check on a file without executing it. Returns the
+/// process exit code: 0 if clean, 1 on any diagnostic, warnings included.
+fn check_file(path: &Path, lossy_utf8: bool) -> i32 {
+    let bytecode = match compile_or_load(path, lossy_utf8) {
+        Ok(bc) => bc,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    if let Err(e) = verify_program(&bytecode) {
+        eprintln!("{}: {}", path.display(), e);
+        return 1;
+    }
+    // Only main starts from a known (empty) stack; word bodies take their
+    // inputs from the caller and are checked through the calls to them
+    match check_program(&bytecode) {
+        Ok(warnings) if warnings.is_empty() => {}
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("warning: {}: {}", path.display(), warning);
+            }
+            return 1;
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return 1;
+        }
+    }
+
+    println!("✓ {}: ok", path.display());
+    0
+}
+
+/// Compile a `.em` file or load a `.ebc` one, without running it. The
+/// error names the file.
+fn compile_or_load(path: &Path, lossy_utf8: bool) -> Result<ProgramBc, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("em") => Compiler::new()
+            .with_lossy_utf8(lossy_utf8)
+            .compile_from_file(path)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        Some("ebc") => load_bytecode(path)
+            .map_err(|e| format!("{}: failed to load bytecode: {}", path.display(), e)),
+        _ => Err(format!(
+            "Error: expected a .em or .ebc file, got {}",
+            path.display()
+        )),
+    }
+}
+
+/// `ember which <word> <file>`: print where the program defines a word.
+/// Returns the process exit code: 1 if the word is unknown.
+fn which_word(name: &str, path: &Path, lossy_utf8: bool) -> i32 {
+    let bytecode = match compile_or_load(path, lossy_utf8) {
+        Ok(bc) => bc,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let cwd = env::current_dir().unwrap_or_default();
+    match describe_definition(name, &bytecode, &cwd) {
+        Some(text) => {
+            print!("{}", text);
+            0
+        }
+        None => {
+            eprintln!("{}: no word named '{}'", path.display(), name);
+            1
+        }
+    }
+}
+
+/// Where `name` is defined and what that definition shadows, with file
+/// paths relative to `cwd` where possible. None if the word is unknown.
+fn describe_definition(name: &str, bytecode: &ProgramBc, cwd: &Path) -> Option<String> {
+    let Some(site) = bytecode.sites.get(name) else {
+        return builtins::lookup(name)
+            .map(|_| format!("{} is a builtin word, not defined in the program\n", name));
+    };
+    let location = |site: &WordSite| {
+        let relative = site.file.as_deref().and_then(|file| {
+            let short = Path::new(file).strip_prefix(cwd).ok()?;
+            Some(format!("{}:{}", short.display(), site.line))
+        });
+        relative.unwrap_or_else(|| site.location())
+    };
+
+    let mut text = format!("{} is defined at {}\n", name, location(site));
+    for earlier in site.replaces.iter().rev() {
+        text.push_str(&format!(
+            "  replacing the definition at {}\n",
+            location(earlier)
+        ));
+    }
+    if native::lookup(name).is_some() {
+        text.push_str(&format!("  shadowing the builtin '{}'\n", name));
+    }
+    Some(text)
+}
+
+/// Run source given on the command line. Unlike `run_from_source` this prints
+/// nothing but the program's own output, so it composes in pipelines.
+fn run_inline(source: &str, config: VmBcConfig, report: ExitReport, entry: &EntryPoint) {
+    let tokens = Lexer::new(source).tokenize().unwrap_or_else(|e| {
+        eprintln!("Lexer error: {}", e);
+        std::process::exit(1);
+    });
+    let program = Parser::new(tokens).parse().unwrap_or_else(|e| {
+        eprintln!("Parse error: {}", e);
+        std::process::exit(1);
+    });
+    let mut bytecode = Compiler::new()
+        .compile_program(&program)
+        .unwrap_or_else(|e| {
+            eprintln!("Compile error: {}", e);
+            std::process::exit(1);
+        });
+    entry.apply(&mut bytecode).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    let report = report.for_source(source);
+    let mut vm = VmBc::with_config(config);
+    entry.prepare(&mut vm);
+    vm.set_source(source.to_string());
+    if let Err(e) = vm.run_compiled(&bytecode) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    report.print(&vm);
+}
+
+/// `ember spec`: run the cases in `.emt` files, reporting each failure.
+/// Returns the process exit code.
+fn run_spec(path: &Path) -> i32 {
+    let summary = spec::run_path(path);
+    for failure in &summary.failures {
+        println!("FAIL {}", failure);
+        println!();
+    }
+    println!(
+        "{} passed, {} failed",
+        summary.passed,
+        summary.failures.len()
+    );
+    if summary.failures.is_empty() { 0 } else { 1 }
+}
+
+/// `ember examples`: list the bundled programs, or run (or show) one by name.
+fn run_example(
+    name: Option<&str>,
+    show_source: bool,
+    config: VmBcConfig,
+    report: ExitReport,
+    entry: &EntryPoint,
+) {
+    let Some(name) = name.filter(|n| !n.starts_with('-')) else {
+        println!("Bundled examples:");
+        for example in examples::EXAMPLES {
+            println!("  {:<14} {}", example.name, example.description);
+        }
+        println!();
+        println!("Run one with `ember examples <name>`, or see its code with --source");
+        return;
+    };
+
+    match examples::find(name) {
+        Some(example) if show_source => print!("{}", example.source),
+        Some(example) => run_inline(example.source, config, report, entry),
+        None => {
+            let names: Vec<&str> = examples::EXAMPLES.iter().map(|e| e.name).collect();
+            eprintln!("Error: no example named '{}'", name);
+            eprintln!("Available: {}", names.join(", "));
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `ember inspect`: report what a bytecode file contains without running it.
+/// Returns the process exit code.
+fn inspect_file(path: &Path) -> i32 {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Failed to read '{}': {}", path.display(), e);
+            return 1;
+        }
+    };
+    let decoded = container::decode(&bytes).map_err(|e| e.to_string());
+    match decoded.and_then(|(header, bytecode)| {
+        verify_program(&bytecode).map_err(|e| e.to_string())?;
+        Ok((header, bytecode))
+    }) {
+        Ok((header, bytecode)) => {
+            println!("{}", path.display());
+            let inspection = Inspection::of(&bytecode, bytes.len()).with_header(header);
+            print!("{}", inspection.render());
+            0
+        }
+        Err(e) => {
+            eprintln!("Error: '{}' is not valid bytecode: {}", path.display(), e);
+            1
+        }
+    }
+}
+
+/// `ember opt`: optimize a bytecode file and write the result to `output`.
+/// Returns the process exit code.
+fn optimize_file(input: &Path, output: &Path, strip: bool, keep_words: bool) -> i32 {
+    let before = match fs::read(input) {
+        Ok(bytes) => bytes.len(),
+        Err(e) => {
+            eprintln!("Failed to read '{}': {}", input.display(), e);
+            return 1;
+        }
+    };
+    let mut bytecode = match load_bytecode(input) {
         Ok(bc) => bc,
         Err(e) => {
             eprintln!("Failed to load bytecode: {}", e);
+            return 1;
+        }
+    };
+
+    optimize_program(&mut bytecode);
+    if !keep_words {
+        let removed = remove_dead_words(&mut bytecode);
+        if !removed.is_empty() {
+            println!(
+                "removed {} unused words: {}",
+                removed.len(),
+                removed.join(", ")
+            );
+        }
+    }
+    if strip {
+        strip_debug_info(&mut bytecode);
+    }
+    // The passes should preserve both; catch it here if one doesn't
+    if let Err(e) = verify_program(&bytecode) {
+        eprintln!("Error: optimized program is invalid: {}", e);
+        return 1;
+    }
+    if let Err(e) = check_program(&bytecode) {
+        eprintln!("Error: optimized program is invalid: {}", e.message);
+        return 1;
+    }
+
+    if let Err(e) = save_bytecode(&bytecode, output) {
+        eprintln!("Failed to write '{}': {}", output.display(), e);
+        return 1;
+    }
+    let after = fs::metadata(output).map_or(0, |m| m.len() as usize);
+    println!(
+        "{}: {} bytes -> {}: {} bytes ({:+.1}%)",
+        input.display(),
+        before,
+        output.display(),
+        after,
+        (after as f64 - before as f64) / before.max(1) as f64 * 100.0
+    );
+    0
+}
+
+/// `ember bundle`: link bytecode files into one, main code in the order
+/// given. Returns the process exit code.
+fn bundle_files(inputs: &[&Path], output: &Path) -> i32 {
+    let mut programs = Vec::new();
+    for input in inputs {
+        match load_bytecode(input) {
+            Ok(bc) => programs.push((input.display().to_string(), bc)),
+            Err(e) => {
+                eprintln!("Failed to load '{}': {}", input.display(), e);
+                return 1;
+            }
+        }
+    }
+
+    let bundle = match link(programs) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = verify_program(&bundle) {
+        eprintln!("Error: bundle is invalid: {}", e);
+        return 1;
+    }
+    if let Err(e) = check_program(&bundle) {
+        eprintln!("Error: bundle is invalid: {}", e.message);
+        return 1;
+    }
+
+    if let Err(e) = save_bytecode(&bundle, output) {
+        eprintln!("Failed to write '{}': {}", output.display(), e);
+        return 1;
+    }
+    println!(
+        "✓ Bundled {} files ({} words) into {}",
+        inputs.len(),
+        bundle.words.len(),
+        output.display()
+    );
+    0
+}
+
+fn run_from_bytecode(
+    path: &Path,
+    disasm: bool,
+    config: VmBcConfig,
+    report: ExitReport,
+    entry: &EntryPoint,
+) {
+    println!("Loading {}...", path.display());
+
+    let mut bytecode = match load_bytecode(path) {
+        Ok(bc) => bc,
+        Err(e) => {
+            if fs::read(path).is_ok_and(|bytes| looks_like_source(&bytes)) {
+                eprintln!(
+                    "Error: '{}' contains Ember source code, not compiled bytecode",
+                    path.display()
+                );
+                eprintln!(
+                    "Hint: give it a .em extension and run `ember {}`, or compile it with --save-bc",
+                    path.with_extension("em").display()
+                );
+            } else {
+                eprintln!("Failed to load bytecode: {}", e);
+            }
             std::process::exit(1);
         }
     };
@@ -167,21 +810,42 @@ fn run_from_bytecode(path: &Path, disasm: bool) {
         println!();
     }
 
+    entry.apply(&mut bytecode).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
     println!("\nExecuting...\n");
-    execute_bytecode(&bytecode);
+    execute_bytecode(&bytecode, config, report, entry);
 }
 
-fn execute_bytecode(bytecode: &ProgramBc) {
-    let mut vm = VmBc::new();
+fn execute_bytecode(
+    bytecode: &ProgramBc,
+    config: VmBcConfig,
+    report: ExitReport,
+    entry: &EntryPoint,
+) {
+    let mut vm = VmBc::with_config(config);
+    entry.prepare(&mut vm);
 
     if let Err(e) = vm.run_compiled(bytecode) {
         eprintln!("\nRuntime error: {}", e);
         std::process::exit(1);
     }
+    report.print(&vm);
 }
 
-fn execute_bytecode_with_source(bytecode: &ProgramBc, source: String, path: &Path) {
-    let mut vm = VmBc::new();
+fn execute_bytecode_with_source(
+    bytecode: &ProgramBc,
+    source: String,
+    path: &Path,
+    config: VmBcConfig,
+    report: ExitReport,
+    entry: &EntryPoint,
+) {
+    let report = report.for_source(&source);
+    let mut vm = VmBc::with_config(config);
+    entry.prepare(&mut vm);
 
     // Set source and file for better error messages
     vm.set_source(source);
@@ -192,16 +856,271 @@ fn execute_bytecode_with_source(bytecode: &ProgramBc, source: String, path: &Pat
         eprintln!("{}", e);
         std::process::exit(1);
     }
+    report.print(&vm);
+}
+
+/// How often `--watch` looks at the file's modification time.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `--watch`: on a background thread, recompile the program whenever the
+/// file changes and hand its words to the running VM. Main code keeps
+/// running as it was; programs that loop by calling their words pick up
+/// the new definitions. A version that fails to compile or check is
+/// reported and skipped.
+fn watch_for_reloads(path: &Path, lossy_utf8: bool, reload: WordReload) {
+    let path = path.to_path_buf();
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last = modified(&path);
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let now = modified(&path);
+            if now == last {
+                continue;
+            }
+            last = now;
+
+            let compiled = Compiler::new()
+                .with_lossy_utf8(lossy_utf8)
+                .compile_from_file(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bc| {
+                    verify_program(&bc).map_err(|e| e.to_string())?;
+                    check_program(&bc).map_err(|e| e.to_string())?;
+                    Ok(bc)
+                });
+            match compiled {
+                Ok(bytecode) => reload.offer(bytecode),
+                Err(e) => eprintln!("reload of {} failed: {}", path.display(), e),
+            }
+        }
+    });
+}
+
+/// The program's file: the first argument that isn't a flag, a flag's value
+/// or the optional `run` subcommand.
+fn file_argument(args: &[String]) -> Option<&String> {
+    let mut rest = args.iter().skip(1).peekable();
+    if rest.peek().is_some_and(|a| *a == "run") {
+        rest.next();
+    }
+    while let Some(arg) = rest.next() {
+        if [
+            "--entry",
+            "--arg",
+            "--profile",
+            "--record",
+            "--replay",
+            "--plugin",
+        ]
+        .contains(&arg.as_str())
+        {
+            rest.next();
+        } else if !arg.starts_with('-') {
+            return Some(arg);
+        }
+    }
+    None
+}
+
+/// `--entry <word>` and `--arg <value>`: what to run instead of the main
+/// code, and the values to push first. Args are pushed in the order given,
+/// so the last `--arg` ends up on top; with `--args-list` they are pushed as
+/// one list instead, in the same order. Also where console input comes
+/// from, with `--record` or `--replay`, and the `--plugin` libraries to load.
+#[derive(Debug, Default)]
+struct EntryPoint {
+    word: Option<String>,
+    args: Vec<Value>,
+    input_log: Option<InputLog>,
+    plugins: Vec<PathBuf>,
+}
+
+/// An `--arg` value, read with the lexer's literal rules: `5`, `-2.5`,
+/// `true` and `"quoted text"` become an integer, float, bool and string.
+/// Anything else is taken as a string as written.
+fn parse_arg(text: &str) -> Value {
+    let tokens = Lexer::new(text).tokenize_clean().unwrap_or_default();
+    match tokens.as_slice() {
+        [literal, eof] if eof.token == Token::Eof => match &literal.token {
+            Token::Integer(n) => Value::Integer(*n),
+            Token::Float(x) => Value::Float(*x),
+            Token::Bool(b) => Value::Bool(*b),
+            Token::String(s) => Value::String(s.clone()),
+            _ => Value::String(text.to_string()),
+        },
+        _ => Value::String(text.to_string()),
+    }
+}
+
+impl EntryPoint {
+    fn from_args(args: &[String]) -> Self {
+        let mut entry = EntryPoint::default();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            if !["--entry", "--arg", "--record", "--replay", "--plugin"].contains(&arg.as_str()) {
+                continue;
+            }
+            let Some(value) = rest.next() else {
+                eprintln!("Error: {} requires a value", arg);
+                std::process::exit(1);
+            };
+            match arg.as_str() {
+                "--entry" => entry.word = Some(value.clone()),
+                "--record" => entry.input_log = Some(InputLog::Record(value.into())),
+                "--replay" => entry.input_log = Some(InputLog::Replay(value.into())),
+                "--plugin" => entry.plugins.push(value.into()),
+                _ => entry.args.push(parse_arg(value)),
+            }
+        }
+        if args.iter().any(|a| a == "--args-list") {
+            entry.args = vec![Value::List(std::mem::take(&mut entry.args))];
+        }
+        entry
+    }
+
+    /// Load the `--plugin` libraries, push the `--arg` values and set up
+    /// `--record` or `--replay`.
+    fn prepare(&self, vm: &mut VmBc) {
+        for path in &self.plugins {
+            if let Err(e) = load_plugin(vm, path) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        for value in &self.args {
+            vm.push(value.clone());
+        }
+        if let Some(log) = &self.input_log
+            && let Err(e) = log.attach(vm)
+        {
+            eprintln!("Error: input log: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    /// Point `bytecode`'s main code at the entry word, if there is one.
+    /// Fails if the word doesn't exist or needs more inputs than were given.
+    fn apply(&self, bytecode: &mut ProgramBc) -> Result<(), String> {
+        let Some(word) = &self.word else {
+            return Ok(());
+        };
+        if !bytecode.words.contains_key(word) {
+            return Err(format!("no word named '{}' to use as --entry", word));
+        }
+        if let Some((inputs, _)) = word_effect(&bytecode.words, word)
+            && inputs > self.args.len()
+        {
+            return Err(format!(
+                "'{}' takes {} value{} from the stack but {} {} given; supply them with --arg",
+                word,
+                inputs,
+                if inputs == 1 { "" } else { "s" },
+                self.args.len(),
+                if self.args.len() == 1 { "was" } else { "were" },
+            ));
+        }
+        bytecode.code[0].ops = vec![Op::CallWord(word.clone()), Op::Return];
+        Ok(())
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn load_plugin(vm: &mut VmBc, path: &Path) -> Result<(), String> {
+    vm.load_plugin(path).map(drop)
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugin(_vm: &mut VmBc, path: &Path) -> Result<(), String> {
+    Err(format!(
+        "cannot load plugin '{}': this build has no plugin support (feature `plugins`)",
+        path.display()
+    ))
+}
+
+/// What to show about the stacks after a program finishes successfully.
+#[derive(Clone, Copy, Default)]
+struct ExitReport {
+    /// `--stack`: print the data and aux stacks with value types
+    show_stack: bool,
+    /// Warn about leftover values; off with `--allow-dirty-stack` or the
+    /// `; pragma: allow-dirty-stack` comment
+    warn_dirty: bool,
+}
+
+/// Comment line that turns off the leftover-values warning for a file.
+const ALLOW_DIRTY_STACK_PRAGMA: &str = "; pragma: allow-dirty-stack";
+
+impl ExitReport {
+    /// Apply any pragmas in the program's source.
+    fn for_source(self, source: &str) -> Self {
+        let allow_dirty = source
+            .lines()
+            .any(|line| line.trim() == ALLOW_DIRTY_STACK_PRAGMA);
+        Self {
+            warn_dirty: self.warn_dirty && !allow_dirty,
+            ..self
+        }
+    }
+
+    fn print(&self, vm: &VmBc) {
+        let floats = vm.config().float_format;
+        if self.show_stack {
+            print!("{}", format_stack("Stack", vm.stack(), floats));
+            print!("{}", format_stack("Aux stack", &vm.aux_stack, floats));
+        }
+
+        let left = vm.stack().len() + vm.aux_stack.len();
+        if self.warn_dirty && left > 0 {
+            eprintln!(
+                "warning: program ended with {} value{} left on the stacks",
+                left,
+                if left == 1 { "" } else { "s" }
+            );
+            // --stack has already listed them
+            if !self.show_stack {
+                for (label, values) in [("Stack", vm.stack()), ("Aux stack", &vm.aux_stack)] {
+                    if !values.is_empty() {
+                        eprint!("{}", format_stack(label, values, floats));
+                    }
+                }
+            }
+            eprintln!(
+                "  (consume them with drop, or silence this with --allow-dirty-stack or `{}`)",
+                ALLOW_DIRTY_STACK_PRAGMA
+            );
+        }
+    }
+}
+
+/// One line per value, bottom of the stack first.
+fn format_stack(label: &str, values: &[Value], floats: FloatFormat) -> String {
+    if values.is_empty() {
+        return format!("{}: empty\n", label);
+    }
+    let mut text = format!("{} ({} items, top last):\n", label, values.len());
+    for (i, value) in values.iter().enumerate() {
+        // Quote strings so "42" and 42 are distinguishable
+        let shown = match value {
+            Value::String(s) => format!("{:?}", s),
+            other => other.display(floats).to_string(),
+        };
+        text.push_str(&format!(
+            "  {:>3}  {:<10} {}\n",
+            i,
+            value.type_name(),
+            shown
+        ));
+    }
+    text
 }
 
 // ============================================================================
-// Bytecode serialization with postcard
+// Bytecode serialization (see bytecode/container.rs for the file layout)
 // ============================================================================
 
 fn save_bytecode(program: &ProgramBc, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-    // Serialize with postcard
-    let bytes =
-        postcard::to_allocvec(program).map_err(|e| format!("Serialization failed: {}", e))?;
+    let bytes = container::encode(program)?;
 
     // Write to file
     fs::write(path, &bytes)?;
@@ -209,13 +1128,189 @@ fn save_bytecode(program: &ProgramBc, path: &Path) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
+/// True if `bytes` decode as a bytecode program.
+fn looks_like_bytecode(bytes: &[u8]) -> bool {
+    container::decode(bytes).is_ok()
+}
+
+/// True if `bytes` are UTF-8 text that the lexer accepts.
+fn looks_like_source(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|text| Lexer::new(text).tokenize().is_ok())
+}
+
 fn load_bytecode(path: &Path) -> Result<ProgramBc, Box<dyn std::error::Error>> {
     // Read file
     let bytes = fs::read(path)?;
 
-    // Deserialize with postcard
-    let program: ProgramBc =
-        postcard::from_bytes(&bytes).map_err(|e| format!("Deserialization failed: {}", e))?;
+    decode_bytecode(&bytes)
+}
+
+/// Deserialize a program and verify it. A `.ebc` file may be stale, corrupt
+/// or hostile, so nothing decoded here reaches the VM unverified.
+fn decode_bytecode(bytes: &[u8]) -> Result<ProgramBc, Box<dyn std::error::Error>> {
+    let (_, program) = container::decode(bytes)?;
+
+    verify_program(&program)?;
 
     Ok(program)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compiled(source: &str) -> Vec<u8> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let bytecode = Compiler::new().compile_program(&program).unwrap();
+        container::encode(&bytecode).unwrap()
+    }
+
+    #[test]
+    fn test_detects_bytecode() {
+        let bytes = compiled("def sq dup * end 5 sq print");
+        assert!(looks_like_bytecode(&bytes));
+        assert!(!looks_like_source(&bytes));
+    }
+
+    #[test]
+    fn test_detects_source() {
+        let source = b"def sq dup * end\n5 sq print\n";
+        assert!(looks_like_source(source));
+        assert!(!looks_like_bytecode(source));
+    }
+
+    #[test]
+    fn test_format_stack() {
+        let values = [Value::Integer(42), Value::String("42".to_string())];
+        assert_eq!(
+            format_stack("Stack", &values, FloatFormat::Shortest),
+            "Stack (2 items, top last):\n    0  integer    42\n    1  string     \"42\"\n"
+        );
+        assert_eq!(
+            format_stack("Aux stack", &[], FloatFormat::Shortest),
+            "Aux stack: empty\n"
+        );
+        assert_eq!(
+            format_stack("Stack", &[Value::Float(0.5)], FloatFormat::Fixed(2)),
+            "Stack (1 items, top last):\n    0  float      0.50\n"
+        );
+    }
+
+    #[test]
+    fn test_allow_dirty_stack_pragma() {
+        let report = ExitReport {
+            show_stack: false,
+            warn_dirty: true,
+        };
+        assert!(report.for_source("1 2 +\n").warn_dirty);
+        assert!(
+            !report
+                .for_source("; pragma: allow-dirty-stack\n1 2 +\n")
+                .warn_dirty
+        );
+    }
+
+    #[test]
+    fn test_decode_bytecode_verifies() {
+        let mut program = ProgramBc::new();
+        program.code[0].ops = vec![ember::bytecode::Op::Jump(99)];
+        let bytes = ember::bytecode::container::encode(&program).unwrap();
+
+        let err = decode_bytecode(&bytes).unwrap_err();
+        assert!(err.to_string().contains("verify error"), "{}", err);
+        assert!(decode_bytecode(&compiled("1 2 +")).is_ok());
+    }
+
+    #[test]
+    fn test_bytecode_is_reproducible() {
+        let source = "def c 3 end def a 1 end def b 2 end ; Doc\ndef d a end a b c d";
+        assert_eq!(compiled(source), compiled(source));
+    }
+
+    #[test]
+    fn test_describe_definition() {
+        let tokens = Lexer::new("def sq dup * end\ndef clamp 1 end\ndef sq dup dup * * end")
+            .tokenize()
+            .unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let bytecode = Compiler::new().compile_program(&program).unwrap();
+        let cwd = Path::new("/");
+
+        assert_eq!(
+            describe_definition("sq", &bytecode, cwd).unwrap(),
+            "sq is defined at line 3\n  replacing the definition at line 1\n"
+        );
+        assert_eq!(
+            describe_definition("clamp", &bytecode, cwd).unwrap(),
+            "clamp is defined at line 2\n  shadowing the builtin 'clamp'\n"
+        );
+        assert_eq!(
+            describe_definition("dup", &bytecode, cwd).unwrap(),
+            "dup is a builtin word, not defined in the program\n"
+        );
+        assert!(describe_definition("nope", &bytecode, cwd).is_none());
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let args: Vec<String> = ["ember", "run", "--entry", "add", "--arg", "5", "tools.em"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        assert_eq!(file_argument(&args).unwrap(), "tools.em");
+
+        let tokens = Lexer::new("def add + end def hello \"hi\" print end 1 2 add")
+            .tokenize()
+            .unwrap();
+        let program = Parser::new(tokens).parse().unwrap();
+        let bytecode = Compiler::new().compile_program(&program).unwrap();
+
+        let mut entry = EntryPoint::from_args(&args);
+        let err = entry.apply(&mut bytecode.clone()).unwrap_err();
+        assert!(err.contains("'add' takes 2 values from the stack but 1 was given"));
+
+        entry.args.push(Value::String("x".into()));
+        let mut with_args = bytecode.clone();
+        entry.apply(&mut with_args).unwrap();
+        assert_eq!(
+            with_args.code[0].ops,
+            vec![Op::CallWord("add".into()), Op::Return]
+        );
+
+        let missing = EntryPoint {
+            word: Some("nope".into()),
+            ..EntryPoint::default()
+        };
+        assert!(missing.apply(&mut bytecode.clone()).is_err());
+    }
+
+    #[test]
+    fn test_parse_arg() {
+        assert_eq!(parse_arg("5"), Value::Integer(5));
+        assert_eq!(parse_arg("-2.5"), Value::Float(-2.5));
+        assert_eq!(parse_arg("true"), Value::Bool(true));
+        assert_eq!(parse_arg("\"42\""), Value::String("42".into()));
+        assert_eq!(parse_arg("Ada"), Value::String("Ada".into()));
+        assert_eq!(parse_arg("5 6"), Value::String("5 6".into()));
+        assert_eq!(parse_arg("[1"), Value::String("[1".into()));
+    }
+
+    #[test]
+    fn test_neither() {
+        let garbage = [0xff, 0xfe, 0x00, 0x13];
+        assert!(!looks_like_source(&garbage));
+        assert!(!looks_like_bytecode(&garbage));
+    }
+
+    #[test]
+    fn test_check_fails_on_warnings() {
+        let path = std::env::temp_dir().join(format!("ember-check-{}.em", std::process::id()));
+        fs::write(&path, "1 print").unwrap();
+        assert_eq!(check_file(&path, false), 0);
+        // The `when` body leaves a value on one path only
+        fs::write(&path, "true [ 1 ] when").unwrap();
+        assert_eq!(check_file(&path, false), 1);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,369 @@
+//! Interactive read-eval-print loop, started by running `ember` with no
+//! arguments.
+//!
+//! Each line runs against the stacks and words left by the previous ones.
+//! A line that fails, or is interrupted with Ctrl-C, is rolled back: the
+//! stacks and word definitions return to what they were before it ran.
+//!
+//! `:watch <code>` adds an expression that runs after every line against a
+//! copy of the stacks; whenever the value it leaves on top changes, the
+//! new value is shown. It should only inspect the stack - anything it
+//! prints is printed each time.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use ember::bytecode::ProgramBc;
+use ember::bytecode::compile::Compiler;
+use ember::frontend::lexer::Lexer;
+use ember::frontend::parser::Parser;
+use ember::lang::node::Node;
+use ember::lang::value::Value;
+use ember::runtime::help_words::describe_word;
+use ember::runtime::vm_bc::{VmBc, VmBcConfig};
+
+pub struct Repl {
+    vm: VmBc,
+    watches: Vec<Watch>,
+    /// Variables declared with `var` on earlier lines
+    variables: BTreeSet<String>,
+}
+
+/// An expression added with `:watch`.
+struct Watch {
+    source: String,
+    bytecode: ProgramBc,
+    /// What it showed after the last line, to report only changes.
+    last: Option<String>,
+}
+
+impl Repl {
+    pub fn new(config: VmBcConfig) -> Self {
+        Self {
+            vm: VmBc::with_config(config),
+            watches: Vec::new(),
+            variables: BTreeSet::new(),
+        }
+    }
+
+    /// Evaluate one line. On any error, including an interrupt, the stacks
+    /// and words are restored to their state before the line.
+    pub fn eval(&mut self, line: &str) -> Result<(), String> {
+        let (bytecode, declared) = compile(line, &self.variables)?;
+
+        let snapshot = self.vm.snapshot();
+        self.vm.config().cancel.reset();
+        self.vm.set_source(line.to_string());
+        self.vm.run_more(&bytecode).map_err(|e| {
+            self.vm.restore(snapshot);
+            e.to_string()
+        })?;
+        self.variables.extend(declared);
+        Ok(())
+    }
+
+    pub fn stack(&self) -> &[Value] {
+        self.vm.stack()
+    }
+
+    pub fn vm(&self) -> &VmBc {
+        &self.vm
+    }
+
+    /// Run `source` against the current stacks and words and return the
+    /// stack it leaves, then put everything back as it was.
+    pub fn probe(&mut self, source: &str) -> Result<Vec<Value>, String> {
+        let (bytecode, _) = compile(source, &self.variables)?;
+        let snapshot = self.vm.snapshot();
+        let result = self.vm.run_more(&bytecode);
+        let stack = self.vm.stack().to_vec();
+        self.vm.restore(snapshot);
+        result.map_err(|e| e.message.clone())?;
+        Ok(stack)
+    }
+
+    /// Read a line from the REPL's input; `None` at end of input.
+    pub fn read_line(&mut self) -> Result<Option<String>, String> {
+        self.vm.read_input_line().map_err(|e| e.to_string())
+    }
+
+    /// Add a watch expression and return its current value.
+    pub fn add_watch(&mut self, source: &str) -> Result<String, String> {
+        let (bytecode, _) = compile(source, &self.variables)?;
+        let value = watch_value(&mut self.vm, &bytecode);
+        self.watches.push(Watch {
+            source: source.to_string(),
+            bytecode,
+            last: Some(value.clone()),
+        });
+        Ok(value)
+    }
+
+    /// Remove the watch numbered `number` (from 1), returning its source.
+    pub fn remove_watch(&mut self, number: usize) -> Option<String> {
+        let index = number.checked_sub(1).filter(|&i| i < self.watches.len())?;
+        Some(self.watches.remove(index).source)
+    }
+
+    /// Re-run every watch, returning `(number, source, value)` for those
+    /// whose value changed since the last time.
+    pub fn changed_watches(&mut self) -> Vec<(usize, String, String)> {
+        let mut changed = Vec::new();
+        for (i, watch) in self.watches.iter_mut().enumerate() {
+            let value = watch_value(&mut self.vm, &watch.bytecode);
+            if watch.last.as_ref() != Some(&value) {
+                changed.push((i + 1, watch.source.clone(), value.clone()));
+                watch.last = Some(value);
+            }
+        }
+        changed
+    }
+}
+
+/// Run a watch against the current stacks, then put them back. Its value
+/// is the top of the stack it leaves, or the error it hit.
+fn watch_value(vm: &mut VmBc, bytecode: &ProgramBc) -> String {
+    let snapshot = vm.snapshot();
+    let result = vm.run_more(bytecode);
+    let floats = vm.config().float_format;
+    let value = match result {
+        Ok(()) => match vm.stack().last() {
+            Some(Value::String(s)) => format!("{:?}", s),
+            Some(top) => top.display(floats).to_string(),
+            None => "(empty stack)".to_string(),
+        },
+        Err(e) => format!("error: {}", e.message),
+    };
+    vm.restore(snapshot);
+    value
+}
+
+/// Compile one line, knowing the variables declared before it. Also
+/// returns the variables the line declares.
+fn compile(source: &str, variables: &BTreeSet<String>) -> Result<(ProgramBc, Vec<String>), String> {
+    let tokens = Lexer::new(source)
+        .tokenize()
+        .map_err(|e| format!("Lexer error: {}", e))?;
+    let program = Parser::new(tokens)
+        .parse()
+        .map_err(|e| format!("Parse error: {}", e))?;
+    let declared = program
+        .definitions
+        .iter()
+        .filter_map(|def| match def {
+            Node::Var(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    let bytecode = Compiler::new()
+        .with_variables(variables.iter().cloned())
+        .compile_program(&program)
+        .map_err(|e| format!("Compile error: {}", e))?;
+    Ok((bytecode, declared))
+}
+
+/// Make Ctrl-C interrupt the running line instead of exiting.
+pub fn interrupt_on_ctrl_c(config: &VmBcConfig) {
+    let cancel = config.cancel.clone();
+    if let Err(e) = ctrlc::set_handler(move || cancel.cancel()) {
+        eprintln!("warning: Ctrl-C will exit instead of interrupting: {}", e);
+    }
+}
+
+/// Run the REPL on standard input until end of input or `:quit`.
+pub fn run(config: VmBcConfig) {
+    interrupt_on_ctrl_c(&config);
+
+    let mut repl = Repl::new(config);
+    println!("EMBER - Ctrl-C interrupts a running line; :quit or Ctrl-D exits");
+    loop {
+        print!("ember> ");
+        std::io::stdout().flush().ok();
+
+        let line = match repl.read_line() {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                println!();
+                break;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        };
+
+        match line.trim() {
+            "" => continue,
+            ":quit" | ":q" => break,
+            ":help" => print_commands(),
+            command if command.starts_with(":help ") => {
+                let name = command[":help ".len()..].trim();
+                match describe_word(&repl.vm, name) {
+                    Some(text) => print!("{}", text),
+                    None => eprintln!("no word named '{}'", name),
+                }
+            }
+            ":watch" => {
+                for (i, watch) in repl.watches.iter().enumerate() {
+                    let last = watch.last.as_deref().unwrap_or("");
+                    println!("  {}: {} = {}", i + 1, watch.source, last);
+                }
+            }
+            command if command.starts_with(":watch ") => {
+                let source = command[":watch ".len()..].trim();
+                match repl.add_watch(source) {
+                    Ok(value) => println!("  {}: {} = {}", repl.watches.len(), source, value),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            command if command.starts_with(":unwatch ") => {
+                let number = command[":unwatch ".len()..].trim();
+                match number.parse().ok().and_then(|n| repl.remove_watch(n)) {
+                    Some(source) => println!("  removed watch: {}", source),
+                    None => eprintln!("no watch numbered '{}'", number),
+                }
+            }
+            ":stack" => {
+                let floats = repl.vm.config().float_format;
+                print!("{}", crate::format_stack("Stack", repl.stack(), floats));
+                print!(
+                    "{}",
+                    crate::format_stack("Aux stack", &repl.vm.aux_stack, floats)
+                );
+            }
+            _ => {
+                match repl.eval(&line) {
+                    Ok(()) => print_stack_line(&repl),
+                    Err(e) => eprintln!("{}", e),
+                }
+                for (number, source, value) in repl.changed_watches() {
+                    println!("  watch {}: {} = {}", number, source, value);
+                }
+            }
+        }
+    }
+}
+
+fn print_commands() {
+    println!("  :help <word>   Stack effect and description of a word");
+    println!("  :stack         List the data and aux stacks");
+    println!("  :watch <code>  Show the top value <code> leaves whenever it changes");
+    println!("  :watch         List the watches");
+    println!("  :unwatch <n>   Remove watch number n");
+    println!("  :quit          Exit (or Ctrl-D)");
+    println!("Ctrl-C stops a running line and restores the stack from before it.");
+}
+
+/// The data stack on one line, bottom first, after each evaluation.
+pub fn print_stack_line(repl: &Repl) {
+    if repl.stack().is_empty() {
+        return;
+    }
+    let floats = repl.vm.config().float_format;
+    let shown: Vec<String> = repl
+        .stack()
+        .iter()
+        .map(|value| match value {
+            Value::String(s) => format!("{:?}", s),
+            other => other.display(floats).to_string(),
+        })
+        .collect();
+    println!("  {}", shown.join(" "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lines_share_stack_and_words() {
+        let mut repl = Repl::new(VmBcConfig::default());
+        repl.eval("def double 2 * end").unwrap();
+        repl.eval("1 2").unwrap();
+        repl.eval("+ double").unwrap();
+        assert_eq!(repl.stack(), &[Value::Integer(6)]);
+    }
+
+    #[test]
+    fn test_failed_line_restores_stack_and_words() {
+        let mut repl = Repl::new(VmBcConfig::default());
+        repl.eval("1 2").unwrap();
+
+        let err = repl.eval("def f 1 end 3 4 undefined-word").unwrap_err();
+        assert!(err.contains("undefined word"), "{}", err);
+        assert_eq!(repl.stack(), &[Value::Integer(1), Value::Integer(2)]);
+        assert!(repl.eval("f").unwrap_err().contains("undefined word"));
+
+        // Rejected before running: nothing to roll back
+        assert!(repl.eval("[ ").is_err());
+        assert_eq!(repl.stack().len(), 2);
+    }
+
+    #[test]
+    fn test_variables_last_across_lines() {
+        let mut repl = Repl::new(VmBcConfig::default());
+        repl.eval("var n 1 n !").unwrap();
+        assert!(repl.eval("n @ 1 + n ! undefined-word").is_err());
+        repl.eval("n @").unwrap();
+        assert_eq!(repl.stack(), &[Value::Integer(1)]);
+
+        let err = repl.eval("m @").unwrap_err();
+        assert!(err.contains("variable 'm' is not declared"), "{}", err);
+    }
+
+    #[test]
+    fn test_interrupt_restores_stack() {
+        let config = VmBcConfig::default();
+        let cancel = config.cancel.clone();
+        let mut repl = Repl::new(config);
+        repl.eval("1 2").unwrap();
+
+        let interrupter = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        let err = repl
+            .eval("drop 1000000000000 [ 1 drop ] times")
+            .unwrap_err();
+        interrupter.join().unwrap();
+
+        assert!(err.contains("interrupted"), "{}", err);
+        assert_eq!(repl.stack(), &[Value::Integer(1), Value::Integer(2)]);
+
+        // The token is cleared for the next line
+        repl.eval("+").unwrap();
+        assert_eq!(repl.stack(), &[Value::Integer(3)]);
+    }
+
+    #[test]
+    fn test_watch_reports_changes() {
+        let mut repl = Repl::new(VmBcConfig::default());
+        repl.eval("1 2").unwrap();
+        assert_eq!(repl.add_watch("depth 3 >").unwrap(), "false");
+        assert_eq!(repl.add_watch("dup").unwrap(), "2");
+
+        // The watch ran on a copy: the stack is untouched
+        assert_eq!(repl.stack(), &[Value::Integer(1), Value::Integer(2)]);
+
+        repl.eval("2").unwrap();
+        assert!(repl.changed_watches().is_empty());
+        repl.eval("5").unwrap();
+        assert_eq!(
+            repl.changed_watches(),
+            vec![
+                (1, "depth 3 >".to_string(), "true".to_string()),
+                (2, "dup".to_string(), "5".to_string()),
+            ]
+        );
+
+        repl.eval("clear").unwrap();
+        let changed = repl.changed_watches();
+        assert!(changed[1].2.starts_with("error:"), "{:?}", changed);
+
+        assert_eq!(repl.remove_watch(1).as_deref(), Some("depth 3 >"));
+        assert!(repl.remove_watch(2).is_none());
+        assert!(repl.add_watch("[").is_err());
+    }
+}
@@ -0,0 +1,409 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Binary data
+// =============================================================================
+//
+// Words over `Value::Bytes`: conversion to and from strings and lists,
+// fixed-width integer packing, and base64 / hex text encodings. Encoders also
+// accept a string, which is taken as its UTF-8 bytes.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("to-bytes", to_bytes),
+    ("from-utf8", from_utf8),
+    ("byte-list", byte_list),
+    ("pack-u32-le", pack_u32_le),
+    ("pack-u32-be", pack_u32_be),
+    ("unpack-u32-le", unpack_u32_le),
+    ("unpack-u32-be", unpack_u32_be),
+    ("base64-encode", base64_encode_word),
+    ("base64-decode", base64_decode_word),
+    ("hex-encode", hex_encode_word),
+    ("hex-decode", hex_decode_word),
+];
+
+/// ( str|list -- bytes ) UTF-8 bytes of a string, or a list of integers 0-255.
+fn to_bytes(vm: &mut VmBc) -> RuntimeResult<()> {
+    let bytes = match vm.pop()? {
+        Value::String(s) => s.into_bytes(),
+        Value::Bytes(bytes) => bytes,
+        Value::List(items) => {
+            let mut bytes = Vec::with_capacity(items.len());
+            for (i, item) in items.iter().enumerate() {
+                match item {
+                    Value::Integer(n) if (0..=255).contains(n) => bytes.push(*n as u8),
+                    other => {
+                        return Err(vm
+                            .error_with_context(format!(
+                                "to-bytes: item {} is {}, expected an integer 0-255",
+                                i, other
+                            ))
+                            .boxed());
+                    }
+                }
+            }
+            bytes
+        }
+        other => {
+            return Err(vm.type_error_with_context("string, list or bytes", other.type_name()));
+        }
+    };
+    vm.push(Value::Bytes(bytes));
+    Ok(())
+}
+
+/// ( bytes -- str ) Decode UTF-8, failing on invalid sequences.
+fn from_utf8(vm: &mut VmBc) -> RuntimeResult<()> {
+    let bytes = pop_bytes(vm)?;
+    let s = String::from_utf8(bytes).map_err(|e| {
+        vm.error_with_context(format!(
+            "from-utf8: invalid UTF-8 at byte {}",
+            e.utf8_error().valid_up_to()
+        ))
+        .boxed()
+    })?;
+    vm.push(Value::String(s));
+    Ok(())
+}
+
+/// ( bytes -- list ) Each byte as an integer.
+fn byte_list(vm: &mut VmBc) -> RuntimeResult<()> {
+    let bytes = pop_bytes(vm)?;
+    let items = bytes
+        .into_iter()
+        .map(|b| Value::Integer(b as i64))
+        .collect();
+    vm.push(Value::List(items));
+    Ok(())
+}
+
+fn pack_u32_le(vm: &mut VmBc) -> RuntimeResult<()> {
+    pack_u32(vm, "pack-u32-le", u32::to_le_bytes)
+}
+
+fn pack_u32_be(vm: &mut VmBc) -> RuntimeResult<()> {
+    pack_u32(vm, "pack-u32-be", u32::to_be_bytes)
+}
+
+fn unpack_u32_le(vm: &mut VmBc) -> RuntimeResult<()> {
+    unpack_u32(vm, "unpack-u32-le", u32::from_le_bytes)
+}
+
+fn unpack_u32_be(vm: &mut VmBc) -> RuntimeResult<()> {
+    unpack_u32(vm, "unpack-u32-be", u32::from_be_bytes)
+}
+
+/// ( n -- bytes ) Four bytes in the given byte order.
+fn pack_u32(vm: &mut VmBc, word: &str, to_bytes: fn(u32) -> [u8; 4]) -> RuntimeResult<()> {
+    let n = vm.pop_int()?;
+    let n = u32::try_from(n).map_err(|_| {
+        vm.error_with_context(format!("{}: {} does not fit in 32 unsigned bits", word, n))
+            .boxed()
+    })?;
+    vm.push(Value::Bytes(to_bytes(n).to_vec()));
+    Ok(())
+}
+
+/// ( bytes offset -- n ) Read four bytes starting at `offset`.
+fn unpack_u32(vm: &mut VmBc, word: &str, from_bytes: fn([u8; 4]) -> u32) -> RuntimeResult<()> {
+    let offset = vm.pop_int()?;
+    let bytes = pop_bytes(vm)?;
+    let chunk = usize::try_from(offset)
+        .ok()
+        .and_then(|start| bytes.get(start..start.checked_add(4)?));
+    let Some(chunk) = chunk else {
+        return Err(vm
+            .error_with_context(format!(
+                "{}: offset {} needs 4 bytes but only {} available",
+                word,
+                offset,
+                bytes.len()
+            ))
+            .boxed());
+    };
+    let array: [u8; 4] = chunk.try_into().expect("slice has length 4");
+    vm.push(Value::Integer(from_bytes(array) as i64));
+    Ok(())
+}
+
+/// ( bytes|str -- str )
+fn base64_encode_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let bytes = pop_data(vm)?;
+    vm.push(Value::String(base64_encode(&bytes)));
+    Ok(())
+}
+
+/// ( str -- bytes ) Padding is optional.
+fn base64_decode_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let text = vm.pop_string()?;
+    let bytes = base64_decode(&text).map_err(|e| {
+        vm.error_with_context(format!("base64-decode: {}", e))
+            .boxed()
+    })?;
+    vm.push(Value::Bytes(bytes));
+    Ok(())
+}
+
+/// ( bytes|str -- str ) Lowercase hex, two digits per byte.
+fn hex_encode_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let bytes = pop_data(vm)?;
+    vm.push(Value::String(hex_encode(&bytes)));
+    Ok(())
+}
+
+/// ( str -- bytes ) Accepts upper- or lowercase digits.
+fn hex_decode_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let text = vm.pop_string()?;
+    let bytes = hex_decode(&text)
+        .map_err(|e| vm.error_with_context(format!("hex-decode: {}", e)).boxed())?;
+    vm.push(Value::Bytes(bytes));
+    Ok(())
+}
+
+fn pop_bytes(vm: &mut VmBc) -> RuntimeResult<Vec<u8>> {
+    match vm.pop()? {
+        Value::Bytes(bytes) => Ok(bytes),
+        other => Err(vm.type_error_with_context("bytes", other.type_name())),
+    }
+}
+
+/// Pop bytes, or a string as its UTF-8 encoding.
+pub(crate) fn pop_data(vm: &mut VmBc) -> RuntimeResult<Vec<u8>> {
+    match vm.pop()? {
+        Value::Bytes(bytes) => Ok(bytes),
+        Value::String(s) => Ok(s.into_bytes()),
+        other => Err(vm.type_error_with_context("bytes or string", other.type_name())),
+    }
+}
+
+// Encodings
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 (RFC 4648) with `=` padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard base64. Trailing `=` padding may be omitted.
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.trim_end_matches('=');
+    if text.len() - digits.len() > 2 {
+        return Err("too much padding".to_string());
+    }
+    if digits.len() % 4 == 1 {
+        return Err(format!("invalid length {}", text.len()));
+    }
+
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for (i, ch) in digits.chars().enumerate() {
+        let value = match ch {
+            'A'..='Z' => ch as u32 - 'A' as u32,
+            'a'..='z' => ch as u32 - 'a' as u32 + 26,
+            '0'..='9' => ch as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(format!("invalid character '{}' at position {}", ch, i)),
+        };
+        acc = acc << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+pub fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hex_decode(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("odd number of digits ({})", text.len()));
+    }
+    text.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| format!("invalid hex digits at position {}", i * 2))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_support::run;
+
+    /// Every length up to 8 with varied content, plus all 256 byte values.
+    fn samples() -> Vec<Vec<u8>> {
+        let mut samples: Vec<Vec<u8>> = (0..=8u8)
+            .map(|len| {
+                (0..len)
+                    .map(|i| i.wrapping_mul(37).wrapping_add(len))
+                    .collect()
+            })
+            .collect();
+        samples.push((0..=255).collect());
+        samples.push(vec![0xff; 7]);
+        samples
+    }
+
+    #[test]
+    fn test_base64_known_vectors() {
+        // RFC 4648 section 10
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(base64_encode(plain.as_bytes()), encoded);
+            assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_base64_round_trip() {
+        for bytes in samples() {
+            let encoded = base64_encode(&bytes);
+            assert_eq!(base64_decode(&encoded).unwrap(), bytes, "{}", encoded);
+            assert_eq!(
+                base64_decode(encoded.trim_end_matches('=')).unwrap(),
+                bytes,
+                "unpadded {}",
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn test_base64_decode_errors() {
+        assert!(
+            base64_decode("Zm9!")
+                .unwrap_err()
+                .contains("invalid character '!'")
+        );
+        assert!(base64_decode("Z").unwrap_err().contains("invalid length"));
+        assert!(
+            base64_decode("Zg===")
+                .unwrap_err()
+                .contains("too much padding")
+        );
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        for bytes in samples() {
+            let encoded = hex_encode(&bytes);
+            assert_eq!(encoded.len(), bytes.len() * 2);
+            assert_eq!(hex_decode(&encoded).unwrap(), bytes);
+            assert_eq!(hex_decode(&encoded.to_uppercase()).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_hex_decode_errors() {
+        assert!(hex_decode("abc").unwrap_err().contains("odd number"));
+        assert!(hex_decode("zz").unwrap_err().contains("position 0"));
+        assert!(hex_decode("00é").is_err());
+    }
+
+    #[test]
+    fn test_pack_unpack_round_trip() {
+        for n in [0u32, 1, 0xff, 0x1234_5678, u32::MAX] {
+            let src = format!("{n} pack-u32-le 0 unpack-u32-le {n} pack-u32-be 0 unpack-u32-be");
+            let n = Value::Integer(n as i64);
+            assert_eq!(run(&src).unwrap(), vec![n.clone(), n]);
+        }
+    }
+
+    #[test]
+    fn test_pack_byte_order() {
+        assert_eq!(
+            run("305419896 pack-u32-le 305419896 pack-u32-be").unwrap(),
+            vec![
+                Value::Bytes(vec![0x78, 0x56, 0x34, 0x12]),
+                Value::Bytes(vec![0x12, 0x34, 0x56, 0x78]),
+            ]
+        );
+        assert_eq!(
+            run("{ 0 0 1 2 0 0 } to-bytes 2 unpack-u32-be").unwrap(),
+            vec![Value::Integer(0x0102_0000)]
+        );
+    }
+
+    #[test]
+    fn test_pack_unpack_errors() {
+        let err = run("-1 pack-u32-le").unwrap_err();
+        assert!(err.contains("does not fit in 32 unsigned bits"), "{}", err);
+        let err = run("4294967296 pack-u32-be").unwrap_err();
+        assert!(err.contains("does not fit"), "{}", err);
+        let err = run("{ 1 2 3 4 } to-bytes 1 unpack-u32-le").unwrap_err();
+        assert!(
+            err.contains("offset 1 needs 4 bytes but only 4 available"),
+            "{}",
+            err
+        );
+        let err = run("{ 1 2 3 4 } to-bytes -1 unpack-u32-le").unwrap_err();
+        assert!(err.contains("offset -1"), "{}", err);
+    }
+
+    #[test]
+    fn test_bytes_words() {
+        assert_eq!(
+            run(r#""hi" to-bytes dup len swap byte-list"#).unwrap(),
+            vec![
+                Value::Integer(2),
+                Value::List(vec![Value::Integer(104), Value::Integer(105)])
+            ]
+        );
+        assert_eq!(
+            run(r#""héllo" base64-encode base64-decode from-utf8"#).unwrap(),
+            vec![Value::String("héllo".to_string())]
+        );
+        assert_eq!(
+            run(r#""ok" hex-encode "6f6B" hex-decode from-utf8"#).unwrap(),
+            vec![
+                Value::String("6f6b".to_string()),
+                Value::String("ok".to_string())
+            ]
+        );
+        let err = run("{ 255 } to-bytes from-utf8").unwrap_err();
+        assert!(err.contains("invalid UTF-8 at byte 0"), "{}", err);
+        let err = run("{ 256 } to-bytes").unwrap_err();
+        assert!(err.contains("expected an integer 0-255"), "{}", err);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Checkpoints
+// =============================================================================
+//
+// A host that enables `checkpoints` in the config can freeze a run with
+// `VmBc::serialize_state` and continue it later, in another process, with
+// `VmBc::resume_state`. The interpreter keeps word calls and quotations on
+// the Rust stack, so a run can only pause between instructions of main,
+// where the whole state is the stacks, the words and one instruction
+// pointer. Loops that the compiler lowered to jumps in main run at that
+// level, so they can pause mid-iteration.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("checkpoint", checkpoint)];
+
+/// ( -- ) Pause the run at the next instruction of main, if the host
+/// enabled checkpoints. Does nothing otherwise.
+fn checkpoint(vm: &mut VmBc) -> RuntimeResult<()> {
+    if vm.config().checkpoints {
+        vm.request_pause();
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Color words
+// =============================================================================
+//
+// `colorize` and `style` wrap a string in ANSI escapes for terminal output.
+// They only do so when the config's `color` is on, which the CLI turns off
+// for `--no-color`, a `NO_COLOR` environment variable, or output that isn't
+// a terminal; otherwise the string comes back unchanged. Names are checked
+// either way, so a typo fails in a pipe as it would on a terminal.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("colorize", colorize), ("style", style)];
+
+const COLORS: &[(&str, u8)] = &[
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+    ("gray", 90),
+];
+
+const STYLES: &[(&str, u8)] = &[
+    ("bold", 1),
+    ("dim", 2),
+    ("italic", 3),
+    ("underline", 4),
+    ("reverse", 7),
+];
+
+/// ( str color -- str ) Show a string in a color.
+fn colorize(vm: &mut VmBc) -> RuntimeResult<()> {
+    apply(vm, "colorize", COLORS)
+}
+
+/// ( str style -- str ) Show a string bold, dim, italic, underlined or
+/// reversed.
+fn style(vm: &mut VmBc) -> RuntimeResult<()> {
+    apply(vm, "style", STYLES)
+}
+
+fn apply(vm: &mut VmBc, word: &str, table: &[(&str, u8)]) -> RuntimeResult<()> {
+    let name = vm.pop_string()?;
+    let text = vm.pop_string()?;
+    let Some(&(_, code)) = table.iter().find(|(known, _)| *known == name) else {
+        let names: Vec<&str> = table.iter().map(|(known, _)| *known).collect();
+        return Err(vm
+            .error_with_context(format!("{}: unknown name '{}'", word, name))
+            .with_help(format!("Use one of: {}", names.join(", ")))
+            .boxed());
+    };
+    vm.push(Value::String(if vm.config().color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run_with;
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    fn run(source: &str, color: bool) -> Result<Vec<Value>, String> {
+        run_with(
+            VmBcConfig {
+                color,
+                ..Default::default()
+            },
+            source,
+        )
+    }
+
+    #[test]
+    fn test_escapes_only_with_color() {
+        let source = "\"error\" \"red\" colorize \"bold\" style";
+        assert_eq!(
+            run(source, true).unwrap(),
+            vec![Value::String("\x1b[1m\x1b[31merror\x1b[0m\x1b[0m".into())]
+        );
+        assert_eq!(
+            run(source, false).unwrap(),
+            vec![Value::String("error".into())]
+        );
+
+        let err = run("\"x\" \"mauve\" colorize", false).unwrap_err();
+        assert_eq!(err, "colorize: unknown name 'mauve'");
+    }
+}
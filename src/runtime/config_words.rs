@@ -0,0 +1,182 @@
+#[cfg(feature = "yaml")]
+use std::collections::BTreeMap;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Config file words
+// =============================================================================
+//
+// `toml-parse` and `yaml-parse` turn the text of a config file into maps and
+// lists, to be read with `get` and `get-in`. Each needs its feature (`toml`,
+// `yaml`). Tables and mappings become maps, arrays and sequences become
+// lists, and scalars become the matching EMBER value; TOML dates and times
+// are kept as their text.
+//
+// EMBER has no null. A YAML key whose value is null is left out of its map,
+// so it reads as absent, and an empty document is an empty map; a null
+// anywhere else is an error. YAML keys that are numbers or booleans become
+// their text, since map keys are strings.
+
+#[cfg(feature = "toml")]
+pub const TOML_WORDS: &[(&str, NativeFn)] = &[("toml-parse", toml_parse)];
+
+#[cfg(feature = "yaml")]
+pub const YAML_WORDS: &[(&str, NativeFn)] = &[("yaml-parse", yaml_parse)];
+
+/// ( str -- map ) Parse a TOML document.
+#[cfg(feature = "toml")]
+fn toml_parse(vm: &mut VmBc) -> RuntimeResult<()> {
+    let text = vm.pop_string()?;
+    let table = text
+        .parse::<toml::Table>()
+        .map_err(|e| parse_error(vm, "toml-parse", e.message()))?;
+    vm.push(from_toml(toml::Value::Table(table)));
+    Ok(())
+}
+
+#[cfg(feature = "toml")]
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(n) => Value::Integer(n),
+        toml::Value::Float(x) => Value::Float(x),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(when) => Value::String(when.to_string()),
+        toml::Value::Array(items) => Value::List(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key, from_toml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// ( str -- data ) Parse a YAML document.
+#[cfg(feature = "yaml")]
+fn yaml_parse(vm: &mut VmBc) -> RuntimeResult<()> {
+    let text = vm.pop_string()?;
+    let document: serde_yaml::Value =
+        serde_yaml::from_str(&text).map_err(|e| parse_error(vm, "yaml-parse", &e.to_string()))?;
+    let value = match document {
+        serde_yaml::Value::Null => Ok(Value::Map(BTreeMap::new())),
+        document => from_yaml(document),
+    }
+    .map_err(|e| parse_error(vm, "yaml-parse", &e))?;
+    vm.push(value);
+    Ok(())
+}
+
+#[cfg(feature = "yaml")]
+fn from_yaml(value: serde_yaml::Value) -> Result<Value, String> {
+    use serde_yaml::Value as Yaml;
+    match value {
+        Yaml::Null => Err("null has no EMBER value outside a mapping".to_string()),
+        Yaml::Bool(b) => Ok(Value::Bool(b)),
+        Yaml::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Ok(Value::Integer(i)),
+            (None, Some(x)) if !n.is_u64() => Ok(Value::Float(x)),
+            _ => Err(format!("integer {} is out of range", n)),
+        },
+        Yaml::String(s) => Ok(Value::String(s)),
+        Yaml::Sequence(items) => items
+            .into_iter()
+            .map(from_yaml)
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        Yaml::Mapping(entries) => {
+            let mut map = BTreeMap::new();
+            for (key, value) in entries {
+                let key = match key {
+                    Yaml::String(s) => s,
+                    Yaml::Bool(b) => b.to_string(),
+                    Yaml::Number(n) => n.to_string(),
+                    other => return Err(format!("unsupported mapping key {:?}", other)),
+                };
+                if !value.is_null() {
+                    map.insert(key, from_yaml(value)?);
+                }
+            }
+            Ok(Value::Map(map))
+        }
+        Yaml::Tagged(tagged) => from_yaml(tagged.value),
+    }
+}
+
+fn parse_error(vm: &VmBc, word: &str, message: &str) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: {}", word, message.trim_end()))
+        .with_help("The text must be a whole document, e.g. from reading a config file")
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::test_support::run;
+    use std::collections::BTreeMap;
+
+    use crate::lang::value::Value;
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_parse() {
+        let source = r#""name = 'ember'\n[server]\nport = 8080\nratio = 0.5\ntags = ['a', 'b']\nup = true\n" toml-parse"#;
+        assert_eq!(
+            run(source).unwrap(),
+            vec![map(&[
+                ("name", Value::String("ember".into())),
+                (
+                    "server",
+                    map(&[
+                        ("port", Value::Integer(8080)),
+                        ("ratio", Value::Float(0.5)),
+                        (
+                            "tags",
+                            Value::List(vec![Value::String("a".into()), Value::String("b".into())])
+                        ),
+                        ("up", Value::Bool(true)),
+                    ])
+                ),
+            ])]
+        );
+        let err = run(r#""port = " toml-parse"#).unwrap_err();
+        assert!(err.starts_with("toml-parse: "), "{}", err);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_parse() {
+        let source = r#""server:\n  port: 8080\n  hosts: [a, b]\n  proxy:\n1: one\n" yaml-parse"#;
+        assert_eq!(
+            run(source).unwrap(),
+            vec![map(&[
+                ("1", Value::String("one".into())),
+                (
+                    "server",
+                    map(&[
+                        (
+                            "hosts",
+                            Value::List(vec![Value::String("a".into()), Value::String("b".into())])
+                        ),
+                        ("port", Value::Integer(8080)),
+                    ])
+                ),
+            ])]
+        );
+        assert_eq!(run(r#""" yaml-parse"#).unwrap(), vec![map(&[])]);
+        let err = run(r#""[1, ~]" yaml-parse"#).unwrap_err();
+        assert_eq!(err, "yaml-parse: null has no EMBER value outside a mapping");
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::{self, Write};
+use std::process::{Command, Output, Stdio};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Desktop words (feature = "desktop")
+// =============================================================================
+//
+// The system clipboard and the default URL handler, for glue scripts. Rather
+// than linking a GUI toolkit, each word runs the platform's own helper:
+// pbcopy/pbpaste and `open` on macOS, clip, PowerShell and the URL protocol
+// handler on Windows, and elsewhere the first of wl-clipboard, xclip or xsel
+// that is installed, and xdg-open. Because they start programs, every word
+// is refused when the VM's `allow_process` flag is off.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("clipboard-get", clipboard_get),
+    ("clipboard-set", clipboard_set),
+    ("open-url", open_url),
+];
+
+#[cfg(target_os = "macos")]
+const PASTE: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(target_os = "macos")]
+const COPY: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(target_os = "macos")]
+const OPEN: &[&str] = &["open"];
+
+#[cfg(windows)]
+const PASTE: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]];
+#[cfg(windows)]
+const COPY: &[&[&str]] = &[&["clip"]];
+#[cfg(windows)]
+const OPEN: &[&str] = &["rundll32", "url.dll,FileProtocolHandler"];
+
+#[cfg(not(any(target_os = "macos", windows)))]
+const PASTE: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+];
+#[cfg(not(any(target_os = "macos", windows)))]
+const COPY: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+];
+#[cfg(not(any(target_os = "macos", windows)))]
+const OPEN: &[&str] = &["xdg-open"];
+
+/// ( -- str ) The text on the clipboard.
+fn clipboard_get(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_process("clipboard-get")?;
+    let output = run_first(vm, "clipboard-get", PASTE, None)?;
+    vm.push(Value::String(
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+    ));
+    Ok(())
+}
+
+/// ( str -- ) Put text on the clipboard.
+fn clipboard_set(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_process("clipboard-set")?;
+    let text = vm.pop_string()?;
+    run_first(vm, "clipboard-set", COPY, Some(&text))?;
+    Ok(())
+}
+
+/// ( url -- ) Open a URL with the default handler, e.g. a web page in the
+/// browser.
+fn open_url(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_process("open-url")?;
+    let url = vm.pop_string()?;
+    if !has_scheme(&url) {
+        return Err(vm
+            .error_with_context(format!("open-url: '{}' is not a URL", url))
+            .with_help(
+                "URLs start with a scheme, like https://example.com or mailto:me@example.com",
+            )
+            .boxed());
+    }
+    let mut command = OPEN.to_vec();
+    command.push(&url);
+    run_first(vm, "open-url", &[&command], None)?;
+    Ok(())
+}
+
+/// Whether `url` starts with `scheme:`, which also keeps it from being
+/// read as an option by the opener.
+fn has_scheme(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+/// Run the first of `commands` that is installed, writing `input` to its
+/// stdin, and return its output. Fails if none is installed or the one that
+/// ran exits unsuccessfully.
+fn run_first(
+    vm: &VmBc,
+    word: &str,
+    commands: &[&[&str]],
+    input: Option<&str>,
+) -> RuntimeResult<Output> {
+    for command in commands {
+        let (program, args) = command.split_first().expect("commands are not empty");
+        let output = match run(program, args, input) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(vm
+                    .error_with_context(format!("{}: failed to run '{}': {}", word, program, e))
+                    .boxed());
+            }
+            Ok(output) => output,
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match stderr.trim() {
+                "" => output.status.to_string(),
+                stderr => stderr.to_string(),
+            };
+            return Err(vm
+                .error_with_context(format!("{}: '{}' failed: {}", word, program, reason))
+                .boxed());
+        }
+        return Ok(output);
+    }
+    Err(not_installed(vm, word, commands))
+}
+
+fn run(program: &str, args: &[&str], input: Option<&str>) -> io::Result<Output> {
+    // xclip and wl-copy leave a process behind to serve the clipboard, which
+    // would hold output pipes open, so a copy only reports its exit status
+    let pipe = |wanted: bool| {
+        if wanted {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        }
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(pipe(input.is_some()))
+        .stdout(pipe(input.is_none()))
+        .stderr(pipe(input.is_none()))
+        .spawn()?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    child.wait_with_output()
+}
+
+fn not_installed(vm: &VmBc, word: &str, commands: &[&[&str]]) -> Box<RuntimeError> {
+    let programs: Vec<&str> = commands.iter().map(|command| command[0]).collect();
+    vm.error_with_context(format!(
+        "{}: no helper program found (tried {})",
+        word,
+        programs.join(", ")
+    ))
+    .with_help("Install one of them and make sure it is on PATH")
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::has_scheme;
+    use crate::runtime::test_support::run_with;
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    #[test]
+    fn test_open_url_needs_a_scheme() {
+        assert!(has_scheme("https://example.com/a?b=c"));
+        assert!(has_scheme("mailto:me@example.com"));
+        assert!(!has_scheme("example.com"));
+        assert!(!has_scheme("--help"));
+        assert!(!has_scheme("-x:y"));
+        let err = run_with(VmBcConfig::default(), r#""--version" open-url"#).unwrap_err();
+        assert_eq!(err, "open-url: '--version' is not a URL");
+    }
+
+    #[test]
+    fn test_needs_process() {
+        let config = VmBcConfig {
+            allow_process: false,
+            ..Default::default()
+        };
+        let err = run_with(config, r#""hi" clipboard-set"#).unwrap_err();
+        assert_eq!(err, "clipboard-set: process execution is disabled");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::bytecode::op::Op;
+use crate::lang::builtins::{self, StackDelta};
+use crate::lang::value::{FloatFormat, Value};
+use std::collections::BTreeMap;
+
+// =============================================================================
+// Teaching mode
+// =============================================================================
+//
+// `--explain` describes each instruction in plain English as it runs: what
+// the word does, the values it used and the stack afterwards. Words and
+// quotation-running builtins get a line before they start and another when
+// they finish, so the steps inside them read in order. Jumps and returns
+// are bookkeeping the compiler added and aren't described.
+
+/// Which steps `--explain` describes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explain {
+    /// Stop after describing this many steps.
+    pub limit: usize,
+    /// Only describe steps inside these words; every step when empty.
+    pub words: Vec<String>,
+}
+
+impl Default for Explain {
+    fn default() -> Self {
+        Explain {
+            limit: 100,
+            words: Vec::new(),
+        }
+    }
+}
+
+impl std::str::FromStr for Explain {
+    type Err = String;
+
+    /// Parse a step limit (`500`) or a comma-separated list of words.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(limit) = s.parse() {
+            return Ok(Explain {
+                limit,
+                ..Explain::default()
+            });
+        }
+        let words: Vec<String> = s
+            .split(',')
+            .filter(|w| !w.is_empty())
+            .map(String::from)
+            .collect();
+        if words.is_empty() {
+            return Err("expected a step count or a list of words".to_string());
+        }
+        Ok(Explain {
+            words,
+            ..Explain::default()
+        })
+    }
+}
+
+/// When a step's description is printed.
+pub(crate) enum Step {
+    /// Once, after it runs, followed by the stack.
+    After(String),
+    /// Before it runs, since other steps run inside it, and again with the
+    /// stack when `name` finishes.
+    Around { line: String, name: String },
+}
+
+/// Describe `op`, given the stack before it runs. None for internal ops.
+pub(crate) fn describe(
+    op: &Op,
+    stack: &[Value],
+    words: &BTreeMap<String, Vec<Op>>,
+    floats: FloatFormat,
+) -> Option<Step> {
+    let (name, description, delta) = match op {
+        Op::Push(Value::CompiledQuotation(_)) => {
+            return Some(Step::After("push a quotation".to_string()));
+        }
+        Op::Push(value) => {
+            return Some(Step::After(format!("push {}", show(value, floats))));
+        }
+        Op::ToAux => {
+            return Some(Step::After(
+                "move the top value to the aux stack".to_string(),
+            ));
+        }
+        Op::FromAux => {
+            return Some(Step::After(
+                "move the top of the aux stack back".to_string(),
+            ));
+        }
+        Op::CheckTimes => {
+            return Some(Step::After(
+                "check the times count is a non-negative integer".to_string(),
+            ));
+        }
+        Op::CheckBool => {
+            return Some(Step::After(
+                "check the right operand left a bool".to_string(),
+            ));
+        }
+        Op::NextLine => {
+            return Some(Step::After("read the next input line".to_string()));
+        }
+        Op::Peek(n) => {
+            return Some(Step::After(format!("copy the value {} below the top", n)));
+        }
+        Op::Store(name) => {
+            return Some(Step::After(format!("store the top value in {}", name)));
+        }
+        Op::Load(name) => {
+            return Some(Step::After(format!("push the value of {}", name)));
+        }
+        Op::StoreLocal(slot) => {
+            return Some(Step::After(format!(
+                "store the top value in local {}",
+                slot
+            )));
+        }
+        Op::LoadLocal(slot) => {
+            return Some(Step::After(format!("push local {}", slot)));
+        }
+        Op::Capture(_) => {
+            return Some(Step::After(
+                "push a quotation holding the word's locals".to_string(),
+            ));
+        }
+        Op::CallWord(name) if words.contains_key(name) => {
+            return Some(Step::Around {
+                line: format!("call your word {}", name),
+                name: name.clone(),
+            });
+        }
+        Op::CallQualified { module, word } => {
+            let name = format!("{}.{}", module, word);
+            return Some(Step::Around {
+                line: format!("call {}", name),
+                name,
+            });
+        }
+        Op::CallWord(name) => match builtins::lookup(name) {
+            Some(doc) => (name.as_str(), doc.description, None),
+            None => (name.as_str(), "", None),
+        },
+        other => {
+            let name = builtins::op_name(other)?;
+            let doc = builtins::lookup(name)?;
+            (name, doc.description, builtins::op_delta(other))
+        }
+    };
+
+    let mut line = name.to_string();
+    if !description.is_empty() {
+        line.push_str(": ");
+        line.push_str(description.trim_end_matches('.'));
+    }
+    let inputs = match delta {
+        Some(StackDelta::Fixed { inputs, .. } | StackDelta::Dynamic { inputs }) => inputs,
+        _ => 0,
+    };
+    if inputs > 0 && inputs <= stack.len() {
+        let used: Vec<String> = stack[stack.len() - inputs..]
+            .iter()
+            .map(|v| show(v, floats))
+            .collect();
+        line.push_str(&format!(" (using {})", used.join(" ")));
+    }
+    Some(match delta {
+        Some(StackDelta::Dynamic { .. }) => Step::Around {
+            line,
+            name: name.to_string(),
+        },
+        _ => Step::After(line),
+    })
+}
+
+/// `[5 5]`, with strings quoted so they can't be mistaken for words.
+pub(crate) fn show_stack(stack: &[Value], floats: FloatFormat) -> String {
+    let values: Vec<String> = stack.iter().map(|v| show(v, floats)).collect();
+    format!("[{}]", values.join(" "))
+}
+
+fn show(value: &Value, floats: FloatFormat) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        other => other.display(floats).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(step: Option<Step>) -> String {
+        match step {
+            Some(Step::After(s)) => format!("after: {}", s),
+            Some(Step::Around { line, name }) => format!("around {}: {}", name, line),
+            None => "none".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        let words = BTreeMap::from([("sq".to_string(), vec![Op::Dup, Op::Mul])]);
+        let floats = FloatFormat::default();
+        let stack = [Value::Integer(5)];
+
+        assert_eq!(
+            line(describe(&Op::Dup, &stack, &words, floats)),
+            "after: dup: Duplicate the top value (using 5)"
+        );
+        assert_eq!(
+            line(describe(&Op::CallWord("sq".into()), &stack, &words, floats)),
+            "around sq: call your word sq"
+        );
+        assert_eq!(
+            line(describe(
+                &Op::Push(Value::String("hi".into())),
+                &stack,
+                &words,
+                floats
+            )),
+            "after: push \"hi\""
+        );
+        assert_eq!(line(describe(&Op::Jump(2), &stack, &words, floats)), "none");
+        assert_eq!(
+            show_stack(&[Value::Integer(1), Value::String("a".into())], floats),
+            "[1 \"a\"]"
+        );
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        assert_eq!("500".parse::<Explain>().unwrap().limit, 500);
+        assert_eq!(
+            "sq,cube".parse::<Explain>().unwrap().words,
+            vec!["sq", "cube"]
+        );
+        assert!(",".parse::<Explain>().is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// File words
+// =============================================================================
+//
+// Whole-file reads and writes for scripts. `read-file` returns the text of a
+// UTF-8 file; `write-file` and `append-file` take a string, written as
+// UTF-8, or bytes, written as they are, and create the file if it is
+// missing. Every word is refused when the VM's `allow_fs` flag is off.
+// Files that can't be read or written are runtime errors naming the path
+// and the reason, so a script can `try` them like any other failure.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("read-file", read_file),
+    ("write-file", write_file),
+    ("append-file", append_file),
+    ("file-exists?", file_exists),
+    ("delete-file", delete_file),
+];
+
+/// ( path -- str ) The text of a UTF-8 file.
+fn read_file(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("read-file")?;
+    let path = vm.pop_string()?;
+    let bytes = fs::read(&path).map_err(|e| file_error(vm, "read-file", &path, e))?;
+    let text = String::from_utf8(bytes).map_err(|e| {
+        vm.error_with_context(format!(
+            "read-file: '{}' is not valid UTF-8 (at byte {})",
+            path,
+            e.utf8_error().valid_up_to()
+        ))
+        .with_help("Only text files can be read as strings")
+        .boxed()
+    })?;
+    vm.push(Value::String(text));
+    Ok(())
+}
+
+/// ( data path -- ) Replace a file's contents.
+fn write_file(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("write-file")?;
+    let path = vm.pop_string()?;
+    let data = pop_data(vm)?;
+    fs::write(&path, data).map_err(|e| file_error(vm, "write-file", &path, e))
+}
+
+/// ( data path -- ) Add to the end of a file.
+fn append_file(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("append-file")?;
+    let path = vm.pop_string()?;
+    let data = pop_data(vm)?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(&data))
+        .map_err(|e| file_error(vm, "append-file", &path, e))
+}
+
+/// ( path -- bool ) Whether a file or directory exists at the path.
+fn file_exists(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("file-exists?")?;
+    let path = vm.pop_string()?;
+    vm.push(Value::Bool(Path::new(&path).exists()));
+    Ok(())
+}
+
+/// ( path -- ) Delete a file.
+fn delete_file(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("delete-file")?;
+    let path = vm.pop_string()?;
+    fs::remove_file(&path).map_err(|e| file_error(vm, "delete-file", &path, e))
+}
+
+/// Pop what to write: a string's UTF-8 bytes, or bytes.
+fn pop_data(vm: &mut VmBc) -> RuntimeResult<Vec<u8>> {
+    match vm.pop()? {
+        Value::String(s) => Ok(s.into_bytes()),
+        Value::Bytes(bytes) => Ok(bytes),
+        other => Err(vm.type_error_with_context("string or bytes", other.type_name())),
+    }
+}
+
+fn file_error(vm: &VmBc, word: &str, path: &str, e: std::io::Error) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: cannot access '{}': {}", word, path, e))
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::test_support::{run, run_with};
+    use std::path::PathBuf;
+
+    use crate::lang::value::Value;
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ember-file-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_write_append_read_delete() {
+        let path = temp_path("notes.txt");
+        let p = path.display();
+        let source = format!(
+            r#""one\n" "{p}" write-file
+            "two\n" to-bytes "{p}" append-file
+            "{p}" file-exists?
+            "{p}" read-file
+            "{p}" delete-file
+            "{p}" file-exists?"#
+        );
+        assert_eq!(
+            run(&source).unwrap(),
+            vec![
+                Value::Bool(true),
+                Value::String("one\ntwo\n".into()),
+                Value::Bool(false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let path = temp_path("missing.txt");
+        let err = run(&format!(r#""{}" read-file"#, path.display())).unwrap_err();
+        assert!(err.starts_with("read-file: cannot access '"), "{}", err);
+        let err = run(&format!(r#""{}" delete-file"#, path.display())).unwrap_err();
+        assert!(err.starts_with("delete-file: cannot access '"), "{}", err);
+
+        let binary = temp_path("binary.bin");
+        let source = format!(
+            r#""/w==" base64-decode "{b}" write-file "{b}" read-file"#,
+            b = binary.display()
+        );
+        let err = run(&source).unwrap_err();
+        std::fs::remove_file(&binary).unwrap();
+        assert!(err.ends_with("is not valid UTF-8 (at byte 0)"), "{}", err);
+
+        let err = run(r#"42 "x.txt" write-file"#).unwrap_err();
+        assert!(err.contains("string or bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_needs_fs() {
+        let config = VmBcConfig {
+            allow_fs: false,
+            ..Default::default()
+        };
+        let err = run_with(config, r#""x.txt" file-exists?"#).unwrap_err();
+        assert_eq!(err, "file-exists?: file system access is disabled");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Handlers
+// =============================================================================
+//
+// Event-driven plugins: a script registers quotations under event names,
+//
+//     "on-message" [ "got: " swap . ] register-handler
+//
+// and after the run the host calls them with `VmBc::call_registered`, as
+// often as it likes. Each call runs on a stack of its own, holding only the
+// arguments the host passed, and returns whatever the handler leaves there;
+// the program's own stack is left alone. A handler sees the words and
+// variables of the run that registered it, and the same limits apply to
+// each call as to a run. A host word may also call one in the middle of a
+// run; the call then counts towards that run's limits.
+//
+// Registering a name again replaces its handler. Starting a new run with
+// `run_compiled` or `run_shared` forgets every handler.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("register-handler", register_handler)];
+
+/// ( name quot -- ) Register `quot` as the handler the host calls for the
+/// event `name`.
+fn register_handler(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handler = vm.pop()?;
+    let Value::CompiledQuotation(ops) = handler else {
+        return Err(vm.type_error_with_context("quotation", handler.type_name()));
+    };
+    let name = vm.pop_string()?;
+    vm.register_handler(name, ops);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run_in;
+    use crate::runtime::vm_bc::VmBc;
+
+    fn run(source: &str) -> VmBc {
+        let mut vm = VmBc::new();
+        run_in(&mut vm, source).unwrap();
+        vm
+    }
+
+    #[test]
+    fn test_call_registered() {
+        let mut vm = run(r#"
+            var seen
+            0 seen !
+            def shout " !" . end
+            "on-message" [ shout seen @ 1 + seen ! ] register-handler
+            "on-sum" [ + ] register-handler
+            "left on the stack"
+        "#);
+        assert_eq!(
+            vm.call_registered("on-message", vec![Value::String("hi".into())])
+                .unwrap(),
+            vec![Value::String("hi !".into())]
+        );
+        assert_eq!(
+            vm.call_registered("on-sum", vec![Value::Integer(2), Value::Integer(3)])
+                .unwrap(),
+            vec![Value::Integer(5)]
+        );
+        // Calls share the run's variables, not its stack
+        vm.call_registered("on-message", vec![Value::String("again".into())])
+            .unwrap();
+        let count = vm.call_registered("on-sum", vec![Value::Integer(0)]);
+        assert!(count.unwrap_err().message.contains("stack"));
+        assert_eq!(vm.stack(), &[Value::String("left on the stack".into())]);
+        assert_eq!(
+            vm.handler_names().collect::<Vec<_>>(),
+            ["on-message", "on-sum"]
+        );
+    }
+
+    #[test]
+    fn test_call_registered_from_a_host_word() {
+        let mut vm = VmBc::new();
+        vm.register_word("notify", |vm| {
+            let left = vm.call_registered("on-event", vec![Value::Integer(1)])?;
+            vm.push(Value::List(left));
+            Ok(())
+        });
+        let stack = run_in(
+            &mut vm,
+            r#"
+            def double :> n n n + end
+            "on-event" [ double ] register-handler
+            def f :> a :> b notify drop a b - end
+            10 3 f
+            "#,
+        );
+        assert_eq!(stack.unwrap(), vec![Value::Integer(-7)]);
+    }
+
+    #[test]
+    fn test_unknown_and_replaced_handlers() {
+        let mut vm = run(r#""tick" [ 1 ] register-handler "tick" [ 2 ] register-handler"#);
+        assert_eq!(
+            vm.call_registered("tick", vec![]).unwrap(),
+            vec![Value::Integer(2)]
+        );
+        let err = vm.call_registered("tock", vec![]).unwrap_err();
+        assert_eq!(err.message, "no handler is registered for 'tock'");
+
+        let err = run_in(&mut vm, r#""tick" 5 register-handler"#).unwrap_err();
+        assert!(err.contains("quotation"), "{}", err);
+        // A new run starts without handlers
+        assert!(vm.call_registered("tick", vec![]).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::lang::value::Value;
+use crate::runtime::bytes::{hex_encode, pop_data};
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Checksums and hashing
+// =============================================================================
+//
+// Every word takes bytes or a string (as UTF-8) and returns the digest as a
+// lowercase hex string, the same form `hex-encode` produces, so results can
+// be compared, printed, or turned back into bytes with `hex-decode`.
+//
+// `hash` is FNV-1a (64-bit): fast and stable across runs and platforms, which
+// makes it suitable for cache keys and dedup, but not for anything
+// adversarial. `sha256` needs the `sha256` feature.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("crc32", crc32_word), ("hash", hash_word)];
+
+#[cfg(feature = "sha256")]
+pub const SHA256_WORDS: &[(&str, NativeFn)] = &[("sha256", sha256_word)];
+
+/// ( data -- hex ) CRC-32 (IEEE), 8 hex digits.
+fn crc32_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let data = pop_data(vm)?;
+    vm.push(Value::String(hex_encode(&crc32(&data).to_be_bytes())));
+    Ok(())
+}
+
+/// ( data -- hex ) FNV-1a 64-bit, 16 hex digits.
+fn hash_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    let data = pop_data(vm)?;
+    vm.push(Value::String(hex_encode(&fnv1a64(&data).to_be_bytes())));
+    Ok(())
+}
+
+/// ( data -- hex ) SHA-256, 64 hex digits.
+#[cfg(feature = "sha256")]
+fn sha256_word(vm: &mut VmBc) -> RuntimeResult<()> {
+    use sha2::{Digest, Sha256};
+
+    let data = pop_data(vm)?;
+    vm.push(Value::String(hex_encode(&Sha256::digest(&data))));
+    Ok(())
+}
+
+/// CRC-32 with the IEEE polynomial, as used by zip, gzip and PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 != 0 {
+                    0xedb8_8320 ^ (crc >> 1)
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+pub fn fnv1a64(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_support::run;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_crc32_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414f_a339
+        );
+    }
+
+    #[test]
+    fn test_fnv1a64_known_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a64(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_hash_words() {
+        assert_eq!(
+            run(r#""123456789" crc32 "a" hash"#).unwrap(),
+            vec![s("cbf43926"), s("af63dc4c8601ec8c")]
+        );
+        // Strings hash as their UTF-8 bytes
+        assert_eq!(
+            run(r#""abc" hash "abc" to-bytes hash ="#).unwrap(),
+            vec![Value::Bool(true)]
+        );
+        let err = run("42 crc32").unwrap_err();
+        assert!(err.contains("expected bytes or string"), "{}", err);
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            run(r#""abc" sha256"#).unwrap(),
+            vec![s(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+            )]
+        );
+    }
+}
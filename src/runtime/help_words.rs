@@ -0,0 +1,207 @@
+use crate::bytecode::decompile::decompile;
+use crate::lang::builtins;
+use crate::runtime::native::{self, NativeFn};
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Help words
+// =============================================================================
+//
+// Runtime access to the builtin reference table and to the doc comments of
+// user words. The REPL's `:help` shows the same text.
+//
+// `todo` and the hole `???` stand in for code not written yet: a program
+// sketched top-down still compiles and passes the stack checker (neither
+// has a known effect), and fails only if the missing part runs.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("describe", describe),
+    ("see", see),
+    ("todo", todo),
+    ("???", hole),
+];
+
+/// ( name -- ) Print the stack effect and description of a word.
+fn describe(vm: &mut VmBc) -> RuntimeResult<()> {
+    let name = vm.pop_string()?;
+    match describe_word(vm, &name) {
+        Some(text) => vm.write_output(format_args!("{}", text)),
+        None => Err(vm
+            .error_with_context(format!("describe: no word named '{}'", name))
+            .with_help(
+                "Builtin and native words, and words defined in this program, can be described",
+            )
+            .boxed()),
+    }
+}
+
+/// ( name -- ) Print the definition of a user word as source.
+fn see(vm: &mut VmBc) -> RuntimeResult<()> {
+    let name = vm.pop_string()?;
+    let Some(ops) = vm.word_ops(&name) else {
+        let help = if builtins::lookup(&name).is_some() {
+            format!(
+                "'{}' is a builtin; `\"{}\" describe` explains it",
+                name, name
+            )
+        } else {
+            "Only words defined in this program have a definition to show".to_string()
+        };
+        return Err(vm
+            .error_with_context(format!("see: no user word named '{}'", name))
+            .with_help(help)
+            .boxed());
+    };
+    let text = match decompile(ops) {
+        Some(body) if body.is_empty() => format!("def {} end", name),
+        Some(body) => format!("def {} {} end", name, body),
+        None => format!("def {} ... end  ; compiled code with no source form", name),
+    };
+    vm.write_output(format_args!("{}\n", text))
+}
+
+/// ( -- ) Fail: the word running this isn't written yet.
+fn todo(vm: &mut VmBc) -> RuntimeResult<()> {
+    let place = unwritten(vm);
+    Err(vm
+        .error_with_context(format!("not yet implemented: {}", place))
+        .with_help("Replace `todo` with the word's definition")
+        .boxed())
+}
+
+/// ( -- ) Fail: a hole in the code was reached.
+fn hole(vm: &mut VmBc) -> RuntimeResult<()> {
+    let place = unwritten(vm);
+    Err(vm
+        .error_with_context(format!("not yet implemented: hole (???) in {}", place))
+        .with_help("Fill in the `???` with the code it stands for")
+        .boxed())
+}
+
+/// The running word and where it was defined, e.g. `parse at app.em:12`.
+fn unwritten(vm: &VmBc) -> String {
+    let Some(word) = vm.current_word() else {
+        return match &vm.file {
+            Some(file) => format!("main at {}", file.display()),
+            None => "main".to_string(),
+        };
+    };
+    match vm.word_site(word) {
+        Some(site) => format!("{} at {}", word, site.location()),
+        None => word.to_string(),
+    }
+}
+
+/// Help text for a user word, keyword or native word: the name and stack
+/// effect on one line, then the description indented below it.
+pub fn describe_word(vm: &VmBc, name: &str) -> Option<String> {
+    if vm.has_word(name) {
+        let mut text = match vm.word_doc(name) {
+            Some(doc) => {
+                let (effect, description) = split_effect(doc);
+                format_help(name, effect, description)
+            }
+            None => format_help(name, "", "User word without a doc comment."),
+        };
+        if native::lookup(name).is_some() {
+            text.push_str(&format!("  (shadows the builtin '{}')\n", name));
+        }
+        return Some(text);
+    }
+
+    builtins::lookup(name).map(|doc| format_help(doc.name, doc.effect, doc.description))
+}
+
+/// Split a leading `( a -- b )` off a doc comment.
+fn split_effect(doc: &str) -> (&str, &str) {
+    if doc.starts_with('(')
+        && let Some(end) = doc.find(')')
+    {
+        return (&doc[..=end], doc[end + 1..].trim_start());
+    }
+    ("", doc)
+}
+
+fn format_help(name: &str, effect: &str, description: &str) -> String {
+    let mut text = if effect.is_empty() {
+        format!("{}\n", name)
+    } else {
+        format!("{} {}\n", name, effect)
+    };
+    for line in description.lines() {
+        text.push_str(&format!("  {}\n", line));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_support::run_in;
+
+    fn run_vm(source: &str) -> Result<VmBc, String> {
+        let mut vm = VmBc::new();
+        vm.set_output(std::io::sink());
+        run_in(&mut vm, source)?;
+        Ok(vm)
+    }
+
+    #[test]
+    fn test_describe_builtin() {
+        let vm = run_vm("").unwrap();
+        assert_eq!(
+            describe_word(&vm, "map").unwrap(),
+            "map ( list quot -- list )\n  Apply `quot` to each element, collecting the results.\n"
+        );
+        assert!(
+            describe_word(&vm, "path-join")
+                .unwrap()
+                .starts_with("path-join ( base part -- path )")
+        );
+        assert!(describe_word(&vm, "no-such-word").is_none());
+    }
+
+    #[test]
+    fn test_describe_user_word_uses_doc_comment() {
+        let vm = run_vm(
+            "; ( n -- n*n ) Square a number.\n\
+             ; Works on floats too.\n\
+             def square dup * end\n\
+             \n\
+             def bare 1 end\n\
+             def get 2 end",
+        )
+        .unwrap();
+        assert_eq!(
+            describe_word(&vm, "square").unwrap(),
+            "square ( n -- n*n )\n  Square a number.\n  Works on floats too.\n"
+        );
+        assert_eq!(
+            describe_word(&vm, "bare").unwrap(),
+            "bare\n  User word without a doc comment.\n"
+        );
+        assert!(
+            describe_word(&vm, "get")
+                .unwrap()
+                .ends_with("(shadows the builtin 'get')\n")
+        );
+    }
+
+    #[test]
+    fn test_describe_word() {
+        assert!(run_vm(r#""dup" describe"#).is_ok());
+        let err = run_vm(r#""no-such-word" describe"#).err().unwrap();
+        assert!(
+            err.contains("describe: no word named 'no-such-word'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_split_effect() {
+        assert_eq!(split_effect("( a -- b ) Doc."), ("( a -- b )", "Doc."));
+        assert_eq!(split_effect("Just text."), ("", "Just text."));
+    }
+}
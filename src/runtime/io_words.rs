@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use crate::bytecode::op::Op;
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Input words
+// =============================================================================
+//
+// Line-oriented helpers for filters such as `cat data | ember -e '...'`.
+// Both read from the VM's input source (stdin unless replaced with
+// `VmBc::set_input`), and strip `\n` / `\r\n` line endings. They are
+// refused when the VM's `allow_io` flag is off.
+//
+// `stdin-lines` is a sequence (see `sequence.rs`) whose `next` reads a line,
+// so `map`, `filter` and `seq-take` read only as far as they get. Unlike
+// other sequences it can't start over: input that has been read is gone.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("stdin-lines", stdin_lines), ("each-line", each_line)];
+
+/// ( -- seq ) The remaining input lines, read as they are asked for.
+fn stdin_lines(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("stdin-lines")?;
+    let next = Value::CompiledQuotation(vec![Op::NextLine]);
+    let entries = BTreeMap::from([
+        ("next".to_string(), next),
+        ("state".to_string(), Value::Integer(0)),
+    ]);
+    vm.push(Value::Map(entries));
+    Ok(())
+}
+
+/// ( n -- n' line true | false ) The `next` of `stdin-lines` (`Op::NextLine`):
+/// read a line, counting the lines read so far in the state.
+pub(crate) fn next_line(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("stdin-lines")?;
+    let count = vm.pop_int()?;
+    match vm.read_input_line()? {
+        Some(line) => {
+            vm.push(Value::Integer(count + 1));
+            vm.push(Value::String(line));
+            vm.push(Value::Bool(true));
+        }
+        None => vm.push(Value::Bool(false)),
+    }
+    Ok(())
+}
+
+/// ( quot -- ) Call `quot` with each input line as it is read, so input of
+/// any size is processed in constant memory.
+fn each_line(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("each-line")?;
+    let ops = vm.pop_quotation_ops()?;
+    while let Some(line) = vm.read_input_line()? {
+        vm.push(Value::String(line));
+        vm.exec_ops(&ops)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run_in;
+    use crate::runtime::vm_bc::VmBc;
+
+    fn run_with_input(source: &str, input: &str) -> Result<Vec<Value>, String> {
+        let mut vm = VmBc::new();
+        vm.set_input(Cursor::new(input.to_string()));
+        run_in(&mut vm, source)
+    }
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_stdin_lines() {
+        assert_eq!(
+            run_with_input("stdin-lines [ ] map", "a\r\nb\nc").unwrap(),
+            vec![Value::List(vec![s("a"), s("b"), s("c")])]
+        );
+        assert_eq!(
+            run_with_input("stdin-lines [ ] map", "").unwrap(),
+            vec![Value::List(vec![])]
+        );
+    }
+
+    #[test]
+    fn test_stdin_lines_reads_on_demand() {
+        // Only the lines taken are read; the rest is still there for `read`
+        assert_eq!(
+            run_with_input("stdin-lines 2 seq-take read", "a\nb\nc\nd\n").unwrap(),
+            vec![Value::List(vec![s("a"), s("b")]), s("c")]
+        );
+        assert_eq!(
+            run_with_input("0 stdin-lines [ len + ] each", "ab\ncde\n").unwrap(),
+            vec![Value::Integer(5)]
+        );
+    }
+
+    #[test]
+    fn test_stdin_lines_reader_is_internal() {
+        // The sequence reads with an op of its own rather than a word
+        let err = run_with_input("0 stdin-next", "a\n").unwrap_err();
+        assert!(err.contains("stdin-next"), "{}", err);
+        assert_eq!(
+            run_with_input("stdin-lines \"next\" get 0 swap call", "a\n").unwrap(),
+            vec![Value::Integer(1), s("a"), Value::Bool(true)]
+        );
+    }
+
+    #[test]
+    fn test_each_line() {
+        assert_eq!(
+            run_with_input("0 [ len + ] each-line", "ab\ncde\n").unwrap(),
+            vec![Value::Integer(5)]
+        );
+    }
+
+    #[test]
+    fn test_read_uses_injected_input() {
+        assert_eq!(
+            run_with_input("read read read", "first\nsecond\n").unwrap(),
+            vec![s("first"), s("second"), s("")]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// List words
+// =============================================================================
+//
+// Positional updates that return a new list, leaving the original as it
+// was. Indexes count from 0, like `nth`; `insert-at` also accepts the
+// length, to insert at the end.
+//
+// The searching words compare items the way `=` does: values of different
+// types are never equal, so `1` and `1.0` are distinct, and a float NaN is
+// not equal even to itself.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("set-nth", set_nth),
+    ("insert-at", insert_at),
+    ("remove-at", remove_at),
+    ("update-at", update_at),
+    ("unique", unique),
+    ("contains?", contains),
+    ("index-of", index_of),
+];
+
+/// ( list i x -- list ) Replace the item at `i`.
+fn set_nth(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let index = vm.pop_int()?;
+    let mut list = vm.pop_list()?;
+    let i = check_index(vm, "set-nth", index, list.len(), false)?;
+    list[i] = value;
+    vm.push(Value::List(list));
+    Ok(())
+}
+
+/// ( list i x -- list ) Insert before the item at `i`.
+fn insert_at(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let index = vm.pop_int()?;
+    let mut list = vm.pop_list()?;
+    let i = check_index(vm, "insert-at", index, list.len(), true)?;
+    list.insert(i, value);
+    vm.push(Value::List(list));
+    Ok(())
+}
+
+/// ( list i -- list ) Drop the item at `i`.
+fn remove_at(vm: &mut VmBc) -> RuntimeResult<()> {
+    let index = vm.pop_int()?;
+    let mut list = vm.pop_list()?;
+    let i = check_index(vm, "remove-at", index, list.len(), false)?;
+    list.remove(i);
+    vm.push(Value::List(list));
+    Ok(())
+}
+
+/// ( list i quot -- list ) Replace the item at `i` with what `quot` makes
+/// of it.
+fn update_at(vm: &mut VmBc) -> RuntimeResult<()> {
+    let body = vm.pop_quotation_ops()?;
+    let index = vm.pop_int()?;
+    let mut list = vm.pop_list()?;
+    let i = check_index(vm, "update-at", index, list.len(), false)?;
+    let height = vm.stack().len();
+    vm.push(std::mem::replace(&mut list[i], Value::Bool(false)));
+    vm.exec_ops(&body)?;
+    vm.check_item_effect("update-at", "exactly one value", i, height)?;
+    list[i] = vm.pop()?;
+    vm.push(Value::List(list));
+    Ok(())
+}
+
+/// ( list -- list ) Drop repeated items, keeping the first of each.
+fn unique(vm: &mut VmBc) -> RuntimeResult<()> {
+    let list = vm.pop_list()?;
+    let mut kept: Vec<Value> = Vec::with_capacity(list.len());
+    for item in list {
+        if !kept.contains(&item) {
+            kept.push(item);
+        }
+    }
+    vm.push(Value::List(kept));
+    Ok(())
+}
+
+/// ( list x -- bool ) Whether `x` is an item of the list.
+fn contains(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let list = vm.pop_list()?;
+    vm.push(Value::Bool(list.contains(&value)));
+    Ok(())
+}
+
+/// ( list x -- i ) Index of the first item equal to `x`, or -1.
+fn index_of(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let list = vm.pop_list()?;
+    let index = list.iter().position(|item| *item == value);
+    vm.push(Value::Integer(index.map_or(-1, |i| i as i64)));
+    Ok(())
+}
+
+/// `index` as a position in a list of `len` items (or just past its end,
+/// if `allow_end`), or an out-of-bounds error.
+fn check_index(
+    vm: &VmBc,
+    word: &str,
+    index: i64,
+    len: usize,
+    allow_end: bool,
+) -> RuntimeResult<usize> {
+    match usize::try_from(index) {
+        Ok(i) if i < len || (allow_end && i == len) => Ok(i),
+        _ => Err(vm
+            .error_with_context(format!(
+                "{}: index {} out of bounds for list of length {}",
+                word, index, len
+            ))
+            .with_help("Indexes count from 0; check `len` first")
+            .boxed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    fn ints(ns: &[i64]) -> Value {
+        Value::List(ns.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    #[test]
+    fn test_positional_updates() {
+        assert_eq!(
+            run("{ 1 2 3 } 1 99 set-nth").unwrap(),
+            vec![ints(&[1, 99, 3])]
+        );
+        assert_eq!(
+            run("{ 1 2 } 0 0 insert-at { 1 2 } 2 3 insert-at").unwrap(),
+            vec![ints(&[0, 1, 2]), ints(&[1, 2, 3])]
+        );
+        assert_eq!(run("{ 1 2 3 } 0 remove-at").unwrap(), vec![ints(&[2, 3])]);
+        assert_eq!(
+            run("{ 1 2 3 } 2 [ 10 * ] update-at").unwrap(),
+            vec![ints(&[1, 2, 30])]
+        );
+        // The original list is unchanged
+        assert_eq!(
+            run("{ 1 2 } dup 0 5 set-nth drop").unwrap(),
+            vec![ints(&[1, 2])]
+        );
+    }
+
+    #[test]
+    fn test_positional_update_errors() {
+        assert_eq!(
+            run("{ 1 2 } 2 0 set-nth").unwrap_err(),
+            "set-nth: index 2 out of bounds for list of length 2"
+        );
+        assert_eq!(
+            run("{ 1 2 } 3 0 insert-at").unwrap_err(),
+            "insert-at: index 3 out of bounds for list of length 2"
+        );
+        assert!(run("{ } 0 remove-at").is_err());
+        assert!(run("{ 1 } -1 [ ] update-at").is_err());
+        let err = run("{ 1 } 0 [ dup ] update-at").unwrap_err();
+        assert!(
+            err.starts_with(
+                "update-at: the quotation must replace each item with exactly one value"
+            )
+        );
+    }
+
+    #[test]
+    fn test_searching() {
+        assert_eq!(run("{ 3 1 3 2 1 } unique").unwrap(), vec![ints(&[3, 1, 2])]);
+        assert_eq!(
+            run("{ 1 2 } 2 contains? { 1 2 } 2.0 contains? { \"a\" } \"a\" contains?").unwrap(),
+            vec![Value::Bool(true), Value::Bool(false), Value::Bool(true)]
+        );
+        assert_eq!(
+            run("{ 5 6 5 } 5 index-of { 5 6 } 7 index-of").unwrap(),
+            vec![Value::Integer(0), Value::Integer(-1)]
+        );
+    }
+}
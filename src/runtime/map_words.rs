@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Map words
+// =============================================================================
+//
+// Maps are written `#{ "name" "ann" "age" 36 }`, keys and values
+// alternating, and library words such as `run-process` return them too.
+// Keys are strings and are kept in sorted order, so `keys`, `values` and
+// printing are deterministic. Like the list words, the updating words
+// return a new map and leave the original as it was.
+//
+// The `-in` words follow a path into nested maps and lists: each step is a
+// string key of a map or an integer index of a list, so
+// `data { "users" 0 "name" } get-in` is the name of the first user. `set-in`
+// and `update-in` return the whole structure with one value replaced; the
+// last step of `set-in` may also add a new key to a map.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("get", get),
+    ("put", put),
+    ("remove", remove),
+    ("has-key?", has_key),
+    ("keys", keys),
+    ("values", values),
+    ("get-in", get_in),
+    ("set-in", set_in),
+    ("update-in", update_in),
+];
+
+/// ( map key -- value ) Look up a key, failing if it is absent.
+fn get(vm: &mut VmBc) -> RuntimeResult<()> {
+    let key = vm.pop_string()?;
+    let entries = pop_map(vm)?;
+    match entries.get(&key) {
+        Some(value) => {
+            vm.push(value.clone());
+            Ok(())
+        }
+        None => {
+            let known: Vec<&str> = entries.keys().map(String::as_str).collect();
+            Err(vm
+                .error_with_context(format!("get: key '{}' not found", key))
+                .with_help(format!("Available keys: {}", known.join(", ")))
+                .boxed())
+        }
+    }
+}
+
+/// ( map key value -- map ) Add a key, or replace its value.
+fn put(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let key = vm.pop_string()?;
+    let mut entries = pop_map(vm)?;
+    entries.insert(key, value);
+    vm.push(Value::Map(entries));
+    Ok(())
+}
+
+/// ( map key -- map ) Drop a key, if it is present.
+fn remove(vm: &mut VmBc) -> RuntimeResult<()> {
+    let key = vm.pop_string()?;
+    let mut entries = pop_map(vm)?;
+    entries.remove(&key);
+    vm.push(Value::Map(entries));
+    Ok(())
+}
+
+/// ( map key -- bool ) Whether the map has a key.
+fn has_key(vm: &mut VmBc) -> RuntimeResult<()> {
+    let key = vm.pop_string()?;
+    let entries = pop_map(vm)?;
+    vm.push(Value::Bool(entries.contains_key(&key)));
+    Ok(())
+}
+
+/// ( map -- keys ) The keys, sorted.
+fn keys(vm: &mut VmBc) -> RuntimeResult<()> {
+    let entries = pop_map(vm)?;
+    vm.push(Value::List(
+        entries.into_keys().map(Value::String).collect(),
+    ));
+    Ok(())
+}
+
+/// ( map -- values ) The values, in the order of their keys.
+fn values(vm: &mut VmBc) -> RuntimeResult<()> {
+    let entries = pop_map(vm)?;
+    vm.push(Value::List(entries.into_values().collect()));
+    Ok(())
+}
+
+fn pop_map(vm: &mut VmBc) -> RuntimeResult<BTreeMap<String, Value>> {
+    match vm.pop()? {
+        Value::Map(entries) => Ok(entries),
+        other => Err(vm.type_error_with_context("map", other.type_name())),
+    }
+}
+
+/// ( data path -- value ) The value at the end of a path.
+fn get_in(vm: &mut VmBc) -> RuntimeResult<()> {
+    let path = vm.pop_list()?;
+    let data = vm.pop()?;
+    let value = lookup(&data, &path)
+        .map_err(|e| path_error(vm, "get-in", e))?
+        .clone();
+    vm.push(value);
+    Ok(())
+}
+
+/// ( data path value -- data ) Replace the value at the end of a path.
+fn set_in(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let path = vm.pop_list()?;
+    let mut data = vm.pop()?;
+    replace(&mut data, &path, value).map_err(|e| path_error(vm, "set-in", e))?;
+    vm.push(data);
+    Ok(())
+}
+
+/// ( data path quot -- data ) Replace the value at the end of a path with
+/// what `quot` makes of it.
+fn update_in(vm: &mut VmBc) -> RuntimeResult<()> {
+    let body = vm.pop_quotation_ops()?;
+    let path = vm.pop_list()?;
+    let mut data = vm.pop()?;
+    let old = lookup(&data, &path)
+        .map_err(|e| path_error(vm, "update-in", e))?
+        .clone();
+    let height = vm.stack().len();
+    vm.push(old);
+    vm.exec_ops(&body)?;
+    vm.check_item_effect("update-in", "exactly one value", 0, height)?;
+    let new = vm.pop()?;
+    replace(&mut data, &path, new).map_err(|e| path_error(vm, "update-in", e))?;
+    vm.push(data);
+    Ok(())
+}
+
+/// The value `path` leads to from `data`.
+fn lookup<'a>(data: &'a Value, path: &[Value]) -> Result<&'a Value, String> {
+    path.iter()
+        .enumerate()
+        .try_fold(data, |value, (i, step)| child(value, step, i))
+}
+
+/// Put `value` where `path` leads from `data`; the last step may add a key
+/// to a map.
+fn replace(data: &mut Value, path: &[Value], value: Value) -> Result<(), String> {
+    let Some((last, init)) = path.split_last() else {
+        *data = value;
+        return Ok(());
+    };
+    let mut target = data;
+    for (i, step) in init.iter().enumerate() {
+        target = child_mut(target, step, i)?;
+    }
+    match (target, last) {
+        (Value::Map(entries), Value::String(key)) => {
+            entries.insert(key.clone(), value);
+        }
+        (target, step) => *child_mut(target, step, init.len())? = value,
+    }
+    Ok(())
+}
+
+fn child<'a>(value: &'a Value, step: &Value, i: usize) -> Result<&'a Value, String> {
+    match (value, step) {
+        (Value::Map(entries), Value::String(key)) => entries
+            .get(key)
+            .ok_or_else(|| format!("step {}: key '{}' not found", i, key)),
+        (Value::List(items), Value::Integer(index)) => usize::try_from(*index)
+            .ok()
+            .and_then(|n| items.get(n))
+            .ok_or_else(|| out_of_bounds(i, *index, items.len())),
+        (value, step) => Err(wrong_step(i, value, step)),
+    }
+}
+
+fn child_mut<'a>(value: &'a mut Value, step: &Value, i: usize) -> Result<&'a mut Value, String> {
+    match (value, step) {
+        (Value::Map(entries), Value::String(key)) => entries
+            .get_mut(key)
+            .ok_or_else(|| format!("step {}: key '{}' not found", i, key)),
+        (Value::List(items), Value::Integer(index)) => {
+            let len = items.len();
+            usize::try_from(*index)
+                .ok()
+                .and_then(|n| items.get_mut(n))
+                .ok_or_else(|| out_of_bounds(i, *index, len))
+        }
+        (value, step) => Err(wrong_step(i, value, step)),
+    }
+}
+
+fn out_of_bounds(i: usize, index: i64, len: usize) -> String {
+    format!(
+        "step {}: index {} out of bounds for list of length {}",
+        i, index, len
+    )
+}
+
+fn wrong_step(i: usize, value: &Value, step: &Value) -> String {
+    match value {
+        Value::Map(_) | Value::List(_) => format!(
+            "step {}: a {} is indexed by {}, not {}",
+            i,
+            value.type_name(),
+            if matches!(value, Value::Map(_)) {
+                "string keys"
+            } else {
+                "integers"
+            },
+            step.type_name()
+        ),
+        _ => format!("step {}: can't look inside {}", i, value.type_name()),
+    }
+}
+
+fn path_error(vm: &VmBc, word: &str, message: String) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: {}", word, message))
+        .with_help("Path steps are string keys for maps and indexes from 0 for lists")
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    fn ints(ns: &[i64]) -> Value {
+        Value::List(ns.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_map_words() {
+        let m = r#"#{ "b" 2 "a" 1 }"#;
+        assert_eq!(
+            run(&format!(r#"{} "c" 3 put "b" remove"#, m)).unwrap(),
+            vec![map(&[("a", Value::Integer(1)), ("c", Value::Integer(3))])]
+        );
+        assert_eq!(
+            run(&format!(r#"{} keys {} values"#, m, m)).unwrap(),
+            vec![
+                Value::List(vec![Value::String("a".into()), Value::String("b".into())]),
+                ints(&[1, 2])
+            ]
+        );
+        assert_eq!(
+            run(&format!(r#"{} "a" has-key? {} "z" has-key?"#, m, m)).unwrap(),
+            vec![Value::Bool(true), Value::Bool(false)]
+        );
+        // The original map is unchanged
+        assert_eq!(
+            run(&format!(r#"{} dup "a" 9 put drop "a" get"#, m)).unwrap(),
+            vec![Value::Integer(1)]
+        );
+        assert_eq!(
+            run(r#"#{ "a" 1 } "b" get"#).unwrap_err(),
+            "get: key 'b' not found"
+        );
+        assert!(run("{ } keys").unwrap_err().contains("map"));
+    }
+
+    #[test]
+    fn test_paths_into_lists() {
+        let data = "{ { 1 2 } { 3 { 4 5 } } }";
+        assert_eq!(
+            run(&format!("{} {{ 1 1 0 }} get-in", data)).unwrap(),
+            vec![Value::Integer(4)]
+        );
+        assert_eq!(
+            run(&format!("{} {{ 0 }} 9 set-in {{ 0 }} get-in", data)).unwrap(),
+            vec![Value::Integer(9)]
+        );
+        assert_eq!(
+            run(&format!(
+                "{} {{ 1 1 1 }} [ 10 + ] update-in {{ 1 1 }} get-in",
+                data
+            ))
+            .unwrap(),
+            vec![ints(&[4, 15])]
+        );
+        // An empty path is the whole value
+        assert_eq!(run("7 { } get-in").unwrap(), vec![Value::Integer(7)]);
+    }
+
+    #[test]
+    fn test_paths_into_maps() {
+        let data = r#"#{ "users" { #{ "name" "ann" } } }"#;
+        assert_eq!(
+            run(&format!(r#"{} {{ "users" 0 "name" }} get-in"#, data)).unwrap(),
+            vec![Value::String("ann".into())]
+        );
+        assert_eq!(
+            run(&format!(
+                r#"{} {{ "users" 0 "age" }} 36 set-in {{ "users" 0 "age" }} get-in"#,
+                data
+            ))
+            .unwrap(),
+            vec![Value::Integer(36)]
+        );
+    }
+
+    #[test]
+    fn test_path_errors() {
+        assert_eq!(
+            run("{ 1 { 2 } } { 1 3 } get-in").unwrap_err(),
+            "get-in: step 1: index 3 out of bounds for list of length 1"
+        );
+        assert_eq!(
+            run("{ 1 } { 0 0 } 5 set-in").unwrap_err(),
+            "set-in: step 1: can't look inside integer"
+        );
+        assert_eq!(
+            run("{ 1 } { \"a\" } get-in").unwrap_err(),
+            "get-in: step 0: a list is indexed by integers, not string"
+        );
+    }
+}
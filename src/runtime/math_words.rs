@@ -0,0 +1,402 @@
+use std::cmp::Ordering;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Range, extremum and division words
+// =============================================================================
+//
+// `range` counts up from `start` to just before `end`; `range-step` counts
+// by any nonzero step, down too, and `range-incl` includes `end`. A range
+// that ends where it starts is empty (`range-incl` then has the one item),
+// and one whose end lies behind its start, for the direction it counts in,
+// is an error rather than silently empty.
+//
+// Integers and floats mix as they do in arithmetic: two integers compare and
+// stay integral, and anything involving a float compares and results as a
+// float. Anything else is an error rather than an arbitrary cross-type order.
+//
+// `/` and `%` truncate like Rust: `-7 2 /` is -3 and `-7 2 %` is -1. The
+// integer division words round the other ways. `floor-div` rounds down and
+// `ceil-div` up; `divmod` floors, so its remainder takes the divisor's sign
+// and `q * b + r = a` always holds. For a positive divisor that is also the
+// Euclidean division (remainder never negative).
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("between?", between),
+    ("clamp", clamp),
+    ("minimum", minimum),
+    ("maximum", maximum),
+    ("range-step", range_step),
+    ("range-incl", range_incl),
+    ("divmod", divmod),
+    ("floor-div", floor_div),
+    ("ceil-div", ceil_div),
+];
+
+/// ( x lo hi -- bool ) Whether `lo <= x <= hi`. Always false when `lo > hi`.
+fn between(vm: &mut VmBc) -> RuntimeResult<()> {
+    let hi = pop_number(vm, "between?")?;
+    let lo = pop_number(vm, "between?")?;
+    let x = pop_number(vm, "between?")?;
+    let inside = compare_numbers(&lo, &x).is_some_and(Ordering::is_le)
+        && compare_numbers(&x, &hi).is_some_and(Ordering::is_le);
+    vm.push(Value::Bool(inside));
+    Ok(())
+}
+
+/// ( x lo hi -- y ) `x` limited to `lo..=hi`.
+fn clamp(vm: &mut VmBc) -> RuntimeResult<()> {
+    let hi = pop_number(vm, "clamp")?;
+    let lo = pop_number(vm, "clamp")?;
+    let x = pop_number(vm, "clamp")?;
+    if !compare_numbers(&lo, &hi).is_some_and(Ordering::is_le) {
+        return Err(vm
+            .error_with_context(format!(
+                "clamp: lower bound {} is not at most upper bound {}",
+                lo, hi
+            ))
+            .boxed());
+    }
+
+    let any_float = [&x, &lo, &hi].iter().any(|v| matches!(v, Value::Float(_)));
+    let limited = if compare_numbers(&x, &lo) == Some(Ordering::Less) {
+        lo
+    } else if compare_numbers(&x, &hi) == Some(Ordering::Greater) {
+        hi
+    } else {
+        x
+    };
+    vm.push(match limited {
+        Value::Integer(n) if any_float => Value::Float(n as f64),
+        other => other,
+    });
+    Ok(())
+}
+
+/// ( list -- x ) The smallest number in a non-empty list.
+fn minimum(vm: &mut VmBc) -> RuntimeResult<()> {
+    extremum(vm, "minimum", Ordering::Less)
+}
+
+/// ( list -- x ) The largest number in a non-empty list.
+fn maximum(vm: &mut VmBc) -> RuntimeResult<()> {
+    extremum(vm, "maximum", Ordering::Greater)
+}
+
+fn extremum(vm: &mut VmBc, word: &str, prefer: Ordering) -> RuntimeResult<()> {
+    let items = vm.pop_list()?;
+    let mut best: Option<Value> = None;
+    for item in items {
+        if !matches!(item, Value::Integer(_) | Value::Float(_)) {
+            return Err(vm
+                .error_with_context(format!(
+                    "{}: expected a list of numbers, found {}",
+                    word,
+                    item.type_name()
+                ))
+                .boxed());
+        }
+        best = Some(match best {
+            Some(current) => pick_number(current, item, prefer),
+            None => item,
+        });
+    }
+    let best = best.ok_or_else(|| {
+        vm.error_with_context(format!("{}: empty list", word))
+            .with_help("There is no smallest or largest of nothing; check `len` first")
+            .boxed()
+    })?;
+    vm.push(best);
+    Ok(())
+}
+
+/// ( start end step -- list ) Integers from `start` toward `end`, not
+/// including it, `step` apart.
+fn range_step(vm: &mut VmBc) -> RuntimeResult<()> {
+    let step = pop_integer(vm, "range-step")?;
+    let end = pop_integer(vm, "range-step")?;
+    let start = pop_integer(vm, "range-step")?;
+    if step == 0 {
+        return Err(vm
+            .error_with_context("range-step: step cannot be 0")
+            .with_help("Use a positive step to count up and a negative one to count down")
+            .boxed());
+    }
+    if (step > 0 && start > end) || (step < 0 && start < end) {
+        return Err(vm
+            .error_with_context(format!(
+                "range-step: counting {} from {} never reaches {}",
+                if step > 0 { "up" } else { "down" },
+                start,
+                end
+            ))
+            .with_help("Give a negative step to count down, e.g. 5 0 -1 range-step")
+            .boxed());
+    }
+    let items = std::iter::successors(Some(start), |n| n.checked_add(step))
+        .take_while(|&n| if step > 0 { n < end } else { n > end })
+        .map(Value::Integer)
+        .collect();
+    vm.push(Value::List(items));
+    Ok(())
+}
+
+/// ( start end -- list ) Integers from `start` up to and including `end`.
+fn range_incl(vm: &mut VmBc) -> RuntimeResult<()> {
+    let end = pop_integer(vm, "range-incl")?;
+    let start = pop_integer(vm, "range-incl")?;
+    if start > end {
+        return Err(vm
+            .error_with_context(format!(
+                "range-incl: start ({}) cannot be greater than end ({})",
+                start, end
+            ))
+            .with_help("Count down with range-step and a negative step")
+            .boxed());
+    }
+    vm.push(Value::List((start..=end).map(Value::Integer).collect()));
+    Ok(())
+}
+
+/// ( a b -- pair ) Floored quotient and remainder: `{ q r }`.
+fn divmod(vm: &mut VmBc) -> RuntimeResult<()> {
+    let (a, b) = pop_division(vm, "divmod")?;
+    let q = floor_quotient(a, b).ok_or_else(|| vm.integer_overflow("divmod"))?;
+    // Can't overflow once the quotient hasn't
+    let r = a - q * b;
+    vm.push(Value::List(vec![Value::Integer(q), Value::Integer(r)]));
+    Ok(())
+}
+
+/// ( a b -- q ) `a / b` rounded toward negative infinity.
+fn floor_div(vm: &mut VmBc) -> RuntimeResult<()> {
+    let (a, b) = pop_division(vm, "floor-div")?;
+    let q = floor_quotient(a, b).ok_or_else(|| vm.integer_overflow("floor-div"))?;
+    vm.push(Value::Integer(q));
+    Ok(())
+}
+
+/// ( a b -- q ) `a / b` rounded toward positive infinity.
+fn ceil_div(vm: &mut VmBc) -> RuntimeResult<()> {
+    let (a, b) = pop_division(vm, "ceil-div")?;
+    let q = a
+        .checked_div(b)
+        .map(|q| {
+            if a % b != 0 && (a < 0) == (b < 0) {
+                q + 1
+            } else {
+                q
+            }
+        })
+        .ok_or_else(|| vm.integer_overflow("ceil-div"))?;
+    vm.push(Value::Integer(q));
+    Ok(())
+}
+
+/// `a / b` rounded down, or None on overflow. `b` is not zero.
+fn floor_quotient(a: i64, b: i64) -> Option<i64> {
+    let q = a.checked_div(b)?;
+    Some(if a % b != 0 && (a < 0) != (b < 0) {
+        q - 1
+    } else {
+        q
+    })
+}
+
+/// Pop an integer dividend and a nonzero integer divisor.
+fn pop_division(vm: &mut VmBc, word: &str) -> RuntimeResult<(i64, i64)> {
+    let b = pop_integer(vm, word)?;
+    let a = pop_integer(vm, word)?;
+    if b == 0 {
+        return Err(vm
+            .error_with_context(format!("{}: division by zero", word))
+            .with_help("Check that the divisor is not zero before dividing")
+            .boxed());
+    }
+    Ok((a, b))
+}
+
+fn pop_integer(vm: &mut VmBc, word: &str) -> RuntimeResult<i64> {
+    match vm.pop()? {
+        Value::Integer(n) => Ok(n),
+        other => Err(vm
+            .error_with_context(format!(
+                "{}: expected an integer, got {}",
+                word,
+                other.type_name()
+            ))
+            .with_help("These words divide integers; convert floats with to-int first")
+            .boxed()),
+    }
+}
+
+/// The number `prefer`red over the other (`Less` for min, `Greater` for
+/// max), keeping `a` on ties. NaN on either side gives NaN.
+pub(crate) fn pick_number(a: Value, b: Value, prefer: Ordering) -> Value {
+    let any_float = matches!(a, Value::Float(_)) || matches!(b, Value::Float(_));
+    let chosen = match compare_numbers(&a, &b) {
+        None => return Value::Float(f64::NAN),
+        Some(order) if order == prefer.reverse() => b,
+        Some(_) => a,
+    };
+    match chosen {
+        Value::Integer(n) if any_float => Value::Float(n as f64),
+        other => other,
+    }
+}
+
+/// Order two numbers: integers exactly, anything else as floats. None when
+/// either is NaN.
+pub(crate) fn compare_numbers(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(x), Value::Integer(y)) => Some(x.cmp(y)),
+        _ => as_f64(a)?.partial_cmp(&as_f64(b)?),
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(n) => Some(*n as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// Pop an integer or float, naming `word` if it is anything else.
+pub(crate) fn pop_number(vm: &mut VmBc, word: &str) -> RuntimeResult<Value> {
+    match vm.pop()? {
+        number @ (Value::Integer(_) | Value::Float(_)) => Ok(number),
+        other => Err(vm
+            .error_with_context(format!(
+                "{}: expected a number, got {}",
+                word,
+                other.type_name()
+            ))
+            .boxed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    #[test]
+    fn test_between() {
+        let flags = run(concat!(
+            "5 1 10 between? 1 1 10 between? 10 1 10 between? 11 1 10 between? ",
+            "2.5 2 3 between? 3 2.5 2.9 between? 5 10 1 between?"
+        ))
+        .unwrap();
+        let expected = [true, true, true, false, true, false, false];
+        assert_eq!(
+            flags,
+            expected.map(Value::Bool).to_vec(),
+            "inclusive bounds, mixed types, empty range"
+        );
+    }
+
+    #[test]
+    fn test_clamp() {
+        assert_eq!(
+            run("15 0 10 clamp -3 0 10 clamp 7 0 10 clamp").unwrap(),
+            vec![Value::Integer(10), Value::Integer(0), Value::Integer(7)]
+        );
+        // Any float bound or value makes the result a float
+        assert_eq!(
+            run("5 0 2.5 clamp 1 0 2.5 clamp 0.5 0 1 clamp").unwrap(),
+            vec![Value::Float(2.5), Value::Float(1.0), Value::Float(0.5)]
+        );
+    }
+
+    #[test]
+    fn test_minimum_maximum() {
+        assert_eq!(
+            run("{ 3 1 2 } minimum { 3 1 2 } maximum { 4 } minimum").unwrap(),
+            vec![Value::Integer(1), Value::Integer(3), Value::Integer(4)]
+        );
+        assert_eq!(
+            run("{ 3 1.5 2 } minimum { 3 1.5 2 } maximum").unwrap(),
+            vec![Value::Float(1.5), Value::Float(3.0)]
+        );
+        let err = run("{ } maximum").unwrap_err();
+        assert!(err.contains("maximum: empty list"), "{}", err);
+        let err = run("{ 1 \"2\" } minimum").unwrap_err();
+        assert!(
+            err.contains("minimum: expected a list of numbers, found string"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_clamp_errors() {
+        let err = run("5 10 1 clamp").unwrap_err();
+        assert!(
+            err.contains("lower bound 10 is not at most upper bound 1"),
+            "{}",
+            err
+        );
+        let err = run("\"5\" 0 10 clamp").unwrap_err();
+        assert!(
+            err.contains("clamp: expected a number, got string"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_integer_division() {
+        let pair = |q, r| Value::List(vec![Value::Integer(q), Value::Integer(r)]);
+        assert_eq!(
+            run("7 2 divmod -7 2 divmod 7 -2 divmod -7 -2 divmod").unwrap(),
+            vec![pair(3, 1), pair(-4, 1), pair(-4, -1), pair(3, -1)]
+        );
+        assert_eq!(
+            run("-7 2 floor-div -7 2 ceil-div 7 2 ceil-div 6 3 ceil-div -6 -4 ceil-div").unwrap(),
+            [-4, -3, 4, 2, 2].map(Value::Integer).to_vec()
+        );
+
+        assert_eq!(run("1 0 divmod").unwrap_err(), "divmod: division by zero");
+        let err = run("-9223372036854775807 1 - -1 floor-div").unwrap_err();
+        assert_eq!(err, "floor-div: integer overflow");
+        assert!(run("1.5 1 ceil-div").is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        let ints = |ns: &[i64]| Value::List(ns.iter().map(|&n| Value::Integer(n)).collect());
+        assert_eq!(
+            run(
+                "0 10 3 range-step 5 0 -2 range-step 4 4 1 range-step 1 3 range-incl 2 2 range-incl"
+            )
+            .unwrap(),
+            vec![
+                ints(&[0, 3, 6, 9]),
+                ints(&[5, 3, 1]),
+                ints(&[]),
+                ints(&[1, 2, 3]),
+                ints(&[2]),
+            ]
+        );
+        // Stops rather than overflowing
+        assert_eq!(
+            run("9223372036854775806 9223372036854775807 5 range-step").unwrap(),
+            vec![ints(&[9223372036854775806])]
+        );
+
+        assert_eq!(
+            run("0 5 0 range-step").unwrap_err(),
+            "range-step: step cannot be 0"
+        );
+        assert_eq!(
+            run("5 0 1 range-step").unwrap_err(),
+            "range-step: counting up from 5 never reaches 0"
+        );
+        assert!(run("3 1 range-incl").is_err());
+    }
+}
@@ -1,2 +1,43 @@
+pub mod bytes;
+pub mod checkpoint;
+pub mod color_words;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+pub mod config_words;
+#[cfg(feature = "desktop")]
+pub mod desktop_words;
+pub mod explain;
+pub mod file_words;
+pub mod handler_words;
+pub mod hash_words;
+pub mod help_words;
+pub mod io_words;
+pub mod list_words;
+pub mod map_words;
+pub mod math_words;
+pub mod native;
+#[cfg(feature = "net")]
+pub mod net_words;
+pub mod pair_words;
+pub mod path_words;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod process_words;
+pub mod progress_words;
+pub mod prompt_words;
+pub mod pure;
+pub mod reflect_words;
+#[cfg(feature = "regex")]
+pub mod regex_words;
+pub mod replay;
 pub mod runtime_error;
+pub mod sequence;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_words;
+pub mod store_words;
+pub mod string_words;
+pub mod table_words;
+#[cfg(feature = "concurrency")]
+pub mod task_words;
+#[cfg(test)]
+mod test_support;
 pub mod vm_bc;
@@ -0,0 +1,116 @@
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Native words
+// =============================================================================
+//
+// Words implemented directly in Rust rather than as dedicated opcodes. They
+// compile to an ordinary `CallWord` and are resolved at call time, after
+// user-defined words, so a script can still shadow any of them. This keeps
+// optional, library-backed words (behind cargo features) out of the core
+// token/op tables.
+
+/// A native word operates directly on the VM's stack.
+pub type NativeFn = fn(&mut VmBc) -> RuntimeResult<()>;
+
+/// Every enabled group of native words.
+const GROUPS: &[&[(&str, NativeFn)]] = &[
+    crate::runtime::bytes::WORDS,
+    crate::runtime::checkpoint::WORDS,
+    crate::runtime::color_words::WORDS,
+    #[cfg(feature = "toml")]
+    crate::runtime::config_words::TOML_WORDS,
+    #[cfg(feature = "yaml")]
+    crate::runtime::config_words::YAML_WORDS,
+    #[cfg(feature = "desktop")]
+    crate::runtime::desktop_words::WORDS,
+    crate::runtime::file_words::WORDS,
+    crate::runtime::handler_words::WORDS,
+    crate::runtime::hash_words::WORDS,
+    #[cfg(feature = "sha256")]
+    crate::runtime::hash_words::SHA256_WORDS,
+    crate::runtime::help_words::WORDS,
+    crate::runtime::io_words::WORDS,
+    crate::runtime::list_words::WORDS,
+    crate::runtime::map_words::WORDS,
+    crate::runtime::math_words::WORDS,
+    crate::runtime::pair_words::WORDS,
+    crate::runtime::path_words::WORDS,
+    crate::runtime::process_words::WORDS,
+    crate::runtime::progress_words::WORDS,
+    crate::runtime::prompt_words::WORDS,
+    #[cfg(feature = "password")]
+    crate::runtime::prompt_words::PASSWORD_WORDS,
+    crate::runtime::reflect_words::WORDS,
+    crate::runtime::sequence::WORDS,
+    crate::runtime::store_words::WORDS,
+    crate::runtime::string_words::WORDS,
+    crate::runtime::table_words::WORDS,
+    #[cfg(feature = "concurrency")]
+    crate::runtime::task_words::WORDS,
+    #[cfg(feature = "net")]
+    crate::runtime::net_words::WORDS,
+    #[cfg(feature = "regex")]
+    crate::runtime::regex_words::WORDS,
+    #[cfg(feature = "sqlite")]
+    crate::runtime::sqlite_words::WORDS,
+];
+
+/// Find a native word by name.
+pub fn lookup(name: &str) -> Option<NativeFn> {
+    GROUPS
+        .iter()
+        .flat_map(|group| group.iter())
+        .find(|(word, _)| *word == name)
+        .map(|(_, f)| *f)
+}
+
+/// Names of every enabled native word.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    GROUPS
+        .iter()
+        .flat_map(|group| group.iter())
+        .map(|(word, _)| *word)
+}
+
+/// Native words that need a sandbox permission, with the `VmBcConfig` flag
+/// that allows them. Words that only use a handle one of these returned
+/// (e.g. `socket-send`) aren't listed.
+pub const CAPABILITIES: &[(&str, &str)] = &[
+    ("canonicalize", "allow_fs"),
+    ("read-file", "allow_fs"),
+    ("write-file", "allow_fs"),
+    ("append-file", "allow_fs"),
+    ("file-exists?", "allow_fs"),
+    ("delete-file", "allow_fs"),
+    ("glob", "allow_fs"),
+    ("store-load", "allow_fs"),
+    ("store-save", "allow_fs"),
+    ("run-process", "allow_process"),
+    ("run-process-timeout", "allow_process"),
+    ("clipboard-get", "allow_process"),
+    ("clipboard-set", "allow_process"),
+    ("open-url", "allow_process"),
+    ("list->quot", "allow_compile"),
+    ("eval", "allow_compile"),
+    ("spawn", "allow_spawn"),
+    ("tcp-connect", "allow_net"),
+    ("tcp-listen", "allow_net"),
+    ("udp-bind", "allow_net"),
+    ("sqlite-open", "allow_fs"),
+    ("stdin-lines", "allow_io"),
+    ("each-line", "allow_io"),
+    ("prompt", "allow_io"),
+    ("confirm", "allow_io"),
+    ("read-password", "allow_io"),
+    ("print-table", "allow_io"),
+];
+
+/// The sandbox flag a native word needs, if any.
+pub fn capability(name: &str) -> Option<&'static str> {
+    CAPABILITIES
+        .iter()
+        .find(|(word, _)| *word == name)
+        .map(|(_, flag)| *flag)
+}
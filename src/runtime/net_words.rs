@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Network words (feature = "net")
+// =============================================================================
+//
+// Sockets live in a table on the VM and scripts hold them as integer handles.
+// Data is sent and received as strings; received bytes that are not valid
+// UTF-8 are replaced. Every word that opens a socket is refused when the VM's
+// `allow_net` flag is off. Handles stay open until `socket-close` or until
+// the VM is dropped.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("tcp-connect", tcp_connect),
+    ("tcp-listen", tcp_listen),
+    ("accept", accept),
+    ("tcp-send", tcp_send),
+    ("tcp-recv", tcp_recv),
+    ("udp-bind", udp_bind),
+    ("udp-send", udp_send),
+    ("udp-recv", udp_recv),
+    ("socket-addr", socket_addr),
+    ("socket-close", socket_close),
+];
+
+/// Largest chunk returned by a single `tcp-recv` / `udp-recv`.
+const RECV_BUFFER: usize = 64 * 1024;
+
+enum Socket {
+    Stream(TcpStream),
+    Listener(TcpListener),
+    Datagram(UdpSocket),
+}
+
+impl Socket {
+    fn kind(&self) -> &'static str {
+        match self {
+            Socket::Stream(_) => "TCP connection",
+            Socket::Listener(_) => "TCP listener",
+            Socket::Datagram(_) => "UDP socket",
+        }
+    }
+}
+
+/// Open sockets, keyed by the handle given to the script.
+#[derive(Default)]
+pub struct Sockets {
+    next_handle: i64,
+    open: HashMap<i64, Socket>,
+}
+
+impl Sockets {
+    fn insert(&mut self, socket: Socket) -> Value {
+        self.next_handle += 1;
+        self.open.insert(self.next_handle, socket);
+        Value::Integer(self.next_handle)
+    }
+}
+
+/// ( addr -- conn ) Connect to `"host:port"`.
+fn tcp_connect(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_net("tcp-connect")?;
+    let addr = vm.pop_string()?;
+    let stream = TcpStream::connect(&addr).map_err(|e| io_error(vm, "tcp-connect", &addr, e))?;
+    let handle = vm.sockets.insert(Socket::Stream(stream));
+    vm.push(handle);
+    Ok(())
+}
+
+/// ( addr -- listener ) Listen on `"host:port"`; port 0 picks a free port.
+fn tcp_listen(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_net("tcp-listen")?;
+    let addr = vm.pop_string()?;
+    let listener = TcpListener::bind(&addr).map_err(|e| io_error(vm, "tcp-listen", &addr, e))?;
+    let handle = vm.sockets.insert(Socket::Listener(listener));
+    vm.push(handle);
+    Ok(())
+}
+
+/// ( listener -- conn ) Block until a client connects.
+fn accept(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let accepted = match socket(vm, "accept", handle)? {
+        Socket::Listener(listener) => listener.accept().map(|(stream, _)| stream),
+        other => {
+            let kind = other.kind();
+            return Err(wrong_kind(vm, "accept", handle, kind, "TCP listener"));
+        }
+    };
+    let stream = accepted.map_err(|e| io_error(vm, "accept", &handle.to_string(), e))?;
+    let conn = vm.sockets.insert(Socket::Stream(stream));
+    vm.push(conn);
+    Ok(())
+}
+
+/// ( conn data -- ) Write all of `data`.
+fn tcp_send(vm: &mut VmBc) -> RuntimeResult<()> {
+    let data = vm.pop_string()?;
+    let handle = vm.pop_int()?;
+    let sent = match socket(vm, "tcp-send", handle)? {
+        Socket::Stream(stream) => stream.write_all(data.as_bytes()),
+        other => {
+            let kind = other.kind();
+            return Err(wrong_kind(vm, "tcp-send", handle, kind, "TCP connection"));
+        }
+    };
+    sent.map_err(|e| io_error(vm, "tcp-send", &handle.to_string(), e))
+}
+
+/// ( conn -- data ) Read whatever is available, blocking until something
+/// arrives. Returns `""` once the peer has closed the connection.
+fn tcp_recv(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let mut buf = vec![0u8; RECV_BUFFER];
+    let read = match socket(vm, "tcp-recv", handle)? {
+        Socket::Stream(stream) => stream.read(&mut buf),
+        other => {
+            let kind = other.kind();
+            return Err(wrong_kind(vm, "tcp-recv", handle, kind, "TCP connection"));
+        }
+    };
+    let n = read.map_err(|e| io_error(vm, "tcp-recv", &handle.to_string(), e))?;
+    vm.push(Value::String(
+        String::from_utf8_lossy(&buf[..n]).into_owned(),
+    ));
+    Ok(())
+}
+
+/// ( addr -- sock ) Bind a UDP socket to `"host:port"`.
+fn udp_bind(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_net("udp-bind")?;
+    let addr = vm.pop_string()?;
+    let sock = UdpSocket::bind(&addr).map_err(|e| io_error(vm, "udp-bind", &addr, e))?;
+    let handle = vm.sockets.insert(Socket::Datagram(sock));
+    vm.push(handle);
+    Ok(())
+}
+
+/// ( sock data addr -- ) Send one datagram to `"host:port"`.
+fn udp_send(vm: &mut VmBc) -> RuntimeResult<()> {
+    let addr = vm.pop_string()?;
+    let data = vm.pop_string()?;
+    let handle = vm.pop_int()?;
+    let sent = match socket(vm, "udp-send", handle)? {
+        Socket::Datagram(sock) => sock.send_to(data.as_bytes(), &addr),
+        other => {
+            let kind = other.kind();
+            return Err(wrong_kind(vm, "udp-send", handle, kind, "UDP socket"));
+        }
+    };
+    sent.map(|_| ())
+        .map_err(|e| io_error(vm, "udp-send", &addr, e))
+}
+
+/// ( sock -- data from ) Block until a datagram arrives.
+fn udp_recv(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let mut buf = vec![0u8; RECV_BUFFER];
+    let received = match socket(vm, "udp-recv", handle)? {
+        Socket::Datagram(sock) => sock.recv_from(&mut buf),
+        other => {
+            let kind = other.kind();
+            return Err(wrong_kind(vm, "udp-recv", handle, kind, "UDP socket"));
+        }
+    };
+    let (n, from) = received.map_err(|e| io_error(vm, "udp-recv", &handle.to_string(), e))?;
+    vm.push(Value::String(
+        String::from_utf8_lossy(&buf[..n]).into_owned(),
+    ));
+    vm.push(Value::String(from.to_string()));
+    Ok(())
+}
+
+/// ( handle -- addr ) Local `"host:port"` of any socket, e.g. to learn the
+/// port picked for `"127.0.0.1:0"`.
+fn socket_addr(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let addr: io::Result<SocketAddr> = match socket(vm, "socket-addr", handle)? {
+        Socket::Stream(stream) => stream.local_addr(),
+        Socket::Listener(listener) => listener.local_addr(),
+        Socket::Datagram(sock) => sock.local_addr(),
+    };
+    let addr = addr.map_err(|e| io_error(vm, "socket-addr", &handle.to_string(), e))?;
+    vm.push(Value::String(addr.to_string()));
+    Ok(())
+}
+
+/// ( handle -- ) Close a socket. Closing an unknown handle is an error.
+fn socket_close(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    if vm.sockets.open.remove(&handle).is_none() {
+        return Err(unknown_handle(vm, "socket-close", handle));
+    }
+    Ok(())
+}
+
+fn socket<'a>(vm: &'a mut VmBc, word: &str, handle: i64) -> RuntimeResult<&'a mut Socket> {
+    if !vm.sockets.open.contains_key(&handle) {
+        return Err(unknown_handle(vm, word, handle));
+    }
+    Ok(vm.sockets.open.get_mut(&handle).expect("checked above"))
+}
+
+fn unknown_handle(vm: &VmBc, word: &str, handle: i64) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: {} is not an open socket", word, handle))
+        .with_help("Socket handles come from tcp-connect, tcp-listen, accept or udp-bind")
+        .boxed()
+}
+
+fn wrong_kind(vm: &VmBc, word: &str, handle: i64, kind: &str, expected: &str) -> Box<RuntimeError> {
+    vm.error_with_context(format!(
+        "{}: socket {} is a {}, expected a {}",
+        word, handle, kind, expected
+    ))
+    .boxed()
+}
+
+fn io_error(vm: &VmBc, word: &str, target: &str, e: io::Error) -> Box<RuntimeError> {
+    let help = match e.kind() {
+        io::ErrorKind::ConnectionRefused => Some("Nothing is listening at that address"),
+        io::ErrorKind::AddrInUse => Some("Another process is already using that port"),
+        io::ErrorKind::InvalidInput => Some("Addresses are written as \"host:port\""),
+        _ => None,
+    };
+    let err = vm.error_with_context(format!("{}: {}: {}", word, target, e));
+    match help {
+        Some(help) => err.with_help(help).boxed(),
+        None => err.boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::{run, run_with};
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_tcp_round_trip() {
+        // The kernel completes the handshake before `accept`, so a single
+        // thread can play both client and server
+        let source = r#"
+            "127.0.0.1:0" tcp-listen
+            dup socket-addr tcp-connect
+            swap accept
+            over "ping" tcp-send
+            dup tcp-recv
+            rot rot
+            socket-close socket-close
+        "#;
+        assert_eq!(run(source).unwrap(), vec![s("ping")]);
+    }
+
+    #[test]
+    fn test_udp_round_trip() {
+        let source = r#"
+            "127.0.0.1:0" udp-bind
+            dup dup "hello" rot socket-addr udp-send
+            udp-recv drop
+        "#;
+        assert_eq!(run(source).unwrap(), vec![s("hello")]);
+    }
+
+    #[test]
+    fn test_connect_refused() {
+        // Bind then close to find a port nothing is listening on
+        let err = run(r#""127.0.0.1:0" tcp-listen dup socket-addr swap socket-close tcp-connect"#)
+            .unwrap_err();
+        assert!(err.starts_with("tcp-connect: 127.0.0.1:"), "{}", err);
+    }
+
+    #[test]
+    fn test_bad_handles() {
+        let err = run("42 tcp-recv").unwrap_err();
+        assert!(err.contains("42 is not an open socket"), "{}", err);
+        let err = run(r#""127.0.0.1:0" tcp-listen "x" tcp-send"#).unwrap_err();
+        assert!(
+            err.contains("is a TCP listener, expected a TCP connection"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_net_respects_sandbox() {
+        let config = VmBcConfig {
+            allow_net: false,
+            ..Default::default()
+        };
+        let err = run_with(config, r#""127.0.0.1:0" tcp-listen"#).unwrap_err();
+        assert!(err.contains("network access is disabled"), "{}", err);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Pair words
+// =============================================================================
+//
+// A pair is a two-item list. Words with two results that belong together
+// (`zip`'s items, the halves `partition` splits a list into, `divmod`) return
+// one pair rather than inventing their own layout, so `unpair`, `first` and
+// `second` work on all of them.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("pair", pair),
+    ("unpair", unpair),
+    ("first", first),
+    ("second", second),
+    ("zip", zip),
+    ("partition", partition),
+];
+
+/// ( a b -- pair ) Make a pair.
+fn pair(vm: &mut VmBc) -> RuntimeResult<()> {
+    let b = vm.pop()?;
+    let a = vm.pop()?;
+    vm.push(Value::List(vec![a, b]));
+    Ok(())
+}
+
+/// ( pair -- a b ) Push both halves of a pair.
+fn unpair(vm: &mut VmBc) -> RuntimeResult<()> {
+    let [a, b] = pop_pair(vm, "unpair")?;
+    vm.push(a);
+    vm.push(b);
+    Ok(())
+}
+
+/// ( pair -- a ) The first half of a pair.
+fn first(vm: &mut VmBc) -> RuntimeResult<()> {
+    let [a, _] = pop_pair(vm, "first")?;
+    vm.push(a);
+    Ok(())
+}
+
+/// ( pair -- b ) The second half of a pair.
+fn second(vm: &mut VmBc) -> RuntimeResult<()> {
+    let [_, b] = pop_pair(vm, "second")?;
+    vm.push(b);
+    Ok(())
+}
+
+/// ( xs ys -- pairs ) Pair up items at the same index; the longer list's
+/// extra items are dropped.
+fn zip(vm: &mut VmBc) -> RuntimeResult<()> {
+    let ys = vm.pop_list()?;
+    let xs = vm.pop_list()?;
+    let pairs = xs
+        .into_iter()
+        .zip(ys)
+        .map(|(x, y)| Value::List(vec![x, y]))
+        .collect();
+    vm.push(Value::List(pairs));
+    Ok(())
+}
+
+/// ( list quot -- pair ) Split a list into the items `quot` accepts and
+/// the rest, keeping their order.
+fn partition(vm: &mut VmBc) -> RuntimeResult<()> {
+    let body = vm.pop_quotation_ops()?;
+    let list = vm.pop_list()?;
+    let mut accepted = Vec::new();
+    let mut rejected = Vec::new();
+    for (index, item) in list.into_iter().enumerate() {
+        let height = vm.stack().len();
+        vm.push(item.clone());
+        vm.exec_ops(&body)?;
+        vm.check_item_effect("partition", "a flag", index, height)?;
+        if vm.pop_bool()? {
+            accepted.push(item);
+        } else {
+            rejected.push(item);
+        }
+    }
+    vm.push(Value::List(vec![
+        Value::List(accepted),
+        Value::List(rejected),
+    ]));
+    Ok(())
+}
+
+fn pop_pair(vm: &mut VmBc, word: &str) -> RuntimeResult<[Value; 2]> {
+    let value = vm.pop()?;
+    match value {
+        Value::List(items) => <[Value; 2]>::try_from(items).map_err(|items| {
+            vm.error_with_context(format!(
+                "{}: expected a pair, got a list of {} items",
+                word,
+                items.len()
+            ))
+            .with_help("A pair is a two-item list, e.g. from `pair` or `zip`")
+            .boxed()
+        }),
+        other => Err(vm.type_error_with_context("pair", other.type_name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    fn ints(ns: &[i64]) -> Value {
+        Value::List(ns.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    #[test]
+    fn test_pairs() {
+        assert_eq!(
+            run("1 2 pair dup first swap second").unwrap(),
+            vec![Value::Integer(1), Value::Integer(2)]
+        );
+        assert_eq!(
+            run("{ 1 2 3 } { 4 5 } zip").unwrap(),
+            vec![Value::List(vec![ints(&[1, 4]), ints(&[2, 5])])]
+        );
+        assert_eq!(
+            run("{ 1 2 3 4 5 } [ 2 % 0 = ] partition unpair").unwrap(),
+            vec![ints(&[2, 4]), ints(&[1, 3, 5])]
+        );
+
+        let err = run("{ 1 2 3 } unpair").unwrap_err();
+        assert_eq!(err, "unpair: expected a pair, got a list of 3 items");
+        let err = run("{ 1 } [ drop ] partition").unwrap_err();
+        assert!(err.contains("partition: the quotation must replace each item with a flag"));
+    }
+}
@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Path words
+// =============================================================================
+//
+// Paths are plain strings on the stack. `basename`, `dirname`, `extension`
+// and `path-join` only inspect the string and never touch the disk; `glob`
+// and `canonicalize` do, so they are refused when the VM's `allow_fs` flag is
+// off. Missing components yield an empty string rather than an error.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("glob", glob),
+    ("path-join", path_join),
+    ("basename", basename),
+    ("dirname", dirname),
+    ("extension", extension),
+    ("canonicalize", canonicalize),
+];
+
+/// ( pattern -- paths ) Matching paths, sorted. Unreadable entries are skipped.
+fn glob(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("glob")?;
+    let pattern = vm.pop_string()?;
+    let entries = glob::glob(&pattern).map_err(|e| {
+        vm.error_with_context(format!("glob: invalid pattern '{}': {}", pattern, e))
+            .with_help("Use *, ?, [abc] and ** (recursive) wildcards")
+            .boxed()
+    })?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(Result::ok).collect();
+    paths.sort();
+    vm.push(Value::List(paths.iter().map(|p| path_value(p)).collect()));
+    Ok(())
+}
+
+/// ( base part -- path ) Join two components with the platform separator.
+/// An absolute `part` replaces `base`.
+fn path_join(vm: &mut VmBc) -> RuntimeResult<()> {
+    let part = vm.pop_string()?;
+    let base = vm.pop_string()?;
+    vm.push(path_value(&Path::new(&base).join(part)));
+    Ok(())
+}
+
+/// ( path -- name ) Final component: `"src/main.rs"` → `"main.rs"`.
+fn basename(vm: &mut VmBc) -> RuntimeResult<()> {
+    let path = vm.pop_string()?;
+    let name = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    vm.push(Value::String(name));
+    Ok(())
+}
+
+/// ( path -- dir ) Everything but the final component: `"src/main.rs"` → `"src"`.
+fn dirname(vm: &mut VmBc) -> RuntimeResult<()> {
+    let path = vm.pop_string()?;
+    let dir = Path::new(&path)
+        .parent()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    vm.push(Value::String(dir));
+    Ok(())
+}
+
+/// ( path -- ext ) Extension without the dot: `"notes.txt"` → `"txt"`.
+fn extension(vm: &mut VmBc) -> RuntimeResult<()> {
+    let path = vm.pop_string()?;
+    let ext = Path::new(&path)
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    vm.push(Value::String(ext));
+    Ok(())
+}
+
+/// ( path -- abs ) Absolute path with symlinks and `..` resolved. The path
+/// must exist.
+fn canonicalize(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("canonicalize")?;
+    let path = vm.pop_string()?;
+    let resolved = std::fs::canonicalize(&path).map_err(|e| {
+        vm.error_with_context(format!("canonicalize: cannot resolve '{}': {}", path, e))
+            .boxed()
+    })?;
+    vm.push(path_value(&resolved));
+    Ok(())
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::{run, run_with};
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_path_components() {
+        assert_eq!(
+            run(r#""src/runtime/vm_bc.rs" dup basename swap dup dirname swap extension"#).unwrap(),
+            vec![s("vm_bc.rs"), s("src/runtime"), s("rs")]
+        );
+        assert_eq!(
+            run(r#""Makefile" extension "/" basename "Makefile" dirname"#).unwrap(),
+            vec![s(""), s(""), s("")]
+        );
+    }
+
+    #[test]
+    fn test_path_join() {
+        assert_eq!(
+            run(r#""src" "main.rs" path-join"#).unwrap(),
+            vec![s("src/main.rs")]
+        );
+        assert_eq!(run(r#""src" "/etc" path-join"#).unwrap(), vec![s("/etc")]);
+    }
+
+    #[test]
+    fn test_glob() {
+        let stack = run(r#""src/runtime/*_words.rs" glob"#).unwrap();
+        let Value::List(paths) = &stack[0] else {
+            panic!("expected list, got {:?}", stack);
+        };
+        assert!(paths.contains(&s("src/runtime/path_words.rs")));
+        assert!(
+            paths
+                .windows(2)
+                .all(|w| w[0].to_string() <= w[1].to_string())
+        );
+    }
+
+    #[test]
+    fn test_glob_invalid_pattern() {
+        let err = run(r#""[" glob"#).unwrap_err();
+        assert!(err.contains("glob: invalid pattern"), "{}", err);
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let stack = run(r#""src/../src/main.rs" canonicalize"#).unwrap();
+        let expected = std::fs::canonicalize("src/main.rs").unwrap();
+        assert_eq!(stack, vec![s(&expected.to_string_lossy())]);
+
+        let err = run(r#""does/not/exist" canonicalize"#).unwrap_err();
+        assert!(err.contains("cannot resolve"), "{}", err);
+    }
+
+    #[test]
+    fn test_fs_words_respect_sandbox() {
+        let config = || VmBcConfig {
+            allow_fs: false,
+            ..Default::default()
+        };
+        let err = run_with(config(), r#""*" glob"#).unwrap_err();
+        assert!(
+            err.contains("glob: file system access is disabled"),
+            "{}",
+            err
+        );
+        let err = run_with(config(), r#""." canonicalize"#).unwrap_err();
+        assert!(err.contains("file system access is disabled"), "{}", err);
+
+        // Pure path manipulation is still allowed
+        assert_eq!(
+            run_with(config(), r#""a/b.txt" basename"#).unwrap(),
+            vec![s("b.txt")]
+        );
+    }
+}
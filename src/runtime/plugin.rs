@@ -0,0 +1,424 @@
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::path::Path;
+
+use libloading::Library;
+
+use crate::lang::value::Value;
+use crate::runtime::native;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Plugins
+// =============================================================================
+//
+// Native word packs loaded from shared libraries at run time, so words with
+// heavyweight dependencies (databases, GUI toolkits) can be built and
+// shipped apart from the interpreter. A plugin exports two C functions:
+//
+//     uint32_t ember_plugin_abi(void);
+//     void ember_plugin_register(const EmberRegistrar *registrar);
+//
+// The host calls `ember_plugin_abi` first and refuses the library unless it
+// returns `ABI_VERSION`; any change to the structs below bumps it. Then
+// `ember_plugin_register` calls `registrar->add_word` once for each word.
+//
+// A plugin word is `bool word(const EmberApi *api)`. It reaches the stack
+// only through the functions in `api`, which move integers, floats, bools
+// and strings; a pop that fails (empty stack, wrong type) returns false,
+// and the word should then return false too. Returning false fails the
+// word, with the message passed to `api->fail` or the pop's error. Strings
+// are UTF-8 pointer/length pairs; a popped string stays valid until the
+// word returns.
+//
+// Plugin words resolve after user words and built-in natives. They run
+// with the host's full permissions, since the sandbox flags can't reach
+// into a library, so only load plugins you trust.
+
+/// The plugin ABI this host speaks.
+pub const ABI_VERSION: u32 = 1;
+
+/// A word exported by a plugin.
+pub type PluginWordFn = unsafe extern "C" fn(api: *const EmberApi) -> bool;
+
+type AbiFn = unsafe extern "C" fn() -> u32;
+type RegisterFn = unsafe extern "C" fn(registrar: *const EmberRegistrar);
+
+/// Passed to `ember_plugin_register`.
+#[repr(C)]
+pub struct EmberRegistrar {
+    pub ctx: *mut c_void,
+    pub add_word:
+        unsafe extern "C" fn(ctx: *mut c_void, name: *const u8, len: usize, word: PluginWordFn),
+}
+
+/// Passed to each call of a plugin word.
+#[repr(C)]
+pub struct EmberApi {
+    pub ctx: *mut c_void,
+    pub pop_int: unsafe extern "C" fn(ctx: *mut c_void, out: *mut i64) -> bool,
+    /// Pops an integer or a float
+    pub pop_float: unsafe extern "C" fn(ctx: *mut c_void, out: *mut f64) -> bool,
+    pub pop_bool: unsafe extern "C" fn(ctx: *mut c_void, out: *mut bool) -> bool,
+    pub pop_string:
+        unsafe extern "C" fn(ctx: *mut c_void, ptr: *mut *const u8, len: *mut usize) -> bool,
+    pub push_int: unsafe extern "C" fn(ctx: *mut c_void, value: i64),
+    pub push_float: unsafe extern "C" fn(ctx: *mut c_void, value: f64),
+    pub push_bool: unsafe extern "C" fn(ctx: *mut c_void, value: bool),
+    /// Returns false, pushing nothing, if the bytes aren't UTF-8
+    pub push_string: unsafe extern "C" fn(ctx: *mut c_void, ptr: *const u8, len: usize) -> bool,
+    pub fail: unsafe extern "C" fn(ctx: *mut c_void, message: *const u8, len: usize),
+}
+
+/// The plugin words a VM has loaded, and the libraries that hold their
+/// code.
+#[derive(Default)]
+pub struct Plugins {
+    words: BTreeMap<String, PluginWordFn>,
+    libraries: Vec<Library>,
+}
+
+impl Plugins {
+    /// Load the plugin at `path` and return the names of its words.
+    pub fn load(&mut self, path: &Path) -> Result<Vec<String>, String> {
+        // SAFETY: loading a library runs its initializers; plugins are
+        // trusted code, as the module comment says.
+        let library = unsafe { Library::new(path) }
+            .map_err(|e| format!("cannot load plugin '{}': {}", path.display(), e))?;
+        let symbols = unsafe {
+            library.get::<AbiFn>(b"ember_plugin_abi\0").and_then(|abi| {
+                Ok((
+                    *abi,
+                    *library.get::<RegisterFn>(b"ember_plugin_register\0")?,
+                ))
+            })
+        };
+        let (abi, register) =
+            symbols.map_err(|e| format!("'{}' is not an EMBER plugin: {}", path.display(), e))?;
+        let names = self
+            .install(abi, register)
+            .map_err(|e| format!("plugin '{}': {}", path.display(), e))?;
+        self.libraries.push(library);
+        Ok(names)
+    }
+
+    /// Check the plugin's ABI version and add the words it registers. On
+    /// error no word is added.
+    fn install(&mut self, abi: AbiFn, register: RegisterFn) -> Result<Vec<String>, String> {
+        let version = unsafe { abi() };
+        if version != ABI_VERSION {
+            return Err(format!(
+                "built for plugin ABI {}, but this host speaks {}",
+                version, ABI_VERSION
+            ));
+        }
+        let mut registration = Registration {
+            plugins: self,
+            words: Vec::new(),
+            error: None,
+        };
+        let registrar = EmberRegistrar {
+            ctx: &mut registration as *mut Registration as *mut c_void,
+            add_word,
+        };
+        unsafe { register(&registrar) };
+        if let Some(error) = registration.error {
+            return Err(error);
+        }
+        let names = registration.words.iter().map(|(n, _)| n.clone()).collect();
+        self.words.extend(registration.words);
+        Ok(names)
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<PluginWordFn> {
+        self.words.get(name).copied()
+    }
+}
+
+/// Words a plugin has registered so far, while `ember_plugin_register` runs.
+struct Registration<'a> {
+    plugins: &'a Plugins,
+    words: Vec<(String, PluginWordFn)>,
+    /// The first bad registration; later ones are ignored
+    error: Option<String>,
+}
+
+unsafe extern "C" fn add_word(ctx: *mut c_void, name: *const u8, len: usize, word: PluginWordFn) {
+    let registration = unsafe { &mut *(ctx as *mut Registration) };
+    if registration.error.is_some() {
+        return;
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(name, len) };
+    let error = match std::str::from_utf8(bytes) {
+        Err(_) => Some("a word name is not valid UTF-8".to_string()),
+        Ok("") => Some("a word name is empty".to_string()),
+        Ok(name)
+            if native::lookup(name).is_some()
+                || registration.plugins.lookup(name).is_some()
+                || registration.words.iter().any(|(n, _)| n == name) =>
+        {
+            Some(format!("'{}' is already defined", name))
+        }
+        Ok(name) => {
+            registration.words.push((name.to_string(), word));
+            None
+        }
+    };
+    registration.error = error;
+}
+
+/// One call of a plugin word.
+struct Call<'a> {
+    vm: &'a mut VmBc,
+    /// Why the word failed, from a pop or `fail`
+    error: Option<Box<RuntimeError>>,
+    /// Strings popped during the call, kept alive until it returns
+    strings: Vec<String>,
+}
+
+impl Call<'_> {
+    fn pop<T>(&mut self, pop: impl FnOnce(&mut VmBc) -> RuntimeResult<T>) -> Option<T> {
+        match pop(self.vm) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                None
+            }
+        }
+    }
+}
+
+/// Run plugin word `name`.
+pub fn call(vm: &mut VmBc, name: &str, word: PluginWordFn) -> RuntimeResult<()> {
+    let mut call = Call {
+        vm,
+        error: None,
+        strings: Vec::new(),
+    };
+    let api = EmberApi {
+        ctx: &mut call as *mut Call as *mut c_void,
+        pop_int,
+        pop_float,
+        pop_bool,
+        pop_string,
+        push_int,
+        push_float,
+        push_bool,
+        push_string,
+        fail,
+    };
+    let ok = unsafe { word(&api) };
+    match call.error {
+        Some(e) => Err(e),
+        None if !ok => Err(call
+            .vm
+            .error_with_context(format!("{}: plugin word failed", name))
+            .boxed()),
+        None => Ok(()),
+    }
+}
+
+/// Run `f` on the call in progress. The borrow ends when `f` returns, so
+/// it can't outlive the callback that asked for it.
+///
+/// # Safety
+///
+/// `ctx` must be the `ctx` of the `EmberApi` that `call` handed to the
+/// plugin word now running.
+unsafe fn with_call<R>(ctx: *mut c_void, f: impl FnOnce(&mut Call<'_>) -> R) -> R {
+    // SAFETY: per the contract, `ctx` points to the `Call` on `call`'s
+    // stack frame, which outlives the word and nothing else borrows.
+    f(unsafe { &mut *(ctx as *mut Call) })
+}
+
+/// Write a popped value to the plugin's `out`. False if the pop failed.
+///
+/// # Safety
+///
+/// `out` must be valid for writes and properly aligned.
+unsafe fn store<T>(out: *mut T, value: Option<T>) -> bool {
+    match value {
+        Some(value) => {
+            // SAFETY: guaranteed by the caller
+            unsafe { out.write(value) };
+            true
+        }
+        None => false,
+    }
+}
+
+// In the callbacks below, `ctx` is the one `call` put in the `EmberApi`:
+// plugins pass it back unchanged, as the API requires. Output pointers
+// point to the plugin's own variables.
+
+unsafe extern "C" fn pop_int(ctx: *mut c_void, out: *mut i64) -> bool {
+    // SAFETY: `ctx` and `out` as described above
+    unsafe { store(out, with_call(ctx, |call| call.pop(VmBc::pop_int))) }
+}
+
+unsafe extern "C" fn pop_float(ctx: *mut c_void, out: *mut f64) -> bool {
+    // SAFETY: `ctx` and `out` as described above
+    unsafe { store(out, with_call(ctx, |call| call.pop(VmBc::pop_float))) }
+}
+
+unsafe extern "C" fn pop_bool(ctx: *mut c_void, out: *mut bool) -> bool {
+    // SAFETY: `ctx` and `out` as described above
+    unsafe { store(out, with_call(ctx, |call| call.pop(VmBc::pop_bool))) }
+}
+
+unsafe extern "C" fn pop_string(ctx: *mut c_void, ptr: *mut *const u8, len: *mut usize) -> bool {
+    // SAFETY: `ctx` as described above
+    let popped = unsafe {
+        with_call(ctx, |call| {
+            let s = call.pop(VmBc::pop_string)?;
+            let popped = (s.as_ptr(), s.len());
+            // The string's buffer doesn't move when `strings` grows
+            call.strings.push(s);
+            Some(popped)
+        })
+    };
+    let Some((start, length)) = popped else {
+        return false;
+    };
+    // SAFETY: `ptr` and `len` as described above
+    unsafe {
+        ptr.write(start);
+        len.write(length);
+    }
+    true
+}
+
+unsafe extern "C" fn push_int(ctx: *mut c_void, value: i64) {
+    // SAFETY: `ctx` as described above
+    unsafe { with_call(ctx, |call| call.vm.push(Value::Integer(value))) }
+}
+
+unsafe extern "C" fn push_float(ctx: *mut c_void, value: f64) {
+    // SAFETY: `ctx` as described above
+    unsafe { with_call(ctx, |call| call.vm.push(Value::Float(value))) }
+}
+
+unsafe extern "C" fn push_bool(ctx: *mut c_void, value: bool) {
+    // SAFETY: `ctx` as described above
+    unsafe { with_call(ctx, |call| call.vm.push(Value::Bool(value))) }
+}
+
+unsafe extern "C" fn push_string(ctx: *mut c_void, ptr: *const u8, len: usize) -> bool {
+    let bytes = unsafe { std::slice::from_raw_parts(ptr, len) };
+    match std::str::from_utf8(bytes) {
+        Ok(s) => {
+            // SAFETY: `ctx` as described above
+            unsafe { with_call(ctx, |call| call.vm.push(Value::String(s.to_string()))) };
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+unsafe extern "C" fn fail(ctx: *mut c_void, message: *const u8, len: usize) {
+    let bytes = unsafe { std::slice::from_raw_parts(message, len) };
+    // SAFETY: `ctx` as described above
+    unsafe {
+        with_call(ctx, |call| {
+            let error = call
+                .vm
+                .error_with_context(String::from_utf8_lossy(bytes).into_owned())
+                .boxed();
+            call.error.get_or_insert(error);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::test_support::run_in;
+
+    // A plugin compiled into the test binary, registered the way a loaded
+    // library would be.
+
+    unsafe extern "C" fn abi() -> u32 {
+        ABI_VERSION
+    }
+
+    unsafe extern "C" fn old_abi() -> u32 {
+        0
+    }
+
+    unsafe extern "C" fn greet(api: *const EmberApi) -> bool {
+        let api = unsafe { &*api };
+        let (mut ptr, mut len) = (std::ptr::null(), 0);
+        if !unsafe { (api.pop_string)(api.ctx, &mut ptr, &mut len) } {
+            return false;
+        }
+        let name = unsafe { std::slice::from_raw_parts(ptr, len) };
+        let greeting = [b"hello, ", name].concat();
+        unsafe { (api.push_string)(api.ctx, greeting.as_ptr(), greeting.len()) }
+    }
+
+    unsafe extern "C" fn halve(api: *const EmberApi) -> bool {
+        let api = unsafe { &*api };
+        let mut x = 0.0;
+        if !unsafe { (api.pop_float)(api.ctx, &mut x) } {
+            return false;
+        }
+        if x < 0.0 {
+            let message = "halve: negative input";
+            unsafe { (api.fail)(api.ctx, message.as_ptr(), message.len()) };
+            return false;
+        }
+        unsafe { (api.push_float)(api.ctx, x / 2.0) };
+        true
+    }
+
+    unsafe extern "C" fn register(registrar: *const EmberRegistrar) {
+        let registrar = unsafe { &*registrar };
+        for (name, word) in [("greet", greet as PluginWordFn), ("halve", halve)] {
+            unsafe { (registrar.add_word)(registrar.ctx, name.as_ptr(), name.len(), word) };
+        }
+    }
+
+    unsafe extern "C" fn register_clash(registrar: *const EmberRegistrar) {
+        let registrar = unsafe { &*registrar };
+        let name = "read-file";
+        unsafe { (registrar.add_word)(registrar.ctx, name.as_ptr(), name.len(), greet) };
+    }
+
+    #[test]
+    fn test_plugin_words() {
+        let mut vm = VmBc::new();
+        assert_eq!(
+            vm.plugins.install(abi, register).unwrap(),
+            ["greet", "halve"]
+        );
+        assert_eq!(
+            run_in(&mut vm, r#""ember" greet 3 halve"#).unwrap(),
+            vec![Value::String("hello, ember".into()), Value::Float(1.5)]
+        );
+        let err = run_in(&mut vm, "-1 halve").unwrap_err();
+        assert_eq!(err, "halve: negative input");
+        let err = run_in(&mut vm, "5 greet").unwrap_err();
+        assert!(err.contains("string"), "{}", err);
+    }
+
+    #[test]
+    fn test_rejected_plugins() {
+        let mut plugins = Plugins::default();
+        let err = plugins.install(old_abi, register).unwrap_err();
+        assert_eq!(err, "built for plugin ABI 0, but this host speaks 1");
+        let err = plugins.install(abi, register_clash).unwrap_err();
+        assert_eq!(err, "'read-file' is already defined");
+        plugins.install(abi, register).unwrap();
+        let err = plugins.install(abi, register).unwrap_err();
+        assert_eq!(err, "'greet' is already defined");
+        assert_eq!(plugins.words.keys().collect::<Vec<_>>(), ["greet", "halve"]);
+
+        let err = plugins.load(Path::new("no-such-plugin.so")).unwrap_err();
+        assert!(
+            err.starts_with("cannot load plugin 'no-such-plugin.so'"),
+            "{}",
+            err
+        );
+    }
+}
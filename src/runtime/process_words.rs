@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Process words
+// =============================================================================
+//
+// `run-process` runs a program to completion and returns a map:
+//
+//   #{ status: 0 stderr: "" stdout: "..." success: true }
+//
+// A non-zero exit is not an error; scripts inspect `status` / `success`. Only
+// failing to start the program, or exceeding the timeout, raises. The child's
+// stdin is closed so it can never block waiting on the script's input. Both
+// words are refused when the VM's `allow_process` flag is off.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("run-process", run_process),
+    ("run-process-timeout", run_process_timeout),
+];
+
+/// Exit status plus captured stdout and stderr.
+type Finished = (ExitStatus, Vec<u8>, Vec<u8>);
+
+/// How often a child with a deadline is polled for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// ( program args -- result )
+fn run_process(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_process("run-process")?;
+    let args = pop_args(vm, "run-process")?;
+    let program = vm.pop_string()?;
+    let result = run(vm, "run-process", &program, &args, None)?;
+    vm.push(result);
+    Ok(())
+}
+
+/// ( program args ms -- result ) Like `run-process`, but kills the child and
+/// fails if it runs longer than `ms` milliseconds.
+fn run_process_timeout(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_process("run-process-timeout")?;
+    let ms = vm.pop_int()?;
+    if ms <= 0 {
+        return Err(vm
+            .error_with_context(format!(
+                "run-process-timeout: timeout must be positive, got {}",
+                ms
+            ))
+            .boxed());
+    }
+    let args = pop_args(vm, "run-process-timeout")?;
+    let program = vm.pop_string()?;
+    let timeout = Duration::from_millis(ms as u64);
+    let result = run(vm, "run-process-timeout", &program, &args, Some(timeout))?;
+    vm.push(result);
+    Ok(())
+}
+
+/// Pop the argument list. Scalars are passed in their printed form so
+/// `{ "-n" 5 }` works; nested structures are rejected.
+fn pop_args(vm: &mut VmBc, word: &str) -> RuntimeResult<Vec<String>> {
+    let items = vm.pop_list()?;
+    items
+        .iter()
+        .map(|item| match item {
+            Value::String(s) => Ok(s.clone()),
+            Value::Integer(_) | Value::Float(_) | Value::Bool(_) => Ok(item.to_string()),
+            other => Err(vm
+                .error_with_context(format!(
+                    "{}: arguments must be strings or numbers, got {}",
+                    word,
+                    other.type_name()
+                ))
+                .boxed()),
+        })
+        .collect()
+}
+
+fn run(
+    vm: &VmBc,
+    word: &str,
+    program: &str,
+    args: &[String],
+    timeout: Option<Duration>,
+) -> RuntimeResult<Value> {
+    let child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let message = match e.kind() {
+                io::ErrorKind::NotFound => format!("{}: program '{}' not found", word, program),
+                io::ErrorKind::PermissionDenied => {
+                    format!("{}: permission denied running '{}'", word, program)
+                }
+                _ => format!("{}: failed to start '{}': {}", word, program, e),
+            };
+            vm.error_with_context(message)
+                .with_help("Check that the program is installed and on PATH")
+                .boxed()
+        })?;
+
+    let finished = wait(child, timeout).map_err(|e| {
+        vm.error_with_context(format!("{}: error waiting for '{}': {}", word, program, e))
+            .boxed()
+    })?;
+
+    let Some((status, stdout, stderr)) = finished else {
+        let ms = timeout.map(|t| t.as_millis()).unwrap_or_default();
+        return Err(vm
+            .error_with_context(format!(
+                "{}: '{}' timed out after {}ms and was killed",
+                word, program, ms
+            ))
+            .boxed());
+    };
+
+    let mut result = BTreeMap::new();
+    // Death by signal has no exit code
+    result.insert(
+        "status".to_string(),
+        Value::Integer(status.code().map(i64::from).unwrap_or(-1)),
+    );
+    result.insert("success".to_string(), Value::Bool(status.success()));
+    result.insert(
+        "stdout".to_string(),
+        Value::String(String::from_utf8_lossy(&stdout).into_owned()),
+    );
+    result.insert(
+        "stderr".to_string(),
+        Value::String(String::from_utf8_lossy(&stderr).into_owned()),
+    );
+    Ok(Value::Map(result))
+}
+
+/// Wait for the child, collecting its output. Returns `None` if the timeout
+/// elapsed, in which case the child has been killed.
+fn wait(mut child: Child, timeout: Option<Duration>) -> io::Result<Option<Finished>> {
+    let Some(timeout) = timeout else {
+        let output = child.wait_with_output()?;
+        return Ok(Some((output.status, output.stdout, output.stderr)));
+    };
+
+    // Drain the pipes on their own threads so a chatty child can't fill a
+    // pipe buffer and stall while we poll for its exit
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            // Its own children may still hold the pipes open, so leave the
+            // readers to finish whenever they do
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    Ok(Some((status, stdout, stderr)))
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::{run, run_with};
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_run_process_captures_output() {
+        assert_eq!(
+            run(r#""echo" { "hello" 42 } run-process dup "stdout" get swap "status" get"#).unwrap(),
+            vec![s("hello 42\n"), Value::Integer(0)]
+        );
+    }
+
+    #[test]
+    fn test_run_process_nonzero_exit_is_not_an_error() {
+        assert_eq!(
+            run(r#""sh" { "-c" "echo oops >&2; exit 3" } run-process dup dup "status" get swap "success" get rot "stderr" get"#)
+                .unwrap(),
+            vec![Value::Integer(3), Value::Bool(false), s("oops\n")]
+        );
+    }
+
+    #[test]
+    fn test_run_process_missing_program() {
+        let err = run(r#""no-such-program-xyz" { } run-process"#).unwrap_err();
+        assert!(
+            err.contains("program 'no-such-program-xyz' not found"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_run_process_bad_argument() {
+        let err = run(r#""echo" { { 1 } } run-process"#).unwrap_err();
+        assert!(
+            err.contains("arguments must be strings or numbers"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_run_process_timeout() {
+        assert_eq!(
+            run(r#""echo" { "fast" } 5000 run-process-timeout "stdout" get"#).unwrap(),
+            vec![s("fast\n")]
+        );
+        let err = run(r#""sleep" { 5 } 50 run-process-timeout"#).unwrap_err();
+        assert!(err.contains("timed out after 50ms"), "{}", err);
+        // The shell's `sleep` outlives it with the pipes open
+        let started = Instant::now();
+        let err = run(r#""sh" { "-c" "sleep 4; echo hi" } 100 run-process-timeout"#).unwrap_err();
+        assert!(err.contains("timed out after 100ms"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        let err = run(r#""echo" { } 0 run-process-timeout"#).unwrap_err();
+        assert!(err.contains("timeout must be positive"), "{}", err);
+    }
+
+    #[test]
+    fn test_run_process_respects_sandbox() {
+        let config = VmBcConfig {
+            allow_process: false,
+            ..Default::default()
+        };
+        let err = run_with(config, r#""echo" { } run-process"#).unwrap_err();
+        assert!(err.contains("process execution is disabled"), "{}", err);
+    }
+
+    #[test]
+    fn test_get_missing_key() {
+        let err = run(r#""true" { } run-process "nope" get"#).unwrap_err();
+        assert!(err.contains("key 'nope' not found"), "{}", err);
+    }
+}
@@ -0,0 +1,153 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Progress words
+// =============================================================================
+//
+// `with-progress` runs a loop like `each` (given a list) or `times` (given a
+// count) and keeps a progress bar on stderr while it does, so stdout stays
+// clean for the program's own output. The bar is redrawn at most every
+// `REDRAW` and cleared when the loop ends, however it ends. It is only drawn
+// when the config's `progress` is on, which the CLI sets when stderr is a
+// terminal; anywhere else the word is just the loop.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("with-progress", with_progress)];
+
+/// How often the bar may be redrawn, so drawing never slows the loop down.
+const REDRAW: Duration = Duration::from_millis(100);
+
+/// Width of the bar itself, between its brackets.
+const BAR_WIDTH: usize = 30;
+
+/// ( list quot -- ) or ( n quot -- ) Run `quot` on each item, or `n` times,
+/// showing progress on stderr.
+fn with_progress(vm: &mut VmBc) -> RuntimeResult<()> {
+    let body = vm.pop_quotation_ops()?;
+    let (items, total) = match vm.pop()? {
+        Value::List(items) => {
+            let total = items.len();
+            (Some(items), total)
+        }
+        Value::Integer(n) => (None, usize::try_from(n).unwrap_or(0)),
+        other => {
+            return Err(vm.type_error_with_context("list or integer", other.type_name()));
+        }
+    };
+
+    let mut bar = vm.config().progress.then(|| Bar::new(total));
+    let mut items = items.map(Vec::into_iter);
+    let mut result = Ok(());
+    for done in 0..total {
+        if let Some(bar) = &mut bar {
+            bar.update(done);
+        }
+        if let Some(item) = items.as_mut().and_then(Iterator::next) {
+            vm.push(item);
+        }
+        result = vm.exec_ops(&body);
+        if result.is_err() {
+            break;
+        }
+    }
+    if let Some(bar) = bar {
+        bar.clear();
+    }
+    result
+}
+
+/// A progress bar on stderr, redrawn in place.
+struct Bar {
+    total: usize,
+    last_drawn: Option<Instant>,
+}
+
+impl Bar {
+    fn new(total: usize) -> Self {
+        Bar {
+            total,
+            last_drawn: None,
+        }
+    }
+
+    /// Show `done` items finished, unless the bar was drawn very recently.
+    fn update(&mut self, done: usize) {
+        let now = Instant::now();
+        if self
+            .last_drawn
+            .is_some_and(|drawn| now.duration_since(drawn) < REDRAW)
+        {
+            return;
+        }
+        self.last_drawn = Some(now);
+        let mut stderr = io::stderr().lock();
+        // Progress is a courtesy: a stderr that can't be written to
+        // shouldn't stop the loop
+        let _ = write!(stderr, "\r{}", render_bar(done, self.total));
+        let _ = stderr.flush();
+    }
+
+    /// Erase the bar, if it was ever drawn.
+    fn clear(self) {
+        if self.last_drawn.is_some() {
+            let _ = write!(io::stderr().lock(), "\r\x1b[K");
+        }
+    }
+}
+
+/// The bar's text, e.g. `[#########.....................]  30% 3/10`.
+fn render_bar(done: usize, total: usize) -> String {
+    let fraction = if total == 0 {
+        1.0
+    } else {
+        done as f64 / total as f64
+    };
+    let filled = ((fraction * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+    format!(
+        "[{}{}] {:>3}% {}/{}",
+        "#".repeat(filled),
+        ".".repeat(BAR_WIDTH - filled),
+        (fraction * 100.0) as usize,
+        done,
+        total
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render_bar;
+    use crate::runtime::test_support::run;
+
+    use crate::lang::value::Value;
+
+    #[test]
+    fn test_loops_like_each_and_times() {
+        assert_eq!(
+            run("0 { 1 2 3 } [ + ] with-progress").unwrap(),
+            vec![Value::Integer(6)]
+        );
+        assert_eq!(
+            run("0 4 [ 1 + ] with-progress 0 -1 [ 1 + ] with-progress").unwrap(),
+            vec![Value::Integer(4), Value::Integer(0)]
+        );
+        let err = run("\"abc\" [ ] with-progress").unwrap_err();
+        assert!(err.contains("list or integer"), "{}", err);
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_eq!(
+            render_bar(3, 10),
+            "[#########.....................]  30% 3/10"
+        );
+        assert_eq!(
+            render_bar(0, 0),
+            "[##############################] 100% 0/0"
+        );
+    }
+}
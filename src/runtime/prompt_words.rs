@@ -0,0 +1,159 @@
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Prompt words
+// =============================================================================
+//
+// Questions for interactive scripts. The question goes to the VM's output
+// and the answer comes from its input, so tests (and `--replay`) can answer
+// in place of a person. A question is written as given, so end it with the
+// space you want before the cursor.
+//
+// Running out of input is an error for `prompt`, since a script asking
+// again for a valid answer would otherwise loop forever; `confirm` takes it
+// as "no". All three are refused when the VM's `allow_io` flag is off.
+// `read-password` needs the `password` feature. At a terminal it
+// hides what is typed, and what it reads is never written to a `--record`
+// log.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("prompt", prompt), ("confirm", confirm)];
+
+#[cfg(feature = "password")]
+pub const PASSWORD_WORDS: &[(&str, NativeFn)] = &[("read-password", read_password)];
+
+/// ( question -- line ) Ask a question and read the answer.
+fn prompt(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("prompt")?;
+    let question = vm.pop_string()?;
+    let answer = ask(vm, &question)?;
+    push_answer(vm, "prompt", answer)
+}
+
+/// ( question -- bool ) Ask a yes/no question, asking again until the answer
+/// is y, yes, n or no (in any case).
+fn confirm(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("confirm")?;
+    let question = vm.pop_string()?;
+    let question = format!("{} [y/n] ", question.trim_end());
+    let answer = loop {
+        let Some(line) = ask(vm, &question)? else {
+            break false;
+        };
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => break true,
+            "n" | "no" => break false,
+            _ => vm.write_output(format_args!("Please answer y or n.\n"))?,
+        }
+    };
+    vm.push(Value::Bool(answer));
+    Ok(())
+}
+
+/// ( question -- line ) Ask for a secret, without showing what is typed.
+#[cfg(feature = "password")]
+fn read_password(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("read-password")?;
+    let question = vm.pop_string()?;
+    if !vm.terminal_input() {
+        let answer = ask(vm, &question)?;
+        return push_answer(vm, "read-password", answer);
+    }
+    vm.write_output(format_args!("{}", question))?;
+    vm.flush_output()?;
+    let line = rpassword::read_password().map_err(|e| {
+        vm.error_with_context(format!("read-password: {}", e))
+            .boxed()
+    })?;
+    vm.push(Value::String(line));
+    Ok(())
+}
+
+/// Write `question` and read one line in reply; `None` at end of input.
+fn ask(vm: &mut VmBc, question: &str) -> RuntimeResult<Option<String>> {
+    vm.write_output(format_args!("{}", question))?;
+    vm.flush_output()?;
+    vm.read_input_line()
+}
+
+/// Push the answer `word` read, or fail if input ran out first.
+fn push_answer(vm: &mut VmBc, word: &str, answer: Option<String>) -> RuntimeResult<()> {
+    match answer {
+        Some(line) => {
+            vm.push(Value::String(line));
+            Ok(())
+        }
+        None => Err(vm
+            .error_with_context(format!("{}: no input left to answer with", word))
+            .with_help("Input ended before an answer was given")
+            .boxed()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{Cursor, Write};
+    use std::rc::Rc;
+
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run_in;
+    use crate::runtime::vm_bc::VmBc;
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The stack (or error) and everything written, answering from `input`.
+    fn run(source: &str, input: &str) -> (Result<Vec<Value>, String>, String) {
+        let output = Shared::default();
+        let mut vm = VmBc::new();
+        vm.set_input(Cursor::new(input.to_string()));
+        vm.set_output(output.clone());
+        let result = run_in(&mut vm, source);
+        let written = String::from_utf8(output.0.borrow().clone()).unwrap();
+        (result, written)
+    }
+
+    #[test]
+    fn test_prompt() {
+        let (stack, written) = run("\"Name? \" prompt", "Ada\n");
+        assert_eq!(stack.unwrap(), vec![Value::String("Ada".into())]);
+        assert_eq!(written, "Name? ");
+
+        let (stack, _) = run("\"Name? \" prompt", "");
+        assert_eq!(stack.unwrap_err(), "prompt: no input left to answer with");
+    }
+
+    #[test]
+    fn test_confirm() {
+        let (stack, written) = run("\"Delete?\" confirm", "maybe\nYES\n");
+        assert_eq!(stack.unwrap(), vec![Value::Bool(true)]);
+        assert_eq!(
+            written,
+            "Delete? [y/n] Please answer y or n.\nDelete? [y/n] "
+        );
+
+        let (stack, _) = run("\"Delete?\" confirm \"Sure?\" confirm", "n\n");
+        assert_eq!(stack.unwrap(), vec![Value::Bool(false), Value::Bool(false)]);
+    }
+
+    #[cfg(feature = "password")]
+    #[test]
+    fn test_read_password_from_injected_input() {
+        let (stack, written) = run("\"Password: \" read-password", "hunter2\n");
+        assert_eq!(stack.unwrap(), vec![Value::String("hunter2".into())]);
+        assert_eq!(written, "Password: ");
+    }
+}
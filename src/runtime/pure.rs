@@ -0,0 +1,119 @@
+use std::io;
+
+use crate::bytecode::compile::Compiler;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::lang::value::Value;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::{Profile, VmBc};
+
+// =============================================================================
+// Pure evaluation
+// =============================================================================
+//
+// `eval_pure` is the entry point for programs that use EMBER snippets as
+// user-written rules or config: a discount rule, a routing decision, a
+// computed setting. It runs the snippet under `Profile::Pure`, so the
+// snippet cannot touch files, processes, the network or the console, cannot
+// build code at run time, and runs out of steps rather than running
+// forever. Nothing it can do depends on the clock or the machine, so the
+// same source and inputs always give the same result or the same error.
+//
+// Imports (`use`) are not followed: the snippet is the whole program. Each
+// call runs on a thread of its own, with stack enough for the deepest
+// recursion the profile allows.
+
+/// Room for the VM's recursion at the profile's `max_call_depth`, whatever
+/// stack the calling thread happens to have.
+const EVAL_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Run `source` with `inputs` on the stack (the last on top) and return the
+/// stack it leaves.
+pub fn eval_pure(source: &str, inputs: Vec<Value>) -> RuntimeResult<Vec<Value>> {
+    let source = source.to_string();
+    std::thread::Builder::new()
+        .name("ember-eval-pure".to_string())
+        .stack_size(EVAL_STACK_SIZE)
+        .spawn(move || run(&source, inputs))
+        .map_err(|e| RuntimeError::new(&format!("cannot start evaluation: {}", e)).boxed())?
+        .join()
+        .unwrap_or_else(|_| Err(RuntimeError::new("evaluation panicked").boxed()))
+}
+
+fn run(source: &str, inputs: Vec<Value>) -> RuntimeResult<Vec<Value>> {
+    let compile_error = |e: String| {
+        RuntimeError::new(&e)
+            .with_source(source.to_string())
+            .boxed()
+    };
+    let tokens = Lexer::new(source)
+        .tokenize()
+        .map_err(|e| compile_error(e.to_string()))?;
+    let ast = Parser::new(tokens)
+        .parse()
+        .map_err(|e| compile_error(e.to_string()))?;
+    let program = Compiler::new()
+        .compile_program(&ast)
+        .map_err(|e| compile_error(e.to_string()))?;
+
+    let mut vm = VmBc::with_config(Profile::Pure.config());
+    vm.set_source(source.to_string());
+    // The profile already refuses console I/O; these make sure nothing can
+    // reach the host's stdin or stdout regardless
+    vm.set_input(io::empty());
+    vm.set_output(io::sink());
+    for input in inputs {
+        vm.push(input);
+    }
+    vm.run_compiled(&program)?;
+    Ok(vm.stack().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::eval_pure;
+    use crate::lang::value::Value;
+
+    fn message(source: &str, inputs: Vec<Value>) -> String {
+        eval_pure(source, inputs).unwrap_err().message
+    }
+
+    #[test]
+    fn test_runs_on_inputs() {
+        let rule = "def discount 100 > [ 10 ] [ 0 ] if end discount";
+        assert_eq!(
+            eval_pure(rule, vec![Value::Integer(250)]).unwrap(),
+            vec![Value::Integer(10)]
+        );
+        assert_eq!(
+            eval_pure("-", vec![Value::Integer(5), Value::Integer(2)]).unwrap(),
+            vec![Value::Integer(3)]
+        );
+        assert_eq!(eval_pure("", vec![]).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_refuses_effects() {
+        assert!(message("\"hi\" print", vec![]).contains("print: console I/O is disabled"));
+        assert!(message("stdin-lines", vec![]).contains("stdin-lines: console I/O is disabled"));
+        assert!(message("\"*\" glob", vec![]).contains("file system access is disabled"));
+        assert!(message("\"1\" eval", vec![]).contains("building code at run time is disabled"));
+        assert!(
+            message("\"ls\" { } run-process", vec![]).contains("process execution is disabled")
+        );
+    }
+
+    #[test]
+    fn test_fuel_is_bounded() {
+        let err = message("def loop loop end loop", vec![]);
+        assert!(err.contains("depth"), "{}", err);
+        let err = message("0 [ 1 + ] 100000000 swap times", vec![]);
+        assert!(err.contains("step"), "{}", err);
+    }
+
+    #[test]
+    fn test_reports_bad_source() {
+        assert!(!message("{ 1 2", vec![]).is_empty());
+        assert!(message("+", vec![]).contains("stack"));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::bytecode::ProgramBc;
+use crate::bytecode::compile::Compiler;
+use crate::bytecode::decompile::decompile;
+use crate::bytecode::op::Op;
+use crate::bytecode::verify::verify_quotation;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::lang::builtins;
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Reflection words
+// =============================================================================
+//
+// Quotations as data. `quot->list` turns a quotation into one descriptor
+// per instruction:
+//
+//   "dup", "sq", "io.read"   a call of that word
+//   { 5 }                    push the value inside (a literal)
+//   "#jump +3"               control flow the compiler lowered to jumps,
+//   "#jump-if-false +3"      with offsets counted in descriptors; also
+//   "#jump-if-true -2"       "#peek N", "#to-aux", "#from-aux"
+//   "#store x", "#load x"    `x !` and `x @` on the variable `x`
+//
+// `list->quot` builds a quotation back from descriptors, so a program can
+// inspect, rewrite or generate code, and `eval` compiles and runs source
+// text. Building code at run time needs `allow_compile`, and quotations
+// built from descriptors are verified like loaded bytecode.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("quot->list", quot_to_list),
+    ("list->quot", list_to_quot),
+    ("eval", eval),
+    ("quot->string", quot_to_string),
+];
+
+/// ( quot -- list ) Describe each instruction of a quotation.
+fn quot_to_list(vm: &mut VmBc) -> RuntimeResult<()> {
+    let ops = vm.pop_quotation_ops()?;
+    let descriptors = ops.iter().map(descriptor).collect();
+    vm.push(Value::List(descriptors));
+    Ok(())
+}
+
+/// ( list -- quot ) Build a quotation from instruction descriptors.
+fn list_to_quot(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_compile("list->quot")?;
+    let items = vm.pop_list()?;
+    let ops = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            parse_descriptor(item).map_err(|e| {
+                vm.error_with_context(format!("list->quot: item {}: {}", i, e))
+                    .with_help("Use a word name, a one-item list for a literal, or a #-descriptor from quot->list")
+                    .boxed()
+            })
+        })
+        .collect::<RuntimeResult<Vec<Op>>>()?;
+
+    verify_quotation(&ops).map_err(|e| {
+        vm.error_with_context(format!("list->quot: {}", e.message))
+            .boxed()
+    })?;
+    vm.push(Value::CompiledQuotation(ops));
+    Ok(())
+}
+
+/// ( quot -- str ) Render a quotation as source text.
+fn quot_to_string(vm: &mut VmBc) -> RuntimeResult<()> {
+    let ops = vm.pop_quotation_ops()?;
+    let mut words = vec!["[".to_string()];
+    match decompile(&ops) {
+        Some(text) if !text.is_empty() => words.push(text),
+        Some(_) => {}
+        None => {
+            return Err(vm
+                .error_with_context("quot->string: the quotation has no source form")
+                .with_help("Quotations holding maps or bytes, or built with #-descriptors that match no control-flow word, can't be shown as source; try quot->list")
+                .boxed());
+        }
+    }
+    words.push("]".to_string());
+    vm.push(Value::String(words.join(" ")));
+    Ok(())
+}
+
+/// ( source -- ... ) Compile source text and run it on the current stack.
+/// Words it defines stay defined, as if the text were part of the program.
+fn eval(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_compile("eval")?;
+    let source = vm.pop_string()?;
+    let program = compile_source(&source).map_err(|e| {
+        vm.error_with_context(format!("eval: {}", e))
+            .with_help("The string must be complete Ember code, e.g. \"1 2 +\" eval")
+            .boxed()
+    })?;
+    let main = program.code[0].ops.clone();
+    vm.reload_words(program);
+    vm.exec_ops(&main)
+}
+
+fn compile_source(source: &str) -> Result<ProgramBc, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| e.to_string())?;
+    let program = Parser::new(tokens).parse().map_err(|e| e.to_string())?;
+    Compiler::new()
+        .compile_program(&program)
+        .map_err(|e| e.to_string())
+}
+
+/// The descriptor `quot->list` gives an instruction.
+fn descriptor(op: &Op) -> Value {
+    let text = match op {
+        Op::Push(value) => return Value::List(vec![value.clone()]),
+        Op::CallWord(name) => name.clone(),
+        Op::CallQualified { module, word } => format!("{}.{}", module, word),
+        Op::Jump(offset) => format!("#jump {:+}", offset),
+        Op::JumpIfFalse(offset) => format!("#jump-if-false {:+}", offset),
+        Op::JumpIfTrue(offset) => format!("#jump-if-true {:+}", offset),
+        Op::Peek(n) => format!("#peek {}", n),
+        Op::Store(name) => format!("#store {}", name),
+        Op::Load(name) => format!("#load {}", name),
+        Op::StoreLocal(slot) => format!("#store-local {}", slot),
+        Op::LoadLocal(slot) => format!("#load-local {}", slot),
+        Op::Capture(_) => "#capture".to_string(),
+        Op::ToAux => "#to-aux".to_string(),
+        Op::FromAux => "#from-aux".to_string(),
+        Op::CheckTimes => "#check-times".to_string(),
+        Op::CheckBool => "#check-bool".to_string(),
+        Op::NextLine => "#next-line".to_string(),
+        Op::Return => "#return".to_string(),
+        other => match builtins::op_name(other) {
+            Some(name) => name.to_string(),
+            None => format!("#{:?}", other).to_lowercase(),
+        },
+    };
+    Value::String(text)
+}
+
+/// The instruction a descriptor stands for.
+fn parse_descriptor(item: &Value) -> Result<Op, String> {
+    let text = match item {
+        Value::List(items) if items.len() == 1 => return Ok(Op::Push(items[0].clone())),
+        Value::String(text) => text,
+        other => {
+            return Err(format!(
+                "expected a string or a one-item list, got {}",
+                other.type_name()
+            ));
+        }
+    };
+
+    if let Some(internal) = text.strip_prefix('#') {
+        let (name, arg) = internal.split_once(' ').unwrap_or((internal, ""));
+        let offset = || {
+            arg.parse::<i32>()
+                .map_err(|_| format!("'{}' needs an offset like +3", text))
+        };
+        return match name {
+            "jump" => Ok(Op::Jump(offset()?)),
+            "jump-if-false" => Ok(Op::JumpIfFalse(offset()?)),
+            "jump-if-true" => Ok(Op::JumpIfTrue(offset()?)),
+            "peek" => arg
+                .parse()
+                .map(Op::Peek)
+                .map_err(|_| format!("'{}' needs a depth like 1", text)),
+            "store" | "load" if arg.is_empty() || arg.contains(char::is_whitespace) => {
+                Err(format!("'{}' needs a variable name", text))
+            }
+            "store" => Ok(Op::Store(arg.to_string())),
+            "load" => Ok(Op::Load(arg.to_string())),
+            "store-local" | "load-local" => {
+                let slot = arg
+                    .parse()
+                    .map_err(|_| format!("'{}' needs a slot like 0", text))?;
+                Ok(match name {
+                    "store-local" => Op::StoreLocal(slot),
+                    _ => Op::LoadLocal(slot),
+                })
+            }
+            "to-aux" => Ok(Op::ToAux),
+            "from-aux" => Ok(Op::FromAux),
+            "check-times" => Ok(Op::CheckTimes),
+            "check-bool" => Ok(Op::CheckBool),
+            "next-line" => Ok(Op::NextLine),
+            "return" => Ok(Op::Return),
+            _ => Err(format!("unknown descriptor '{}'", text)),
+        };
+    }
+
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return Err(format!("'{}' is not a word name", text));
+    }
+    if let Some(op) = builtins::keyword(text)
+        .and_then(|token| builtins::node_for(&token))
+        .and_then(|node| builtins::op_for(&node))
+    {
+        return Ok(op);
+    }
+    match text.split_once('.') {
+        Some((module, word)) if !module.is_empty() && !word.is_empty() => Ok(Op::CallQualified {
+            module: module.to_string(),
+            word: word.to_string(),
+        }),
+        _ => Ok(Op::CallWord(text.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_descriptors_round_trip() {
+        let ops = vec![
+            Op::Push(Value::Integer(1)),
+            Op::Gt,
+            Op::JumpIfFalse(3),
+            Op::Push(Value::String("dup".into())),
+            Op::Jump(2),
+            Op::CallWord("sq".into()),
+            Op::Peek(1),
+            Op::CallQualified {
+                module: "m".into(),
+                word: "w".into(),
+            },
+        ];
+        let descriptors: Vec<Value> = ops.iter().map(descriptor).collect();
+        assert_eq!(descriptors[1], text(">"));
+        assert_eq!(descriptors[2], text("#jump-if-false +3"));
+        // A string literal stays distinct from a call of that word
+        assert_eq!(descriptors[3], Value::List(vec![text("dup")]));
+
+        let parsed: Vec<Op> = descriptors
+            .iter()
+            .map(|d| parse_descriptor(d).unwrap())
+            .collect();
+        assert_eq!(parsed, ops);
+    }
+
+    #[test]
+    fn test_bad_descriptors() {
+        assert!(parse_descriptor(&text("#jump")).is_err());
+        assert!(parse_descriptor(&text("#frobnicate")).is_err());
+        assert!(parse_descriptor(&text("two words")).is_err());
+        assert!(parse_descriptor(&Value::Integer(3)).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use regex::Regex;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Regex words (feature = "regex")
+// =============================================================================
+//
+// The pattern is always the top string; the subject sits beneath it, matching
+// the argument order of `split`. Patterns are compiled on first use and cached
+// on the VM, so a pattern used inside a loop is only compiled once.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("re-match?", re_match),
+    ("re-find", re_find),
+    ("re-find-all", re_find_all),
+    ("re-replace", re_replace),
+    ("re-split", re_split),
+];
+
+/// ( str pattern -- bool ) True if the pattern matches anywhere in `str`.
+fn re_match(vm: &mut VmBc) -> RuntimeResult<()> {
+    let re = pop_regex(vm, "re-match?")?;
+    let s = vm.pop_string()?;
+    vm.push(Value::Bool(re.is_match(&s)));
+    Ok(())
+}
+
+/// ( str pattern -- captures ) First match as `{ whole group1 ... }`, or `{ }`
+/// if nothing matched. Groups that did not participate are empty strings.
+fn re_find(vm: &mut VmBc) -> RuntimeResult<()> {
+    let re = pop_regex(vm, "re-find")?;
+    let s = vm.pop_string()?;
+    let captures = match re.captures(&s) {
+        Some(caps) => caps
+            .iter()
+            .map(|m| Value::String(m.map(|m| m.as_str()).unwrap_or("").to_string()))
+            .collect(),
+        None => Vec::new(),
+    };
+    vm.push(Value::List(captures));
+    Ok(())
+}
+
+/// ( str pattern -- matches ) Every non-overlapping match.
+fn re_find_all(vm: &mut VmBc) -> RuntimeResult<()> {
+    let re = pop_regex(vm, "re-find-all")?;
+    let s = vm.pop_string()?;
+    let matches = re
+        .find_iter(&s)
+        .map(|m| Value::String(m.as_str().to_string()))
+        .collect();
+    vm.push(Value::List(matches));
+    Ok(())
+}
+
+/// ( str pattern replacement -- str ) Replace every match. `$1` / `${name}`
+/// in the replacement expand to capture groups.
+fn re_replace(vm: &mut VmBc) -> RuntimeResult<()> {
+    let replacement = vm.pop_string()?;
+    let re = pop_regex(vm, "re-replace")?;
+    let s = vm.pop_string()?;
+    let replaced = re.replace_all(&s, replacement.as_str()).into_owned();
+    vm.push(Value::String(replaced));
+    Ok(())
+}
+
+/// ( str pattern -- parts ) Split on every match.
+fn re_split(vm: &mut VmBc) -> RuntimeResult<()> {
+    let re = pop_regex(vm, "re-split")?;
+    let s = vm.pop_string()?;
+    let parts = re.split(&s).map(|p| Value::String(p.to_string())).collect();
+    vm.push(Value::List(parts));
+    Ok(())
+}
+
+/// Pop a pattern string and compile it, reusing the VM's cache.
+fn pop_regex(vm: &mut VmBc, word: &str) -> RuntimeResult<Regex> {
+    let pattern = vm.pop_string()?;
+    if let Some(re) = vm.regex_cache.get(&pattern) {
+        return Ok(re.clone());
+    }
+    let re = Regex::new(&pattern).map_err(|e| {
+        vm.error_with_context(format!("{}: invalid pattern: {}", word, e))
+            .with_help("Patterns use Rust regex syntax; escape literal characters with '\\'")
+            .boxed()
+    })?;
+    vm.regex_cache.insert(pattern, re.clone());
+    Ok(re)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    fn s(v: &str) -> Value {
+        Value::String(v.to_string())
+    }
+
+    #[test]
+    fn test_re_match() {
+        assert_eq!(
+            run(r#""abc123" "[0-9]+" re-match?"#).unwrap(),
+            vec![Value::Bool(true)]
+        );
+        assert_eq!(
+            run(r#""abc" "^[0-9]+$" re-match?"#).unwrap(),
+            vec![Value::Bool(false)]
+        );
+    }
+
+    #[test]
+    fn test_re_find() {
+        assert_eq!(
+            run(r#""key=value" "(\\w+)=(\\w+)" re-find"#).unwrap(),
+            vec![Value::List(vec![s("key=value"), s("key"), s("value")])]
+        );
+        assert_eq!(
+            run(r#""nothing" "[0-9]" re-find"#).unwrap(),
+            vec![Value::List(vec![])]
+        );
+    }
+
+    #[test]
+    fn test_re_find_all() {
+        assert_eq!(
+            run(r#""a1 b22 c333" "[0-9]+" re-find-all"#).unwrap(),
+            vec![Value::List(vec![s("1"), s("22"), s("333")])]
+        );
+    }
+
+    #[test]
+    fn test_re_replace() {
+        assert_eq!(
+            run(r#""2024-01-15" "(\\d+)-(\\d+)-(\\d+)" "$3/$2/$1" re-replace"#).unwrap(),
+            vec![s("15/01/2024")]
+        );
+    }
+
+    #[test]
+    fn test_re_split() {
+        assert_eq!(
+            run(r#""a, b;c" "[,;] *" re-split"#).unwrap(),
+            vec![Value::List(vec![s("a"), s("b"), s("c")])]
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let err = run(r#""abc" "(" re-match?"#).unwrap_err();
+        assert!(err.contains("re-match?: invalid pattern"), "{}", err);
+    }
+
+    #[test]
+    fn test_user_word_shadows_native() {
+        assert_eq!(
+            run("def re-split 42 end \"a\" re-split").unwrap(),
+            vec![s("a"), Value::Integer(42)]
+        );
+    }
+}
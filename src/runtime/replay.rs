@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Input record and replay
+// =============================================================================
+//
+// Console input is the one nondeterministic source every program can reach
+// (`read`, `stdin-lines`, `each-line`), so `--record` saves exactly the bytes
+// a run consumed and `--replay` feeds them back to reproduce it. The log is
+// a header line followed by the raw input. Results of processes, sockets
+// and the file system are not recorded.
+
+const HEADER: &[u8] = b"EMBER-INPUT-LOG 1\n";
+
+/// Where a VM's input is recorded to or replayed from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputLog {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl InputLog {
+    /// Set up `vm`'s input: tee it into a new log, or read it from one.
+    pub fn attach(&self, vm: &mut VmBc) -> io::Result<()> {
+        match self {
+            InputLog::Record(path) => {
+                let mut log = File::create(path)?;
+                log.write_all(HEADER)?;
+                vm.record_input(log);
+            }
+            InputLog::Replay(path) => vm.set_input(read_log(path)?),
+        }
+        Ok(())
+    }
+}
+
+fn read_log(path: &Path) -> io::Result<Cursor<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    match bytes.strip_prefix(HEADER) {
+        Some(input) => Ok(Cursor::new(input.to_vec())),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("'{}' is not an input log", path.display()),
+        )),
+    }
+}
+
+/// Reads from `inner` and copies each consumed byte to `log`, so bytes
+/// buffered but never read by the program aren't recorded.
+pub(crate) struct Recording<R, W> {
+    pub(crate) inner: R,
+    pub(crate) log: W,
+}
+
+impl<R: BufRead, W: Write> Read for Recording<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.log.write_all(&buf[..n])?;
+        self.log.flush()?;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead, W: Write> BufRead for Recording<R, W> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        // `consume` can't fail; a log that stops growing is the symptom.
+        // Flushed each time so a run that crashes still leaves its input
+        if let Ok(buf) = self.inner.fill_buf() {
+            let _ = self.log.write_all(&buf[..amt.min(buf.len())]);
+            let _ = self.log.flush();
+        }
+        self.inner.consume(amt);
+    }
+}
+
+/// Stands in for the VM's input while it is being wrapped.
+pub(crate) fn empty_input() -> Box<dyn BufRead> {
+    Box::new(BufReader::new(io::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_only_consumed_input() {
+        let mut recording = Recording {
+            inner: Cursor::new(b"one\ntwo\nthree\n".to_vec()),
+            log: Vec::new(),
+        };
+        let mut line = String::new();
+        recording.read_line(&mut line).unwrap();
+        recording.read_line(&mut line).unwrap();
+        assert_eq!(line, "one\ntwo\n");
+        assert_eq!(recording.log, b"one\ntwo\n");
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        let path = std::env::temp_dir().join("ember-not-a-log.txt");
+        std::fs::write(&path, "hello").unwrap();
+        let err = read_log(&path).unwrap_err();
+        assert!(err.to_string().contains("is not an input log"));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::frontend::lexer::Span;
+use crate::lang::value::Value;
+use std::borrow::Cow;
 use std::fmt;
 use std::path::PathBuf;
 
@@ -14,6 +16,11 @@ pub struct RuntimeError {
     pub file: Option<PathBuf>,
     pub call_stack: Vec<String>,
     pub help: Option<String>,
+    /// The value passed to `throw`, which `try` hands to its handler
+    pub thrown: Option<Value>,
+    /// Whether `try` may catch the error; interrupts and the step and time
+    /// limits are not caught, so a script can't swallow them
+    pub catchable: bool,
 }
 
 impl RuntimeError {
@@ -25,6 +32,8 @@ impl RuntimeError {
             file: None,
             call_stack: Vec::new(),
             help: None,
+            thrown: None,
+            catchable: true,
         }
     }
 
@@ -52,6 +61,16 @@ impl RuntimeError {
         self
     }
 
+    pub fn with_thrown(mut self, value: Value) -> Self {
+        self.thrown = Some(value);
+        self
+    }
+
+    pub fn uncatchable(mut self) -> Self {
+        self.catchable = false;
+        self
+    }
+
     pub fn with_context(mut self, word: &str) -> Self {
         if !word.is_empty() {
             self.call_stack.push(word.to_string());
@@ -71,6 +90,16 @@ impl RuntimeError {
         }
     }
 
+    /// The source the span points into: `source`, or else the file, which
+    /// errors in words from another file leave to be read here.
+    fn source_text(&self) -> Option<Cow<'_, str>> {
+        match (&self.source, &self.file) {
+            (Some(source), _) => Some(Cow::Borrowed(source)),
+            (None, Some(file)) => std::fs::read_to_string(file).ok().map(Cow::Owned),
+            (None, None) => None,
+        }
+    }
+
     /// Format error with beautiful context
     pub fn display_with_context(&self) -> String {
         let mut output = String::new();
@@ -92,7 +121,7 @@ impl RuntimeError {
             }
 
             // Source context
-            if let Some(source) = &self.source {
+            if let Some(source) = self.source_text() {
                 let lines: Vec<&str> = source.lines().collect();
                 if span.line > 0 && span.line <= lines.len() {
                     let line_idx = span.line - 1;
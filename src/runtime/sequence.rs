@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use crate::bytecode::op::Op;
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Sequences
+// =============================================================================
+//
+// A sequence produces its items on demand, so libraries can offer ranges,
+// readers and tree walks that `each`, `map`, `filter` and `fold` consume one
+// item at a time, as they do lists. A sequence is a map holding a `state`
+// and a `next` quotation, which turns the state into the next one and an
+// item, or says the sequence is done:
+//
+//     ( state -- state' item true )    or    ( state -- false )
+//
+// `sequence` builds one from the two; a library can equally build the map
+// itself. Sequences are plain values: iterating one never changes it, so it
+// can be iterated again from the start. `map` and `filter` over a sequence
+// collect their results into a list, which for an endless sequence never
+// finishes; `seq-take` takes a bounded prefix.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("sequence", sequence), ("seq-take", seq_take)];
+
+/// ( state next -- seq ) Make a sequence from its first state and its
+/// `next` quotation.
+fn sequence(vm: &mut VmBc) -> RuntimeResult<()> {
+    let next = vm.pop()?;
+    if !matches!(next, Value::CompiledQuotation(_)) {
+        return Err(vm.type_error_with_context("quotation", next.type_name()));
+    }
+    let state = vm.pop()?;
+    let entries = BTreeMap::from([("next".to_string(), next), ("state".to_string(), state)]);
+    vm.push(Value::Map(entries));
+    Ok(())
+}
+
+/// ( seq n -- list ) The first `n` items of a sequence or list, or all of
+/// them if there are fewer.
+fn seq_take(vm: &mut VmBc) -> RuntimeResult<()> {
+    let n = usize::try_from(vm.pop_int()?).unwrap_or(0);
+    let mut items = Items::pop(vm, "seq-take")?;
+    let mut taken = Vec::new();
+    while taken.len() < n {
+        match items.next(vm)? {
+            Some((_, item)) => taken.push(item),
+            None => break,
+        }
+    }
+    vm.push(Value::List(taken));
+    Ok(())
+}
+
+/// What a combinator iterates over: a list, or a sequence's items as they
+/// are produced.
+pub(crate) enum Items {
+    List(std::iter::Enumerate<std::vec::IntoIter<Value>>),
+    /// A sequence's current state (`None` once it is done), its `next`
+    /// quotation, and how many items it has produced
+    Sequence {
+        state: Option<Value>,
+        next: Vec<Op>,
+        produced: usize,
+    },
+}
+
+impl Items {
+    /// Pop a list or sequence for `word` to iterate over.
+    pub(crate) fn pop(vm: &mut VmBc, word: &str) -> RuntimeResult<Self> {
+        match vm.pop()? {
+            Value::List(items) => Ok(Items::List(items.into_iter().enumerate())),
+            Value::Map(mut entries) if entries.contains_key("next") => {
+                match (entries.remove("state"), entries.remove("next")) {
+                    (Some(state), Some(Value::CompiledQuotation(next))) => Ok(Items::Sequence {
+                        state: Some(state),
+                        next,
+                        produced: 0,
+                    }),
+                    _ => Err(vm
+                        .error_with_context(format!(
+                            "{}: a sequence needs a 'state' and a 'next' quotation",
+                            word
+                        ))
+                        .with_help("Build sequences with `sequence ( state next -- seq )`")
+                        .boxed()),
+                }
+            }
+            other => Err(vm.type_error_with_context("list or sequence", other.type_name())),
+        }
+    }
+
+    /// The next item and its index, or `None` when there are no more.
+    pub(crate) fn next(&mut self, vm: &mut VmBc) -> RuntimeResult<Option<(usize, Value)>> {
+        match self {
+            Items::List(items) => Ok(items.next()),
+            Items::Sequence {
+                state,
+                next,
+                produced,
+            } => {
+                let Some(current) = state.take() else {
+                    return Ok(None);
+                };
+                let height = vm.stack().len();
+                vm.push(current);
+                vm.exec_ops(next)?;
+                let left = vm.stack().len() as i64 - height as i64;
+                match (vm.stack().last(), left) {
+                    (Some(Value::Bool(true)), 3) => {
+                        vm.pop()?;
+                        let item = vm.pop()?;
+                        *state = Some(vm.pop()?);
+                        *produced += 1;
+                        Ok(Some((*produced - 1, item)))
+                    }
+                    (Some(Value::Bool(false)), 1) => {
+                        vm.pop()?;
+                        Ok(None)
+                    }
+                    _ => Err(RuntimeError::new(&format!(
+                        "sequence: 'next' must leave `state item true` or `false`, but it left {} value(s) with {} on top",
+                        left.max(0),
+                        vm.stack().last().map_or("nothing", Value::type_name)
+                    ))
+                    .boxed()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run;
+
+    fn ints(ns: &[i64]) -> Value {
+        Value::List(ns.iter().map(|&n| Value::Integer(n)).collect())
+    }
+
+    /// The integers from 0 below `n`, as a sequence.
+    const UPTO: &str = "
+        def upto
+          0 swap pair
+          [ unpair over over < [ over 1 + swap pair swap true ] [ drop drop false ] if ]
+          sequence
+        end
+    ";
+
+    #[test]
+    fn test_combinators_take_sequences() {
+        let src = |body: &str| format!("{} {}", UPTO, body);
+        assert_eq!(
+            run(&src("4 upto [ 10 * ] map")).unwrap(),
+            vec![ints(&[0, 10, 20, 30])]
+        );
+        assert_eq!(
+            run(&src("6 upto [ 2 % 0 = ] filter")).unwrap(),
+            vec![ints(&[0, 2, 4])]
+        );
+        assert_eq!(
+            run(&src("5 upto 0 [ + ] fold")).unwrap(),
+            vec![Value::Integer(10)]
+        );
+        assert_eq!(
+            run(&src("0 3 upto [ + ] each")).unwrap(),
+            vec![Value::Integer(3)]
+        );
+        // A sequence is a value: iterating it again starts over
+        assert_eq!(
+            run(&src("3 upto dup [ ] map swap [ ] map")).unwrap(),
+            vec![ints(&[0, 1, 2]), ints(&[0, 1, 2])]
+        );
+    }
+
+    #[test]
+    fn test_seq_take() {
+        let naturals = "0 [ dup 1 + swap true ] sequence";
+        assert_eq!(
+            run(&format!("{} 3 seq-take", naturals)).unwrap(),
+            vec![ints(&[0, 1, 2])]
+        );
+        assert_eq!(run("{ 1 2 } 5 seq-take").unwrap(), vec![ints(&[1, 2])]);
+    }
+
+    #[test]
+    fn test_bad_next() {
+        let err = run("0 [ true ] sequence [ ] map").unwrap_err();
+        assert!(err.starts_with("sequence: 'next' must leave"), "{}", err);
+        let err = run("5 [ ] each").unwrap_err();
+        assert!(err.contains("list or sequence"), "{}", err);
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, params_from_iter};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// SQLite words (feature = "sqlite")
+// =============================================================================
+//
+// Databases live in a table on the VM and scripts hold them as integer
+// handles, like sockets. `sqlite-open` is refused when the VM's `allow_fs`
+// flag is off, even for `":memory:"`.
+//
+// Statements take their parameters as a list bound to the `?` placeholders
+// in order, so values never need to be spliced into SQL text:
+//
+//     db @ "SELECT name FROM users WHERE age > ?" { 30 } sqlite-query
+//
+// Integers, floats, strings and bytes map to SQLite's INTEGER, REAL, TEXT
+// and BLOB, and bools are stored as 0 and 1. Rows come back as maps from
+// column name to value; EMBER has no null, so a NULL column is left out of
+// its row's map.
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("sqlite-open", sqlite_open),
+    ("sqlite-exec", sqlite_exec),
+    ("sqlite-query", sqlite_query),
+    ("sqlite-close", sqlite_close),
+];
+
+/// Open databases, keyed by the handle given to the script.
+#[derive(Default)]
+pub struct Databases {
+    next_handle: i64,
+    open: HashMap<i64, Connection>,
+}
+
+/// ( path -- db ) Open a database file, creating it if it is missing;
+/// `":memory:"` opens a private in-memory database.
+fn sqlite_open(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("sqlite-open")?;
+    let path = vm.pop_string()?;
+    let conn = Connection::open(&path).map_err(|e| sql_error(vm, "sqlite-open", e))?;
+    let databases = &mut vm.databases;
+    databases.next_handle += 1;
+    databases.open.insert(databases.next_handle, conn);
+    let handle = databases.next_handle;
+    vm.push(Value::Integer(handle));
+    Ok(())
+}
+
+/// ( db sql params -- changes ) Run a statement and return how many rows
+/// it changed. With no params the SQL may hold several statements, and the
+/// count is that of the last one.
+fn sqlite_exec(vm: &mut VmBc) -> RuntimeResult<()> {
+    let params = pop_params(vm, "sqlite-exec")?;
+    let sql = vm.pop_string()?;
+    let handle = vm.pop_int()?;
+    let conn = database(vm, "sqlite-exec", handle)?;
+    let changed = if params.is_empty() {
+        conn.execute_batch(&sql).map(|()| conn.changes() as usize)
+    } else {
+        conn.execute(&sql, params_from_iter(params))
+    };
+    let changed = changed.map_err(|e| sql_error(vm, "sqlite-exec", e))?;
+    vm.push(Value::Integer(changed as i64));
+    Ok(())
+}
+
+/// ( db sql params -- rows ) Run a query and return its rows as a list of
+/// maps.
+fn sqlite_query(vm: &mut VmBc) -> RuntimeResult<()> {
+    let params = pop_params(vm, "sqlite-query")?;
+    let sql = vm.pop_string()?;
+    let handle = vm.pop_int()?;
+    let conn = database(vm, "sqlite-query", handle)?;
+    let rows = query(conn, &sql, params).map_err(|e| sql_error(vm, "sqlite-query", e))?;
+    vm.push(Value::List(rows));
+    Ok(())
+}
+
+/// ( db -- ) Close a database. Closing an unknown handle is an error.
+fn sqlite_close(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    if vm.databases.open.remove(&handle).is_none() {
+        return Err(unknown_handle(vm, "sqlite-close", handle));
+    }
+    Ok(())
+}
+
+fn query(conn: &Connection, sql: &str, params: Vec<SqlValue>) -> rusqlite::Result<Vec<Value>> {
+    let mut statement = conn.prepare(sql)?;
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut rows = statement.query(params_from_iter(params))?;
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut map = BTreeMap::new();
+        for (i, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Null => continue,
+                ValueRef::Integer(n) => Value::Integer(n),
+                ValueRef::Real(x) => Value::Float(x),
+                ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(bytes) => Value::Bytes(bytes.to_vec()),
+            };
+            map.insert(column.clone(), value);
+        }
+        result.push(Value::Map(map));
+    }
+    Ok(result)
+}
+
+/// Pop the list of values bound to a statement's `?` placeholders.
+fn pop_params(vm: &mut VmBc, word: &str) -> RuntimeResult<Vec<SqlValue>> {
+    vm.pop_list()?
+        .into_iter()
+        .map(|value| match value {
+            Value::Integer(n) => Ok(SqlValue::Integer(n)),
+            Value::Float(x) => Ok(SqlValue::Real(x)),
+            Value::String(s) => Ok(SqlValue::Text(s)),
+            Value::Bool(b) => Ok(SqlValue::Integer(b as i64)),
+            Value::Bytes(bytes) => Ok(SqlValue::Blob(bytes)),
+            other => Err(vm
+                .error_with_context(format!(
+                    "{}: a {} can't be a statement parameter",
+                    word,
+                    other.type_name()
+                ))
+                .with_help("Parameters are integers, floats, strings, bools or bytes")
+                .boxed()),
+        })
+        .collect()
+}
+
+fn database<'a>(vm: &'a VmBc, word: &str, handle: i64) -> RuntimeResult<&'a Connection> {
+    vm.databases
+        .open
+        .get(&handle)
+        .ok_or_else(|| unknown_handle(vm, word, handle))
+}
+
+fn unknown_handle(vm: &VmBc, word: &str, handle: i64) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: {} is not an open database", word, handle))
+        .with_help("Database handles come from sqlite-open")
+        .boxed()
+}
+
+fn sql_error(vm: &VmBc, word: &str, e: rusqlite::Error) -> Box<RuntimeError> {
+    let err = vm.error_with_context(format!("{}: {}", word, e));
+    match e {
+        rusqlite::Error::InvalidParameterCount(..) => err
+            .with_help("Pass one value in the params list for each `?` in the SQL")
+            .boxed(),
+        _ => err.boxed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::{run, run_with};
+    use crate::runtime::vm_bc::VmBcConfig;
+
+    #[test]
+    fn test_exec_and_query() {
+        let stack = run(r#"
+            var db
+            ":memory:" sqlite-open db !
+            db @ "CREATE TABLE users (name TEXT, age INTEGER, note TEXT); CREATE INDEX by_age ON users (age)"
+            { } sqlite-exec drop
+            db @ "INSERT INTO users (name, age) VALUES (?, ?)" { "ada" 36 } sqlite-exec
+            db @ "INSERT INTO users VALUES (?, ?, ?)" { "bob" 25 "new" } sqlite-exec
+            db @ "SELECT * FROM users WHERE age > ? ORDER BY age" { 20 } sqlite-query
+            db @ sqlite-close
+        "#)
+        .unwrap();
+        let row = |name: &str, age: i64, note: Option<&str>| {
+            let mut map = std::collections::BTreeMap::new();
+            map.insert("name".to_string(), Value::String(name.into()));
+            map.insert("age".to_string(), Value::Integer(age));
+            if let Some(note) = note {
+                map.insert("note".to_string(), Value::String(note.into()));
+            }
+            Value::Map(map)
+        };
+        assert_eq!(
+            stack,
+            vec![
+                Value::Integer(1),
+                Value::Integer(1),
+                Value::List(vec![row("bob", 25, Some("new")), row("ada", 36, None)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = run(r#"":memory:" sqlite-open "SELEKT 1" { } sqlite-query"#).unwrap_err();
+        assert!(err.starts_with("sqlite-query: "), "{}", err);
+        let err = run(r#"":memory:" sqlite-open "SELECT ?" { } sqlite-query"#).unwrap_err();
+        assert!(err.starts_with("sqlite-query: "), "{}", err);
+        let err = run(r#"":memory:" sqlite-open "SELECT ?" { { 1 } } sqlite-query"#).unwrap_err();
+        assert_eq!(err, "sqlite-query: a list can't be a statement parameter");
+        let err = run(r#"7 "SELECT 1" { } sqlite-query"#).unwrap_err();
+        assert_eq!(err, "sqlite-query: 7 is not an open database");
+
+        let config = VmBcConfig {
+            allow_fs: false,
+            ..Default::default()
+        };
+        let err = run_with(config, r#"":memory:" sqlite-open"#).unwrap_err();
+        assert_eq!(err, "sqlite-open: file system access is disabled");
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::Deserialize;
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Key-value store
+// =============================================================================
+//
+// `store-save` writes a Map to disk and `store-load` reads it back, so scripts
+// can keep state between runs. The file is a short magic header followed by
+// the map in postcard encoding, which round-trips every data value exactly
+// (integers stay integers, floats keep all their bits). Quotations are code,
+// not data, and are rejected. Both words need `allow_fs`.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("store-save", store_save), ("store-load", store_load)];
+
+/// Identifies store files; the last byte is the format version.
+const MAGIC: &[u8] = b"EMST\x02";
+
+/// Version 1 files were written before `Value::Bytes` existed, which moved
+/// the encoding of maps. They still load.
+const MAGIC_V1: &[u8] = b"EMST\x01";
+
+/// ( map path -- ) Write `map` to `path`, replacing any existing file.
+fn store_save(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("store-save")?;
+    let path = vm.pop_string()?;
+    let entries = match vm.pop()? {
+        Value::Map(entries) => entries,
+        other => return Err(vm.type_error_with_context("map", other.type_name())),
+    };
+
+    for (key, value) in &entries {
+        if let Some(kind) = find_code(value) {
+            return Err(vm
+                .error_with_context(format!(
+                    "store-save: cannot store a {} (under key '{}')",
+                    kind, key
+                ))
+                .with_help(
+                    "Only data values can be stored: numbers, strings, booleans, lists and maps",
+                )
+                .boxed());
+        }
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    let body = postcard::to_allocvec(&entries)
+        .map_err(|e| vm.error_with_context(format!("store-save: {}", e)).boxed())?;
+    bytes.extend(body);
+    fs::write(&path, bytes).map_err(|e| file_error(vm, "store-save", &path, e))
+}
+
+/// ( path -- map ) Read a map written by `store-save`.
+fn store_load(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_fs("store-load")?;
+    let path = vm.pop_string()?;
+    let bytes = fs::read(&path).map_err(|e| file_error(vm, "store-load", &path, e))?;
+
+    let entries = decode(&bytes).ok_or_else(|| {
+        vm.error_with_context(format!("store-load: '{}' is not a valid store file", path))
+            .with_help("Store files are written by store-save")
+            .boxed()
+    })?;
+    vm.push(Value::Map(entries));
+    Ok(())
+}
+
+/// The map in a store file of any version, or None if it isn't one.
+fn decode(bytes: &[u8]) -> Option<BTreeMap<String, Value>> {
+    if let Some(body) = bytes.strip_prefix(MAGIC) {
+        return postcard::from_bytes(body).ok();
+    }
+    let body = bytes.strip_prefix(MAGIC_V1)?;
+    let entries: BTreeMap<String, StoredV1> = postcard::from_bytes(body).ok()?;
+    Some(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+}
+
+/// A stored value as version 1 encodes it: the data variants of `Value`,
+/// numbered as they were then.
+#[derive(Deserialize)]
+enum StoredV1 {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    List(Vec<StoredV1>),
+    Map(BTreeMap<String, StoredV1>),
+}
+
+impl From<StoredV1> for Value {
+    fn from(old: StoredV1) -> Self {
+        match old {
+            StoredV1::Integer(n) => Value::Integer(n),
+            StoredV1::Float(x) => Value::Float(x),
+            StoredV1::String(s) => Value::String(s),
+            StoredV1::Bool(b) => Value::Bool(b),
+            StoredV1::List(items) => Value::List(items.into_iter().map(Into::into).collect()),
+            StoredV1::Map(entries) => {
+                Value::Map(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+/// Name of the first quotation found inside `value`, if any.
+fn find_code(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::Quotation(_) | Value::CompiledQuotation(_) => Some("quotation"),
+        Value::List(items) => items.iter().find_map(find_code),
+        Value::Map(entries) => entries.values().find_map(find_code),
+        _ => None,
+    }
+}
+
+fn file_error(vm: &VmBc, word: &str, path: &str, e: std::io::Error) -> Box<RuntimeError> {
+    vm.error_with_context(format!("{}: cannot access '{}': {}", word, path, e))
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::run_in;
+    use crate::runtime::vm_bc::VmBc;
+
+    /// Run `source` with `initial` already on the stack.
+    fn run_on(initial: Vec<Value>, source: &str) -> Result<Vec<Value>, String> {
+        let mut vm = VmBc::new();
+        for value in initial {
+            vm.push(value);
+        }
+        run_in(&mut vm, source)
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ember-store-{}-{}", std::process::id(), name))
+    }
+
+    fn sample() -> BTreeMap<String, Value> {
+        let mut inner = BTreeMap::new();
+        inner.insert("nested".to_string(), Value::Bool(true));
+        let mut map = BTreeMap::new();
+        map.insert("count".to_string(), Value::Integer(3));
+        map.insert("ratio".to_string(), Value::Float(0.1));
+        map.insert("name".to_string(), Value::String("ember".to_string()));
+        map.insert(
+            "items".to_string(),
+            Value::List(vec![Value::Integer(1), Value::Map(inner)]),
+        );
+        map
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let path = temp_path("round-trip");
+        let p = path.display();
+        let stack = run_on(
+            vec![Value::Map(sample())],
+            &format!(r#""{p}" store-save "{p}" store-load"#),
+        )
+        .unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(stack, vec![Value::Map(sample())]);
+    }
+
+    #[test]
+    fn test_store_loads_version_1_files() {
+        // `sample()` as store-save wrote it before `Value::Bytes` existed
+        let old: &[u8] = &[
+            69, 77, 83, 84, 1, 4, 5, 99, 111, 117, 110, 116, 0, 6, 5, 105, 116, 101, 109, 115, 4,
+            2, 0, 2, 5, 1, 6, 110, 101, 115, 116, 101, 100, 3, 1, 4, 110, 97, 109, 101, 2, 5, 101,
+            109, 98, 101, 114, 5, 114, 97, 116, 105, 111, 1, 154, 153, 153, 153, 153, 153, 185, 63,
+        ];
+        let path = temp_path("version-1");
+        fs::write(&path, old).unwrap();
+        let stack = run_on(vec![], &format!(r#""{}" store-load"#, path.display())).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(stack, vec![Value::Map(sample())]);
+    }
+
+    #[test]
+    fn test_store_rejects_quotations() {
+        let mut map = sample();
+        map.insert(
+            "code".to_string(),
+            Value::List(vec![Value::CompiledQuotation(vec![])]),
+        );
+        let path = temp_path("quotation");
+        let err = run_on(
+            vec![Value::Map(map)],
+            &format!(r#""{}" store-save"#, path.display()),
+        )
+        .unwrap_err();
+        assert!(!path.exists());
+        assert!(
+            err.contains("cannot store a quotation (under key 'code')"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_store_load_errors() {
+        let err = run_on(vec![], r#""/no/such/store" store-load"#).unwrap_err();
+        assert!(err.contains("cannot access '/no/such/store'"), "{}", err);
+
+        let path = temp_path("corrupt");
+        fs::write(&path, b"not a store").unwrap();
+        let err = run_on(vec![], &format!(r#""{}" store-load"#, path.display())).unwrap_err();
+        fs::remove_file(&path).ok();
+        assert!(err.contains("is not a valid store file"), "{}", err);
+    }
+
+    #[test]
+    fn test_store_save_requires_map() {
+        let err = run_on(vec![], r#"{ 1 2 } "x" store-save"#).unwrap_err();
+        assert!(err.contains("expected map, got list"), "{}", err);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::lang::value::{FloatFormat, Value};
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// String words
+// =============================================================================
+//
+// `format` builds a string from a template and a list of values, in place of
+// chains of `to-string` and `.`:
+//
+//     "{} of {} done ({:.1}%)" { 3 10 30.0 } format    ; "3 of 10 done (30.0%)"
+//
+// The placeholders follow Rust's: `{}` takes the next value and `{2}` the
+// value at index 2, and `{{` and `}}` are literal braces. After a colon
+// come an optional alignment (`<` or `>`), a width and a precision:
+// `{:>6}`, `{:8.2}`, `{1:.3}`. Values print as `to-string` prints them;
+// numbers are right-aligned by default and everything else left-aligned,
+// and a precision gives a number that many decimal places.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("format", format)];
+
+/// ( template values -- str ) Fill the template's placeholders with values
+/// from the list.
+fn format(vm: &mut VmBc) -> RuntimeResult<()> {
+    let values = vm.pop_list()?;
+    let template = vm.pop_string()?;
+    let text = render(&template, &values, vm.config().float_format).map_err(|e| {
+        vm.error_with_context(format!("format: {}", e))
+            .with_help("Placeholders are {} for the next value or {0} for one by index, {{ and }} for braces")
+            .boxed()
+    })?;
+    vm.push(Value::String(text));
+    Ok(())
+}
+
+fn render(template: &str, values: &[Value], floats: FloatFormat) -> Result<String, String> {
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed '{{' in \"{}\"", template))?;
+                let placeholder = &rest[..end];
+                chars = rest[end + 1..].chars();
+
+                let (index, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+                let index = match index {
+                    "" => {
+                        next += 1;
+                        next - 1
+                    }
+                    index => index
+                        .parse()
+                        .map_err(|_| format!("'{{{}}}' is not a placeholder", placeholder))?,
+                };
+                let value = values.get(index).ok_or_else(|| {
+                    format!(
+                        "no value for placeholder {} (given {})",
+                        index,
+                        values.len()
+                    )
+                })?;
+                let spec = Spec::parse(spec)
+                    .ok_or_else(|| format!("'{{{}}}' has a bad format spec", placeholder))?;
+                out.push_str(&spec.apply(value, floats)?);
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '}' => return Err(format!("unmatched '}}' in \"{}\"", template)),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// The part of a placeholder after the colon.
+struct Spec {
+    /// `Some(true)` to align left, `Some(false)` to align right
+    left: Option<bool>,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Spec> {
+        let (left, spec) = match spec.strip_prefix('<') {
+            Some(rest) => (Some(true), rest),
+            None => match spec.strip_prefix('>') {
+                Some(rest) => (Some(false), rest),
+                None => (None, spec),
+            },
+        };
+        let (width, precision) = match spec.split_once('.') {
+            Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+            None => (spec, None),
+        };
+        let width = if width.is_empty() {
+            0
+        } else {
+            width.parse().ok()?
+        };
+        Some(Spec {
+            left,
+            width,
+            precision,
+        })
+    }
+
+    fn apply(&self, value: &Value, floats: FloatFormat) -> Result<String, String> {
+        let number = match value {
+            Value::Integer(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        };
+        let text = match (self.precision, number) {
+            (Some(places), Some(x)) => format!("{:.*}", places, x),
+            (Some(_), None) => {
+                return Err(format!(
+                    "a precision needs a number, not a {}",
+                    value.type_name()
+                ));
+            }
+            (None, _) => value.display(floats).to_string(),
+        };
+        let width = self.width;
+        if self.left.unwrap_or(number.is_none()) {
+            Ok(format!("{:<width$}", text))
+        } else {
+            Ok(format!("{:>width$}", text))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::lang::value::{FloatFormat, Value};
+
+    fn fill(template: &str, values: &[Value]) -> Result<String, String> {
+        render(template, values, FloatFormat::default())
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let values = [
+            Value::Integer(3),
+            Value::String("ab".into()),
+            Value::Float(2.5),
+        ];
+        assert_eq!(fill("{} {} {}", &values).unwrap(), "3 ab 2.5");
+        assert_eq!(fill("{2}/{0}/{2}", &values).unwrap(), "2.5/3/2.5");
+        assert_eq!(fill("{{{}}}", &values).unwrap(), "{3}");
+        assert_eq!(fill("[{:4}][{1:4}]", &values).unwrap(), "[   3][ab  ]");
+        assert_eq!(fill("[{:<4}][{1:>4}]", &values).unwrap(), "[3   ][  ab]");
+        assert_eq!(fill("{:.2} {2:6.3}", &values).unwrap(), "3.00  2.500");
+    }
+
+    #[test]
+    fn test_errors() {
+        let values = [Value::String("x".into())];
+        assert_eq!(
+            fill("{} {}", &values).unwrap_err(),
+            "no value for placeholder 1 (given 1)"
+        );
+        assert_eq!(
+            fill("{:.1}", &values).unwrap_err(),
+            "a precision needs a number, not a string"
+        );
+        assert_eq!(
+            fill("{name}", &values).unwrap_err(),
+            "'{name}' is not a placeholder"
+        );
+        assert_eq!(
+            fill("{:x}", &values).unwrap_err(),
+            "'{:x}' has a bad format spec"
+        );
+        assert_eq!(fill("{", &values).unwrap_err(), "unclosed '{' in \"{\"");
+        assert_eq!(
+            fill("a}b", &values).unwrap_err(),
+            "unmatched '}' in \"a}b\""
+        );
+    }
+}
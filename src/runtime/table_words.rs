@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+
+use crate::lang::value::{FloatFormat, Value};
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::RuntimeResult;
+use crate::runtime::vm_bc::VmBc;
+
+// =============================================================================
+// Table words
+// =============================================================================
+//
+// `print-table` shows a list of rows as an aligned table, for a quick look
+// at data. Rows are all lists (one cell per item) or all maps (one column
+// per key). The header names the columns: for map rows it also picks and
+// orders the keys, and an empty header shows every key, sorted. Numbers are
+// right-aligned, everything else left-aligned.
+
+pub const WORDS: &[(&str, NativeFn)] = &[("print-table", print_table)];
+
+/// ( rows header -- ) Print rows as an aligned table.
+fn print_table(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_io("print-table")?;
+    let header = vm.pop_list()?;
+    let rows = vm.pop_list()?;
+    let floats = vm.config().float_format;
+    let header: Vec<String> = header
+        .iter()
+        .map(|name| match name {
+            Value::String(s) => s.clone(),
+            other => other.display(floats).to_string(),
+        })
+        .collect();
+    let (header, cells) = table_cells(rows, header, floats).map_err(|e| {
+        vm.error_with_context(format!("print-table: {}", e))
+            .with_help("Give a list of lists, or a list of maps, and a list of column names")
+            .boxed()
+    })?;
+    vm.write_output(format_args!("{}", render_table(&header, &cells)))
+}
+
+/// One table cell: its text, and whether it is right-aligned.
+struct Cell {
+    text: String,
+    numeric: bool,
+}
+
+impl Cell {
+    fn of(value: &Value, floats: FloatFormat) -> Self {
+        Cell {
+            text: match value {
+                Value::String(s) => s.clone(),
+                other => other.display(floats).to_string(),
+            },
+            numeric: matches!(value, Value::Integer(_) | Value::Float(_)),
+        }
+    }
+
+    fn empty() -> Self {
+        Cell {
+            text: String::new(),
+            numeric: false,
+        }
+    }
+}
+
+/// The header and cells of the table for `rows`.
+fn table_cells(
+    rows: Vec<Value>,
+    header: Vec<String>,
+    floats: FloatFormat,
+) -> Result<(Vec<String>, Vec<Vec<Cell>>), String> {
+    let maps = matches!(rows.first(), Some(Value::Map(_)));
+    let header = if maps && header.is_empty() {
+        let mut keys = BTreeSet::new();
+        for row in &rows {
+            if let Value::Map(entries) = row {
+                keys.extend(entries.keys().cloned());
+            }
+        }
+        keys.into_iter().collect()
+    } else {
+        header
+    };
+
+    let mut cells = Vec::with_capacity(rows.len());
+    for (i, row) in rows.into_iter().enumerate() {
+        cells.push(match row {
+            Value::Map(entries) if maps => header
+                .iter()
+                .map(|key| {
+                    entries
+                        .get(key)
+                        .map_or_else(Cell::empty, |v| Cell::of(v, floats))
+                })
+                .collect(),
+            Value::List(items) if !maps => items.iter().map(|v| Cell::of(v, floats)).collect(),
+            other => {
+                return Err(format!(
+                    "row {}: expected a {} like the first row, got {}",
+                    i,
+                    if maps { "map" } else { "list" },
+                    other.type_name()
+                ));
+            }
+        });
+    }
+    Ok((header, cells))
+}
+
+/// Draw the table, with the header (if any) ruled off from the rows.
+/// Short rows are padded with empty cells; a table with no columns draws
+/// nothing.
+fn render_table(header: &[String], rows: &[Vec<Cell>]) -> String {
+    let columns = rows
+        .iter()
+        .map(Vec::len)
+        .chain([header.len()])
+        .max()
+        .unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let mut widths = vec![0; columns];
+    for (i, name) in header.iter().enumerate() {
+        widths[i] = name.chars().count();
+    }
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.text.chars().count());
+        }
+    }
+
+    let rule = {
+        let dashes: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+        format!("+{}+\n", dashes.join("+"))
+    };
+    let line = |texts: &mut dyn Iterator<Item = (String, bool)>| {
+        let padded: Vec<String> = widths
+            .iter()
+            .map(|&width| {
+                let (text, right) = texts.next().unwrap_or_default();
+                if right {
+                    format!(" {:>width$} ", text)
+                } else {
+                    format!(" {:<width$} ", text)
+                }
+            })
+            .collect();
+        format!("|{}|\n", padded.join("|"))
+    };
+
+    let mut out = rule.clone();
+    if !header.is_empty() {
+        out.push_str(&line(&mut header.iter().map(|h| (h.clone(), false))));
+        out.push_str(&rule);
+    }
+    for row in rows {
+        out.push_str(&line(
+            &mut row.iter().map(|cell| (cell.text.clone(), cell.numeric)),
+        ));
+    }
+    out.push_str(&rule);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn render(rows: Vec<Value>, header: &[&str]) -> Result<String, String> {
+        let header = header.iter().map(|h| h.to_string()).collect();
+        let (header, cells) = table_cells(rows, header, FloatFormat::default())?;
+        Ok(render_table(&header, &cells))
+    }
+
+    #[test]
+    fn test_list_rows() {
+        let rows = vec![
+            Value::List(vec![text("ann"), Value::Integer(7)]),
+            Value::List(vec![text("bartholomew"), Value::Integer(1234), text("x")]),
+        ];
+        assert_eq!(
+            render(rows, &["name", "n"]).unwrap(),
+            "\
++-------------+------+---+
+| name        | n    |   |
++-------------+------+---+
+| ann         |    7 |   |
+| bartholomew | 1234 | x |
++-------------+------+---+
+"
+        );
+    }
+
+    #[test]
+    fn test_map_rows() {
+        let row = |pairs: &[(&str, Value)]| {
+            Value::Map(
+                pairs
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+        };
+        let rows = vec![
+            row(&[("b", Value::Integer(1)), ("a", text("x"))]),
+            row(&[("c", Value::Bool(true))]),
+        ];
+        assert_eq!(
+            render(rows.clone(), &[]).unwrap(),
+            "\
++---+---+------+
+| a | b | c    |
++---+---+------+
+| x | 1 |      |
+|   |   | true |
++---+---+------+
+"
+        );
+        // The header picks and orders the columns
+        assert_eq!(
+            render(rows, &["c", "a"]).unwrap(),
+            "\
++------+---+
+| c    | a |
++------+---+
+|      | x |
+| true |   |
++------+---+
+"
+        );
+    }
+
+    #[test]
+    fn test_mixed_rows() {
+        let rows = vec![Value::List(vec![]), Value::Integer(3)];
+        let err = render(rows, &[]).unwrap_err();
+        assert_eq!(
+            err,
+            "row 1: expected a list like the first row, got integer"
+        );
+    }
+}
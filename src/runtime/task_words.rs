@@ -0,0 +1,625 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::lang::value::Value;
+use crate::runtime::native::NativeFn;
+use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+use crate::runtime::vm_bc::{VmBc, VmBcConfig, WordReload, internal_error};
+
+// =============================================================================
+// Tasks and channels (feature = "concurrency")
+// =============================================================================
+//
+// `spawn` runs a quotation on a thread of its own, in a VM of its own that
+// starts with an empty stack and shares the parent's word table read-only.
+// Tasks share no values: the only way to pass data between them is a
+// channel, and a value sent on one is copied, so neither side can see the
+// other change it.
+//
+//     def square-in-background :> n
+//         chan :> results
+//         [ results n n * send ] spawn drop
+//         results recv
+//     end
+//
+// Quotations don't see the parent's variables, but they do carry the
+// locals they read, which is how a task gets its channels. A task runs
+// under the parent's config, so the same sandbox and limits apply to it,
+// each task counting its steps and time afresh. That is why `spawn` needs
+// `allow_spawn`, which the minimal, sandbox and pure profiles turn off.
+// Tasks read no input and write to standard output.
+//
+// The program doesn't wait for its tasks unless it joins them. `task-join`
+// waits for a task and pushes what it left on its stack as a list; if the
+// task failed, it fails in the parent with the task's error, which `try`
+// can catch even when the task itself couldn't have (an interrupt or a
+// limit). A task can be joined once. An error in a task that is never
+// joined is reported on stderr once both the task and the VM that spawned
+// it are done.
+//
+//     [ 6 7 * ] spawn task-join                            ; { 42 }
+//     [ [ 1 0 / ] spawn task-join ] [ drop "failed" ] try   ; "failed"
+//
+// A task can't be cancelled by itself: the cancel token belongs to the
+// config it shares with the parent, so cancelling the parent cancels every
+// task, and a `task-join` or `recv` waiting at the time is interrupted.
+//
+// Task and channel handles are integers, like sockets. A channel handle
+// means the same channel in the VM that made it and in every task spawned
+// from it.
+//
+// Besides channels, tasks can share two kinds of state, both handles too:
+// atomic counters, which any task may add to, and once-cells, which hold
+// the first value any task puts in them. There is deliberately nothing
+// more general; results that need more than a sum or a winner go over a
+// channel.
+//
+//     def count-if-even :> evens :> x
+//         [ x 2 % 0 = [ evens 1 atomic-add drop ] when ] spawn
+//     end
+//     def count-evens :> xs
+//         0 atomic-new :> evens
+//         xs [ evens count-if-even ] map [ task-join drop ] each
+//         evens atomic-get
+//     end
+
+pub const WORDS: &[(&str, NativeFn)] = &[
+    ("spawn", spawn),
+    ("chan", chan),
+    ("send", send),
+    ("recv", recv),
+    ("task-join", task_join),
+    ("task-join-timeout", task_join_timeout),
+    ("task-done?", task_done),
+    ("atomic-new", atomic_new),
+    ("atomic-add", atomic_add),
+    ("atomic-get", atomic_get),
+    ("once-new", once_new),
+    ("once-set", once_set),
+    ("once-get", once_get),
+];
+
+/// Room for the VM's recursion at the default `max_call_depth`, like
+/// `eval_pure`.
+const TASK_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// How often a blocked `recv` checks whether the run was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(50);
+
+/// The tasks a VM has spawned, and the channels, counters and cells it
+/// shares with them.
+#[derive(Default)]
+pub struct Tasks {
+    next_handle: i64,
+    /// Tasks not joined yet, by handle
+    spawned: HashMap<i64, Arc<Outcome>>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Tasks {
+    /// The table for a task spawned by the VM owning this one.
+    fn for_child(&self) -> Self {
+        Tasks {
+            next_handle: 0,
+            spawned: HashMap::new(),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+/// Where a task leaves its final stack, or the error it stopped with.
+struct Outcome {
+    handle: i64,
+    result: Mutex<Option<RuntimeResult<Vec<Value>>>>,
+    finished: Condvar,
+}
+
+impl Drop for Outcome {
+    /// Nobody joined the task, so this is the last chance to report its error.
+    fn drop(&mut self) {
+        if let Ok(Some(Err(e))) = self.result.get_mut().map(Option::take) {
+            eprintln!("Error in task {}: {}", self.handle, e.message);
+        }
+    }
+}
+
+/// Everything a VM and its tasks share, under one sequence of handles.
+#[derive(Default)]
+struct Shared {
+    next_handle: i64,
+    channels: HashMap<i64, Arc<Channel>>,
+    atomics: HashMap<i64, Arc<AtomicI64>>,
+    cells: HashMap<i64, Arc<OnceCell>>,
+}
+
+impl Shared {
+    fn next_handle(&mut self) -> i64 {
+        self.next_handle += 1;
+        self.next_handle
+    }
+}
+
+/// An unbounded queue any number of tasks may send to and receive from.
+#[derive(Default)]
+struct Channel {
+    queue: Mutex<VecDeque<Value>>,
+    ready: Condvar,
+}
+
+/// A value set at most once, by whichever task gets there first.
+#[derive(Default)]
+struct OnceCell {
+    value: Mutex<Option<Value>>,
+    set: Condvar,
+}
+
+/// ( quot -- task ) Run `quot` on another thread in a VM of its own.
+fn spawn(vm: &mut VmBc) -> RuntimeResult<()> {
+    vm.require_spawn("spawn")?;
+    let ops = vm.pop_quotation_ops()?;
+    let program = vm.share_words(ops);
+    let config = VmBcConfig {
+        // A reload is meant for the VM the host gave it to
+        reload: WordReload::default(),
+        checkpoints: false,
+        ..vm.config().clone()
+    };
+    let tasks = vm.tasks.for_child();
+    vm.tasks.next_handle += 1;
+    let handle = vm.tasks.next_handle;
+    let outcome = Arc::new(Outcome {
+        handle,
+        result: Mutex::new(None),
+        finished: Condvar::new(),
+    });
+    let shared = Arc::clone(&outcome);
+
+    thread::Builder::new()
+        .name(format!("ember-task-{}", handle))
+        .stack_size(TASK_STACK_SIZE)
+        .spawn(move || {
+            let mut child = VmBc::with_config(config);
+            child.tasks = tasks;
+            child.set_input(io::empty());
+            // A panic can only end this thread, so it always becomes the
+            // task's error rather than leaving `task-join` waiting
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                child.run_shared(&program).map(|()| child.stack().to_vec())
+            }))
+            .unwrap_or_else(|payload| Err(internal_error(payload.as_ref()).boxed()));
+            *shared.result.lock().unwrap() = Some(result);
+            shared.finished.notify_all();
+        })
+        .map_err(|e| {
+            vm.error_with_context(format!("spawn: cannot start a thread: {}", e))
+                .boxed()
+        })?;
+    vm.tasks.spawned.insert(handle, outcome);
+    vm.push(Value::Integer(handle));
+    Ok(())
+}
+
+/// ( task -- stack ) Wait for a task to finish and push its final stack as
+/// a list. Fails with the task's error if it failed.
+fn task_join(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let stack =
+        wait(vm, "task-join", handle, None)?.expect("task-join waits until the task is done");
+    vm.push(Value::List(stack));
+    Ok(())
+}
+
+/// ( task ms -- stack done? ) Like `task-join`, giving up after `ms`
+/// milliseconds. A task still running then pushes `{ } false` and can be
+/// joined later.
+fn task_join_timeout(vm: &mut VmBc) -> RuntimeResult<()> {
+    let ms = vm.pop_int()?;
+    if ms < 0 {
+        return Err(vm
+            .error_with_context(format!(
+                "task-join-timeout: timeout must not be negative, got {}",
+                ms
+            ))
+            .boxed());
+    }
+    let handle = vm.pop_int()?;
+    let timeout = Duration::from_millis(ms as u64);
+    let stack = wait(vm, "task-join-timeout", handle, Some(timeout))?;
+    let done = stack.is_some();
+    vm.push(Value::List(stack.unwrap_or_default()));
+    vm.push(Value::Bool(done));
+    Ok(())
+}
+
+/// ( task -- bool ) Whether a task has finished, without waiting for it.
+fn task_done(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let outcome = task(vm, "task-done?", handle)?;
+    let done = outcome.result.lock().unwrap().is_some();
+    vm.push(Value::Bool(done));
+    Ok(())
+}
+
+/// Wait for a task to finish, for at most `timeout` if one is given, and
+/// take what it left. None if it is still running at the timeout.
+fn wait(
+    vm: &mut VmBc,
+    word: &str,
+    handle: i64,
+    timeout: Option<Duration>,
+) -> RuntimeResult<Option<Vec<Value>>> {
+    let outcome = task(vm, word, handle)?;
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut slot = outcome.result.lock().unwrap();
+    let result = loop {
+        if let Some(result) = slot.take() {
+            break result;
+        }
+        if vm.config().cancel.is_cancelled() {
+            return Err(RuntimeError::new(&format!("interrupted in '{}'", word))
+                .uncatchable()
+                .boxed());
+        }
+        let poll = match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    return Ok(None);
+                }
+                left.min(CANCEL_POLL)
+            }
+            None => CANCEL_POLL,
+        };
+        slot = outcome.finished.wait_timeout(slot, poll).unwrap().0;
+    };
+    drop(slot);
+    vm.tasks.spawned.remove(&handle);
+
+    result.map(Some).map_err(|e| {
+        let mut error = vm
+            .error_with_context(format!("{}: task {} failed: {}", word, handle, e.message))
+            .with_help("Catch it with `[ ... ] [ handler ] try`");
+        error.thrown = e.thrown;
+        error.boxed()
+    })
+}
+
+fn task(vm: &VmBc, word: &str, handle: i64) -> RuntimeResult<Arc<Outcome>> {
+    vm.tasks.spawned.get(&handle).cloned().ok_or_else(|| {
+        vm.error_with_context(format!("{}: {} is not a task", word, handle))
+            .with_help("Task handles come from spawn, and a task can be joined once")
+            .boxed()
+    })
+}
+
+/// ( -- ch ) Make a channel.
+fn chan(vm: &mut VmBc) -> RuntimeResult<()> {
+    let mut shared = vm.tasks.shared.lock().unwrap();
+    let handle = shared.next_handle();
+    shared.channels.insert(handle, Arc::default());
+    drop(shared);
+    vm.push(Value::Integer(handle));
+    Ok(())
+}
+
+/// ( ch value -- ) Put a copy of `value` on a channel. Never blocks.
+fn send(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let handle = vm.pop_int()?;
+    let channel = channel(vm, "send", handle)?;
+    channel.queue.lock().unwrap().push_back(value);
+    channel.ready.notify_one();
+    Ok(())
+}
+
+/// ( ch -- value ) Take the oldest value from a channel, waiting for one if
+/// it is empty.
+fn recv(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let channel = channel(vm, "recv", handle)?;
+    let mut queue = channel.queue.lock().unwrap();
+    let value = loop {
+        if let Some(value) = queue.pop_front() {
+            break value;
+        }
+        if vm.config().cancel.is_cancelled() {
+            return Err(RuntimeError::new("interrupted in 'recv'")
+                .uncatchable()
+                .boxed());
+        }
+        queue = channel.ready.wait_timeout(queue, CANCEL_POLL).unwrap().0;
+    };
+    drop(queue);
+    vm.push(value);
+    Ok(())
+}
+
+fn channel(vm: &VmBc, word: &str, handle: i64) -> RuntimeResult<Arc<Channel>> {
+    let shared = vm.tasks.shared.lock().unwrap();
+    shared.channels.get(&handle).cloned().ok_or_else(|| {
+        vm.error_with_context(format!("{}: {} is not a channel", word, handle))
+            .with_help("Channel handles come from chan")
+            .boxed()
+    })
+}
+
+/// ( n -- atomic ) Make an atomic counter starting at `n`.
+fn atomic_new(vm: &mut VmBc) -> RuntimeResult<()> {
+    let n = vm.pop_int()?;
+    let mut shared = vm.tasks.shared.lock().unwrap();
+    let handle = shared.next_handle();
+    shared.atomics.insert(handle, Arc::new(AtomicI64::new(n)));
+    drop(shared);
+    vm.push(Value::Integer(handle));
+    Ok(())
+}
+
+/// ( atomic n -- total ) Add `n` to a counter in one step, so concurrent
+/// adds are never lost, and push the new total.
+fn atomic_add(vm: &mut VmBc) -> RuntimeResult<()> {
+    let n = vm.pop_int()?;
+    let handle = vm.pop_int()?;
+    let atomic = atomic(vm, "atomic-add", handle)?;
+    let old = atomic
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |total| {
+            total.checked_add(n)
+        })
+        .map_err(|_| vm.integer_overflow("atomic-add"))?;
+    vm.push(Value::Integer(old + n));
+    Ok(())
+}
+
+/// ( atomic -- n ) The current total of a counter.
+fn atomic_get(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let total = atomic(vm, "atomic-get", handle)?.load(Ordering::SeqCst);
+    vm.push(Value::Integer(total));
+    Ok(())
+}
+
+fn atomic(vm: &VmBc, word: &str, handle: i64) -> RuntimeResult<Arc<AtomicI64>> {
+    let shared = vm.tasks.shared.lock().unwrap();
+    shared.atomics.get(&handle).cloned().ok_or_else(|| {
+        vm.error_with_context(format!("{}: {} is not an atomic", word, handle))
+            .with_help("Atomic handles come from atomic-new")
+            .boxed()
+    })
+}
+
+/// ( -- cell ) Make an empty once-cell.
+fn once_new(vm: &mut VmBc) -> RuntimeResult<()> {
+    let mut shared = vm.tasks.shared.lock().unwrap();
+    let handle = shared.next_handle();
+    shared.cells.insert(handle, Arc::default());
+    drop(shared);
+    vm.push(Value::Integer(handle));
+    Ok(())
+}
+
+/// ( cell value -- set? ) Put a copy of `value` in a cell if it is still
+/// empty. Pushes whether this call was the one that set it.
+fn once_set(vm: &mut VmBc) -> RuntimeResult<()> {
+    let value = vm.pop()?;
+    let handle = vm.pop_int()?;
+    let cell = cell(vm, "once-set", handle)?;
+    let mut slot = cell.value.lock().unwrap();
+    let set = slot.is_none();
+    if set {
+        *slot = Some(value);
+        cell.set.notify_all();
+    }
+    drop(slot);
+    vm.push(Value::Bool(set));
+    Ok(())
+}
+
+/// ( cell -- value ) A copy of the value in a cell, waiting for one if it is
+/// empty.
+fn once_get(vm: &mut VmBc) -> RuntimeResult<()> {
+    let handle = vm.pop_int()?;
+    let cell = cell(vm, "once-get", handle)?;
+    let mut slot = cell.value.lock().unwrap();
+    let value = loop {
+        if let Some(value) = &*slot {
+            break value.clone();
+        }
+        if vm.config().cancel.is_cancelled() {
+            return Err(RuntimeError::new("interrupted in 'once-get'")
+                .uncatchable()
+                .boxed());
+        }
+        slot = cell.set.wait_timeout(slot, CANCEL_POLL).unwrap().0;
+    };
+    drop(slot);
+    vm.push(value);
+    Ok(())
+}
+
+fn cell(vm: &VmBc, word: &str, handle: i64) -> RuntimeResult<Arc<OnceCell>> {
+    let shared = vm.tasks.shared.lock().unwrap();
+    shared.cells.get(&handle).cloned().ok_or_else(|| {
+        vm.error_with_context(format!("{}: {} is not a once-cell", word, handle))
+            .with_help("Once-cell handles come from once-new")
+            .boxed()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lang::value::Value;
+    use crate::runtime::test_support::{run, run_with};
+    use crate::runtime::vm_bc::Profile;
+
+    #[test]
+    fn test_spawn_and_channels() {
+        let stack = run(r#"
+            def sq dup * end
+            def work :> n :> out
+                [ out n sq send ] spawn drop
+            end
+            def main
+                chan :> results
+                results 3 work
+                results 4 work
+                results recv results recv +
+            end
+            main
+        "#)
+        .unwrap();
+        assert_eq!(stack, vec![Value::Integer(25)]);
+    }
+
+    #[test]
+    fn test_tasks_pass_values_both_ways() {
+        // The task gets a list, doubles it and hands back a copy
+        let stack = run(r#"
+            def double-in-task :> xs
+                chan :> inbox
+                chan :> outbox
+                [ outbox inbox recv [ 2 * ] map send ] spawn drop
+                inbox xs send
+                outbox recv
+            end
+            { 1 2 3 } double-in-task
+        "#)
+        .unwrap();
+        assert_eq!(
+            stack,
+            vec![Value::List(vec![
+                Value::Integer(2),
+                Value::Integer(4),
+                Value::Integer(6)
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_join_returns_the_final_stack() {
+        let stack = run("[ 6 7 * \"done\" ] spawn task-join").unwrap();
+        assert_eq!(
+            stack,
+            vec![Value::List(vec![
+                Value::Integer(42),
+                Value::String("done".to_string())
+            ])]
+        );
+
+        // A task blocked on a channel isn't done until it gets a value
+        let stack = run(r#"
+            def main
+                chan :> ch
+                [ ch recv 1 + ] spawn :> task
+                task task-done?
+                task 10 task-join-timeout
+                ch 41 send
+                task task-join
+            end
+            main
+        "#)
+        .unwrap();
+        assert_eq!(
+            stack,
+            vec![
+                Value::Bool(false),
+                Value::List(vec![]),
+                Value::Bool(false),
+                Value::List(vec![Value::Integer(42)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_errors_surface_in_join() {
+        let stack = run("[ [ 1 0 / ] spawn task-join ] [ ] try").unwrap();
+        match &stack[..] {
+            [Value::String(message)] => {
+                assert!(
+                    message.contains("task-join: task 1 failed: division by zero"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("expected the error message, got {:?}", other),
+        }
+        // A thrown value reaches the parent's handler as it was thrown
+        let stack = run("[ [ 7 throw ] spawn task-join ] [ ] try").unwrap();
+        assert_eq!(stack, vec![Value::Integer(7)]);
+
+        let err = run("[ ] spawn dup task-join drop task-join").unwrap_err();
+        assert!(err.contains("task-join: 1 is not a task"), "{}", err);
+    }
+
+    #[test]
+    fn test_atomics_count_across_tasks() {
+        let stack = run(r#"
+            def count-if-even :> evens :> x
+                [ x 2 % 0 = [ evens 1 atomic-add drop ] when ] spawn
+            end
+            def count-evens :> xs
+                0 atomic-new :> evens
+                xs [ evens count-if-even ] map [ task-join drop ] each
+                evens atomic-get
+            end
+            { 1 2 3 4 5 6 7 8 } count-evens
+        "#)
+        .unwrap();
+        assert_eq!(stack, vec![Value::Integer(4)]);
+
+        let stack = run("10 atomic-new dup 5 atomic-add swap -3 atomic-add").unwrap();
+        assert_eq!(stack, vec![Value::Integer(15), Value::Integer(12)]);
+
+        let err = run("9223372036854775807 atomic-new 1 atomic-add").unwrap_err();
+        assert!(err.contains("atomic-add: integer overflow"), "{}", err);
+    }
+
+    #[test]
+    fn test_once_cells_keep_the_first_value() {
+        let stack = run(r#"
+            def offer :> winner :> n
+                [ winner n once-set drop ] spawn
+            end
+            def first-done
+                once-new :> winner
+                { 1 2 3 } [ winner offer ] map [ task-join drop ] each
+                winner once-get
+            end
+            first-done { 1 2 3 } swap contains?
+        "#)
+        .unwrap();
+        assert_eq!(stack, vec![Value::Bool(true)]);
+
+        let stack = run("once-new dup \"a\" once-set over \"b\" once-set rot once-get").unwrap();
+        assert_eq!(
+            stack,
+            vec![
+                Value::Bool(true),
+                Value::Bool(false),
+                Value::String("a".to_string())
+            ]
+        );
+
+        let err = run("chan 1 atomic-add").unwrap_err();
+        assert!(err.contains("atomic-add: 1 is not an atomic"), "{}", err);
+    }
+
+    #[test]
+    fn test_spawn_needs_permission() {
+        // A task would start with a step budget of its own
+        for profile in [Profile::Minimal, Profile::Sandbox, Profile::Pure] {
+            let err = run_with(profile.config(), "[ 1 ] spawn task-join").unwrap_err();
+            assert!(err.contains("spawn: starting tasks is disabled"), "{}", err);
+        }
+        assert!(run_with(Profile::Script.config(), "[ 1 ] spawn task-join").is_ok());
+    }
+
+    #[test]
+    fn test_unknown_channel() {
+        let err = run("99 1 send").unwrap_err();
+        assert!(err.contains("send: 99 is not a channel"), "{}", err);
+    }
+}
@@ -0,0 +1,40 @@
+use crate::bytecode::ProgramBc;
+use crate::bytecode::compile::Compiler;
+use crate::frontend::lexer::Lexer;
+use crate::frontend::parser::Parser;
+use crate::lang::value::Value;
+use crate::runtime::vm_bc::{VmBc, VmBcConfig};
+
+// =============================================================================
+// Test support
+// =============================================================================
+//
+// Runs EMBER source from the tests of the runtime modules the way `ember -e`
+// would. Errors of every stage come back as their message, for tests to
+// match on.
+
+/// Lex, parse and compile `source`.
+pub fn compile(source: &str) -> Result<ProgramBc, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| e.to_string())?;
+    let ast = Parser::new(tokens).parse().map_err(|e| e.to_string())?;
+    Compiler::new()
+        .compile_program(&ast)
+        .map_err(|e| e.to_string())
+}
+
+/// Run `source` on `vm`, returning the stack afterwards.
+pub fn run_in(vm: &mut VmBc, source: &str) -> Result<Vec<Value>, String> {
+    let program = compile(source)?;
+    vm.run_compiled(&program).map_err(|e| e.message.clone())?;
+    Ok(vm.stack().to_vec())
+}
+
+/// Run `source` on a fresh VM with the default config.
+pub fn run(source: &str) -> Result<Vec<Value>, String> {
+    run_in(&mut VmBc::new(), source)
+}
+
+/// Run `source` on a fresh VM with `config`.
+pub fn run_with(config: VmBcConfig, source: &str) -> Result<Vec<Value>, String> {
+    run_in(&mut VmBc::with_config(config), source)
+}
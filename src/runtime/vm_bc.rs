@@ -1,44 +1,442 @@
-use crate::bytecode::ProgramBc;
+use crate::bytecode::CodeObject;
+use crate::bytecode::container;
+use crate::bytecode::ir::span_at;
 use crate::bytecode::op::Op;
-use crate::bytecode::stack_check_error::check_ops;
-use crate::frontend::lexer::Span;
-use crate::lang::value::Value;
+use crate::bytecode::stack_check_error::{StackCheckError, check_program_with};
+use crate::bytecode::verify::verify_program;
+use crate::bytecode::{OpSpan, ProgramBc, WordSite};
+use crate::lang::value::{FloatFormat, Value};
+use crate::runtime::explain::{self, Explain, Step};
+use crate::runtime::replay::{self, Recording};
 use crate::runtime::runtime_error::{
     RuntimeError, RuntimeResult, division_by_zero, index_out_of_bounds, stack_underflow,
     undefined_word,
 };
-use std::collections::HashMap;
-use std::io::{self, BufRead, Write};
+use crate::runtime::sequence::Items;
+use crate::runtime::{io_words, math_words, native};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How many steps run between wall-clock checks. Reading the clock on every
+/// op would dominate tight loops, so `max_wall_time` is only sampled this often.
+const WALL_TIME_CHECK_INTERVAL: usize = 1024;
+
+/// Shared flag that stops a running VM before its next instruction, e.g.
+/// from a Ctrl-C handler on another thread. Clones share the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear a cancellation so the next run can start.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Resources used by one run, for hosts that bill or throttle the scripts
+/// they run. Limits in the config still apply; this measures usage under
+/// them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunReport {
+    /// Instructions executed, as counted against `max_steps`.
+    pub steps: usize,
+    /// Most values on the data stack at once.
+    pub peak_stack: usize,
+    /// Estimated heap bytes of the values pushed: each string, list, map or
+    /// quotation counts its own length, not its nested contents, and values
+    /// that are moved rather than copied count again.
+    pub allocated_bytes: usize,
+    pub wall_time: Duration,
+}
+
+/// Recompiled word definitions handed to a running VM, e.g. by a thread
+/// watching the source file. The VM swaps them in before its next
+/// instruction; a body that is already running finishes with its old code,
+/// and later calls get the new one. Clones share the same slot.
+#[derive(Debug, Clone, Default)]
+pub struct WordReload(Arc<ReloadSlot>);
+
+#[derive(Debug, Default)]
+struct ReloadSlot {
+    pending: AtomicBool,
+    program: Mutex<Option<ProgramBc>>,
+}
+
+impl WordReload {
+    /// Queue `program`'s words, replacing any not yet picked up.
+    pub fn offer(&self, program: ProgramBc) {
+        *self.0.program.lock().unwrap_or_else(|e| e.into_inner()) = Some(program);
+        self.0.pending.store(true, Ordering::Release);
+    }
+
+    fn take(&self) -> Option<ProgramBc> {
+        // Checked before every instruction, so skip the write when idle
+        if !self.0.pending.load(Ordering::Relaxed) || !self.0.pending.swap(false, Ordering::Acquire)
+        {
+            return None;
+        }
+        self.0
+            .program
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+/// A named starting point for `VmBcConfig`, so hosts and the CLI
+/// (`--profile`) agree on what each kind of program may do. EMBER has no
+/// prelude to switch off, so profiles differ in permissions and limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    /// Computation only: no file system, processes, network or tasks.
+    Minimal,
+    /// Everything allowed, as for a script you wrote yourself.
+    #[default]
+    Script,
+    /// For untrusted code: nothing outside the VM, no building code at run
+    /// time, and bounded steps, time, stack and call depth.
+    Sandbox,
+    /// For rules and config evaluated inside another program (`eval_pure`):
+    /// the sandbox without console I/O or a clock, so the stack it leaves
+    /// depends only on the code and its inputs.
+    Pure,
+}
+
+impl Profile {
+    pub fn config(self) -> VmBcConfig {
+        let base = VmBcConfig::default();
+        match self {
+            Profile::Script => base,
+            Profile::Minimal => VmBcConfig {
+                allow_fs: false,
+                allow_process: false,
+                allow_net: false,
+                allow_spawn: false,
+                ..base
+            },
+            Profile::Sandbox => VmBcConfig {
+                allow_compile: false,
+                max_call_depth: 256,
+                max_steps: Some(10_000_000),
+                max_stack_size: 10_000,
+                max_wall_time: Some(Duration::from_secs(10)),
+                catch_panics: true,
+                ..Profile::Minimal.config()
+            },
+            // Steps are the only fuel: a wall-clock limit could fail the
+            // same run on a slower machine
+            Profile::Pure => VmBcConfig {
+                allow_io: false,
+                max_wall_time: None,
+                ..Profile::Sandbox.config()
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    /// Parse `minimal`, `script`, `sandbox` or `pure`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(Profile::Minimal),
+            "script" => Ok(Profile::Script),
+            "sandbox" => Ok(Profile::Sandbox),
+            "pure" => Ok(Profile::Pure),
+            _ => Err(format!(
+                "unknown profile '{}' (expected minimal, script, sandbox or pure)",
+                s
+            )),
+        }
+    }
+}
+
+/// A host check on encoded programs, e.g. that a signature shipped alongside
+/// a `.ebc` file matches its bytes. EMBER doesn't pick a crypto stack: the
+/// closure gets the payload and says why it's rejected, if it is.
+#[derive(Clone)]
+pub struct BytecodeVerifier(Arc<VerifyFn>);
+
+type VerifyFn = dyn Fn(&[u8]) -> Result<(), String> + Send + Sync;
+
+impl BytecodeVerifier {
+    pub fn new(check: impl Fn(&[u8]) -> Result<(), String> + Send + Sync + 'static) -> Self {
+        BytecodeVerifier(Arc::new(check))
+    }
+
+    fn check(&self, bytes: &[u8]) -> RuntimeResult<()> {
+        (self.0)(bytes).map_err(|reason| {
+            RuntimeError::new(&format!("bytecode rejected by verifier: {}", reason)).boxed()
+        })
+    }
+}
+
+/// A word implemented by the host program, see `VmBc::register_word`.
+pub type HostWord = Rc<dyn Fn(&mut VmBc) -> RuntimeResult<()>>;
+
+impl std::fmt::Debug for BytecodeVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BytecodeVerifier(..)")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct VmBcConfig {
     pub max_call_depth: usize,
+    /// Per-word replacements for `max_call_depth`, for intentionally deep
+    /// algorithms. While a listed word is running, its limit applies to the
+    /// whole call depth (the innermost listed word wins).
+    pub word_depth_limits: HashMap<String, usize>,
     pub max_steps: Option<usize>,
     pub max_stack_size: usize,
+    /// Upper bound on wall-clock time for a single run. Unlike `max_steps`,
+    /// this also bounds scripts that spend their time blocked in I/O.
+    pub max_wall_time: Option<Duration>,
+    /// Whether words that touch the file system (`glob`, `canonicalize`)
+    /// may run. Pure path manipulation is always allowed.
+    pub allow_fs: bool,
+    /// Whether `run-process` may spawn other programs.
+    pub allow_process: bool,
+    /// Whether the socket words (feature `net`) may open connections.
+    pub allow_net: bool,
+    /// Whether `list->quot` and `eval` may build code at run time.
+    pub allow_compile: bool,
+    /// Whether `spawn` (feature `concurrency`) may start tasks. Each task
+    /// gets limits of its own, so a sandbox has to keep this off.
+    pub allow_spawn: bool,
+    /// Whether words may read standard input or write standard output
+    /// (`print`, `read`, `prompt`, `print-table`, ...).
+    pub allow_io: bool,
+    /// How floats are written by `print`, `to-string`, `.` and `join`.
+    pub float_format: FloatFormat,
+    /// Whether `colorize` and `style` add ANSI escapes. Off by default,
+    /// since output may not go to a terminal.
+    pub color: bool,
+    /// Whether `with-progress` draws a progress bar on stderr. Off by
+    /// default; the CLI turns it on when stderr is a terminal.
+    pub progress: bool,
+    /// Cancelling this token stops the run with an `interrupted` error.
+    pub cancel: CancelToken,
+    /// Word definitions offered here replace the running ones (`--watch`).
+    pub reload: WordReload,
+    /// Whether the `checkpoint` word pauses the run so the host can save
+    /// it with `serialize_state`. Off by default, where it does nothing.
+    pub checkpoints: bool,
+    /// Describe steps in plain English on stderr as they run (`--explain`).
+    pub explain: Option<Explain>,
+    /// Checked against the raw bytes given to `run_bytecode` and
+    /// `resume_state` before they are decoded.
+    pub bytecode_verifier: Option<BytecodeVerifier>,
+    /// Turn a panic in the VM or a builtin into an uncatchable "internal
+    /// error" instead of unwinding into the host. The VM can run again
+    /// afterwards. Off by default, so a bug shows its backtrace.
+    pub catch_panics: bool,
 }
 
 impl Default for VmBcConfig {
     fn default() -> Self {
         VmBcConfig {
             max_call_depth: 1000,
+            word_depth_limits: HashMap::new(),
             max_steps: None,
             max_stack_size: 10_000,
+            max_wall_time: None,
+            allow_fs: true,
+            allow_process: true,
+            allow_net: true,
+            allow_compile: true,
+            allow_spawn: true,
+            allow_io: true,
+            float_format: FloatFormat::default(),
+            color: false,
+            progress: false,
+            cancel: CancelToken::default(),
+            reload: WordReload::default(),
+            checkpoints: false,
+            explain: None,
+            bytecode_verifier: None,
+            catch_panics: false,
         }
     }
 }
 
+/// An EMBER virtual machine. Each VM owns its stacks, words and I/O, so any
+/// number of them can run side by side, on any threads, without affecting
+/// each other. The only state two VMs can share is what the host shares on
+/// purpose: a `CancelToken` or `WordReload` cloned into both configs, and
+/// the read-only word table of a `SharedProgram`, which a VM copies before
+/// changing (when `run_more` defines words, or on a reload).
 pub struct VmBc {
     stack: Vec<Value>,
     pub aux_stack: Vec<Value>,
-    words: HashMap<String, Vec<Op>>,
+    words: Arc<BTreeMap<String, Vec<Op>>>,
+    /// Doc comments of user words, for `describe`
+    docs: Arc<BTreeMap<String, String>>,
+    /// Where user words were defined, for `todo`
+    sites: Arc<BTreeMap<String, WordSite>>,
+    /// Values of the variables set so far (`var`, `!`, `@`)
+    globals: BTreeMap<String, Value>,
+    /// Quotations registered with `register-handler`, by event name
+    handlers: BTreeMap<String, Vec<Op>>,
+    /// Slots of the locals (`:>`) of every active word, innermost last;
+    /// a slot is `None` until the word binds it
+    locals: Vec<Option<Value>>,
+    /// Where the running word's slots start in `locals`
+    frame_base: usize,
     // Safety limits
     config: VmBcConfig,
     call_depth: usize,
     call_stack: Vec<String>,
+    /// Depth limits of the active words that have an override
+    depth_limits: Vec<usize>,
     steps: usize,
+    /// Steps described so far by `--explain`
+    explained: usize,
+    /// Combinators iterating in the running `exec_ops` calls, which indent
+    /// `--explain` output like calls do
+    iterating: usize,
+    started_at: Instant,
+    /// `RunReport::peak_stack` of the current run
+    peak_stack: usize,
+    /// `RunReport::allocated_bytes` of the current run
+    allocated_bytes: usize,
     pub source: Option<String>,
     pub file: Option<PathBuf>,
+    /// Where `read`, `stdin-lines` and `each-line` take input from
+    input: Box<dyn BufRead>,
+    /// Whether `input` is a terminal, where `read-password` can hide what
+    /// is typed
+    #[cfg_attr(not(feature = "password"), allow(dead_code))]
+    terminal_input: bool,
+    /// Where `print`, `emit` and `debug` write to
+    output: Box<dyn Write>,
+    /// Compiled patterns for the regex words, keyed by pattern text
+    #[cfg(feature = "regex")]
+    pub(crate) regex_cache: HashMap<String, regex::Regex>,
+    /// Open sockets for the network words, addressed by integer handle
+    #[cfg(feature = "net")]
+    pub(crate) sockets: crate::runtime::net_words::Sockets,
+    /// Open databases for the SQLite words, addressed by integer handle
+    #[cfg(feature = "sqlite")]
+    pub(crate) databases: crate::runtime::sqlite_words::Databases,
+    /// Words from shared libraries loaded with `load_plugin`
+    #[cfg(feature = "plugins")]
+    pub(crate) plugins: crate::runtime::plugin::Plugins,
+    /// Words the host registered with `register_word`, by name
+    host_words: HashMap<String, HostWord>,
+    /// Spawned tasks and the channels shared with them
+    #[cfg(feature = "concurrency")]
+    pub(crate) tasks: crate::runtime::task_words::Tasks,
+    /// Set by `checkpoint`; the run pauses at the next instruction of main
+    pause_requested: bool,
+    /// Where a paused run continues
+    paused: Option<PausedMain>,
+    /// Index of the op the innermost failed `exec_ops` stopped at, in the
+    /// code it was given
+    error_at: usize,
+}
+
+/// Main's code and the instruction it will run next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PausedMain {
+    ops: Vec<Op>,
+    ip: usize,
+}
+
+/// An `each`, `map`, `filter` or `fold` in progress. Its quotation runs in
+/// the same loop as the code around it rather than a nested `exec_ops`, so
+/// combinators nested in each other cost no Rust stack or call depth.
+struct Iteration {
+    /// The combinator's op
+    op: Op,
+    body: Vec<Op>,
+    items: Items,
+    /// Index and stack height of the item the body is running on, and the
+    /// item itself for `filter`
+    current: Option<(usize, usize, Option<Value>)>,
+    /// Results of `map` and `filter`
+    collected: Vec<Value>,
+    /// The accumulator of `fold` between items
+    acc: Option<Value>,
+    /// Where the combinator's op is in the enclosing code
+    resume: usize,
+    explain: Option<Step>,
+}
+
+/// Everything `serialize_state` saves.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    stack: Vec<Value>,
+    aux_stack: Vec<Value>,
+    globals: BTreeMap<String, Value>,
+    words: BTreeMap<String, Vec<Op>>,
+    docs: BTreeMap<String, String>,
+    paused: Option<PausedMain>,
+}
+
+/// Identifies saved VM states; the last byte is the format version.
+const STATE_MAGIC: &[u8] = b"EMVM\x02";
+
+/// Saved VM state from [`VmBc::snapshot`].
+pub struct VmSnapshot {
+    stack: Vec<Value>,
+    aux_stack: Vec<Value>,
+    globals: BTreeMap<String, Value>,
+    handlers: BTreeMap<String, Vec<Op>>,
+    words: Arc<BTreeMap<String, Vec<Op>>>,
+    docs: Arc<BTreeMap<String, String>>,
+    sites: Arc<BTreeMap<String, WordSite>>,
+}
+
+/// A checked program that any number of VMs can run without copying its
+/// code: main and the word table are shared read-only. Cheap to clone and
+/// safe to send to other threads.
+#[derive(Debug, Clone)]
+pub struct SharedProgram {
+    main: Arc<[Op]>,
+    main_spans: Arc<[OpSpan]>,
+    words: Arc<BTreeMap<String, Vec<Op>>>,
+    docs: Arc<BTreeMap<String, String>>,
+    sites: Arc<BTreeMap<String, WordSite>>,
+}
+
+impl SharedProgram {
+    /// Run the static checks once, for every VM that will share `prog`.
+    /// `inputs` is how many values the host pushes before each run.
+    pub fn new(prog: ProgramBc, inputs: usize) -> Result<Self, StackCheckError> {
+        check_program_with(&prog, inputs as i32, &prog.words)?;
+        let main = prog.code.into_iter().next().unwrap_or_default();
+        Ok(Self {
+            main: main.ops.into(),
+            main_spans: main.spans.into(),
+            words: Arc::new(prog.words),
+            docs: Arc::new(prog.docs),
+            sites: Arc::new(prog.sites),
+        })
+    }
+}
+
+impl Default for VmBc {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VmBc {
@@ -50,13 +448,42 @@ impl VmBc {
         Self {
             stack: Vec::new(),
             aux_stack: Vec::new(),
-            words: HashMap::new(),
+            words: Default::default(),
+            docs: Default::default(),
+            sites: Default::default(),
+            globals: BTreeMap::new(),
+            handlers: BTreeMap::new(),
+            locals: Vec::new(),
+            frame_base: 0,
             config,
             call_depth: 0,
             call_stack: Vec::new(),
+            depth_limits: Vec::new(),
             steps: 0,
+            explained: 0,
+            iterating: 0,
+            started_at: Instant::now(),
+            peak_stack: 0,
+            allocated_bytes: 0,
             source: None,
             file: None,
+            input: Box::new(BufReader::new(io::stdin())),
+            terminal_input: io::stdin().is_terminal(),
+            output: Box::new(io::stdout()),
+            #[cfg(feature = "regex")]
+            regex_cache: HashMap::new(),
+            #[cfg(feature = "net")]
+            sockets: Default::default(),
+            #[cfg(feature = "sqlite")]
+            databases: Default::default(),
+            #[cfg(feature = "plugins")]
+            plugins: Default::default(),
+            host_words: HashMap::new(),
+            #[cfg(feature = "concurrency")]
+            tasks: Default::default(),
+            pause_requested: false,
+            paused: None,
+            error_at: 0,
         }
     }
 
@@ -69,16 +496,107 @@ impl VmBc {
         self.file = Some(file);
     }
 
+    /// Replace standard input, e.g. with a `Cursor` in tests.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Box::new(input);
+        self.terminal_input = false;
+    }
+
+    /// Copy every byte of input the program reads to `log` (`--record`).
+    pub fn record_input(&mut self, log: impl Write + 'static) {
+        let inner = std::mem::replace(&mut self.input, replay::empty_input());
+        self.input = Box::new(Recording { inner, log });
+    }
+
+    /// Replace standard output, e.g. with a buffer in tests.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Write program output. A closed pipe (`ember x.em | head`) surfaces as
+    /// a runtime error instead of a panic.
+    pub(crate) fn write_output(&mut self, text: std::fmt::Arguments) -> RuntimeResult<()> {
+        self.output
+            .write_fmt(text)
+            .map_err(|e| RuntimeError::new(&format!("write error: {}", e)).boxed())
+    }
+
+    /// Send buffered output on, e.g. before waiting for a reply to a prompt.
+    pub(crate) fn flush_output(&mut self) -> RuntimeResult<()> {
+        self.output
+            .flush()
+            .map_err(|e| RuntimeError::new(&format!("write error: {}", e)).boxed())
+    }
+
+    /// Whether input comes straight from a terminal.
+    #[cfg(feature = "password")]
+    pub(crate) fn terminal_input(&self) -> bool {
+        self.terminal_input
+    }
+
+    /// Read one line of input without its line ending. `None` at end of input.
+    pub fn read_input_line(&mut self) -> RuntimeResult<Option<String>> {
+        let mut line = String::new();
+        let read = self
+            .input
+            .read_line(&mut line)
+            .map_err(|e| RuntimeError::new(&format!("read error: {}", e)).boxed())?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
     // NEW: Helper to create errors with source context
-    fn error_with_context(&self, message: impl Into<String>) -> RuntimeError {
+    /// An error in the running code. Its location is filled in by `locate`
+    /// on the way out of the code object that failed.
+    pub(crate) fn error_with_context(&self, message: impl Into<String>) -> RuntimeError {
         RuntimeError::new(&message.into())
-            .with_span(Span { line: 1, col: 1 })
-            .with_source(self.source.clone().unwrap_or_default())
-            .with_file(self.file.clone().unwrap_or_default())
+    }
+
+    /// Point an error that has no location yet at the op `exec_ops` failed
+    /// on, given the span table of the code it ran. `file` is where that
+    /// code was defined, or None for the file the VM is running.
+    fn locate(&self, error: &mut RuntimeError, spans: &[OpSpan], file: Option<&str>) {
+        if error.span.is_some() {
+            return;
+        }
+        let Some(span) = span_at(spans, self.error_at) else {
+            return;
+        };
+        error.span = Some(span.clone());
+        let elsewhere = file.filter(|file| {
+            let running = self.file.as_ref().and_then(|f| f.canonicalize().ok());
+            running.as_deref() != Some(std::path::Path::new(file))
+        });
+        match elsewhere {
+            // The report reads the other file's source when it is shown
+            Some(file) => {
+                error.file = Some(PathBuf::from(file));
+                error.source = None;
+            }
+            None => {
+                error.file = self.file.clone();
+                error.source = self.source.clone();
+            }
+        }
+    }
+
+    /// Integers are 64-bit and never silently wrap.
+    pub(crate) fn integer_overflow(&self, word: &str) -> Box<RuntimeError> {
+        self.error_with_context(format!("{}: integer overflow", word))
+            .with_help("Integers are 64-bit; convert to float with to-float for larger magnitudes")
+            .boxed()
     }
 
     // Helper for type errors
-    fn type_error_with_context(&self, expected: &str, got: &str) -> Box<RuntimeError> {
+    pub(crate) fn type_error_with_context(&self, expected: &str, got: &str) -> Box<RuntimeError> {
         self.error_with_context(format!("type error: expected {}, got {}", expected, got))
             .with_help(format!(
                 "This operation requires a {} value, but received a {}",
@@ -87,45 +605,536 @@ impl VmBc {
             .boxed()
     }
 
-    #[allow(dead_code)]
+    /// Fail unless the sandbox allows file system access.
+    pub(crate) fn require_fs(&self, word: &str) -> RuntimeResult<()> {
+        self.require(self.config.allow_fs, word, "file system access", "allow_fs")
+    }
+
+    /// Fail unless the sandbox allows spawning processes.
+    pub(crate) fn require_process(&self, word: &str) -> RuntimeResult<()> {
+        self.require(
+            self.config.allow_process,
+            word,
+            "process execution",
+            "allow_process",
+        )
+    }
+
+    /// Fail unless the sandbox allows network access.
+    #[cfg(feature = "net")]
+    pub(crate) fn require_net(&self, word: &str) -> RuntimeResult<()> {
+        self.require(self.config.allow_net, word, "network access", "allow_net")
+    }
+
+    /// Fail unless the sandbox allows building code at run time.
+    pub(crate) fn require_compile(&self, word: &str) -> RuntimeResult<()> {
+        self.require(
+            self.config.allow_compile,
+            word,
+            "building code at run time",
+            "allow_compile",
+        )
+    }
+
+    /// Fail unless the sandbox allows starting tasks.
+    #[cfg(feature = "concurrency")]
+    pub(crate) fn require_spawn(&self, word: &str) -> RuntimeResult<()> {
+        self.require(
+            self.config.allow_spawn,
+            word,
+            "starting tasks",
+            "allow_spawn",
+        )
+    }
+
+    /// Fail unless the sandbox allows console input and output.
+    pub(crate) fn require_io(&self, word: &str) -> RuntimeResult<()> {
+        self.require(self.config.allow_io, word, "console I/O", "allow_io")
+    }
+
+    fn require(&self, allowed: bool, word: &str, what: &str, flag: &str) -> RuntimeResult<()> {
+        if allowed {
+            return Ok(());
+        }
+        Err(self
+            .error_with_context(format!("{}: {} is disabled", word, what))
+            .with_help(format!("This VM was configured with {} = false", flag))
+            .boxed())
+    }
+
     pub fn stack(&self) -> &[Value] {
         &self.stack
     }
 
+    /// Whether a user word with this name is defined.
+    pub fn has_word(&self, name: &str) -> bool {
+        self.words.contains_key(name)
+    }
+
+    /// The compiled body of a user word, if one with this name is defined.
+    pub fn word_ops(&self, name: &str) -> Option<&[Op]> {
+        self.words.get(name).map(Vec::as_slice)
+    }
+
+    /// The doc comment above a user word's definition, if it had one.
+    pub fn word_doc(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(String::as_str)
+    }
+
+    /// The innermost user word running, or None in main.
+    pub(crate) fn current_word(&self) -> Option<&str> {
+        // Natives are on the call stack too
+        self.call_stack
+            .iter()
+            .rev()
+            .find(|name| self.words.contains_key(*name))
+            .map(String::as_str)
+    }
+
+    /// Where a user word was defined, if the program recorded it.
+    pub fn word_site(&self, name: &str) -> Option<&WordSite> {
+        self.sites.get(name)
+    }
+
+    pub fn config(&self) -> &VmBcConfig {
+        &self.config
+    }
+
     pub fn reset_execution_state(&mut self) {
         self.steps = 0;
+        self.explained = 0;
+        self.started_at = Instant::now();
+        self.peak_stack = self.stack.len();
+        self.allocated_bytes = 0;
         self.call_depth = 0;
+        self.iterating = 0;
         self.call_stack.clear();
+        self.depth_limits.clear();
+        self.locals.clear();
+        self.frame_base = 0;
+    }
+
+    /// Decode, verify and run an encoded program (the contents of a `.ebc`
+    /// file), after the config's `bytecode_verifier` accepts its bytes.
+    pub fn run_bytecode(&mut self, bytes: &[u8]) -> RuntimeResult<()> {
+        if let Some(verifier) = &self.config.bytecode_verifier {
+            verifier.check(bytes)?;
+        }
+        let invalid =
+            |reason: String| RuntimeError::new(&format!("invalid bytecode: {}", reason)).boxed();
+        let (_, prog) = container::decode(bytes).map_err(|e| invalid(e.to_string()))?;
+        verify_program(&prog).map_err(|e| invalid(e.to_string()))?;
+        self.run_compiled(&prog)
     }
 
     pub fn run_compiled(&mut self, prog: &ProgramBc) -> RuntimeResult<()> {
         self.reset_execution_state();
+        self.paused = None;
+        self.globals.clear();
+        self.handlers.clear();
+
+        self.words = Arc::new(prog.words.clone());
+        self.docs = Arc::new(prog.docs.clone());
+        self.sites = Arc::new(prog.sites.clone());
+
+        let main = prog
+            .code
+            .first()
+            .ok_or_else(|| RuntimeError::new("bytecode program has no main code object"))?;
+
+        // The host may have pushed inputs, e.g. `--arg` values
+        check_program_with(prog, self.stack.len() as i32, &prog.words)
+            .map_err(|e| RuntimeError::new(&e.message))?;
+
+        self.exec_main(&main.ops, &main.spans)
+    }
+
+    /// Like `run_compiled`, also returning what the run used, whether or
+    /// not it succeeded.
+    pub fn run_compiled_with_report(&mut self, prog: &ProgramBc) -> (RuntimeResult<()>, RunReport) {
+        let result = self.run_compiled(prog);
+        (result, self.report())
+    }
+
+    /// What the current or last run has used so far.
+    pub fn report(&self) -> RunReport {
+        RunReport {
+            steps: self.steps,
+            peak_stack: self.peak_stack,
+            allocated_bytes: self.allocated_bytes,
+            wall_time: self.started_at.elapsed(),
+        }
+    }
+
+    /// Like `run_compiled`, for a program shared with other VMs: its checks
+    /// have already run and its words are not copied.
+    pub fn run_shared(&mut self, prog: &SharedProgram) -> RuntimeResult<()> {
+        self.reset_execution_state();
+        self.paused = None;
+        self.globals.clear();
+        self.handlers.clear();
+
+        self.words = Arc::clone(&prog.words);
+        self.docs = Arc::clone(&prog.docs);
+        self.sites = Arc::clone(&prog.sites);
+
+        self.exec_main(&prog.main, &prog.main_spans)
+    }
+
+    /// A program running `main` against this VM's words, without copying
+    /// them, for `spawn`.
+    #[cfg(feature = "concurrency")]
+    pub(crate) fn share_words(&self, main: Vec<Op>) -> SharedProgram {
+        SharedProgram {
+            main: main.into(),
+            main_spans: Arc::new([]),
+            words: Arc::clone(&self.words),
+            docs: Arc::clone(&self.docs),
+            sites: Arc::clone(&self.sites),
+        }
+    }
+
+    /// Run another program against the current stacks, keeping the words
+    /// defined so far. The REPL evaluates each line this way.
+    pub fn run_more(&mut self, prog: &ProgramBc) -> RuntimeResult<()> {
+        self.reset_execution_state();
 
-        self.words = prog.words.clone();
+        if !prog.words.is_empty() {
+            Arc::make_mut(&mut self.words)
+                .extend(prog.words.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        if !prog.docs.is_empty() {
+            Arc::make_mut(&mut self.docs)
+                .extend(prog.docs.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        if !prog.sites.is_empty() {
+            Arc::make_mut(&mut self.sites)
+                .extend(prog.sites.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
 
         let main = prog
             .code
             .first()
             .ok_or_else(|| RuntimeError::new("bytecode program has no main code object"))?;
 
-        check_ops(&main.ops).map_err(|e| RuntimeError::new(&e.message))?;
+        check_program_with(prog, self.stack.len() as i32, &self.words)
+            .map_err(|e| RuntimeError::new(&e.message))?;
+
+        self.exec_main(&main.ops, &main.spans)
+    }
+
+    fn exec_main(&mut self, ops: &[Op], spans: &[OpSpan]) -> RuntimeResult<()> {
+        self.exec_ops(ops).map_err(|mut e| {
+            self.locate(&mut e, spans, None);
+            e
+        })
+    }
+
+    /// Load a plugin (a shared library of native words, see
+    /// `runtime::plugin`) and return the names of the words it adds. They
+    /// stay available across runs.
+    #[cfg(feature = "plugins")]
+    pub fn load_plugin(&mut self, path: &std::path::Path) -> Result<Vec<String>, String> {
+        self.plugins.load(path)
+    }
+
+    /// Run the plugin word `name`, if a loaded plugin defines one.
+    #[cfg(feature = "plugins")]
+    fn call_plugin(&mut self, name: &str) -> Option<RuntimeResult<()>> {
+        let word = self.plugins.lookup(name)?;
+        Some(crate::runtime::plugin::call(self, name, word))
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    fn call_plugin(&mut self, _name: &str) -> Option<RuntimeResult<()>> {
+        None
+    }
+
+    /// Make `name` run `word`, which takes its inputs from the stack and
+    /// leaves its results there like any builtin. User words of the same
+    /// name take precedence, and so do builtins. Tasks spawned by the
+    /// script don't see host words.
+    pub fn register_word(
+        &mut self,
+        name: impl Into<String>,
+        word: impl Fn(&mut VmBc) -> RuntimeResult<()> + 'static,
+    ) {
+        self.host_words.insert(name.into(), Rc::new(word));
+    }
+
+    pub(crate) fn register_handler(&mut self, name: String, ops: Vec<Op>) {
+        self.handlers.insert(name, ops);
+    }
+
+    /// Names of the handlers the script has registered, sorted.
+    pub fn handler_names(&self) -> impl Iterator<Item = &str> {
+        self.handlers.keys().map(String::as_str)
+    }
+
+    /// Call the handler the script registered for `name`, on a stack of
+    /// its own holding `args` (the last on top), and return what it leaves
+    /// there. The program's stack is not touched.
+    pub fn call_registered(&mut self, name: &str, args: Vec<Value>) -> RuntimeResult<Vec<Value>> {
+        let Some(ops) = self.handlers.get(name).cloned() else {
+            let registered = self.handlers.keys().cloned().collect::<Vec<_>>();
+            let help = if registered.is_empty() {
+                "The script has not called `register-handler`".to_string()
+            } else {
+                format!("Registered handlers: {}", registered.join(", "))
+            };
+            return Err(
+                RuntimeError::new(&format!("no handler is registered for '{}'", name))
+                    .with_help(help)
+                    .boxed(),
+            );
+        };
+        // Called from a host word, the handler runs inside the current run
+        // and is charged to it, so leave that run's state in place
+        let nested = self.call_depth > 0;
+        if !nested {
+            self.reset_execution_state();
+        }
+        let stack = std::mem::replace(&mut self.stack, args);
+        if !nested {
+            self.peak_stack = self.stack.len();
+        }
+        let call_stack = self.call_stack.len();
+        let call_depth = self.call_depth;
+        // Slots the handler sets go after those of the word that called it
+        let caller_base = std::mem::replace(&mut self.frame_base, self.locals.len());
+        let result = self.exec_ops(&ops);
+        self.locals.truncate(self.frame_base);
+        self.frame_base = caller_base;
+        self.call_stack.truncate(call_stack);
+        self.call_depth = call_depth;
+        let left = std::mem::replace(&mut self.stack, stack);
+        result.map(|()| left)
+    }
+
+    /// Whether the last run stopped at a `checkpoint` rather than finishing.
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Ask the run to pause before the next instruction of main.
+    pub(crate) fn request_pause(&mut self) {
+        self.pause_requested = true;
+    }
+
+    /// Save the stacks, the variables, the words and, if the run is paused,
+    /// where it stopped. Output, input, open sockets, handlers and the config
+    /// are not saved.
+    pub fn serialize_state(&self) -> RuntimeResult<Vec<u8>> {
+        let state = SavedState {
+            stack: self.stack.clone(),
+            aux_stack: self.aux_stack.clone(),
+            globals: self.globals.clone(),
+            words: (*self.words).clone(),
+            docs: (*self.docs).clone(),
+            paused: self.paused.clone(),
+        };
+        let encoded = postcard::to_allocvec(&state)
+            .map_err(|e| RuntimeError::new(&format!("cannot save VM state: {}", e)).boxed())?;
+        Ok([STATE_MAGIC, &encoded].concat())
+    }
+
+    /// Load a state saved by `serialize_state`, replacing this VM's stacks,
+    /// variables and words, and continue the run if it was paused.
+    pub fn resume_state(&mut self, bytes: &[u8]) -> RuntimeResult<()> {
+        if let Some(verifier) = &self.config.bytecode_verifier {
+            verifier.check(bytes)?;
+        }
+        let invalid = |reason: String| {
+            RuntimeError::new(&format!("cannot resume VM state: {}", reason)).boxed()
+        };
+        let encoded = bytes
+            .strip_prefix(STATE_MAGIC)
+            .ok_or_else(|| invalid("not a saved EMBER VM state".to_string()))?;
+        let state: SavedState =
+            postcard::from_bytes(encoded).map_err(|e| invalid(e.to_string()))?;
+
+        // Saved states may be corrupt or from an older build, like .ebc files
+        let main = state.paused.as_ref().map_or(&[][..], |p| &p.ops[..]);
+        let program = ProgramBc {
+            code: vec![CodeObject {
+                ops: main.to_vec(),
+                spans: Vec::new(),
+            }],
+            words: state.words.clone(),
+            ..ProgramBc::new()
+        };
+        verify_program(&program).map_err(|e| invalid(e.to_string()))?;
+        if let Some(paused) = &state.paused
+            && paused.ip > paused.ops.len()
+        {
+            return Err(invalid(format!(
+                "instruction {} is past the end of main",
+                paused.ip
+            )));
+        }
+
+        self.stack = state.stack;
+        self.aux_stack = state.aux_stack;
+        self.globals = state.globals;
+        self.words = Arc::new(state.words);
+        self.docs = Arc::new(state.docs);
+        self.sites = Default::default();
+        self.paused = None;
+        match state.paused {
+            Some(PausedMain { ops, ip }) => {
+                self.reset_execution_state();
+                self.call_depth += 1;
+                let result = self.exec_ops_inner(&ops, ip);
+                self.call_depth -= 1;
+                result
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Copy of the stacks, variables, handlers and words, to roll back a failed
+    /// evaluation.
+    pub fn snapshot(&self) -> VmSnapshot {
+        VmSnapshot {
+            stack: self.stack.clone(),
+            aux_stack: self.aux_stack.clone(),
+            globals: self.globals.clone(),
+            handlers: self.handlers.clone(),
+            words: self.words.clone(),
+            docs: self.docs.clone(),
+            sites: self.sites.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: VmSnapshot) {
+        self.stack = snapshot.stack;
+        self.aux_stack = snapshot.aux_stack;
+        self.globals = snapshot.globals;
+        self.handlers = snapshot.handlers;
+        self.words = snapshot.words;
+        self.docs = snapshot.docs;
+        self.sites = snapshot.sites;
+    }
+
+    /// Swap in the words of a recompiled program. Words missing from it
+    /// stay defined, since running code may still call them. Returns a
+    /// summary of what changed, or None if nothing did.
+    pub fn reload_words(&mut self, program: ProgramBc) -> Option<String> {
+        let mut changed = Vec::new();
+        let mut added = Vec::new();
+        for (name, ops) in program.words {
+            match self.words.get(&name) {
+                Some(old) if *old == ops => continue,
+                Some(_) => changed.push(name.clone()),
+                None => added.push(name.clone()),
+            }
+            Arc::make_mut(&mut self.words).insert(name, ops);
+        }
+        if !program.docs.is_empty() {
+            Arc::make_mut(&mut self.docs).extend(program.docs);
+        }
+        if !program.sites.is_empty() {
+            Arc::make_mut(&mut self.sites).extend(program.sites);
+        }
 
-        self.exec_ops(&main.ops)
+        let mut parts = Vec::new();
+        if !changed.is_empty() {
+            parts.push(format!("changed {}", changed.join(", ")));
+        }
+        if !added.is_empty() {
+            parts.push(format!("added {}", added.join(", ")));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
     }
 
     // Execution
 
+    /// Start describing `op` for `--explain`, if this step is one to describe.
+    fn explain_before(&mut self, op: &Op) -> Option<Step> {
+        let explain = self.config.explain.as_ref()?;
+        if self.explained >= explain.limit {
+            return None;
+        }
+        let in_scope = explain.words.is_empty()
+            || self.call_stack.iter().any(|w| explain.words.contains(w))
+            || matches!(op, Op::CallWord(name) if explain.words.contains(name));
+        if !in_scope {
+            return None;
+        }
+        let step = explain::describe(op, &self.stack, &self.words, self.config.float_format)?;
+        self.explained += 1;
+        if let Step::Around { line, .. } = &step {
+            eprintln!("{}{}", self.explain_indent(), line);
+        }
+        Some(step)
+    }
+
+    fn explain_after(&self, step: Step) {
+        let stack = explain::show_stack(&self.stack, self.config.float_format);
+        match step {
+            Step::After(line) => {
+                eprintln!("{}{}, stack is now {}", self.explain_indent(), line, stack)
+            }
+            Step::Around { name, .. } => eprintln!(
+                "{}{} finished, stack is now {}",
+                self.explain_indent(),
+                name,
+                stack
+            ),
+        }
+    }
+
+    fn explain_indent(&self) -> String {
+        "  ".repeat(self.call_depth.saturating_sub(1) + self.iterating)
+    }
+
     fn check_limits(&mut self) -> RuntimeResult<()> {
         self.steps += 1;
 
+        if let Some(program) = self.config.reload.take()
+            && let Some(summary) = self.reload_words(program)
+        {
+            eprintln!("reloaded: {}", summary);
+        }
+
+        if self.config.cancel.is_cancelled() {
+            return Err(match self.call_stack.last() {
+                Some(word) => RuntimeError::new(&format!("interrupted in '{}'", word)),
+                None => RuntimeError::new("interrupted"),
+            }
+            .uncatchable()
+            .boxed());
+        }
+
         if let Some(max) = self.config.max_steps
             && self.steps > max
         {
             return Err(
-                RuntimeError::new(&format!("execution step limit exceeded ({})", max)).boxed(),
+                RuntimeError::new(&format!("execution step limit exceeded ({})", max))
+                    .uncatchable()
+                    .boxed(),
             );
         }
 
+        if let Some(limit) = self.config.max_wall_time
+            && self.steps.is_multiple_of(WALL_TIME_CHECK_INTERVAL)
+            && self.started_at.elapsed() > limit
+        {
+            let context = self.call_stack.last().cloned().unwrap_or_default();
+
+            return Err(RuntimeError::new(&format!(
+                "time limit exceeded ({}ms){}",
+                limit.as_millis(),
+                if context.is_empty() {
+                    String::new()
+                } else {
+                    format!(" in '{}'", context)
+                }
+            ))
+            .uncatchable()
+            .boxed());
+        }
+
         if self.stack.len() > self.config.max_stack_size {
             return Err(RuntimeError::new(&format!(
                 "stack size limit exceeded ({})",
@@ -137,37 +1146,179 @@ impl VmBc {
         Ok(())
     }
 
-    fn exec_ops(&mut self, ops: &[Op]) -> RuntimeResult<()> {
+    /// Run the body of a user word, applying its depth override, if any.
+    fn run_word(&mut self, name: &str, ops: &[Op]) -> RuntimeResult<()> {
+        let limit = self.config.word_depth_limits.get(name).copied();
+        if let Some(limit) = limit {
+            self.depth_limits.push(limit);
+        }
+        let aux_depth = self.aux_stack.len();
+        // The word's locals go after its caller's
+        let caller_base = std::mem::replace(&mut self.frame_base, self.locals.len());
+        let result = self
+            .exec_ops(ops)
+            .map_err(|mut e| {
+                if let Some(site) = self.sites.get(name) {
+                    self.locate(&mut e, &site.spans, site.file.as_deref());
+                }
+                e
+            })
+            .and_then(|()| self.check_aux_balance(name, aux_depth));
+        self.locals.truncate(self.frame_base);
+        self.frame_base = caller_base;
+        if limit.is_some() {
+            self.depth_limits.pop();
+        }
+        result
+    }
+
+    /// A word must leave the aux stack as it found it: `times` keeps its
+    /// counter there, so residue from one word would be popped as the
+    /// counter by an enclosing loop.
+    fn check_aux_balance(&self, word: &str, depth_before: usize) -> RuntimeResult<()> {
+        let depth = self.aux_stack.len();
+        if depth == depth_before {
+            return Ok(());
+        }
+        let message = if depth > depth_before {
+            let left = depth - depth_before;
+            format!(
+                "word '{}' returned with {} value{} left on the aux stack",
+                word,
+                left,
+                if left == 1 { "" } else { "s" }
+            )
+        } else {
+            let taken = depth_before - depth;
+            format!(
+                "word '{}' took {} value{} from its caller's aux stack",
+                word,
+                taken,
+                if taken == 1 { "" } else { "s" }
+            )
+        };
+        Err(self
+            .error_with_context(message)
+            .with_help(
+                "Every value moved to the aux stack must be moved back before the word returns",
+            )
+            .boxed())
+    }
+
+    pub(crate) fn exec_ops(&mut self, ops: &[Op]) -> RuntimeResult<()> {
         self.call_depth += 1;
 
-        if self.call_depth > self.config.max_call_depth {
-            let context = self.call_stack.last().cloned().unwrap_or_default();
+        let limit = self
+            .depth_limits
+            .last()
+            .copied()
+            .unwrap_or(self.config.max_call_depth);
+        if self.call_depth > limit {
+            let context = match summarize_recursion(&self.call_stack) {
+                Some(cycle) => format!(": {}", cycle),
+                None => match self.call_stack.last() {
+                    Some(word) => format!(" in '{}'", word),
+                    None => String::new(),
+                },
+            };
 
             return Err(RuntimeError::new(&format!(
                 "call depth limit exceeded ({}) - possible infinite recursion{}",
-                self.config.max_call_depth,
-                if context.is_empty() {
-                    String::new()
-                } else {
-                    format!(" in '{}'", context)
-                }
+                limit, context
             ))
+            .with_help(
+                "If the recursion is intended, raise max_call_depth or give the word \
+                 its own limit in word_depth_limits",
+            )
             .boxed());
         }
 
-        let result = self.exec_ops_inner(ops);
+        // An error leaves the iterations it interrupted counted
+        let iterating = self.iterating;
+        let result = self.exec_ops_inner(ops, 0);
+        self.iterating = iterating;
 
         self.call_depth -= 1;
         result
     }
 
-    fn exec_ops_inner(&mut self, ops: &[Op]) -> RuntimeResult<()> {
-        let mut ip: usize = 0;
+    /// Run `body`; if it fails with an error `try` may catch, put the stacks
+    /// back as they were before it ran and run `handler` with the thrown
+    /// value, or the error message, on top.
+    fn run_try(&mut self, body: &[Op], handler: &[Op]) -> RuntimeResult<()> {
+        let stack = self.stack.clone();
+        let aux_stack = self.aux_stack.clone();
+        let call_stack = self.call_stack.len();
+        let call_depth = self.call_depth;
+
+        let Err(e) = self.exec_ops(body) else {
+            return Ok(());
+        };
+        if !e.catchable {
+            return Err(e);
+        }
+
+        self.stack = stack;
+        self.aux_stack = aux_stack;
+        self.call_stack.truncate(call_stack);
+        self.call_depth = call_depth;
+        self.push(e.thrown.unwrap_or(Value::String(e.message)));
+        self.exec_ops(handler)
+    }
+
+    fn exec_ops_inner(&mut self, ops: &[Op], start: usize) -> RuntimeResult<()> {
+        let mut at = start;
+        let result = if self.config.catch_panics {
+            // A panic leaves this frame like an error would; the frames
+            // around it then unwind as usual and the next run resets them
+            panic::catch_unwind(AssertUnwindSafe(|| self.exec_ops_at(ops, start, &mut at)))
+                .unwrap_or_else(|payload| Err(internal_error(payload.as_ref()).boxed()))
+        } else {
+            self.exec_ops_at(ops, start, &mut at)
+        };
+        if result.is_err() {
+            self.error_at = at;
+        }
+        result
+    }
+
+    /// The loop of `exec_ops_inner`, keeping `at` on the op of `ops` that is
+    /// running, or the combinator whose quotation is.
+    fn exec_ops_at(&mut self, ops: &[Op], start: usize, at: &mut usize) -> RuntimeResult<()> {
+        let mut ip = start;
+        let mut iterations: Vec<Iteration> = Vec::new();
+
+        loop {
+            let code = iterations.last().map_or(ops, |it| &it.body[..]);
+            if ip >= code.len() {
+                let Some(iteration) = iterations.last_mut() else {
+                    break;
+                };
+                if self.next_item(iteration)? {
+                    ip = 0;
+                } else {
+                    let iteration = iterations.pop().unwrap();
+                    self.iterating -= 1;
+                    ip = iteration.resume + 1;
+                    self.finish_iteration(iteration);
+                }
+                continue;
+            }
 
-        while ip < ops.len() {
+            // Only main (depth 1) can pause: deeper frames live on the Rust stack
+            if self.pause_requested && self.call_depth == 1 && iterations.is_empty() {
+                self.pause_requested = false;
+                self.paused = Some(PausedMain {
+                    ops: ops.to_vec(),
+                    ip,
+                });
+                return Ok(());
+            }
+            *at = iterations.first().map_or(ip, |it| it.resume);
             self.check_limits()?;
+            let step = self.explain_before(&code[ip]);
 
-            match &ops[ip] {
+            match &code[ip] {
                 // Literals
                 Op::Push(v) => self.push(v.clone()),
 
@@ -193,6 +1344,24 @@ impl VmBc {
                     self.push(b);
                     self.push(a);
                 }
+                Op::Peek(n) => {
+                    let len = self.stack.len();
+                    if *n >= len {
+                        return Err(stack_underflow(n + 1, len)
+                            .with_source(self.source.clone().unwrap_or_default())
+                            .with_file(self.file.clone().unwrap_or_default())
+                            .boxed());
+                    }
+                    let value = self.stack[len - 1 - n].clone();
+                    self.push(value);
+                }
+                Op::Tuck => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(b.clone());
+                    self.push(a);
+                    self.push(b);
+                }
                 Op::Rot => {
                     let c = self.pop()?;
                     let b = self.pop()?;
@@ -201,13 +1370,43 @@ impl VmBc {
                     self.push(c);
                     self.push(a);
                 }
+                Op::Nip => {
+                    let b = self.pop()?;
+                    self.pop()?;
+                    self.push(b);
+                }
+                Op::TwoDup => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    self.push(a.clone());
+                    self.push(b.clone());
+                    self.push(a);
+                    self.push(b);
+                }
+                Op::TwoDrop => {
+                    self.pop()?;
+                    self.pop()?;
+                }
+                Op::Pick => {
+                    let index = self.pop_depth("pick")?;
+                    let value = self.stack[index].clone();
+                    self.push(value);
+                }
+                Op::Roll => {
+                    let index = self.pop_depth("roll")?;
+                    let value = self.stack.remove(index);
+                    self.stack.push(value);
+                }
 
                 // Arithmetic
                 Op::Add => {
                     let b = self.pop()?;
                     let a = self.pop()?;
                     let result = match (&a, &b) {
-                        (Value::Integer(a), Value::Integer(b)) => Value::Integer(a + b),
+                        (Value::Integer(x), Value::Integer(y)) => Value::Integer(
+                            x.checked_add(*y)
+                                .ok_or_else(|| self.integer_overflow("+"))?,
+                        ),
                         (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
                         (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 + b),
                         (Value::Float(a), Value::Integer(b)) => Value::Float(a + *b as f64),
@@ -232,7 +1431,10 @@ impl VmBc {
                     let b = self.pop()?;
                     let a = self.pop()?;
                     let result = match (&a, &b) {
-                        (Value::Integer(a), Value::Integer(b)) => Value::Integer(a - b),
+                        (Value::Integer(x), Value::Integer(y)) => Value::Integer(
+                            x.checked_sub(*y)
+                                .ok_or_else(|| self.integer_overflow("-"))?,
+                        ),
                         (Value::Float(a), Value::Float(b)) => Value::Float(a - b),
                         (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 - b),
                         (Value::Float(a), Value::Integer(b)) => Value::Float(a - *b as f64),
@@ -252,7 +1454,10 @@ impl VmBc {
                     let b = self.pop()?;
                     let a = self.pop()?;
                     let result = match (&a, &b) {
-                        (Value::Integer(a), Value::Integer(b)) => Value::Integer(a * b),
+                        (Value::Integer(x), Value::Integer(y)) => Value::Integer(
+                            x.checked_mul(*y)
+                                .ok_or_else(|| self.integer_overflow("*"))?,
+                        ),
                         (Value::Float(a), Value::Float(b)) => Value::Float(a * b),
                         (Value::Integer(a), Value::Float(b)) => Value::Float(*a as f64 * b),
                         (Value::Float(a), Value::Integer(b)) => Value::Float(a * *b as f64),
@@ -279,7 +1484,11 @@ impl VmBc {
                                     .with_file(self.file.clone().unwrap_or_default())
                                     .boxed());
                             }
-                            Value::Integer(a / b)
+                            // Only i64::MIN / -1 can overflow
+                            Value::Integer(
+                                a.checked_div(*b)
+                                    .ok_or_else(|| self.integer_overflow("/"))?,
+                            )
                         }
                         (Value::Float(a), Value::Float(b)) => {
                             if *b == 0.0 {
@@ -329,12 +1538,16 @@ impl VmBc {
                             .with_help("Check that the divisor is not zero")
                             .boxed());
                     }
-                    self.push(Value::Integer(a % b));
+                    let rem = a.checked_rem(b).ok_or_else(|| self.integer_overflow("%"))?;
+                    self.push(Value::Integer(rem));
                 }
                 Op::Neg => {
                     let a = self.pop()?;
                     let result = match a {
-                        Value::Integer(n) => Value::Integer(-n),
+                        Value::Integer(n) => Value::Integer(
+                            n.checked_neg()
+                                .ok_or_else(|| self.integer_overflow("neg"))?,
+                        ),
                         Value::Float(n) => Value::Float(-n),
                         other => {
                             return Err(
@@ -347,7 +1560,10 @@ impl VmBc {
                 Op::Abs => {
                     let a = self.pop()?;
                     let result = match a {
-                        Value::Integer(n) => Value::Integer(n.abs()),
+                        Value::Integer(n) => Value::Integer(
+                            n.checked_abs()
+                                .ok_or_else(|| self.integer_overflow("abs"))?,
+                        ),
                         Value::Float(n) => Value::Float(n.abs()),
                         other => {
                             return Err(RuntimeError::new(&format!("cannot abs {}", other)).boxed());
@@ -410,10 +1626,13 @@ impl VmBc {
                         Value::String(s) => {
                             self.push(Value::Integer(s.len() as i64));
                         }
+                        Value::Bytes(bytes) => {
+                            self.push(Value::Integer(bytes.len() as i64));
+                        }
                         other => {
                             return Err(self
                                 .error_with_context(format!(
-                                    "type error: expected list or string, got {}",
+                                    "type error: expected list, string or bytes, got {}",
                                     other.type_name()
                                 ))
                                 .with_help(
@@ -453,48 +1672,51 @@ impl VmBc {
                 Op::StringConcat => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    self.push(Value::String(format!("{}{}", a, b)));
+                    let floats = self.config.float_format;
+                    self.push(Value::String(format!(
+                        "{}{}",
+                        a.display(floats),
+                        b.display(floats)
+                    )));
                 }
 
                 // I/O
                 Op::Print => {
+                    self.require_io("print")?;
                     let value = self.pop()?;
-                    println!("{}", value);
+                    let floats = self.config.float_format;
+                    self.write_output(format_args!("{}\n", value.display(floats)))?;
                 }
                 Op::Emit => {
+                    self.require_io("emit")?;
                     let code = self.pop_int()?;
                     if let Some(ch) = char::from_u32(code as u32) {
-                        print!("{}", ch);
-                        io::stdout().flush().ok();
+                        self.write_output(format_args!("{}", ch))?;
+                        self.output.flush().ok();
                     }
                 }
                 Op::Read => {
-                    let stdin = io::stdin();
-                    let line = stdin
-                        .lock()
-                        .lines()
-                        .next()
-                        .transpose()
-                        .map_err(|e| RuntimeError::new(&format!("read error: {}", e)))?
-                        .unwrap_or_default();
+                    self.require_io("read")?;
+                    let line = self.read_input_line()?.unwrap_or_default();
                     self.push(Value::String(line));
                 }
                 Op::Debug => {
+                    self.require_io("debug")?;
                     let value = self.pop()?;
-                    println!("[DEBUG] {:?}", value);
+                    self.write_output(format_args!("[DEBUG] {:?}\n", value))?;
                     self.push(value);
                 }
 
                 // stdlib ops (keeping all your existing ones)
                 Op::Min => {
-                    let b = self.pop_int()?;
-                    let a = self.pop_int()?;
-                    self.push(Value::Integer(a.min(b)));
+                    let b = math_words::pop_number(self, "min")?;
+                    let a = math_words::pop_number(self, "min")?;
+                    self.push(math_words::pick_number(a, b, std::cmp::Ordering::Less));
                 }
                 Op::Max => {
-                    let b = self.pop_int()?;
-                    let a = self.pop_int()?;
-                    self.push(Value::Integer(a.max(b)));
+                    let b = math_words::pop_number(self, "max")?;
+                    let a = math_words::pop_number(self, "max")?;
+                    self.push(math_words::pick_number(a, b, std::cmp::Ordering::Greater));
                 }
                 Op::Pow => {
                     let exp = self.pop_int()?;
@@ -540,6 +1762,50 @@ impl VmBc {
                         }
                     }
                 }
+                op @ (Op::Sin | Op::Cos | Op::Tan | Op::Exp) => {
+                    let x = self.pop_float()?;
+                    let y = match op {
+                        Op::Sin => x.sin(),
+                        Op::Cos => x.cos(),
+                        Op::Tan => x.tan(),
+                        _ => x.exp(),
+                    };
+                    self.push(Value::Float(y));
+                }
+                op @ (Op::Ln | Op::Log10) => {
+                    let x = self.pop_float()?;
+                    if x <= 0.0 {
+                        return Err(self
+                            .error_with_context(format!(
+                                "cannot take the logarithm of {}",
+                                Value::Float(x).display(self.config.float_format)
+                            ))
+                            .with_help("Logarithms are only defined for positive numbers")
+                            .boxed());
+                    }
+                    let y = match op {
+                        Op::Ln => x.ln(),
+                        _ => x.log10(),
+                    };
+                    self.push(Value::Float(y));
+                }
+                Op::Atan2 => {
+                    let x = self.pop_float()?;
+                    let y = self.pop_float()?;
+                    self.push(Value::Float(y.atan2(x)));
+                }
+                op @ (Op::Floor | Op::Ceil | Op::Round) => match self.pop()? {
+                    Value::Integer(n) => self.push(Value::Integer(n)),
+                    Value::Float(x) => {
+                        let y = match op {
+                            Op::Floor => x.floor(),
+                            Op::Ceil => x.ceil(),
+                            _ => x.round(),
+                        };
+                        self.push(Value::Float(y));
+                    }
+                    other => return Err(self.type_error_with_context("number", other.type_name())),
+                },
                 Op::Nth => {
                     let idx = self.pop_int()?;
                     let list = self.pop_list()?;
@@ -587,7 +1853,9 @@ impl VmBc {
                 Op::Join => {
                     let sep = self.pop_string()?;
                     let list = self.pop_list()?;
-                    let strings: Vec<String> = list.iter().map(|v| format!("{}", v)).collect();
+                    let floats = self.config.float_format;
+                    let strings: Vec<String> =
+                        list.iter().map(|v| v.display(floats).to_string()).collect();
                     self.push(Value::String(strings.join(&sep)));
                 }
                 Op::Split => {
@@ -626,6 +1894,8 @@ impl VmBc {
                         Value::String(_) => "String",
                         Value::Bool(_) => "Bool",
                         Value::List(_) => "List",
+                        Value::Bytes(_) => "Bytes",
+                        Value::Map(_) => "Map",
                         Value::Quotation(_) => "Quotation",
                         Value::CompiledQuotation(_) => "CompiledQuotation",
                     };
@@ -634,7 +1904,8 @@ impl VmBc {
                 }
                 Op::ToString => {
                     let value = self.pop()?;
-                    self.push(Value::String(format!("{}", value)));
+                    let floats = self.config.float_format;
+                    self.push(Value::String(value.display(floats).to_string()));
                 }
                 Op::ToInt => {
                     let value = self.pop()?;
@@ -657,11 +1928,131 @@ impl VmBc {
                         }
                     }
                 }
+                Op::ToFloat => {
+                    let value = self.pop()?;
+                    let n = match value {
+                        Value::Float(n) => n,
+                        Value::Integer(n) => n as f64,
+                        Value::String(s) => s.trim().parse().map_err(|_| {
+                            RuntimeError::new(&format!("cannot parse '{}' as float", s))
+                        })?,
+                        other => {
+                            return Err(RuntimeError::new(&format!(
+                                "cannot convert {} to float",
+                                other
+                            ))
+                            .boxed());
+                        }
+                    };
+                    self.push(Value::Float(n));
+                }
+                Op::ParseInt => {
+                    let radix = self.pop_int()?;
+                    let s = self.pop_string()?;
+                    if !(2..=36).contains(&radix) {
+                        return Err(self
+                            .error_with_context(format!(
+                                "parse-int: radix must be between 2 and 36, got {}",
+                                radix
+                            ))
+                            .boxed());
+                    }
+                    let n = i64::from_str_radix(s.trim(), radix as u32).map_err(|_| {
+                        self.error_with_context(format!(
+                            "parse-int: cannot parse '{}' as a base-{} integer",
+                            s, radix
+                        ))
+                        .boxed()
+                    })?;
+                    self.push(Value::Integer(n));
+                }
+
+                // Vector arithmetic
+                Op::VAdd => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = self.broadcast("v+", &a, &b, i64::checked_add, |x, y| x + y)?;
+                    self.push(result);
+                }
+                Op::VMul => {
+                    let b = self.pop()?;
+                    let a = self.pop()?;
+                    let result = self.broadcast("v*", &a, &b, i64::checked_mul, |x, y| x * y)?;
+                    self.push(result);
+                }
+                Op::Scale => {
+                    let k = self.pop()?;
+                    let xs = self.pop()?;
+                    if !matches!(xs, Value::List(_)) {
+                        return Err(self.type_error_with_context("list", xs.type_name()));
+                    }
+                    if !matches!(k, Value::Integer(_) | Value::Float(_)) {
+                        return Err(self.type_error_with_context("number", k.type_name()));
+                    }
+                    let result =
+                        self.broadcast("scale", &xs, &k, i64::checked_mul, |x, y| x * y)?;
+                    self.push(result);
+                }
+                Op::DotProduct => {
+                    let ys = self.pop_list()?;
+                    let xs = self.pop_list()?;
+                    let products = self.broadcast(
+                        "dot",
+                        &Value::List(xs),
+                        &Value::List(ys),
+                        i64::checked_mul,
+                        |x, y| x * y,
+                    )?;
+                    let mut sum = Value::Integer(0);
+                    if let Value::List(items) = products {
+                        for item in &items {
+                            sum =
+                                self.broadcast("dot", &sum, item, i64::checked_add, |x, y| x + y)?;
+                        }
+                    }
+                    self.push(sum);
+                }
+
+                // Matrix helpers
+                Op::Transpose => {
+                    let rows = self.pop_list()?;
+                    let width = self.row_width("transpose", &rows)?;
+                    let mut columns: Vec<Vec<Value>> =
+                        (0..width).map(|_| Vec::with_capacity(rows.len())).collect();
+                    for row in rows {
+                        if let Value::List(items) = row {
+                            for (column, item) in columns.iter_mut().zip(items) {
+                                column.push(item);
+                            }
+                        }
+                    }
+                    self.push(Value::List(columns.into_iter().map(Value::List).collect()));
+                }
+                Op::Shape => {
+                    let value = self.pop()?;
+                    let dims = self.shape_of(&value)?;
+                    self.push(Value::List(
+                        dims.into_iter().map(|d| Value::Integer(d as i64)).collect(),
+                    ));
+                }
+                Op::FlattenDepth => {
+                    let depth = self.pop_int()?;
+                    let list = self.pop_list()?;
+                    if depth < 0 {
+                        return Err(self
+                            .error_with_context(format!(
+                                "flatten-depth: depth must be non-negative, got {}",
+                                depth
+                            ))
+                            .boxed());
+                    }
+                    self.push(Value::List(flatten_depth(list, depth as usize)));
+                }
 
                 // Jump instructions
                 Op::Jump(offset) => {
-                    let new_ip = (ip as i32) + *offset;
-                    if new_ip < 0 || new_ip as usize > ops.len() {
+                    let new_ip = ip as i64 + *offset as i64;
+                    if new_ip < 0 || new_ip as usize > code.len() {
                         return Err(RuntimeError::new(&format!(
                             "jump out of bounds: ip={}, offset={}, target={}",
                             ip, offset, new_ip
@@ -675,8 +2066,8 @@ impl VmBc {
                 Op::JumpIfFalse(offset) => {
                     let cond = self.pop_bool()?;
                     if !cond {
-                        let new_ip = (ip as i32) + *offset;
-                        if new_ip < 0 || new_ip as usize > ops.len() {
+                        let new_ip = ip as i64 + *offset as i64;
+                        if new_ip < 0 || new_ip as usize > code.len() {
                             return Err(RuntimeError::new(&format!(
                                 "jump out of bounds: ip={}, offset={}, target={}",
                                 ip, offset, new_ip
@@ -691,8 +2082,8 @@ impl VmBc {
                 Op::JumpIfTrue(offset) => {
                     let cond = self.pop_bool()?;
                     if cond {
-                        let new_ip = (ip as i32) + *offset;
-                        if new_ip < 0 || new_ip as usize > ops.len() {
+                        let new_ip = ip as i64 + *offset as i64;
+                        if new_ip < 0 || new_ip as usize > code.len() {
                             return Err(RuntimeError::new(&format!(
                                 "jump out of bounds: ip={}, offset={}, target={}",
                                 ip, offset, new_ip
@@ -709,6 +2100,23 @@ impl VmBc {
                     let body = self.pop_quotation_ops()?;
                     self.exec_ops(&body)?;
                 }
+                Op::Try => {
+                    let handler = self.pop_quotation_ops()?;
+                    let body = self.pop_quotation_ops()?;
+                    self.run_try(&body, &handler)?;
+                }
+                Op::Throw => {
+                    let value = self.pop()?;
+                    let message = match &value {
+                        Value::String(s) => s.clone(),
+                        other => format!("uncaught throw: {}", other),
+                    };
+                    return Err(self
+                        .error_with_context(message)
+                        .with_thrown(value)
+                        .with_help("Catch it with `[ ... ] [ handler ] try`")
+                        .boxed());
+                }
                 Op::If => {
                     let else_branch = self.pop_quotation_ops()?;
                     let then_branch = self.pop_quotation_ops()?;
@@ -814,57 +2222,58 @@ impl VmBc {
                 // Loops
                 Op::Times => {
                     let body = self.pop_quotation_ops()?;
+                    self.check_times_count()?;
                     let n = self.pop_int()?;
-                    if n < 0 {
-                        return Err(RuntimeError::new("times expects non-negative integer").boxed());
-                    }
                     for _ in 0..n {
                         self.exec_ops(&body)?;
                     }
                 }
-                Op::Each => {
-                    let body = self.pop_quotation_ops()?;
-                    let list = self.pop_list()?;
-                    for item in list {
-                        self.push(item);
-                        self.exec_ops(&body)?;
-                    }
-                }
-                Op::Map => {
-                    let body = self.pop_quotation_ops()?;
-                    let list = self.pop_list()?;
-                    let mut result = Vec::new();
-                    for item in list {
-                        self.push(item);
-                        self.exec_ops(&body)?;
-                        result.push(self.pop()?);
-                    }
-                    self.push(Value::List(result));
-                }
-                Op::Filter => {
+                // In front of a `times` loop the compiler lowered to jumps
+                Op::CheckTimes => self.check_times_count()?,
+                Op::CheckBool => self.check_short_circuit_operand()?,
+                Op::NextLine => io_words::next_line(self)?,
+                op @ (Op::While | Op::Until) => {
+                    let until = matches!(op, Op::Until);
                     let body = self.pop_quotation_ops()?;
-                    let list = self.pop_list()?;
-                    let mut result = Vec::new();
-                    for item in list {
-                        self.push(item.clone());
-                        self.exec_ops(&body)?;
-                        if self.pop_bool()? {
-                            result.push(item);
+                    let cond = self.pop_quotation_ops()?;
+                    loop {
+                        self.exec_ops(&cond)?;
+                        if self.pop_bool()? == until {
+                            break;
                         }
+                        self.exec_ops(&body)?;
                     }
-                    self.push(Value::List(result));
                 }
-                Op::Fold => {
+                op @ (Op::Each | Op::Map | Op::Filter | Op::Fold) => {
+                    let op = op.clone();
                     let body = self.pop_quotation_ops()?;
-                    let mut acc = self.pop()?;
-                    let list = self.pop_list()?;
-                    for item in list {
-                        self.push(acc);
-                        self.push(item);
-                        self.exec_ops(&body)?;
-                        acc = self.pop()?;
-                    }
-                    self.push(acc);
+                    let acc = match &op {
+                        Op::Fold => Some(self.pop()?),
+                        _ => None,
+                    };
+                    let word = match &op {
+                        Op::Each => "each",
+                        Op::Map => "map",
+                        Op::Filter => "filter",
+                        _ => "fold",
+                    };
+                    let items = Items::pop(self, word)?;
+                    let resume = ip;
+                    self.iterating += 1;
+                    // The first item is pushed at the top of the loop, which
+                    // also finishes the combinator if the list is empty
+                    ip = body.len();
+                    iterations.push(Iteration {
+                        op,
+                        body,
+                        items,
+                        current: None,
+                        collected: Vec::new(),
+                        acc,
+                        resume,
+                        explain: step,
+                    });
+                    continue;
                 }
                 Op::Range => {
                     let end = self.pop_int()?;
@@ -884,14 +2293,21 @@ impl VmBc {
                 Op::CallWord(name) => {
                     self.call_stack.push(name.clone());
 
-                    let ops = self.words.get(name).cloned().ok_or_else(|| {
-                        undefined_word(name)
+                    // User words take precedence over natives of the same name
+                    let result = if let Some(ops) = self.words.get(name).cloned() {
+                        self.run_word(name, &ops)
+                    } else if let Some(native) = native::lookup(name) {
+                        native(self)
+                    } else if let Some(word) = self.host_words.get(name).cloned() {
+                        word(self)
+                    } else if let Some(result) = self.call_plugin(name) {
+                        result
+                    } else {
+                        return Err(undefined_word(name)
                             .with_source(self.source.clone().unwrap_or_default())
                             .with_file(self.file.clone().unwrap_or_default())
-                            .boxed()
-                    })?;
-
-                    let result = self.exec_ops(&ops);
+                            .boxed());
+                    };
                     self.call_stack.pop();
 
                     result.map_err(|e| {
@@ -909,11 +2325,48 @@ impl VmBc {
                     let ops = self.words.get(&qualified).cloned().ok_or_else(|| {
                         RuntimeError::new(&format!("undefined: {}.{}", module, word))
                     })?;
-                    let result = self.exec_ops(&ops);
+                    let result = self.run_word(&qualified, &ops);
                     self.call_stack.pop();
                     result.map_err(|e| e.with_context(&qualified))?;
                 }
 
+                Op::Store(name) => {
+                    let value = self.pop()?;
+                    self.globals.insert(name.clone(), value);
+                }
+                Op::Load(name) => match self.globals.get(name) {
+                    Some(value) => {
+                        let value = value.clone();
+                        self.push(value);
+                    }
+                    None => {
+                        return Err(self
+                            .error_with_context(format!(
+                                "variable '{}' is read before it is set",
+                                name
+                            ))
+                            .with_help(format!("Store a value first, e.g. 0 {} !", name))
+                            .boxed());
+                    }
+                },
+
+                Op::StoreLocal(slot) => {
+                    let value = self.pop()?;
+                    let index = self.frame_base + slot;
+                    if index >= self.locals.len() {
+                        self.locals.resize(index + 1, None);
+                    }
+                    self.locals[index] = Some(value);
+                }
+                Op::LoadLocal(slot) => {
+                    let value = self.local(*slot)?;
+                    self.push(value);
+                }
+                Op::Capture(ops) => {
+                    let ops = self.capture(ops)?;
+                    self.push(Value::CompiledQuotation(ops));
+                }
+
                 Op::ToAux => {
                     let val = self.pop()?;
                     self.aux_stack.push(val);
@@ -927,22 +2380,120 @@ impl VmBc {
                     self.push(val);
                 }
 
-                Op::Return => break,
+                // Ends a word body; quotations have none
+                Op::Return if iterations.is_empty() => break,
+                Op::Return => {
+                    ip = usize::MAX;
+                    continue;
+                }
             }
 
+            if let Some(step) = step {
+                self.explain_after(step);
+            }
             ip += 1;
         }
 
         Ok(())
     }
 
+    /// Fail unless the count of a `times` loop, on top of the stack, is a
+    /// non-negative integer.
+    fn check_times_count(&self) -> RuntimeResult<()> {
+        let Some(count) = self.stack.last() else {
+            // Left to the ops that take the count to report as an underflow
+            return Ok(());
+        };
+        match count {
+            Value::Integer(n) if *n < 0 => {
+                return Err(RuntimeError::new("times expects non-negative integer").boxed());
+            }
+            Value::Integer(_) => return Ok(()),
+            _ => {}
+        }
+        Err(self
+            .error_with_context(format!(
+                "type error in 'times' loop counter: expected integer, got {}",
+                count.type_name()
+            ))
+            .with_help(
+                "`n [ ... ] times` needs a whole number n; to-int converts strings and floats",
+            )
+            .boxed())
+    }
+
+    /// Fail unless the quotation a lowered `and` or `or` just ran left a
+    /// bool on top, as the strict `and`/`or` would require of it.
+    fn check_short_circuit_operand(&self) -> RuntimeResult<()> {
+        match self.stack.last() {
+            // An empty stack is left to the ops after it to report
+            None | Some(Value::Bool(_)) => Ok(()),
+            Some(other) => Err(self
+                .error_with_context(format!(
+                    "type error in 'and'/'or': the quotation must leave a bool, got {}",
+                    other.type_name()
+                ))
+                .with_help("In `a [ b ] and` and `a [ b ] or`, `b` must leave true or false")
+                .boxed()),
+        }
+    }
+
+    /// Finish the item `iteration`'s body just ran on, if any, and push the
+    /// next one. False when the list is done.
+    fn next_item(&mut self, iteration: &mut Iteration) -> RuntimeResult<bool> {
+        if let Some((index, height, item)) = iteration.current.take() {
+            match iteration.op {
+                Op::Map => {
+                    self.check_item_effect("map", "exactly one value", index, height)?;
+                    iteration.collected.push(self.pop()?);
+                }
+                Op::Filter => {
+                    self.check_item_effect("filter", "a flag", index, height)?;
+                    if self.pop_bool()? {
+                        iteration.collected.extend(item);
+                    }
+                }
+                Op::Fold => iteration.acc = Some(self.pop()?),
+                _ => {}
+            }
+        }
+
+        let Some((index, item)) = iteration.items.next(self)? else {
+            return Ok(false);
+        };
+        if let Some(acc) = iteration.acc.take() {
+            self.push(acc);
+        }
+        let height = self.stack.len();
+        let kept = matches!(iteration.op, Op::Filter).then(|| item.clone());
+        self.push(item);
+        iteration.current = Some((index, height, kept));
+        Ok(true)
+    }
+
+    /// Push the result of a finished `map`, `filter` or `fold`.
+    fn finish_iteration(&mut self, iteration: Iteration) {
+        match iteration.op {
+            Op::Map | Op::Filter => self.push(Value::List(iteration.collected)),
+            _ => {}
+        }
+        if let Some(acc) = iteration.acc {
+            self.push(acc);
+        }
+        if let Some(step) = iteration.explain {
+            self.explain_after(step);
+        }
+    }
+
     // Stack operations
 
-    fn push(&mut self, value: Value) {
+    pub fn push(&mut self, value: Value) {
+        self.allocated_bytes += shallow_heap_size(&value);
         self.stack.push(value);
+        self.peak_stack = self.peak_stack.max(self.stack.len());
     }
 
-    fn pop(&mut self) -> RuntimeResult<Value> {
+    pub fn pop(&mut self) -> RuntimeResult<Value> {
         self.stack.pop().ok_or_else(|| {
             stack_underflow(1, 0)
                 .with_source(self.source.clone().unwrap_or_default())
@@ -951,13 +2502,22 @@ impl VmBc {
         })
     }
 
-    fn pop_int(&mut self) -> RuntimeResult<i64> {
+    pub fn pop_int(&mut self) -> RuntimeResult<i64> {
         match self.pop().map_err(|e| e.boxed())? {
             Value::Integer(n) => Ok(n),
             other => Err(self.type_error_with_context("integer", other.type_name())),
         }
     }
 
+    /// Pop a number as a float; integers are converted.
+    pub fn pop_float(&mut self) -> RuntimeResult<f64> {
+        match self.pop()? {
+            Value::Integer(n) => Ok(n as f64),
+            Value::Float(x) => Ok(x),
+            other => Err(self.type_error_with_context("number", other.type_name())),
+        }
+    }
+
     fn pop_two_numeric(&mut self) -> RuntimeResult<(f64, f64)> {
         let b = self.pop()?;
         let a = self.pop()?;
@@ -979,33 +2539,300 @@ impl VmBc {
         Ok((b_f, a_f))
     }
 
-    fn pop_bool(&mut self) -> RuntimeResult<bool> {
+    /// Pop the depth operand of `pick` or `roll` and return the stack index
+    /// it refers to, counting down from the value below it.
+    fn pop_depth(&mut self, word: &str) -> RuntimeResult<usize> {
+        let n = self.pop_int()?;
+        let Ok(n) = usize::try_from(n) else {
+            return Err(self
+                .error_with_context(format!("{}: depth must not be negative, got {}", word, n))
+                .with_help("`0 pick` copies the top value, `1 pick` the one below it")
+                .boxed());
+        };
+        let len = self.stack.len();
+        if n >= len {
+            return Err(stack_underflow(n + 1, len)
+                .with_source(self.source.clone().unwrap_or_default())
+                .with_file(self.file.clone().unwrap_or_default())
+                .boxed());
+        }
+        Ok(len - 1 - n)
+    }
+
+    pub fn pop_bool(&mut self) -> RuntimeResult<bool> {
         match self.pop()? {
             Value::Bool(b) => Ok(b),
             other => Err(self.type_error_with_context("boolean", other.type_name())),
         }
     }
 
-    fn pop_list(&mut self) -> RuntimeResult<Vec<Value>> {
+    pub fn pop_list(&mut self) -> RuntimeResult<Vec<Value>> {
         match self.pop()? {
             Value::List(items) => Ok(items),
             other => Err(self.type_error_with_context("list", other.type_name())),
         }
     }
 
-    fn pop_string(&mut self) -> RuntimeResult<String> {
+    pub fn pop_string(&mut self) -> RuntimeResult<String> {
         match self.pop()? {
             Value::String(s) => Ok(s),
             other => Err(self.type_error_with_context("string", other.type_name())),
         }
     }
 
-    fn pop_quotation_ops(&mut self) -> RuntimeResult<Vec<Op>> {
+    /// After `map` or `filter` runs its quotation on one item, the stack
+    /// should be one value taller than before the item was pushed.
+    pub(crate) fn check_item_effect(
+        &self,
+        word: &str,
+        result: &str,
+        index: usize,
+        height: usize,
+    ) -> RuntimeResult<()> {
+        let change = self.stack.len() as i64 - (height as i64 + 1);
+        if change == 0 {
+            return Ok(());
+        }
+        Err(RuntimeError::new(&format!(
+            "{}: the quotation must replace each item with {}, but it changed the stack by {:+} on item {}",
+            word, result, change, index
+        ))
+        .boxed())
+    }
+
+    /// The value in a slot of the running word's locals.
+    fn local(&self, slot: usize) -> RuntimeResult<Value> {
+        match self.locals.get(self.frame_base + slot) {
+            Some(Some(value)) => Ok(value.clone()),
+            _ => Err(self
+                .error_with_context(format!("local slot {} is read before it is set", slot))
+                .boxed()),
+        }
+    }
+
+    /// A copy of a quotation with the running word's locals filled in, so
+    /// it can run after the word returns or inside another word.
+    fn capture(&self, ops: &[Op]) -> RuntimeResult<Vec<Op>> {
+        ops.iter()
+            .map(|op| {
+                Ok(match op {
+                    Op::LoadLocal(slot) => Op::Push(self.local(*slot)?),
+                    Op::Push(Value::CompiledQuotation(inner)) => {
+                        Op::Push(Value::CompiledQuotation(self.capture(inner)?))
+                    }
+                    Op::Capture(inner) => Op::Capture(self.capture(inner)?),
+                    op => op.clone(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn pop_quotation_ops(&mut self) -> RuntimeResult<Vec<Op>> {
         match self.pop()? {
             Value::CompiledQuotation(ops) => Ok(ops),
             other => Err(self.type_error_with_context("quotation", other.type_name())),
         }
     }
+
+    // Vector helpers
+
+    /// Element-wise arithmetic for the vectorized list words.
+    ///
+    /// Two lists are combined pairwise and must have equal lengths; a scalar
+    /// on either side is broadcast across the other operand. Nested lists are
+    /// handled recursively. Numeric promotion follows `+`/`*`: two integers
+    /// stay integral, anything involving a float becomes a float.
+    fn broadcast(
+        &self,
+        word: &str,
+        a: &Value,
+        b: &Value,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> RuntimeResult<Value> {
+        match (a, b) {
+            (Value::List(xs), Value::List(ys)) => {
+                if xs.len() != ys.len() {
+                    return Err(self
+                        .error_with_context(format!(
+                            "{}: length mismatch ({} vs {})",
+                            word,
+                            xs.len(),
+                            ys.len()
+                        ))
+                        .with_help("Element-wise operations need lists of the same length")
+                        .boxed());
+                }
+                let items = xs
+                    .iter()
+                    .zip(ys)
+                    .map(|(x, y)| self.broadcast(word, x, y, int_op, float_op))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+                Ok(Value::List(items))
+            }
+            (Value::List(xs), scalar) => {
+                let items = xs
+                    .iter()
+                    .map(|x| self.broadcast(word, x, scalar, int_op, float_op))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+                Ok(Value::List(items))
+            }
+            (scalar, Value::List(ys)) => {
+                let items = ys
+                    .iter()
+                    .map(|y| self.broadcast(word, scalar, y, int_op, float_op))
+                    .collect::<RuntimeResult<Vec<_>>>()?;
+                Ok(Value::List(items))
+            }
+            (Value::Integer(x), Value::Integer(y)) => int_op(*x, *y)
+                .map(Value::Integer)
+                .ok_or_else(|| self.integer_overflow(word)),
+            (Value::Integer(x), Value::Float(y)) => Ok(Value::Float(float_op(*x as f64, *y))),
+            (Value::Float(x), Value::Integer(y)) => Ok(Value::Float(float_op(*x, *y as f64))),
+            (Value::Float(x), Value::Float(y)) => Ok(Value::Float(float_op(*x, *y))),
+            (x, y) => Err(self
+                .error_with_context(format!(
+                    "type error: {} expects numbers or lists of numbers, got {} and {}",
+                    word,
+                    x.type_name(),
+                    y.type_name()
+                ))
+                .boxed()),
+        }
+    }
+
+    // Matrix helpers
+
+    /// Common row length of a list of lists, erroring on non-list rows or
+    /// ragged input. An empty list of rows has width 0.
+    fn row_width(&self, word: &str, rows: &[Value]) -> RuntimeResult<usize> {
+        let mut width = None;
+        for (i, row) in rows.iter().enumerate() {
+            let len = match row {
+                Value::List(items) => items.len(),
+                other => {
+                    return Err(self
+                        .error_with_context(format!(
+                            "{}: row {} is a {}, expected a list",
+                            word,
+                            i,
+                            other.type_name()
+                        ))
+                        .boxed());
+                }
+            };
+            match width {
+                None => width = Some(len),
+                Some(w) if w != len => {
+                    return Err(self
+                        .error_with_context(format!(
+                            "{}: ragged input, row {} has {} items but row 0 has {}",
+                            word, i, len, w
+                        ))
+                        .with_help("All rows must have the same length")
+                        .boxed());
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(width.unwrap_or(0))
+    }
+
+    /// Dimensions of a rectangular nested list; scalars have shape `{ }`.
+    fn shape_of(&self, value: &Value) -> RuntimeResult<Vec<usize>> {
+        let items = match value {
+            Value::List(items) => items,
+            _ => return Ok(Vec::new()),
+        };
+        let mut dims = vec![items.len()];
+        if let Some(first) = items.first() {
+            let inner = self.shape_of(first)?;
+            for (i, item) in items.iter().enumerate().skip(1) {
+                if self.shape_of(item)? != inner {
+                    return Err(self
+                        .error_with_context(format!(
+                            "shape: ragged input, element {} differs in shape from element 0",
+                            i
+                        ))
+                        .with_help("All elements of a nested list must have the same shape")
+                        .boxed());
+                }
+            }
+            dims.extend(inner);
+        }
+        Ok(dims)
+    }
+}
+
+/// Heap bytes a value owns directly, for `RunReport::allocated_bytes`.
+/// Nested items aren't walked, so pushing stays constant-time.
+fn shallow_heap_size(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Bytes(bytes) => bytes.len(),
+        Value::List(items) => items.len() * size_of::<Value>(),
+        Value::Map(entries) => entries.len() * (size_of::<String>() + size_of::<Value>()),
+        Value::Quotation(nodes) => nodes.len() * size_of::<crate::lang::node::Node>(),
+        Value::CompiledQuotation(ops) => ops.len() * size_of::<Op>(),
+        Value::Integer(_) | Value::Float(_) | Value::Bool(_) => 0,
+    }
+}
+
+/// The error a caught panic becomes.
+pub(crate) fn internal_error(payload: &(dyn Any + Send)) -> RuntimeError {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message");
+    RuntimeError::new(&format!("internal error, please report: {}", message))
+        .with_help("This is a bug in ember, not in the script")
+        .uncatchable()
+}
+
+/// The longest run of a repeating call pattern at the top of `call_stack`,
+/// e.g. "fib -> helper -> fib repeated 499 times", or None if the innermost
+/// calls don't repeat.
+fn summarize_recursion(call_stack: &[String]) -> Option<String> {
+    // Cycles longer than this are rare and make for unreadable messages
+    const MAX_CYCLE: usize = 8;
+
+    let mut best: Option<(usize, usize)> = None; // (cycle length, repeats)
+    for len in 1..=MAX_CYCLE.min(call_stack.len() / 2) {
+        let unit = &call_stack[call_stack.len() - len..];
+        let repeats = call_stack
+            .rchunks_exact(len)
+            .take_while(|chunk| *chunk == unit)
+            .count();
+        if repeats >= 2 && best.is_none_or(|(l, r)| repeats * len > r * l) {
+            best = Some((len, repeats));
+        }
+    }
+
+    let (len, repeats) = best?;
+    // Start the cycle where the repetition starts, so the entry word leads
+    let start = call_stack.len() - len * repeats;
+    let mut cycle: Vec<&str> = call_stack[start..start + len]
+        .iter()
+        .map(String::as_str)
+        .collect();
+    cycle.push(cycle[0]);
+    Some(format!("{} repeated {} times", cycle.join(" -> "), repeats))
+}
+
+/// Splice nested lists into their parent, up to `depth` levels deep.
+fn flatten_depth(list: Vec<Value>, depth: usize) -> Vec<Value> {
+    if depth == 0 {
+        return list;
+    }
+    let mut out = Vec::with_capacity(list.len());
+    for item in list {
+        match item {
+            Value::List(inner) => out.extend(flatten_depth(inner, depth - 1)),
+            other => out.push(other),
+        }
+    }
+    out
 }
 
 #[allow(clippy::result_large_err)]
@@ -1016,7 +2843,7 @@ mod tests {
     use crate::bytecode::Op;
     use crate::bytecode::{CodeObject, ProgramBc};
     use crate::lang::value::Value;
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     // ============================================================
     // Test Helpers
@@ -1025,16 +2852,26 @@ mod tests {
     /// Create a simple program from a list of ops
     fn program_from_ops(ops: Vec<Op>) -> ProgramBc {
         ProgramBc {
-            code: vec![CodeObject { ops }],
-            words: HashMap::new(),
+            code: vec![CodeObject {
+                ops,
+                spans: Vec::new(),
+            }],
+            words: BTreeMap::new(),
+            docs: BTreeMap::new(),
+            sites: BTreeMap::new(),
         }
     }
 
     /// Create a program with user-defined words
-    fn program_with_words(ops: Vec<Op>, words: HashMap<String, Vec<Op>>) -> ProgramBc {
+    fn program_with_words(ops: Vec<Op>, words: BTreeMap<String, Vec<Op>>) -> ProgramBc {
         ProgramBc {
-            code: vec![CodeObject { ops }],
+            code: vec![CodeObject {
+                ops,
+                spans: Vec::new(),
+            }],
             words,
+            docs: BTreeMap::new(),
+            sites: BTreeMap::new(),
         }
     }
 
@@ -1175,10 +3012,6 @@ mod tests {
 
     #[test]
     fn test_over() {
-        // Note: Based on the VM code, Over pops b, pops a, pushes b, pushes a
-        // This seems like it should be: a b -- a b a (copy second to top)
-        // But the implementation does: a b -- b a (which is swap!)
-        // This might be a bug in the VM. Testing actual behavior:
         assert_stack(
             vec![
                 Op::Push(Value::Integer(1)),
@@ -1190,36 +3023,74 @@ mod tests {
     }
 
     #[test]
-    fn test_rot() {
-        // rot: a b c -- b c a
+    fn test_peek() {
         assert_stack(
             vec![
                 Op::Push(Value::Integer(1)),
                 Op::Push(Value::Integer(2)),
                 Op::Push(Value::Integer(3)),
-                Op::Rot,
+                Op::Peek(2),
+            ],
+            vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3),
+                Value::Integer(1),
             ],
-            vec![Value::Integer(2), Value::Integer(3), Value::Integer(1)],
         );
     }
 
     #[test]
-    fn test_add_integers() {
-        assert_stack(
-            vec![
-                Op::Push(Value::Integer(3)),
-                Op::Push(Value::Integer(4)),
-                Op::Add,
-            ],
-            vec![Value::Integer(7)],
+    fn test_peek_underflow() {
+        assert_error(
+            vec![Op::Push(Value::Integer(1)), Op::Peek(1)],
+            "stack underflow",
         );
     }
 
     #[test]
-    fn test_add_floats() {
+    fn test_tuck() {
         assert_stack(
             vec![
-                Op::Push(Value::Float(1.5)),
+                Op::Push(Value::Integer(1)),
+                Op::Push(Value::Integer(2)),
+                Op::Tuck,
+            ],
+            vec![Value::Integer(2), Value::Integer(1), Value::Integer(2)],
+        );
+    }
+
+    #[test]
+    fn test_rot() {
+        // rot: a b c -- b c a
+        assert_stack(
+            vec![
+                Op::Push(Value::Integer(1)),
+                Op::Push(Value::Integer(2)),
+                Op::Push(Value::Integer(3)),
+                Op::Rot,
+            ],
+            vec![Value::Integer(2), Value::Integer(3), Value::Integer(1)],
+        );
+    }
+
+    #[test]
+    fn test_add_integers() {
+        assert_stack(
+            vec![
+                Op::Push(Value::Integer(3)),
+                Op::Push(Value::Integer(4)),
+                Op::Add,
+            ],
+            vec![Value::Integer(7)],
+        );
+    }
+
+    #[test]
+    fn test_add_floats() {
+        assert_stack(
+            vec![
+                Op::Push(Value::Float(1.5)),
                 Op::Push(Value::Float(2.5)),
                 Op::Add,
             ],
@@ -1983,6 +3854,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_min_max_mixed_numbers() {
+        let min_of = |a: Value, b: Value| {
+            let mut vm = VmBc::new();
+            vm.exec_ops(&[Op::Push(a), Op::Push(b), Op::Min]).unwrap();
+            vm.stack
+        };
+        assert_eq!(
+            min_of(Value::Float(2.5), Value::Float(1.5)),
+            vec![Value::Float(1.5)]
+        );
+        // A float on either side makes the result a float, as in arithmetic
+        assert_eq!(
+            min_of(Value::Integer(2), Value::Float(3.5)),
+            vec![Value::Float(2.0)]
+        );
+        assert_error(
+            vec![
+                Op::Push(Value::Integer(1)),
+                Op::Push(Value::String("2".to_string())),
+                Op::Max,
+            ],
+            "max: expected a number, got string",
+        );
+    }
+
     #[test]
     fn test_pow() {
         assert_stack(
@@ -2113,6 +4010,82 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_float_string_round_trip() {
+        // Display is locale-independent and prints the shortest string that
+        // parses back to the same bits
+        let samples = [
+            0.1,
+            -2.5e-10,
+            1e300,
+            f64::MIN_POSITIVE,
+            f64::MAX,
+            1.0 / 3.0,
+            -0.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ];
+        for x in samples {
+            let stack =
+                run_ops(vec![Op::Push(Value::Float(x)), Op::ToString, Op::ToFloat]).unwrap();
+            match stack.as_slice() {
+                [Value::Float(y)] => assert_eq!(y.to_bits(), x.to_bits(), "{}", x),
+                other => panic!("expected float, got {:?}", other),
+            }
+        }
+
+        let stack = run_ops(vec![
+            Op::Push(Value::Float(f64::NAN)),
+            Op::ToString,
+            Op::ToFloat,
+        ])
+        .unwrap();
+        assert!(matches!(stack.as_slice(), [Value::Float(y)] if y.is_nan()));
+    }
+
+    #[test]
+    fn test_float_format_config() {
+        let ops = || {
+            vec![
+                Op::Push(Value::Float(0.1)),
+                Op::Push(Value::Float(0.2)),
+                Op::Add,
+                Op::ToString,
+            ]
+        };
+        let format = |float_format| {
+            run_ops_with_config(
+                ops(),
+                VmBcConfig {
+                    float_format,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            run_ops(ops()).unwrap(),
+            vec![Value::String("0.30000000000000004".into())]
+        );
+        assert_eq!(
+            format(FloatFormat::Significant(15)),
+            vec![Value::String("0.3".into())]
+        );
+        assert_eq!(
+            format(FloatFormat::Fixed(3)),
+            vec![Value::String("0.300".into())]
+        );
+    }
+
+    #[test]
+    fn test_to_float_invalid_string() {
+        // A comma is never a decimal separator, whatever the system locale
+        assert_error(
+            vec![Op::Push(Value::String("1,5".to_string())), Op::ToFloat],
+            "cannot parse '1,5' as float",
+        );
+    }
+
     #[test]
     fn test_clear() {
         assert_stack(
@@ -2143,6 +4116,32 @@ mod tests {
         assert_stack(vec![Op::Depth], vec![Value::Integer(0)]);
     }
 
+    #[test]
+    fn test_jump_offset_overflow_is_an_error() {
+        // Unverified bytecode must not be able to panic the VM
+        assert_error(vec![Op::Jump(i32::MAX)], "jump out of bounds");
+        assert_error(
+            vec![Op::Push(Value::Integer(1)), Op::Jump(i32::MIN)],
+            "jump out of bounds",
+        );
+    }
+
+    #[test]
+    fn test_integer_overflow_is_an_error() {
+        let max = || Op::Push(Value::Integer(i64::MAX));
+        let min = || Op::Push(Value::Integer(i64::MIN));
+        let one = || Op::Push(Value::Integer(1));
+        let minus_one = || Op::Push(Value::Integer(-1));
+
+        assert_error(vec![max(), one(), Op::Add], "+: integer overflow");
+        assert_error(vec![min(), one(), Op::Sub], "-: integer overflow");
+        assert_error(vec![max(), max(), Op::Mul], "*: integer overflow");
+        assert_error(vec![min(), minus_one(), Op::Div], "/: integer overflow");
+        assert_error(vec![min(), minus_one(), Op::Mod], "%: integer overflow");
+        assert_error(vec![min(), Op::Neg], "neg: integer overflow");
+        assert_error(vec![min(), Op::Abs], "abs: integer overflow");
+    }
+
     #[test]
     fn test_jump_forward() {
         // Jump over Op::Push(99)
@@ -2619,7 +4618,7 @@ mod tests {
 
     #[test]
     fn test_call_word() {
-        let mut words = HashMap::new();
+        let mut words = BTreeMap::new();
         words.insert("double".to_string(), vec![Op::Dup, Op::Add]);
 
         let prog = program_with_words(
@@ -2635,6 +4634,66 @@ mod tests {
         assert_eq!(vm.stack(), vec![Value::Integer(10)]);
     }
 
+    #[test]
+    fn test_word_leaving_aux_residue() {
+        let mut words = BTreeMap::new();
+        words.insert("leaky".to_string(), vec![Op::ToAux]);
+        let prog = program_with_words(
+            vec![
+                Op::Push(Value::Integer(1)),
+                Op::CallWord("leaky".to_string()),
+            ],
+            words,
+        );
+
+        let err = VmBc::new().run_compiled(&prog).unwrap_err();
+        assert!(
+            err.message
+                .contains("word 'leaky' returned with 1 value left on the aux stack")
+        );
+        assert_eq!(err.call_stack, vec!["leaky".to_string()]);
+    }
+
+    #[test]
+    fn test_word_taking_from_callers_aux() {
+        let mut words = BTreeMap::new();
+        words.insert("greedy".to_string(), vec![Op::FromAux]);
+        let prog = program_with_words(
+            vec![
+                Op::Push(Value::Integer(1)),
+                Op::ToAux,
+                Op::CallWord("greedy".to_string()),
+            ],
+            words,
+        );
+
+        let err = VmBc::new().run_compiled(&prog).unwrap_err();
+        assert!(
+            err.message
+                .contains("took 1 value from its caller's aux stack")
+        );
+    }
+
+    #[test]
+    fn test_balanced_aux_in_word() {
+        let mut words = BTreeMap::new();
+        words.insert(
+            "hide".to_string(),
+            vec![Op::ToAux, Op::Push(Value::Integer(2)), Op::FromAux],
+        );
+        let prog = program_with_words(
+            vec![
+                Op::Push(Value::Integer(1)),
+                Op::CallWord("hide".to_string()),
+            ],
+            words,
+        );
+
+        let mut vm = VmBc::new();
+        vm.run_compiled(&prog).unwrap();
+        assert_eq!(vm.stack(), vec![Value::Integer(2), Value::Integer(1)]);
+    }
+
     #[test]
     fn test_call_word_undefined() {
         assert_error(
@@ -2645,7 +4704,7 @@ mod tests {
 
     #[test]
     fn test_call_qualified() {
-        let mut words = HashMap::new();
+        let mut words = BTreeMap::new();
         words.insert("math.square".to_string(), vec![Op::Dup, Op::Mul]);
 
         let prog = program_with_words(
@@ -2667,7 +4726,7 @@ mod tests {
     #[test]
     fn test_recursive_word() {
         // Factorial: n -- n!
-        let mut words = HashMap::new();
+        let mut words = BTreeMap::new();
         words.insert(
             "factorial".to_string(),
             vec![
@@ -2705,7 +4764,7 @@ mod tests {
     #[test]
     fn test_call_depth_limit() {
         // Create infinite recursion
-        let mut words = HashMap::new();
+        let mut words = BTreeMap::new();
         words.insert(
             "infinite".to_string(),
             vec![Op::CallWord("infinite".to_string())],
@@ -2723,6 +4782,83 @@ mod tests {
         assert!(result.unwrap_err().message.contains("call depth limit"));
     }
 
+    #[test]
+    fn test_call_depth_limit_summarizes_cycle() {
+        let mut words = BTreeMap::new();
+        words.insert("fib".to_string(), vec![Op::CallWord("helper".to_string())]);
+        words.insert("helper".to_string(), vec![Op::CallWord("fib".to_string())]);
+        let prog = program_with_words(vec![Op::CallWord("fib".to_string())], words);
+
+        let mut vm = VmBc::with_config(VmBcConfig {
+            max_call_depth: 10,
+            ..Default::default()
+        });
+        let err = vm.run_compiled(&prog).unwrap_err();
+        assert!(
+            err.message
+                .contains("fib -> helper -> fib repeated 5 times"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_word_depth_limit_override() {
+        // count-down: n -- ; recurses n times
+        let mut words = BTreeMap::new();
+        words.insert(
+            "count-down".to_string(),
+            vec![
+                Op::Dup,
+                Op::Push(Value::Integer(0)),
+                Op::Gt,
+                Op::Push(Value::CompiledQuotation(vec![
+                    Op::Push(Value::Integer(1)),
+                    Op::Sub,
+                    Op::CallWord("count-down".to_string()),
+                ])),
+                Op::Push(Value::CompiledQuotation(vec![Op::Drop])),
+                Op::If,
+            ],
+        );
+        let prog = program_with_words(
+            vec![
+                Op::Push(Value::Integer(10)),
+                Op::CallWord("count-down".to_string()),
+            ],
+            words,
+        );
+        let config = VmBcConfig {
+            max_call_depth: 5,
+            ..Default::default()
+        };
+
+        let err = VmBc::with_config(config.clone())
+            .run_compiled(&prog)
+            .unwrap_err();
+        assert!(err.message.contains("call depth limit exceeded (5)"));
+
+        let mut config = config;
+        config
+            .word_depth_limits
+            .insert("count-down".to_string(), 50);
+        VmBc::with_config(config).run_compiled(&prog).unwrap();
+    }
+
+    #[test]
+    fn test_summarize_recursion() {
+        let stack = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            summarize_recursion(&stack(&["main", "f", "f", "f"])).unwrap(),
+            "f -> f repeated 3 times"
+        );
+        assert_eq!(
+            summarize_recursion(&stack(&["go", "a", "b", "a", "b"])).unwrap(),
+            "a -> b -> a repeated 2 times"
+        );
+        assert!(summarize_recursion(&stack(&["a", "b", "c"])).is_none());
+    }
+
     #[test]
     fn test_step_limit() {
         let result = run_ops_with_config(
@@ -2745,6 +4881,55 @@ mod tests {
         assert!(result.unwrap_err().message.contains("step limit"));
     }
 
+    #[test]
+    fn test_try_does_not_catch_limits() {
+        let result = run_ops_with_config(
+            vec![
+                Op::Push(Value::CompiledQuotation(vec![Op::Jump(0)])),
+                Op::Push(Value::CompiledQuotation(vec![])),
+                Op::Try,
+            ],
+            VmBcConfig {
+                max_steps: Some(100),
+                ..Default::default()
+            },
+        );
+
+        assert!(result.unwrap_err().message.contains("step limit"));
+    }
+
+    #[test]
+    fn test_wall_time_limit() {
+        // Jump(0) spins on the same instruction forever
+        let result = run_ops_with_config(
+            vec![Op::Jump(0)],
+            VmBcConfig {
+                max_wall_time: Some(Duration::from_millis(20)),
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().message.contains("time limit exceeded"));
+    }
+
+    #[test]
+    fn test_wall_time_limit_reports_word() {
+        let mut words = BTreeMap::new();
+        words.insert("spin".to_string(), vec![Op::Jump(0)]);
+
+        let prog = program_with_words(vec![Op::CallWord("spin".to_string())], words);
+
+        let mut vm = VmBc::with_config(VmBcConfig {
+            max_wall_time: Some(Duration::from_millis(20)),
+            ..Default::default()
+        });
+
+        let err = vm.run_compiled(&prog).unwrap_err();
+        assert!(err.message.contains("in 'spin'"));
+        assert_eq!(err.call_stack, vec!["spin".to_string()]);
+    }
+
     #[test]
     fn test_stack_size_limit() {
         // Push lots of values
@@ -2780,7 +4965,7 @@ mod tests {
     #[test]
     fn test_fibonacci() {
         // Iterative Fibonacci
-        let mut words = HashMap::new();
+        let mut words = BTreeMap::new();
         words.insert(
             "fib".to_string(),
             vec![
@@ -2802,7 +4987,7 @@ mod tests {
         );
 
         // Simpler test: just compute 5 + 3 = 8 using a word
-        let mut words2 = HashMap::new();
+        let mut words2 = BTreeMap::new();
         words2.insert(
             "add-three".to_string(),
             vec![Op::Push(Value::Integer(3)), Op::Add],
@@ -2818,986 +5003,646 @@ mod tests {
 
         let mut vm = VmBc::new();
         vm.run_compiled(&prog).unwrap();
-        assert_eq!(vm.stack(), vec![Value::Integer(8)]);
-    }
-
-    #[test]
-    fn test_map_filter_fold_pipeline() {
-        // [1 2 3 4 5] => square => filter evens => sum
-        assert_stack(
-            vec![
-                Op::Push(Value::List(vec![
-                    Value::Integer(1),
-                    Value::Integer(2),
-                    Value::Integer(3),
-                    Value::Integer(4),
-                    Value::Integer(5),
-                ])),
-                // Square each
-                Op::Push(Value::CompiledQuotation(vec![Op::Dup, Op::Mul])),
-                Op::Map,
-                // Filter evens
-                Op::Push(Value::CompiledQuotation(vec![
-                    Op::Push(Value::Integer(2)),
-                    Op::Mod,
-                    Op::Push(Value::Integer(0)),
-                    Op::Eq,
-                ])),
-                Op::Filter,
-                // Sum
-                Op::Push(Value::Integer(0)),
-                Op::Push(Value::CompiledQuotation(vec![Op::Add])),
-                Op::Fold,
-            ],
-            vec![Value::Integer(20)], // 4 + 16 = 20
-        );
-    }
-
-    #[test]
-    fn test_nested_quotations() {
-        // Test quotations calling quotations
-        assert_stack(
-            vec![
-                Op::Push(Value::Integer(5)),
-                Op::Push(Value::CompiledQuotation(vec![
-                    Op::Push(Value::CompiledQuotation(vec![
-                        Op::Push(Value::Integer(10)),
-                        Op::Add,
-                    ])),
-                    Op::Call,
-                ])),
-                Op::Call,
-            ],
-            vec![Value::Integer(15)],
-        );
-    }
-
-    #[test]
-    fn test_bi2() {
-        // bi2: a b [p] [q] -- (a b p) (a b q)
-        assert_stack(
-            vec![
-                Op::Push(Value::Integer(10)),
-                Op::Push(Value::Integer(3)),
-                Op::Push(Value::CompiledQuotation(vec![Op::Add])),
-                Op::Push(Value::CompiledQuotation(vec![Op::Sub])),
-                Op::Bi2,
-            ],
-            vec![Value::Integer(13), Value::Integer(7)],
-        );
-    }
-}
-
-#[cfg(test)]
-#[allow(clippy::approx_constant)]
-mod integration_tests {
-    use crate::bytecode::Op;
-    use crate::bytecode::compile::Compiler;
-    use crate::frontend::lexer::Lexer;
-    use crate::frontend::parser::Parser;
-    use crate::lang::node::Node;
-    use crate::lang::program::Program;
-    use crate::lang::value::Value;
-    use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
-    use crate::runtime::vm_bc::VmBc;
-
-    /// Run EMBER source code and return the resulting stack
-    fn run_get_stack(source: &str) -> Vec<Value> {
-        run(source).expect("execution should succeed")
-    }
-
-    /// Run EMBER source code and return Result with stack or error
-    #[allow(clippy::result_large_err)]
-    fn run(source: &str) -> RuntimeResult<Vec<Value>> {
-        let tokens = Lexer::new(source)
-            .tokenize()
-            .map_err(|e| RuntimeError::new(&format!("Lexer error: {:?}", e)))?;
-        let ast = Parser::new(tokens)
-            .parse()
-            .map_err(|e| RuntimeError::new(&format!("Parser error: {:?}", e)))?;
-        let program = Compiler::new()
-            .compile_program(&ast)
-            .map_err(|e| RuntimeError::new(&format!("Compiler error: {:?}", e)))?;
-        let mut vm = VmBc::new();
-        vm.run_compiled(&program)?;
-        Ok(vm.stack().to_vec())
-    }
-
-    /// Assert that running code produces expected stack
-    fn assert_stack(source: &str, expected: Vec<Value>) {
-        let stack = run_get_stack(source);
-        assert_eq!(stack, expected, "source: {}", source);
-    }
-
-    /// Assert that running code produces an error containing substring
-    fn assert_error(source: &str, contains: &str) {
-        match run(source) {
-            Ok(stack) => panic!("expected error '{}', got stack: {:?}", contains, stack),
-            Err(e) => assert!(
-                e.message.contains(contains),
-                "expected '{}' in error, got: {}",
-                contains,
-                e.message
-            ),
-        }
-    }
-
-    // Shorthand constructors
-    fn int(n: i64) -> Value {
-        Value::Integer(n)
-    }
-    fn float(n: f64) -> Value {
-        Value::Float(n)
-    }
-    fn string(s: &str) -> Value {
-        Value::String(s.to_string())
-    }
-    fn bool_(b: bool) -> Value {
-        Value::Bool(b)
-    }
-    fn list(items: Vec<Value>) -> Value {
-        Value::List(items)
-    }
-
-    // =========================================================================
-    // Helper: Create a Def node with inline quotation syntax
-    // =========================================================================
-
-    fn make_inline_def(name: &str, body_nodes: Vec<Node>) -> Node {
-        // Simulates: def name [body_nodes]
-        // Parser produces: Def { name, body: [Literal(Quotation(body_nodes))] }
-        Node::Def {
-            name: name.to_string(),
-            body: vec![Node::Literal(Value::Quotation(body_nodes))],
-        }
-    }
-
-    fn make_block_def(name: &str, body_nodes: Vec<Node>) -> Node {
-        // Simulates: def name body_nodes end
-        // Parser produces: Def { name, body: body_nodes }
-        Node::Def {
-            name: name.to_string(),
-            body: body_nodes,
-        }
-    }
-
-    #[test]
-    fn literals_integers() {
-        assert_stack("42", vec![int(42)]);
-        assert_stack("-17", vec![int(-17)]);
-        assert_stack("0", vec![int(0)]);
-    }
-
-    #[test]
-    fn literals_floats() {
-        assert_stack("3.14", vec![float(3.14)]);
-        assert_stack("-2.5", vec![float(-2.5)]);
-        assert_stack("0.0", vec![float(0.0)]);
-    }
-
-    #[test]
-    fn literals_strings() {
-        assert_stack(r#""hello""#, vec![string("hello")]);
-        assert_stack(r#""hello world""#, vec![string("hello world")]);
-        assert_stack(r#""""#, vec![string("")]);
-    }
-
-    #[test]
-    fn literals_booleans() {
-        assert_stack("true", vec![bool_(true)]);
-        assert_stack("false", vec![bool_(false)]);
-    }
-
-    #[test]
-    fn literals_lists() {
-        assert_stack("{ }", vec![list(vec![])]);
-        assert_stack("{ 1 2 3 }", vec![list(vec![int(1), int(2), int(3)])]);
-        assert_stack(
-            "{ 1 { 2 3 } 4 }",
-            vec![list(vec![int(1), list(vec![int(2), int(3)]), int(4)])],
-        );
-    }
-
-    #[test]
-    fn multiple_values() {
-        assert_stack("1 2 3", vec![int(1), int(2), int(3)]);
-        assert_stack(
-            "1 \"two\" 3.0 true",
-            vec![int(1), string("two"), float(3.0), bool_(true)],
-        );
-    }
-
-    // Stack operations
-
-    #[test]
-    fn stack_dup() {
-        assert_stack("5 dup", vec![int(5), int(5)]);
-        assert_stack("1 2 dup", vec![int(1), int(2), int(2)]);
-    }
-
-    #[test]
-    fn stack_drop() {
-        assert_stack("1 2 drop", vec![int(1)]);
-        assert_stack("1 2 3 drop drop", vec![int(1)]);
-    }
-
-    #[test]
-    fn stack_swap() {
-        assert_stack("1 2 swap", vec![int(2), int(1)]);
-        assert_stack("1 2 3 swap", vec![int(1), int(3), int(2)]);
-    }
-
-    #[test]
-    fn stack_over() {
-        // Note: Current VM implementation of 'over' behaves like swap
-        // Traditional Forth: 1 2 over -> 1 2 1 (copy second to top)
-        // Current EMBER: 1 2 over -> 2 1 (swaps)
-        // The comments document what these operations should do in case you want to implement them later. The over bug in particular is worth noting - in standard Forth/Factor, over copies the second element to the top (a b -- a b a), but your current implementation swaps (a b -- b a).
-        assert_stack("1 2 over", vec![int(1), int(2), int(1)]);
-    }
-
-    #[test]
-    fn stack_rot() {
-        assert_stack("1 2 3 rot", vec![int(2), int(3), int(1)]);
-    }
-
-    // Note: 'nip' is not implemented in the current VM
-    // nip would be: a b -- b (drop second element)
-
-    // Note: 'tuck' is not implemented in the current VM
-    // tuck would be: a b -- b a b (copy top under second)
-
-    // Note: '2dup' behaves as dup dup (duplicates top twice) not as duplicating top 2 elements
-    // Standard Forth 2dup: a b -- a b a b
-    // Current EMBER 2dup: a b -- a b b b
-
-    // Note: '2drop' is not implemented in the current VM
-    // 2drop would be: a b c d -- a b (drop top 2 elements)
-
-    #[test]
-    fn stack_clear() {
-        assert_stack("1 2 3 clear", vec![]);
-        assert_stack("1 2 3 clear 42", vec![int(42)]);
-    }
-
-    #[test]
-    fn stack_depth() {
-        assert_stack("depth", vec![int(0)]);
-        assert_stack("1 2 3 depth", vec![int(1), int(2), int(3), int(3)]);
-    }
-
-    #[test]
-    fn arithmetic_add() {
-        assert_stack("3 4 +", vec![int(7)]);
-        assert_stack("1.5 2.5 +", vec![float(4.0)]);
-        assert_stack("1 2.5 +", vec![float(3.5)]);
-    }
-
-    #[test]
-    fn arithmetic_sub() {
-        assert_stack("10 3 -", vec![int(7)]);
-        assert_stack("3 10 -", vec![int(-7)]);
-    }
-
-    #[test]
-    fn arithmetic_mul() {
-        assert_stack("6 7 *", vec![int(42)]);
-        assert_stack("2.5 4.0 *", vec![float(10.0)]);
-    }
-
-    #[test]
-    fn arithmetic_div() {
-        assert_stack("20 4 /", vec![int(5)]);
-        assert_stack("7 2 /", vec![int(3)]); // integer truncation
-        assert_stack("7.0 2.0 /", vec![float(3.5)]);
-    }
-
-    #[test]
-    fn arithmetic_mod() {
-        assert_stack("17 5 %", vec![int(2)]);
-        assert_stack("10 3 %", vec![int(1)]);
-    }
-
-    #[test]
-    fn arithmetic_neg() {
-        assert_stack("5 neg", vec![int(-5)]);
-        assert_stack("-5 neg", vec![int(5)]);
-        assert_stack("3.14 neg", vec![float(-3.14)]);
-    }
-
-    #[test]
-    fn arithmetic_abs() {
-        assert_stack("5 abs", vec![int(5)]);
-        assert_stack("-5 abs", vec![int(5)]);
-        assert_stack("-3.14 abs", vec![float(3.14)]);
-    }
-
-    #[test]
-    fn arithmetic_complex_expression() {
-        assert_stack("2 3 + 4 *", vec![int(20)]); // (2+3)*4
-        assert_stack("10 2 3 + -", vec![int(5)]); // 10-(2+3)
-        assert_stack("2 3 4 + *", vec![int(14)]); // 2*(3+4)
-    }
-
-    #[test]
-    fn math_min_max() {
-        assert_stack("5 3 min", vec![int(3)]);
-        assert_stack("5 3 max", vec![int(5)]);
-        // 1 2 3 min -> 1 min(2,3)=2 -> then max -> max(1,2)=2
-        assert_stack("1 2 3 min max", vec![int(2)]);
-    }
-
-    #[test]
-    fn math_pow() {
-        assert_stack("2 10 pow", vec![int(1024)]);
-        assert_stack("3 4 pow", vec![int(81)]);
-        assert_stack("5 0 pow", vec![int(1)]);
-    }
-
-    #[test]
-    fn math_sqrt() {
-        assert_stack("16 sqrt", vec![float(4.0)]);
-        assert_stack("2 sqrt", vec![float(std::f64::consts::SQRT_2)]);
-    }
-
-    #[test]
-    fn comparison_eq() {
-        assert_stack("5 5 =", vec![bool_(true)]);
-        assert_stack("5 6 =", vec![bool_(false)]);
-        assert_stack(r#""hello" "hello" ="#, vec![bool_(true)]);
-    }
-
-    #[test]
-    fn comparison_ne() {
-        assert_stack("5 6 !=", vec![bool_(true)]);
-        assert_stack("5 5 !=", vec![bool_(false)]);
-    }
-
-    #[test]
-    fn comparison_lt_gt() {
-        assert_stack("3 5 <", vec![bool_(true)]);
-        assert_stack("5 3 <", vec![bool_(false)]);
-        assert_stack("5 3 >", vec![bool_(true)]);
-        assert_stack("3 5 >", vec![bool_(false)]);
-    }
-
-    #[test]
-    fn comparison_le_ge() {
-        assert_stack("3 5 <=", vec![bool_(true)]);
-        assert_stack("5 5 <=", vec![bool_(true)]);
-        assert_stack("6 5 <=", vec![bool_(false)]);
-        assert_stack("5 3 >=", vec![bool_(true)]);
-        assert_stack("5 5 >=", vec![bool_(true)]);
-    }
-
-    #[test]
-    fn logic_and() {
-        assert_stack("true true and", vec![bool_(true)]);
-        assert_stack("true false and", vec![bool_(false)]);
-        assert_stack("false false and", vec![bool_(false)]);
-    }
-
-    #[test]
-    fn logic_or() {
-        assert_stack("true false or", vec![bool_(true)]);
-        assert_stack("false false or", vec![bool_(false)]);
-        assert_stack("false true or", vec![bool_(true)]);
-    }
-
-    #[test]
-    fn logic_not() {
-        assert_stack("true not", vec![bool_(false)]);
-        assert_stack("false not", vec![bool_(true)]);
-    }
-
-    #[test]
-    fn logic_combined() {
-        assert_stack("true false and not", vec![bool_(true)]);
-        assert_stack("true true and true or", vec![bool_(true)]);
-        // 5 > 3 is true, 2 < 1 is false, true and false = false
-        assert_stack("5 3 > 2 1 < and", vec![bool_(false)]);
-        // Correct version: 5 > 3 is true, 1 < 2 is true, true and true = true
-        assert_stack("5 3 > 1 2 < and", vec![bool_(true)]);
-    }
-
-    #[test]
-    fn list_len() {
-        assert_stack("{ } len", vec![int(0)]);
-        assert_stack("{ 1 2 3 } len", vec![int(3)]);
-    }
-
-    #[test]
-    fn list_head_tail() {
-        assert_stack("{ 1 2 3 } head", vec![int(1)]);
-        assert_stack("{ 1 2 3 } tail", vec![list(vec![int(2), int(3)])]);
-        assert_stack("{ 1 } tail", vec![list(vec![])]);
-    }
-
-    #[test]
-    fn list_cons() {
-        assert_stack("1 { 2 3 } cons", vec![list(vec![int(1), int(2), int(3)])]);
-        assert_stack("1 { } cons", vec![list(vec![int(1)])]);
-    }
-
-    #[test]
-    fn list_concat() {
-        assert_stack(
-            "{ 1 2 } { 3 4 } concat",
-            vec![list(vec![int(1), int(2), int(3), int(4)])],
-        );
-        assert_stack("{ } { 1 2 } concat", vec![list(vec![int(1), int(2)])]);
-    }
-
-    #[test]
-    fn list_nth() {
-        assert_stack("{ 10 20 30 } 0 nth", vec![int(10)]);
-        assert_stack("{ 10 20 30 } 1 nth", vec![int(20)]);
-        assert_stack("{ 10 20 30 } 2 nth", vec![int(30)]);
-    }
-
-    #[test]
-    fn list_append() {
-        assert_stack("{ 1 2 } 3 append", vec![list(vec![int(1), int(2), int(3)])]);
-        assert_stack("{ } 1 append", vec![list(vec![int(1)])]);
-    }
-
-    #[test]
-    fn list_reverse() {
-        assert_stack(
-            "{ 1 2 3 } reverse",
-            vec![list(vec![int(3), int(2), int(1)])],
-        );
-        assert_stack("{ } reverse", vec![list(vec![])]);
-    }
-
-    #[test]
-    fn list_sort() {
-        assert_stack("{ 3 1 2 } sort", vec![list(vec![int(1), int(2), int(3)])]);
-        assert_stack(
-            "{ 5 2 8 1 } sort",
-            vec![list(vec![int(1), int(2), int(5), int(8)])],
-        );
-    }
-
-    #[test]
-    fn string_concat() {
-        // String concatenation uses the . operator
-        assert_stack(r#""hello" " world" ."#, vec![string("hello world")]);
-        assert_stack(r#""" "test" ."#, vec![string("test")]);
-    }
-
-    #[test]
-    fn string_chars() {
-        assert_stack(
-            r#""abc" chars"#,
-            vec![list(vec![string("a"), string("b"), string("c")])],
-        );
-        assert_stack(r#""" chars"#, vec![list(vec![])]);
-    }
-
-    #[test]
-    fn string_join() {
-        assert_stack(r#"{ "a" "b" "c" } "-" join"#, vec![string("a-b-c")]);
-        assert_stack(
-            r#"{ "hello" "world" } " " join"#,
-            vec![string("hello world")],
-        );
-    }
-
-    #[test]
-    fn string_split() {
-        assert_stack(
-            r#""a-b-c" "-" split"#,
-            vec![list(vec![string("a"), string("b"), string("c")])],
-        );
-        assert_stack(
-            r#""hello world" " " split"#,
-            vec![list(vec![string("hello"), string("world")])],
-        );
-    }
-
-    #[test]
-    fn string_upper_lower() {
-        assert_stack(r#""hello" upper"#, vec![string("HELLO")]);
-        assert_stack(r#""HELLO" lower"#, vec![string("hello")]);
-        assert_stack(r#""HeLLo" upper"#, vec![string("HELLO")]);
-    }
-
-    #[test]
-    fn string_trim() {
-        assert_stack(r#""  hello  " trim"#, vec![string("hello")]);
-        assert_stack(r#""hello" trim"#, vec![string("hello")]);
-    }
-
-    #[test]
-    fn type_of() {
-        assert_stack("42 type", vec![int(42), string("Integer")]);
-        assert_stack("3.14 type", vec![float(3.14), string("Float")]);
-        assert_stack(r#""hi" type"#, vec![string("hi"), string("String")]);
-        assert_stack("true type", vec![bool_(true), string("Bool")]);
-        assert_stack(
-            "{ 1 2 } type",
-            vec![list(vec![int(1), int(2)]), string("List")],
-        );
-    }
-
-    #[test]
-    fn to_string() {
-        assert_stack("42 to-string", vec![string("42")]);
-        assert_stack("true to-string", vec![string("true")]);
-    }
-
-    #[test]
-    fn to_int() {
-        assert_stack(r#""42" to-int"#, vec![int(42)]);
-        assert_stack("3.7 to-int", vec![int(3)]);
-        assert_stack("true to-int", vec![int(1)]);
-        assert_stack("false to-int", vec![int(0)]);
-    }
-
-    #[test]
-    fn quotation_basic() {
-        assert_stack("[1 2 +] call", vec![int(3)]);
-        assert_stack("5 [dup *] call", vec![int(25)]);
-    }
-
-    #[test]
-    fn quotation_nested() {
-        assert_stack("[[1 2 +] call] call", vec![int(3)]);
-        assert_stack("5 [[dup] call *] call", vec![int(25)]);
-    }
-
-    #[test]
-    fn if_true_branch() {
-        assert_stack("true [1] [2] if", vec![int(1)]);
-        assert_stack("5 3 > [\"yes\"] [\"no\"] if", vec![string("yes")]);
-    }
-
-    #[test]
-    fn if_false_branch() {
-        assert_stack("false [1] [2] if", vec![int(2)]);
-        assert_stack("3 5 > [\"yes\"] [\"no\"] if", vec![string("no")]);
-    }
-
-    #[test]
-    fn if_nested() {
-        assert_stack("true [true [1] [2] if] [3] if", vec![int(1)]);
-        assert_stack("true [false [1] [2] if] [3] if", vec![int(2)]);
-    }
-
-    #[test]
-    fn when() {
-        assert_stack("true [42] when", vec![int(42)]);
-        assert_stack("false [42] when", vec![]);
-        assert_stack("5 3 > [\"big\"] when", vec![string("big")]);
-    }
-
-    // TODO unless
-    // #[test]
-    // fn unless() {
-    //     assert_stack("false [42] unless", vec![int(42)]);
-    //     assert_stack("true [42] unless", vec![]);
-    // }
-
-    // ─────────────────────────────────────────────────────────────
-    // Loops
-    // ─────────────────────────────────────────────────────────────
-
-    // NOTE: times appears to have a bug - it loops infinitely or uses wrong count
-    // Skipping these tests until the bug is fixed in the compiler/VM
-    //
-    // The issue: "3 [10] times" should push 10 three times, but instead
-    // it hits stack size limit (10000), suggesting infinite loop or wrong count
-    //
-    #[test]
-    fn times_basic() {
-        assert_stack("3 [10] times", vec![int(10), int(10), int(10)]);
-    }
-
-    #[test]
-    fn times_with_operation() {
-        assert_stack("0 5 [1 +] times", vec![int(5)]);
-    }
-
-    #[test]
-    fn times_multiply() {
-        assert_stack("1 4 [2 *] times", vec![int(16)]);
-    }
-
-    #[test]
-    fn times_zero() {
-        assert_stack("42 0 [drop 99] times", vec![int(42)]);
-    }
-
-    #[test]
-    fn each() {
-        assert_stack("0 { 1 2 3 } [+] each", vec![int(6)]);
-        assert_stack("{ 1 2 3 } [dup *] each", vec![int(1), int(4), int(9)]);
+        assert_eq!(vm.stack(), vec![Value::Integer(8)]);
     }
 
     #[test]
-    fn map() {
-        assert_stack(
-            "{ 1 2 3 } [dup *] map",
-            vec![list(vec![int(1), int(4), int(9)])],
-        );
+    fn test_map_filter_fold_pipeline() {
+        // [1 2 3 4 5] => square => filter evens => sum
         assert_stack(
-            "{ 1 2 3 } [1 +] map",
-            vec![list(vec![int(2), int(3), int(4)])],
+            vec![
+                Op::Push(Value::List(vec![
+                    Value::Integer(1),
+                    Value::Integer(2),
+                    Value::Integer(3),
+                    Value::Integer(4),
+                    Value::Integer(5),
+                ])),
+                // Square each
+                Op::Push(Value::CompiledQuotation(vec![Op::Dup, Op::Mul])),
+                Op::Map,
+                // Filter evens
+                Op::Push(Value::CompiledQuotation(vec![
+                    Op::Push(Value::Integer(2)),
+                    Op::Mod,
+                    Op::Push(Value::Integer(0)),
+                    Op::Eq,
+                ])),
+                Op::Filter,
+                // Sum
+                Op::Push(Value::Integer(0)),
+                Op::Push(Value::CompiledQuotation(vec![Op::Add])),
+                Op::Fold,
+            ],
+            vec![Value::Integer(20)], // 4 + 16 = 20
         );
     }
 
     #[test]
-    fn filter() {
-        assert_stack(
-            "{ 1 2 3 4 5 } [2 % 0 =] filter",
-            vec![list(vec![int(2), int(4)])],
-        );
+    fn test_nested_quotations() {
+        // Test quotations calling quotations
         assert_stack(
-            "{ 1 2 3 4 5 } [3 >] filter",
-            vec![list(vec![int(4), int(5)])],
+            vec![
+                Op::Push(Value::Integer(5)),
+                Op::Push(Value::CompiledQuotation(vec![
+                    Op::Push(Value::CompiledQuotation(vec![
+                        Op::Push(Value::Integer(10)),
+                        Op::Add,
+                    ])),
+                    Op::Call,
+                ])),
+                Op::Call,
+            ],
+            vec![Value::Integer(15)],
         );
     }
 
     #[test]
-    fn fold() {
-        assert_stack("{ 1 2 3 4 } 0 [+] fold", vec![int(10)]);
-        assert_stack("{ 1 2 3 4 } 1 [*] fold", vec![int(24)]);
-        assert_stack("{ 1 2 3 } 10 [-] fold", vec![int(4)]); // 10-1-2-3
-    }
-
-    #[test]
-    fn range() {
+    fn test_bi2() {
+        // bi2: a b [p] [q] -- (a b p) (a b q)
         assert_stack(
-            "1 5 range",
-            vec![list(vec![int(1), int(2), int(3), int(4)])],
+            vec![
+                Op::Push(Value::Integer(10)),
+                Op::Push(Value::Integer(3)),
+                Op::Push(Value::CompiledQuotation(vec![Op::Add])),
+                Op::Push(Value::CompiledQuotation(vec![Op::Sub])),
+                Op::Bi2,
+            ],
+            vec![Value::Integer(13), Value::Integer(7)],
         );
-        assert_stack("0 3 range", vec![list(vec![int(0), int(1), int(2)])]);
-        assert_stack("5 5 range", vec![list(vec![])]);
     }
+}
 
-    // ─────────────────────────────────────────────────────────────
-    // Combinators
-    // ─────────────────────────────────────────────────────────────
+#[cfg(test)]
+#[allow(clippy::approx_constant)]
+mod integration_tests {
+    use crate::bytecode::Op;
+    use crate::bytecode::compile::Compiler;
+    use crate::bytecode::container;
+    use crate::frontend::lexer::Lexer;
+    use crate::frontend::parser::Parser;
+    use crate::lang::node::Node;
+    use crate::lang::program::Program;
+    use crate::lang::value::Value;
+    use crate::runtime::runtime_error::{RuntimeError, RuntimeResult};
+    use crate::runtime::vm_bc::{BytecodeVerifier, Profile, SharedProgram, VmBc, VmBcConfig};
 
-    #[test]
-    fn dip() {
-        assert_stack("1 2 [10 +] dip", vec![int(11), int(2)]);
-        assert_stack("1 2 3 [+] dip", vec![int(3), int(3)]);
+    /// Run EMBER source code and return the resulting stack
+    fn run_get_stack(source: &str) -> Vec<Value> {
+        run(source).expect("execution should succeed")
     }
 
-    #[test]
-    fn keep() {
-        assert_stack("5 [dup *] keep", vec![int(25), int(5)]);
-        assert_stack("3 [1 +] keep", vec![int(4), int(3)]);
+    /// Run EMBER source code and return Result with stack or error
+    #[allow(clippy::result_large_err)]
+    fn run(source: &str) -> RuntimeResult<Vec<Value>> {
+        let tokens = Lexer::new(source)
+            .tokenize()
+            .map_err(|e| RuntimeError::new(&format!("Lexer error: {:?}", e)))?;
+        let ast = Parser::new(tokens)
+            .parse()
+            .map_err(|e| RuntimeError::new(&format!("Parser error: {:?}", e)))?;
+        let program = Compiler::new()
+            .compile_program(&ast)
+            .map_err(|e| RuntimeError::new(&format!("Compiler error: {:?}", e)))?;
+        let mut vm = VmBc::new();
+        vm.run_compiled(&program)?;
+        Ok(vm.stack().to_vec())
     }
 
-    #[test]
-    fn bi() {
-        assert_stack("5 [1 +] [2 *] bi", vec![int(6), int(10)]);
-        assert_stack("10 [2 /] [3 -] bi", vec![int(5), int(7)]);
+    // Shorthand constructors
+    fn int(n: i64) -> Value {
+        Value::Integer(n)
     }
-
-    #[test]
-    fn bi2() {
-        assert_stack("10 3 [+] [-] bi2", vec![int(13), int(7)]);
-        assert_stack("6 2 [*] [/] bi2", vec![int(12), int(3)]);
+    fn list(items: Vec<Value>) -> Value {
+        Value::List(items)
     }
 
-    #[test]
-    fn tri() {
-        assert_stack("10 [1 +] [2 *] [neg] tri", vec![int(11), int(20), int(-10)]);
-    }
+    // =========================================================================
+    // Helper: Create a Def node with inline quotation syntax
+    // =========================================================================
 
-    #[test]
-    fn both() {
-        assert_stack("3 4 [dup *] both", vec![int(9), int(16)]);
-        assert_stack("2 5 [1 +] both", vec![int(3), int(6)]);
+    fn make_inline_def(name: &str, body_nodes: Vec<Node>) -> Node {
+        // Simulates: def name [body_nodes]
+        // Parser produces: Def { name, body: [Literal(Quotation(body_nodes))] }
+        Node::Def {
+            name: name.to_string(),
+            body: vec![Node::Literal(Value::Quotation(body_nodes))],
+            line: 1,
+            spans: Vec::new(),
+        }
     }
 
-    #[test]
-    fn compose() {
-        assert_stack("5 [1 +] [2 *] compose call", vec![int(12)]); // (5+1)*2
-        assert_stack("[dup] [*] compose 5 swap call", vec![int(25)]);
+    fn make_block_def(name: &str, body_nodes: Vec<Node>) -> Node {
+        // Simulates: def name body_nodes end
+        // Parser produces: Def { name, body: body_nodes }
+        Node::Def {
+            name: name.to_string(),
+            body: body_nodes,
+            line: 1,
+            spans: Vec::new(),
+        }
     }
 
-    #[test]
-    fn curry() {
-        assert_stack("5 [+] curry 3 swap call", vec![int(8)]); // 3 + 5
-        assert_stack("2 [*] curry 7 swap call", vec![int(14)]); // 7 * 2
+    /// Main ops of compiled source.
+    fn compile_main(source: &str) -> Vec<Op> {
+        let tokens = Lexer::new(source).tokenize().unwrap();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut program = Compiler::new().compile_program(&ast).unwrap();
+        program.code.swap_remove(0).ops
+    }
+
+    /// Run `source` as written, where literal quotations given to `if`,
+    /// `when`, `times`, `while` and `until` are lowered to jumps, and again with a no-op word
+    /// called in front of each of those words, which forces the
+    /// quotation-based ops. Both must leave the same stack, or fail with the
+    /// same message.
+    fn assert_paths_agree(source: &str) {
+        let words: Vec<&str> = source
+            .split_whitespace()
+            .flat_map(|word| match word {
+                "if" | "when" | "times" | "while" | "until" => vec!["dyn", word],
+                _ => vec![word],
+            })
+            .collect();
+        let dynamic = format!("def dyn end {}", words.join(" "));
+
+        let quotation_op =
+            |op: &Op| matches!(op, Op::If | Op::When | Op::Times | Op::While | Op::Until);
+        assert!(
+            !compile_main(source).iter().any(quotation_op),
+            "not lowered to jumps: {}",
+            source
+        );
+        assert!(
+            compile_main(&dynamic).iter().any(quotation_op),
+            "still lowered to jumps: {}",
+            dynamic
+        );
+        let outcome = |source: &str| run(source).map_err(|e| e.message);
+        assert_eq!(outcome(source), outcome(&dynamic), "source: {}", source);
+    }
+
+    #[test]
+    fn jump_and_quotation_paths_agree() {
+        for source in [
+            "true [1] [2] if",
+            "false [1] [2] if",
+            "5 dup 3 > [10 *] [10 +] if",
+            "1 true [] [drop 0] if",
+            "true [false [1] [2] if] [3] if",
+            "true [42] when",
+            "false [42] when",
+            "7 dup 5 > [2 *] when",
+            "0 5 [1 +] times",
+            "0 0 [1 +] times",
+            "0 -3 [1 +] times",
+            "1 10 [2 *] times",
+            "3 [10] times",
+            "0 6 [dup 2 % 0 = [10 +] [1 +] if] times",
+            "0 3 [4 [1 +] times] times",
+            "0 [dup 5 <] [1 +] while",
+            "9 [dup 5 <] [1 +] while",
+            "10 [dup 0 =] [1 -] until",
+            "1 [dup 100 >] [dup 2 % 0 = [3 *] [1 +] if] until",
+            "0 [dup 3 <] [1 + 0 [dup 2 <] [1 +] while drop] while",
+        ] {
+            assert_paths_agree(source);
+        }
     }
 
     #[test]
-    fn apply() {
-        assert_stack("{ 1 2 3 } [+ +] apply", vec![int(6)]);
-        assert_stack("{ 5 3 } [-] apply", vec![int(2)]);
-    }
+    fn reload_replaces_words_while_running() {
+        let compile = |source: &str| {
+            let ast = Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse()
+                .unwrap();
+            Compiler::new().compile_program(&ast).unwrap()
+        };
 
-    // ─────────────────────────────────────────────────────────────
-    // Word Definitions
-    // ─────────────────────────────────────────────────────────────
-    // fix compiler issue with inline code
-    #[test]
-    fn word_simple() {
-        assert_stack("def double dup + end 5 double", vec![int(10)]);
-        assert_stack("def square dup * end 7 square", vec![int(49)]);
-    }
+        // Picked up before the first instruction; `old` isn't in the new
+        // version but stays defined
+        let config = VmBcConfig::default();
+        config
+            .reload
+            .offer(compile("def tag 2 end def extra 3 end"));
+        let mut vm = VmBc::with_config(config);
+        vm.run_compiled(&compile("def tag 1 end def old 9 end tag extra old"))
+            .unwrap();
+        assert_eq!(vm.stack(), &[int(2), int(3), int(9)]);
 
-    #[test]
-    fn test_inline_def_double() {
-        assert_stack("def double [dup +] end 5 double", vec![int(10)]);
+        let summary = vm.reload_words(compile("def tag 4 end def extra 3 end def fresh 5 end"));
+        assert_eq!(summary.as_deref(), Some("changed tag; added fresh"));
+        assert_eq!(vm.reload_words(compile("def tag 4 end")), None);
     }
 
     #[test]
-    fn test_inline_def_square() {
-        assert_stack("def square [dup *] end 7 square", vec![int(49)]);
-    }
+    fn checkpoint_mid_loop_and_resume() {
+        let ast = Parser::new(
+            Lexer::new("def bump 10 + end 0 5 [ bump dup 30 = [ checkpoint ] when ] times 1 +")
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
+        let config = VmBcConfig {
+            checkpoints: true,
+            ..Default::default()
+        };
 
-    #[test]
-    fn test_inline_def_inc() {
-        assert_stack("def inc [1 +] end 10 inc", vec![int(11)]);
-    }
+        let mut vm = VmBc::with_config(config.clone());
+        vm.run_compiled(&program).unwrap();
+        assert!(vm.is_paused());
+        assert_eq!(vm.stack(), &[int(30)]);
+        let saved = vm.serialize_state().unwrap();
 
-    #[test]
-    fn test_inline_def_multiple_words() {
-        assert_stack(
-            "def inc [1 +] end def double [dup +] end 5 inc double",
-            vec![int(12)],
-        );
-    }
+        // A fresh VM, as after a restart, finishes the remaining iterations
+        let mut resumed = VmBc::with_config(config);
+        resumed.resume_state(&saved).unwrap();
+        assert!(!resumed.is_paused());
+        assert_eq!(resumed.stack(), &[int(51)]);
 
-    #[test]
-    fn test_inline_def_calling_inline_def() {
-        assert_stack(
-            "def inc [1 +] end def inc2 [inc inc] end 5 inc2",
-            vec![int(7)],
-        );
+        // Without the config flag the word does nothing
+        let mut vm = VmBc::new();
+        vm.run_compiled(&program).unwrap();
+        assert!(!vm.is_paused());
+        assert_eq!(vm.stack(), &[int(51)]);
     }
 
     #[test]
-    fn test_inline_def_with_control_flow() {
-        // Nested quotations inside the inline def body
-        assert_stack(
-            "def my-abs [dup 0 < [neg] when] end -5 my-abs",
-            vec![int(5)],
+    fn resume_rejects_bad_state() {
+        let mut vm = VmBc::new();
+        let err = vm.resume_state(b"not a state").unwrap_err();
+        assert!(
+            err.message.contains("not a saved EMBER VM state"),
+            "{}",
+            err
         );
-        assert_stack("def my-abs [dup 0 < [neg] when] end 5 my-abs", vec![int(5)]);
+
+        let mut saved = VmBc::new().serialize_state().unwrap();
+        saved.truncate(saved.len() - 1);
+        assert!(vm.resume_state(&saved).is_err());
     }
 
     #[test]
-    fn test_inline_def_with_if() {
-        // Note: ? is not allowed in identifiers, use is-positive instead
-        assert_stack(
-            "def is-positive [0 >] end 5 is-positive",
-            vec![Value::Bool(true)],
-        );
-        assert_stack(
-            "def is-positive [0 >] end -5 is-positive",
-            vec![Value::Bool(false)],
+    fn verifier_gates_bytecode() {
+        let ast = Parser::new(Lexer::new("6 7 *").tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
+        let bytes = container::encode(&program).unwrap();
+        // Stands in for a real signature check
+        let trusted = bytes.clone();
+        let config = VmBcConfig {
+            bytecode_verifier: Some(BytecodeVerifier::new(move |payload| {
+                if payload == trusted {
+                    Ok(())
+                } else {
+                    Err("unknown payload".to_string())
+                }
+            })),
+            ..Default::default()
+        };
+
+        let mut vm = VmBc::with_config(config.clone());
+        vm.run_bytecode(&bytes).unwrap();
+        assert_eq!(vm.stack(), &[int(42)]);
+
+        let mut tampered = bytes.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let err = VmBc::with_config(config)
+            .run_bytecode(&tampered)
+            .unwrap_err();
+        assert!(
+            err.message
+                .contains("rejected by verifier: unknown payload")
         );
-    }
 
-    #[test]
-    fn test_inline_def_recursive_factorial() {
-        let code = r#"
-            def factorial [
-                dup 1 <=
-                [drop 1]
-                [dup 1 - factorial *]
-                if
-            ] end
-            5 factorial
-        "#;
-        assert_stack(code, vec![int(120)]);
+        // Without a verifier, bytes still have to decode and verify
+        assert!(VmBc::new().run_bytecode(b"junk").is_err());
     }
 
     #[test]
-    fn test_inline_def_noop() {
-        assert_stack("def noop [] end 42 noop", vec![int(42)]);
-    }
+    fn profiles_set_permissions_and_limits() {
+        let script: Profile = "script".parse().unwrap();
+        assert!(script.config().allow_fs);
+        assert!(script.config().max_steps.is_none());
 
-    #[test]
-    fn test_block_def_still_works() {
-        // Block form (without quotation brackets): def name ops end
-        assert_stack("def double dup + end 5 double", vec![int(10)]);
-    }
+        let minimal = Profile::Minimal.config();
+        assert!(!minimal.allow_fs && !minimal.allow_process && !minimal.allow_net);
+        assert!(minimal.max_steps.is_none());
 
-    #[test]
-    fn test_inline_and_block_equivalent() {
-        // Both forms should produce the same result
-        assert_stack("def double [dup +] end 5 double", vec![int(10)]);
-        assert_stack("def double dup + end 5 double", vec![int(10)]);
-    }
+        let sandbox = Profile::Sandbox.config();
+        assert!(!sandbox.allow_process);
+        let mut vm = VmBc::with_config(sandbox);
+        let ast = Parser::new(
+            Lexer::new("0 [ 1 + ] 100000000 swap times")
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
+        assert!(vm.run_compiled(&program).is_err());
 
-    #[test]
-    fn test_block_def_with_control_flow() {
-        // Block form with control flow
-        let code = r#"
-            def my-abs
-                dup 0 < [neg] when
-            end
-            -5 my-abs
-        "#;
-        assert_stack(code, vec![int(5)]);
-    }
+        let pure: Profile = "pure".parse().unwrap();
+        let pure = pure.config();
+        assert!(!pure.allow_io && !pure.allow_fs && !pure.allow_compile);
+        assert!(!pure.allow_spawn);
+        assert!(pure.max_steps.is_some() && pure.max_wall_time.is_none());
 
-    #[test]
-    fn test_block_def_recursive() {
-        // Block form recursive factorial
-        let code = r#"
-            def fact
-                dup 1 <=
-                [drop 1]
-                [dup 1 - fact *]
-                if
-            end
-            5 fact
-        "#;
-        assert_stack(code, vec![int(120)]);
+        assert!("lenient".parse::<Profile>().is_err());
     }
 
     #[test]
-    fn word_multiple() {
-        assert_stack(
-            "def inc [1 +] end def double [dup +] end 5 inc double",
-            vec![int(12)],
-        );
-    }
+    fn variables_hold_values_between_words() {
+        let source = "var total
+            def add-to-total total @ + total ! end
+            0 total ! { 1 2 3 4 } [ add-to-total ] each total @";
+        assert_eq!(run_get_stack(source), vec![int(10)]);
 
-    #[test]
-    fn word_calling_word() {
-        assert_stack(
-            "def inc [1 +] end def inc2 [inc inc] end 5 inc2",
-            vec![int(7)],
-        );
+        let err = run("var n n @").unwrap_err();
+        assert_eq!(err.message, "variable 'n' is read before it is set");
     }
 
     #[test]
-    fn word_recursive_factorial() {
-        let code = r#"
-            def factorial [
-                dup 1 <=
-                [drop 1]
-                [dup 1 - factorial *]
-                if
-            ] end
-            5 factorial
-        "#;
-        assert_stack(code, vec![int(120)]);
-    }
+    fn errors_point_at_the_failing_op() {
+        let line_col = |source: &str| {
+            let span = run(source).unwrap_err().span.expect("error has a span");
+            (span.line, span.col)
+        };
+        // In a word, past ops the optimizer removed
+        let source = "def ratio\n    1 drop /\nend\n\n1 0 ratio";
+        assert_eq!(line_col(source), (2, 12));
+        // In main, after a word that ran fine
+        assert_eq!(line_col("def inc 1 + end\n1 inc\n\"a\" inc"), (1, 11));
+        // In a quotation, at the word that ran it
+        assert_eq!(line_col("{ 1 0 }\n  [ 0 / ] map"), (2, 11));
+        // In an inlined `if` branch, at the branch
+        assert_eq!(line_col("true\n  [ 1 0 / ] [ 2 ] if"), (2, 3));
+    }
+
+    #[test]
+    fn caught_panics_become_internal_errors() {
+        struct Broken;
+        impl std::io::Write for Broken {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                panic!("broken output")
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let compile = |source: &str| {
+            let ast = Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse()
+                .unwrap();
+            Compiler::new().compile_program(&ast).unwrap()
+        };
 
-    #[test]
-    fn word_recursive_fibonacci() {
-        let code = r#"
-            def fib [
-                dup 2 <
-                []
-                [dup 1 - fib swap 2 - fib +]
-                if
-            ] end
-            10 fib
-        "#;
-        assert_stack(code, vec![int(55)]);
-    }
+        let mut vm = VmBc::with_config(VmBcConfig {
+            catch_panics: true,
+            ..VmBcConfig::default()
+        });
+        vm.set_output(Broken);
+        let err = vm
+            .run_compiled(&compile("def shout print end 1 [ \"hi\" shout ] [ ] try"))
+            .unwrap_err();
+        assert_eq!(err.message, "internal error, please report: broken output");
+        assert!(!err.catchable);
+        assert_eq!(err.call_stack, vec!["shout"]);
 
-    #[test]
-    fn sum_of_squares() {
-        // sum([1..5]^2) = 1+4+9+16+25 = 55
-        assert_stack("1 6 range [dup *] map 0 [+] fold", vec![int(55)]);
+        // The VM is still usable
+        vm.set_output(Vec::new());
+        vm.run_compiled(&compile("def inc 1 + end 41 inc")).unwrap();
+        assert_eq!(vm.stack().last(), Some(&int(42)));
     }
 
     #[test]
-    fn filter_map_fold_pipeline() {
-        // Take [1..10], filter evens, square them, sum
-        // evens: 2,4,6,8 -> squares: 4,16,36,64 -> sum: 120
-        assert_stack(
-            "1 10 range [2 % 0 =] filter [dup *] map 0 [+] fold",
-            vec![int(120)],
+    fn locals_are_per_call_and_captured_by_quotations() {
+        assert_eq!(
+            run_get_stack("def area :> w :> h w h * end 3 4 area"),
+            vec![int(12)]
+        );
+        // Every call has its own slots
+        assert_eq!(
+            run_get_stack("def fact :> n n 1 <= [ 1 ] [ n 1 - fact n * ] if end 5 fact"),
+            vec![int(120)]
+        );
+        assert_eq!(
+            run_get_stack("def scale-by :> k [ k * ] map end { 1 2 3 } 10 scale-by"),
+            vec![list(vec![int(10), int(20), int(30)])]
+        );
+        // A quotation keeps its values after the word returns, even when
+        // it runs inside another word with locals of its own
+        let source = "def adder :> n [ n + ] end
+            def apply-to :> q :> x x q call end
+            5 3 adder apply-to";
+        assert_eq!(run_get_stack(source), vec![int(8)]);
+        assert_eq!(
+            run_get_stack("def f :> a a 1 + :> a a end 1 f"),
+            vec![int(2)]
         );
     }
 
     #[test]
-    fn fizzbuzz_single() {
-        let code = r#"
-            def fizzbuzz [
-                dup 15 % 0 = ["FizzBuzz"] [
-                    dup 3 % 0 = ["Fizz"] [
-                        dup 5 % 0 = ["Buzz"] [
-                            dup to-string
-                        ] if
-                    ] if
-                ] if
-                swap drop
-            ]
-            end
-            15 fizzbuzz
-        "#;
-        assert_stack(code, vec![string("FizzBuzz")]);
-    }
+    fn eval_runs_in_the_current_vm() {
+        let ast = Parser::new(
+            Lexer::new("def twice 2 * end 5 \"twice def inc 1 + end\" eval inc")
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
 
-    #[test]
-    fn list_operations_chain() {
-        assert_stack("{ 3 1 4 1 5 9 2 6 } sort reverse head", vec![int(9)]);
-    }
+        let mut vm = VmBc::new();
+        vm.run_compiled(&program).unwrap();
+        assert_eq!(vm.stack(), &[int(11)]);
 
-    #[test]
-    fn string_processing() {
-        assert_stack(
-            r#""hello world" " " split [upper] map "-" join"#,
-            vec![string("HELLO-WORLD")],
+        let mut sandboxed = VmBc::with_config(Profile::Sandbox.config());
+        let err = sandboxed.run_compiled(&program).unwrap_err();
+        assert!(
+            err.message
+                .contains("building code at run time is disabled")
         );
     }
 
     #[test]
-    fn nested_data_structures() {
-        assert_stack(
-            "{ { 1 2 } { 3 4 } { 5 6 } } [0 nth] map",
-            vec![list(vec![int(1), int(3), int(5)])],
-        );
+    fn times_rejects_a_negative_count() {
+        // Lowered to jumps, and run by the times op
+        for source in ["0 -3 [ 1 + ] times", "0 [ 1 + ] -3 swap times"] {
+            let err = run(source).unwrap_err();
+            assert_eq!(
+                err.message, "times expects non-negative integer",
+                "{}",
+                source
+            );
+        }
     }
 
     #[test]
-    fn accumulator_pattern() {
-        assert_stack(
-            "{ 1 2 3 4 5 } { } [dup * append] fold",
-            vec![list(vec![int(1), int(4), int(9), int(16), int(25)])],
-        );
+    fn times_names_a_bad_counter() {
+        // Lowered to jumps, and run by the times op
+        for source in ["\"3\" [ 1 ] times", "[ 1 ] 2.5 swap times"] {
+            let ast = Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse()
+                .unwrap();
+            let program = Compiler::new().compile_program(&ast).unwrap();
+            let err = VmBc::new().run_compiled(&program).unwrap_err();
+            assert!(
+                err.message
+                    .starts_with("type error in 'times' loop counter: expected integer"),
+                "{}: {}",
+                source,
+                err.message
+            );
+        }
     }
 
     #[test]
-    fn error_stack_underflow() {
-        assert_error("drop", "stack underflow");
-        assert_error("+", "stack underflow");
-        assert_error("1 +", "stack underflow");
+    fn short_circuit_quotation_leaves_a_bool() {
+        for source in ["true [ 5 ] and", "true [ 1 2 ] and", "false [ \"x\" ] or"] {
+            let err = run(source).unwrap_err();
+            assert!(
+                err.message
+                    .starts_with("type error in 'and'/'or': the quotation must leave a bool"),
+                "{}: {}",
+                source,
+                err.message
+            );
+        }
+        // Not run, so not checked
+        assert_eq!(run("false [ 5 ] and").unwrap(), vec![Value::Bool(false)]);
+        assert_eq!(run("true [ 5 ] or").unwrap(), vec![Value::Bool(true)]);
+        assert_eq!(run("true [ false ] and").unwrap(), vec![Value::Bool(false)]);
+        // Only a literal quotation short-circuits
+        let err = run("true [ 5 ] dup drop and").unwrap_err();
+        assert!(err.message.contains("expected bool"), "{}", err.message);
     }
 
     #[test]
-    fn error_division_by_zero() {
-        assert_error("10 0 /", "division by zero");
-        assert_error("10 0 %", "modulo by zero");
+    fn nested_combinators_add_no_call_depth() {
+        // A million items, three combinators deep, with no depth to spare
+        let source = "0 0 100 range [ drop 0 100 range 0 [ drop 0 100 range [ 2 * ] map \
+                      [ 4 % 0 = ] filter 0 [ + ] fold + ] fold + ] each";
+        let ast = Parser::new(Lexer::new(source).tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
+        let mut vm = VmBc::with_config(VmBcConfig {
+            max_call_depth: 1,
+            ..Default::default()
+        });
+        vm.run_compiled(&program).unwrap();
+        // Each innermost fold sums the even numbers below 100, doubled
+        assert_eq!(vm.stack(), &[int(100 * 100 * 4900)]);
     }
 
     #[test]
-    fn error_type_mismatch() {
-        assert_error(r#"1 "two" +"#, "cannot add");
-        assert_error(r#""hello" not"#, "expected boolean");
+    fn todo_and_holes_compile_and_fail_when_run() {
+        let compile = |source: &str| {
+            let ast = Parser::new(Lexer::new(source).tokenize().unwrap())
+                .parse()
+                .unwrap();
+            Compiler::new().compile_program(&ast).unwrap()
+        };
+        let mut vm = VmBc::new();
+        vm.run_compiled(&compile(
+            "def later todo end\ndef sketch ??? 1 + end\n1 2 +",
+        ))
+        .unwrap();
+        assert_eq!(vm.stack(), &[int(3)]);
+
+        let err = vm.run_more(&compile("later")).unwrap_err();
+        assert_eq!(err.message, "not yet implemented: later at line 1");
+        let err = vm.run_more(&compile("sketch")).unwrap_err();
+        assert_eq!(
+            err.message,
+            "not yet implemented: hole (???) in sketch at line 2"
+        );
     }
 
     #[test]
-    fn error_list_operations() {
-        assert_error("{ } head", "head of empty list");
-        assert_error("{ } tail", "tail of empty list");
-        assert_error("{ 1 2 } 10 nth", "out of bounds");
-    }
+    fn run_report_counts_usage() {
+        let ast = Parser::new(
+            Lexer::new("1 2 3 + + \"abcd\" dup len drop drop")
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
 
-    // #[test]
-    // fn error_negative_times() {
-    //     assert_error("-1 [1] times", "non-negative");
-    // }
+        let mut vm = VmBc::new();
+        let (result, report) = vm.run_compiled_with_report(&program);
+        result.unwrap();
+        assert_eq!(report.steps, program.code[0].ops.len());
+        assert_eq!(report.peak_stack, 3);
+        // The literal, then `dup` pushing it back along with its copy
+        assert_eq!(report.allocated_bytes, 12);
+
+        // Failed runs are reported too. The stack still holds the first
+        // run's result
+        let ast = Parser::new(Lexer::new("1 0 /").tokenize().unwrap())
+            .parse()
+            .unwrap();
+        let program = Compiler::new().compile_program(&ast).unwrap();
+        let (result, report) = vm.run_compiled_with_report(&program);
+        assert!(result.is_err());
+        assert_eq!(report.steps, 3);
+        assert_eq!(report.peak_stack, 3);
+    }
 
     #[test]
-    fn error_undefined_word() {
-        assert_error("nonexistent", "undefined");
+    fn many_vms_share_one_program() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedProgram>();
+
+        let ast = Parser::new(
+            Lexer::new("def sq dup * end def step sq 1 + end 0 3 [ 1 + ] times + step")
+                .tokenize()
+                .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let program =
+            SharedProgram::new(Compiler::new().compile_program(&ast).unwrap(), 1).unwrap();
+
+        // Each VM starts from its own seed; none may see another's stack
+        let results: Vec<Value> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..200)
+                .map(|seed| {
+                    let program = program.clone();
+                    scope.spawn(move || {
+                        let mut vm = VmBc::new();
+                        vm.push(int(seed));
+                        vm.run_shared(&program).unwrap();
+                        assert_eq!(vm.stack().len(), 1);
+                        vm.stack()[0].clone()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for (seed, result) in results.into_iter().enumerate() {
+            let n = seed as i64 + 3;
+            assert_eq!(result, int(n * n + 1));
+        }
+
+        // Redefining a word copies the table instead of changing the shared one
+        let mut vm = VmBc::new();
+        vm.push(int(1));
+        vm.run_shared(&program).unwrap();
+        let ast = Parser::new(Lexer::new("def sq 0 end").tokenize().unwrap())
+            .parse()
+            .unwrap();
+        vm.run_more(&Compiler::new().compile_program(&ast).unwrap())
+            .unwrap();
+        let mut other = VmBc::new();
+        other.push(int(1));
+        other.run_shared(&program).unwrap();
+        assert_eq!(other.stack(), &[int(17)]);
     }
 
     // =========================================================================
@@ -3814,6 +5659,7 @@ mod integration_tests {
                 Node::Literal(Value::Integer(5)),
                 Node::Word("double".to_string()),
             ],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -3855,6 +5701,7 @@ mod integration_tests {
                 vec![Node::Literal(Value::Integer(10)), Node::Add],
             )],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -3883,6 +5730,7 @@ mod integration_tests {
                 ],
             )],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -3915,6 +5763,7 @@ mod integration_tests {
                 Node::Literal(Value::Integer(5)),
                 Node::Word("double".to_string()),
             ],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -3932,11 +5781,13 @@ mod integration_tests {
         let inline_program = Program {
             definitions: vec![make_inline_def("double", vec![Node::Dup, Node::Add])],
             main: vec![],
+            ..Default::default()
         };
 
         let block_program = Program {
             definitions: vec![make_block_def("double", vec![Node::Dup, Node::Add])],
             main: vec![],
+            ..Default::default()
         };
 
         let inline_compiled = Compiler::new().compile_program(&inline_program).unwrap();
@@ -3970,6 +5821,7 @@ mod integration_tests {
         let program = Program {
             definitions: vec![make_inline_def("noop", vec![])],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -4005,6 +5857,7 @@ mod integration_tests {
                 ],
             )],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -4032,8 +5885,11 @@ mod integration_tests {
             definitions: vec![Node::Def {
                 name: "answer".to_string(),
                 body: vec![Node::Literal(Value::Integer(42))],
+                line: 1,
+                spans: Vec::new(),
             }],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -4054,6 +5910,7 @@ mod integration_tests {
                 vec![Node::Dup, Node::Add, Node::Swap, Node::Dup, Node::Add],
             )],
             main: vec![],
+            ..Default::default()
         };
 
         let compiled = Compiler::new().compile_program(&program).unwrap();
@@ -4065,123 +5922,4 @@ mod integration_tests {
         // Should be: Dup, Add, Swap, Dup, Add, Return
         assert_eq!(ops.len(), 6);
     }
-
-    // =========================================================================
-    // Multiline definition forms
-    // =========================================================================
-
-    #[test]
-    fn test_multiline_inline_def() {
-        // def name
-        //    [body]
-        // end
-        let code = r#"
-            def double
-                [dup +]
-            end
-            5 double
-        "#;
-        assert_stack(code, vec![int(10)]);
-    }
-
-    #[test]
-    fn test_multiline_block_def() {
-        // def name
-        //    body
-        // end
-        let code = r#"
-            def double
-                dup +
-            end
-            5 double
-        "#;
-        assert_stack(code, vec![int(10)]);
-    }
-
-    #[test]
-    fn test_multiline_inline_def_complex() {
-        // Multiline inline form with nested quotations
-        let code = r#"
-            def my-abs
-                [dup 0 < [neg] when]
-            end
-            -5 my-abs
-        "#;
-        assert_stack(code, vec![int(5)]);
-    }
-
-    #[test]
-    fn test_multiline_block_def_complex() {
-        // Multiline block form with control flow
-        let code = r#"
-            def my-abs
-                dup 0 < [neg] when
-            end
-            -5 my-abs
-        "#;
-        assert_stack(code, vec![int(5)]);
-    }
-
-    #[test]
-    fn test_all_def_forms_equivalent() {
-        // All four forms should produce identical results:
-        // 1. Single line inline: def name [body] end
-        // 2. Single line block:  def name body end
-        // 3. Multiline inline:   def name \n [body] \n end
-        // 4. Multiline block:    def name \n body \n end
-
-        // Form 1: Single line inline
-        assert_stack("def double [dup +] end 5 double", vec![int(10)]);
-
-        // Form 2: Single line block
-        assert_stack("def double dup + end 5 double", vec![int(10)]);
-
-        // Form 3: Multiline inline
-        let code3 = r#"
-            def double
-                [dup +]
-            end
-            5 double
-        "#;
-        assert_stack(code3, vec![int(10)]);
-
-        // Form 4: Multiline block
-        let code4 = r#"
-            def double
-                dup +
-            end
-            5 double
-        "#;
-        assert_stack(code4, vec![int(10)]);
-    }
-
-    #[test]
-    fn test_multiline_recursive_inline() {
-        let code = r#"
-            def factorial
-                [
-                    dup 1 <=
-                    [drop 1]
-                    [dup 1 - factorial *]
-                    if
-                ]
-            end
-            5 factorial
-        "#;
-        assert_stack(code, vec![int(120)]);
-    }
-
-    #[test]
-    fn test_multiline_recursive_block() {
-        let code = r#"
-            def factorial
-                dup 1 <=
-                [drop 1]
-                [dup 1 - factorial *]
-                if
-            end
-            5 factorial
-        "#;
-        assert_stack(code, vec![int(120)]);
-    }
 }
@@ -0,0 +1,399 @@
+//! `ember spec`: language tests written in EMBER itself.
+//!
+//! A spec file (`.emt`) holds cases. Each starts with `=== name`, followed
+//! by the program, then one or more expectations, each a `---` line and the
+//! text that goes with it:
+//!
+//! ```text
+//! ; Lines before the first case are comments
+//! === swap exchanges the top two values
+//! 1 2 swap
+//! --- stack
+//! 2 1
+//!
+//! === print writes a line
+//! "hi" print
+//! --- output
+//! hi
+//!
+//! === drop needs a value
+//! drop
+//! --- error
+//! stack underflow
+//! ```
+//!
+//! `--- stack` is EMBER code run on its own, and the case passes if the
+//! program leaves the same stack; nothing after it means an empty stack.
+//! `--- output` is the exact text the program prints. `--- error` is text
+//! the error message must contain, whether the program fails to compile or
+//! to run. Blank lines at the end of a section are ignored.
+//!
+//! The suite under `tests/spec/` runs with `cargo test`, so a language test
+//! can be added without writing Rust.
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use ember::bytecode::compile::Compiler;
+use ember::frontend::lexer::Lexer;
+use ember::frontend::parser::Parser;
+use ember::lang::value::{FloatFormat, Value};
+use ember::runtime::vm_bc::{VmBc, VmBcConfig};
+
+/// Output sink that can be read back after the VM has taken ownership of it.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// One case of a spec file.
+#[derive(Debug, PartialEq)]
+pub struct Case {
+    pub name: String,
+    /// Line of the `===` header, from 1
+    pub line: usize,
+    pub source: String,
+    pub expectations: Vec<Expectation>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Expectation {
+    Stack(String),
+    Output(String),
+    Error(String),
+}
+
+/// Split a spec file into its cases.
+pub fn parse(text: &str) -> Result<Vec<Case>, String> {
+    let mut cases: Vec<Case> = Vec::new();
+    // The section lines are being added to: `None` for a case's program
+    let mut section: Option<(&str, String)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        if let Some(name) = line.strip_prefix("===") {
+            finish_section(&mut cases, section.take());
+            cases.push(Case {
+                name: name.trim().to_string(),
+                line: i + 1,
+                source: String::new(),
+                expectations: Vec::new(),
+            });
+            continue;
+        }
+        let Some(case) = cases.last_mut() else {
+            // Before the first case, only comments and blank lines
+            if line.trim().is_empty() || line.trim_start().starts_with(';') {
+                continue;
+            }
+            return Err(format!(
+                "line {}: expected `=== name` to start a case",
+                i + 1
+            ));
+        };
+        if let Some(kind) = line.strip_prefix("---") {
+            finish_section(&mut cases, section.take());
+            let kind = kind.trim();
+            if !["stack", "output", "error"].contains(&kind) {
+                return Err(format!(
+                    "line {}: unknown section '--- {}' (expected stack, output or error)",
+                    i + 1,
+                    kind
+                ));
+            }
+            section = Some((kind, String::new()));
+            continue;
+        }
+        let text = match &mut section {
+            Some((_, text)) => text,
+            None => &mut case.source,
+        };
+        text.push_str(line);
+        text.push('\n');
+    }
+    finish_section(&mut cases, section);
+
+    match cases.iter().find(|case| case.expectations.is_empty()) {
+        Some(case) => Err(format!(
+            "line {}: case '{}' has no expectations",
+            case.line, case.name
+        )),
+        None => Ok(cases),
+    }
+}
+
+fn finish_section(cases: &mut [Case], section: Option<(&str, String)>) {
+    let (Some(case), Some((kind, text))) = (cases.last_mut(), section) else {
+        return;
+    };
+    let text = text.trim_end_matches('\n').to_string();
+    case.expectations.push(match kind {
+        "stack" => Expectation::Stack(text),
+        "output" => Expectation::Output(text),
+        _ => Expectation::Error(text.trim().to_string()),
+    });
+}
+
+/// What a program did: what it printed, the stack it left, and the error
+/// it stopped with.
+struct Outcome {
+    output: String,
+    stack: Vec<Value>,
+    error: Option<String>,
+}
+
+fn run_program(source: &str) -> Outcome {
+    let output = SharedBuffer::default();
+    let mut vm = VmBc::with_config(VmBcConfig {
+        max_wall_time: Some(Duration::from_secs(10)),
+        ..Default::default()
+    });
+    vm.set_input(Cursor::new(Vec::new()));
+    vm.set_output(output.clone());
+
+    let error = match compile(source) {
+        Ok(bytecode) => vm.run_compiled(&bytecode).err().map(|e| e.message.clone()),
+        Err(e) => Some(e),
+    };
+    Outcome {
+        output: String::from_utf8_lossy(&output.0.borrow()).into_owned(),
+        stack: vm.stack().to_vec(),
+        error,
+    }
+}
+
+fn compile(source: &str) -> Result<ember::bytecode::ProgramBc, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| e.to_string())?;
+    let program = Parser::new(tokens).parse().map_err(|e| e.to_string())?;
+    Compiler::new()
+        .compile_program(&program)
+        .map_err(|e| e.to_string())
+}
+
+/// Run a case, describing how it failed.
+pub fn run_case(case: &Case) -> Result<(), String> {
+    let outcome = run_program(&case.source);
+    let mut problems = String::new();
+
+    let expects_error = case
+        .expectations
+        .iter()
+        .any(|e| matches!(e, Expectation::Error(_)));
+    if let (Some(error), false) = (&outcome.error, expects_error) {
+        writeln!(problems, "  unexpected error: {}", error).unwrap();
+    }
+
+    for expectation in &case.expectations {
+        match expectation {
+            Expectation::Stack(expected) => {
+                let wanted = run_program(expected);
+                if let Some(error) = wanted.error {
+                    writeln!(problems, "  the expected stack fails to run: {}", error).unwrap();
+                } else if wanted.stack != outcome.stack {
+                    writeln!(problems, "  expected stack: {}", show(&wanted.stack)).unwrap();
+                    writeln!(problems, "       got stack: {}", show(&outcome.stack)).unwrap();
+                }
+            }
+            Expectation::Output(expected) => {
+                if outcome.output.trim_end_matches('\n') != expected {
+                    writeln!(problems, "  expected output: {:?}", expected).unwrap();
+                    writeln!(problems, "       got output: {:?}", outcome.output).unwrap();
+                }
+            }
+            Expectation::Error(expected) => match &outcome.error {
+                Some(error) if error.contains(expected.as_str()) => {}
+                Some(error) => {
+                    writeln!(problems, "  expected an error containing: {}", expected).unwrap();
+                    writeln!(problems, "                     got error: {}", error).unwrap();
+                }
+                None => {
+                    writeln!(problems, "  expected an error containing: {}", expected).unwrap();
+                    writeln!(
+                        problems,
+                        "     but it ran, leaving stack: {}",
+                        show(&outcome.stack)
+                    )
+                    .unwrap();
+                }
+            },
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems)
+    }
+}
+
+fn show(stack: &[Value]) -> String {
+    if stack.is_empty() {
+        return "(empty)".to_string();
+    }
+    let shown: Vec<String> = stack
+        .iter()
+        .map(|value| match value {
+            Value::String(s) => format!("{:?}", s),
+            other => other.display(FloatFormat::Shortest).to_string(),
+        })
+        .collect();
+    shown.join(" ")
+}
+
+/// Every `.emt` file under `path`, or `path` itself if it is a file, in a
+/// stable order.
+pub fn collect_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    let mut files = Vec::new();
+    for entry in entries {
+        if entry.is_dir() {
+            files.extend(collect_files(&entry)?);
+        } else if entry.extension().is_some_and(|ext| ext == "emt") {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+/// Counts from running spec files.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub passed: usize,
+    /// One report per failed case, or per file that couldn't be read
+    pub failures: Vec<String>,
+}
+
+/// Run every case in the spec files at `path`.
+pub fn run_path(path: &Path) -> Summary {
+    let mut summary = Summary::default();
+    let files = match collect_files(path) {
+        Ok(files) => files,
+        Err(e) => {
+            summary
+                .failures
+                .push(format!("cannot read {}: {}", path.display(), e));
+            return summary;
+        }
+    };
+    for file in files {
+        let cases = fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse(&text));
+        let cases = match cases {
+            Ok(cases) => cases,
+            Err(e) => {
+                summary.failures.push(format!("{}: {}", file.display(), e));
+                continue;
+            }
+        };
+        for case in &cases {
+            match run_case(case) {
+                Ok(()) => summary.passed += 1,
+                Err(problems) => summary.failures.push(format!(
+                    "{}:{} {}\n{}",
+                    file.display(),
+                    case.line,
+                    case.name,
+                    problems.trim_end()
+                )),
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "; about this file\n\
+                    === one\n1 2 +\n--- stack\n3\n\n\
+                    === two\n\"a\" print\n--- output\na\n--- stack\n\
+                    === three\ndrop\n--- error\n  underflow\n";
+        let cases = parse(text).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(
+            cases[0],
+            Case {
+                name: "one".into(),
+                line: 2,
+                source: "1 2 +\n".into(),
+                expectations: vec![Expectation::Stack("3".into())],
+            }
+        );
+        assert_eq!(
+            cases[1].expectations,
+            vec![
+                Expectation::Output("a".into()),
+                Expectation::Stack(String::new())
+            ]
+        );
+        assert_eq!(
+            cases[2].expectations,
+            vec![Expectation::Error("underflow".into())]
+        );
+
+        assert_eq!(
+            parse("1 2 +\n").unwrap_err(),
+            "line 1: expected `=== name` to start a case"
+        );
+        assert_eq!(
+            parse("=== a\n1\n--- stacks\n").unwrap_err(),
+            "line 3: unknown section '--- stacks' (expected stack, output or error)"
+        );
+        assert_eq!(
+            parse("=== a\n1\n").unwrap_err(),
+            "line 1: case 'a' has no expectations"
+        );
+    }
+
+    #[test]
+    fn test_failures_are_described() {
+        let cases = parse(
+            "=== a\n1 2\n--- stack\n2 1\n\
+             === b\n1 drop\n--- error\nunderflow\n\
+             === c\ndrop\n--- stack\n",
+        )
+        .unwrap();
+        assert_eq!(
+            run_case(&cases[0]).unwrap_err(),
+            "  expected stack: 2 1\n       got stack: 1 2\n"
+        );
+        assert!(run_case(&cases[1]).unwrap_err().contains("but it ran"));
+        let problems = run_case(&cases[2]).unwrap_err();
+        assert!(problems.starts_with("  unexpected error: "), "{}", problems);
+        assert!(problems.contains("stack underflow"), "{}", problems);
+    }
+
+    #[test]
+    fn test_spec_suite() {
+        let summary = run_path(Path::new("tests/spec"));
+        assert!(
+            summary.failures.is_empty(),
+            "{}",
+            summary.failures.join("\n\n")
+        );
+        assert!(summary.passed > 0, "no spec cases found in tests/spec");
+    }
+}
@@ -0,0 +1,298 @@
+//! `ember tutorial`: a guided tour of the language, run in the REPL.
+//!
+//! Each lesson explains an idea and then sets exercises. The user types
+//! lines as in the REPL; after each one the exercise's check runs against a
+//! copy of their stacks and words, and the lesson moves on once it passes.
+//! A lesson starts from an empty REPL, and its exercises build on each
+//! other's state. The lessons are compiled into the binary.
+
+use std::io::Write;
+
+use crate::repl::{self, Repl};
+use ember::lang::value::Value;
+use ember::runtime::help_words::describe_word;
+use ember::runtime::vm_bc::VmBcConfig;
+
+pub struct Lesson {
+    pub title: &'static str,
+    pub text: &'static str,
+    pub exercises: &'static [Exercise],
+}
+
+pub struct Exercise {
+    pub task: &'static str,
+    pub hint: &'static str,
+    /// Code run against a copy of the user's state; passes when it leaves
+    /// the same stack as `expected` run on its own. Empty to compare the
+    /// stack as it is.
+    pub check: &'static str,
+    pub expected: &'static str,
+    /// Shown by `:skip`; the tests check that it passes.
+    pub solution: &'static str,
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "The stack",
+        text: "EMBER programs work on a stack of values. A number pushes itself;\n\
+               a word like + pops its inputs and pushes its result. Type code\n\
+               at the prompt and the stack is shown after each line.",
+        exercises: &[
+            Exercise {
+                task: "Put the numbers 2 and 3 on the stack.",
+                hint: "Type them separated by a space: 2 3",
+                check: "",
+                expected: "2 3",
+                solution: "2 3",
+            },
+            Exercise {
+                task: "Add them.",
+                hint: "+ takes the top two values and pushes their sum",
+                check: "",
+                expected: "5",
+                solution: "+",
+            },
+            Exercise {
+                task: "Multiply the 5 by 4, then subtract 6 (the answer is 14).",
+                hint: "Operators come after their inputs: 4 * 6 -",
+                check: "",
+                expected: "14",
+                solution: "4 * 6 -",
+            },
+        ],
+    },
+    Lesson {
+        title: "Shuffling the stack",
+        text: "Stack words rearrange values: dup copies the top, drop discards it,\n\
+               swap exchanges the top two, and over copies the second to the top.",
+        exercises: &[
+            Exercise {
+                task: "Push 1 and 2, then make the stack read 2 1.",
+                hint: "1 2 swap",
+                check: "",
+                expected: "2 1",
+                solution: "1 2 swap",
+            },
+            Exercise {
+                task: "Copy the top value, so the stack reads 2 1 1.",
+                hint: "dup",
+                check: "",
+                expected: "2 1 1",
+                solution: "dup",
+            },
+            Exercise {
+                task: "Clear the stack with drop until it is empty.",
+                hint: "drop drop drop",
+                check: "",
+                expected: "",
+                solution: "drop drop drop",
+            },
+        ],
+    },
+    Lesson {
+        title: "Quotations",
+        text: "Code in square brackets is a quotation: a value holding code that\n\
+               has not run yet. call runs one, and combinators like map run one\n\
+               for each item of a list. Lists are written in braces: { 1 2 3 }.",
+        exercises: &[
+            Exercise {
+                task: "Push 21, then double it by calling the quotation [ 2 * ].",
+                hint: "21 [ 2 * ] call",
+                check: "",
+                expected: "42",
+                solution: "21 [ 2 * ] call",
+            },
+            Exercise {
+                task: "Drop it, then square every number in { 1 2 3 } with map.",
+                hint: "drop { 1 2 3 } [ dup * ] map",
+                check: "",
+                expected: "{ 1 4 9 }",
+                solution: "drop { 1 2 3 } [ dup * ] map",
+            },
+        ],
+    },
+    Lesson {
+        title: "Defining words",
+        text: "def name ... end defines a new word. Using it runs its body, so a\n\
+               word takes its inputs from the stack like any built-in.",
+        exercises: &[
+            Exercise {
+                task: "Define square, which multiplies a number by itself.",
+                hint: "def square dup * end",
+                check: "clear 7 square",
+                expected: "49",
+                solution: "def square dup * end",
+            },
+            Exercise {
+                task: "Define cube, using square.",
+                hint: "def cube dup square * end",
+                check: "clear 3 cube",
+                expected: "27",
+                solution: "def cube dup square * end",
+            },
+        ],
+    },
+    Lesson {
+        title: "Making decisions",
+        text: "Comparisons like < and = push true or false. if pops a flag and two\n\
+               quotations, and runs the first when the flag is true and the\n\
+               second otherwise. % is the remainder of a division.",
+        exercises: &[
+            Exercise {
+                task: "Define is-even, leaving true for even numbers and false for odd.",
+                hint: "def is-even 2 % 0 = end",
+                check: "clear 4 is-even 7 is-even",
+                expected: "true false",
+                solution: "def is-even 2 % 0 = end",
+            },
+            Exercise {
+                task: "Define describe, leaving \"even\" or \"odd\" for a number.",
+                hint: "def describe is-even [ \"even\" ] [ \"odd\" ] if end",
+                check: "clear 10 describe 3 describe",
+                expected: "\"even\" \"odd\"",
+                solution: "def describe is-even [ \"even\" ] [ \"odd\" ] if end",
+            },
+        ],
+    },
+    Lesson {
+        title: "Handling errors",
+        text: "A failing word stops the program, but try runs a body quotation and,\n\
+               if it fails, a handler. The handler starts from the stack the body\n\
+               started from, with the error message on top.",
+        exercises: &[Exercise {
+            task: "Take the head of the empty list { }, giving 0 if that fails.",
+            hint: "[ { } head ] [ drop 0 ] try",
+            check: "",
+            expected: "0",
+            solution: "[ { } head ] [ drop 0 ] try",
+        }],
+    },
+];
+
+/// Run the tutorial from lesson `start` (from 1) on standard input.
+pub fn run(config: VmBcConfig, start: usize) {
+    repl::interrupt_on_ctrl_c(&config);
+    println!("EMBER tutorial - :hint for a hint, :skip to move on, :quit to stop");
+
+    for (number, lesson) in LESSONS.iter().enumerate().skip(start - 1) {
+        println!();
+        println!(
+            "Lesson {} of {}: {}",
+            number + 1,
+            LESSONS.len(),
+            lesson.title
+        );
+        println!();
+        println!("{}", lesson.text);
+
+        let mut repl = Repl::new(config.clone());
+        for exercise in lesson.exercises {
+            println!();
+            println!("> {}", exercise.task);
+            if !run_exercise(&mut repl, exercise) {
+                println!("Pick up here later with `ember tutorial {}`", number + 1);
+                return;
+            }
+        }
+    }
+    println!();
+    println!("That's the tour. `ember examples` has some complete programs to read next.");
+}
+
+/// Take lines until the exercise passes or is skipped. Returns false if the
+/// user quits.
+fn run_exercise(repl: &mut Repl, exercise: &Exercise) -> bool {
+    loop {
+        print!("tutorial> ");
+        std::io::stdout().flush().ok();
+
+        let line = match repl.read_line() {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                println!();
+                return false;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return false;
+            }
+        };
+
+        match line.trim() {
+            "" => continue,
+            ":quit" | ":q" => return false,
+            ":hint" => println!("  hint: {}", exercise.hint),
+            ":skip" => {
+                println!("  one answer: {}", exercise.solution);
+                // Later exercises may build on this one's state
+                if repl.eval(exercise.solution).is_ok() {
+                    repl::print_stack_line(repl);
+                }
+                return true;
+            }
+            command if command.starts_with(":help ") => {
+                let name = command[":help ".len()..].trim();
+                match describe_word(repl.vm(), name) {
+                    Some(text) => print!("{}", text),
+                    None => eprintln!("no word named '{}'", name),
+                }
+            }
+            _ => {
+                match repl.eval(&line) {
+                    Ok(()) => repl::print_stack_line(repl),
+                    Err(e) => eprintln!("{}", e),
+                }
+                if passes(repl, exercise) {
+                    println!("  ✓ well done");
+                    return true;
+                }
+            }
+        }
+    }
+}
+
+/// Whether the user's state passes the exercise's check.
+pub fn passes(repl: &mut Repl, exercise: &Exercise) -> bool {
+    let expected = stack_of(exercise.expected);
+    repl.probe(exercise.check).ok() == expected
+}
+
+/// The stack `source` leaves when run on its own.
+fn stack_of(source: &str) -> Option<Vec<Value>> {
+    let mut repl = Repl::new(VmBcConfig::default());
+    repl.eval(source).ok()?;
+    Some(repl.stack().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solutions_pass() {
+        for lesson in LESSONS {
+            let mut repl = Repl::new(VmBcConfig::default());
+            for exercise in lesson.exercises {
+                assert!(
+                    !passes(&mut repl, exercise),
+                    "'{}' passes before it is attempted",
+                    exercise.task
+                );
+                repl.eval(exercise.solution)
+                    .unwrap_or_else(|e| panic!("solution to '{}' failed: {}", exercise.task, e));
+                assert!(passes(&mut repl, exercise), "'{}' fails", exercise.task);
+            }
+        }
+    }
+
+    #[test]
+    fn test_checks_use_words_without_touching_the_stack() {
+        let exercise = &LESSONS[3].exercises[0];
+        let mut repl = Repl::new(VmBcConfig::default());
+        repl.eval("1 def square dup dup * * end").unwrap();
+        assert!(!passes(&mut repl, exercise));
+        repl.eval("def square dup * end").unwrap();
+        assert!(passes(&mut repl, exercise));
+        assert_eq!(repl.stack(), &[Value::Integer(1)]);
+    }
+}
@@ -0,0 +1,4 @@
+; The stack checker sees that + has only one input
+def add-one + end
+
+1 add-one print
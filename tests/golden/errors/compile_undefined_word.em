@@ -0,0 +1,4 @@
+; A call to a word that is never defined
+def double 2 * end
+
+5 duble print
@@ -0,0 +1,2 @@
+; A quotation that is never closed
+{ 1 2 3 } [ 2 *
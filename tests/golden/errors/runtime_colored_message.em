@@ -0,0 +1,2 @@
+; A thrown message with color escapes, which the snapshot strips
+"disk full" "red" colorize throw
@@ -0,0 +1,5 @@
+; Dividing by zero two words deep, so the call stack is shown
+def ratio / end
+def average-of-none 0 0 ratio end
+
+average-of-none print
@@ -0,0 +1,2 @@
+; Indexing past the end of a list
+{ 1 2 3 } 10 nth
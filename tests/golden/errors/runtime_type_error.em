@@ -0,0 +1,3 @@
+; Adding a number and a string
+"total: " print
+1 "a" +
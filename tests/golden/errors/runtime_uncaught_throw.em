@@ -0,0 +1,2 @@
+; A thrown value that no try catches
+{ "code" 42 } throw